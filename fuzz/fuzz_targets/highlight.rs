@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    // The reader never hands over a line with a newline in it.
+    for line in input.lines() {
+        logr::fuzz::highlight(line);
+    }
+});
@@ -0,0 +1,1176 @@
+use std::{collections::VecDeque, time::Instant};
+
+use ratatui::style::Color;
+
+use crate::{
+    AppState, LogLine, LogrError, MatchCost, PatternSpec, Snooze, Viewport, audit,
+    buffer::LogBuffer,
+    build_pattern, build_spec,
+    filter::FilterExpr,
+    json::JsonView,
+    level::Level,
+    parser::Format,
+    pattern_file, pattern_regex, refresh_matches, set_word, share,
+    ui::{next_pattern_color, pattern_color},
+    visible_indices, visible_line_index,
+};
+
+/// A change to the app state made by the user. Applied actions can be undone and redone, and
+/// are journaled so a session's interactions can be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action {
+    AddPattern {
+        pattern: String,
+        case_sensitive: bool,
+    },
+    RemovePattern {
+        index: usize,
+    },
+    /// Changes the text of a pattern, which keeps its place (and with it its color) and its
+    /// settings.
+    EditPattern {
+        index: usize,
+        pattern: String,
+    },
+    ToggleCase {
+        index: usize,
+    },
+    /// Switches between matching a pattern's text as a regex and as it is.
+    ToggleLiteral {
+        index: usize,
+    },
+    /// Switches between matching a pattern anywhere and only as whole words.
+    ToggleWord {
+        index: usize,
+    },
+    ToggleExclude {
+        index: usize,
+    },
+    ToggleEnabled {
+        index: usize,
+    },
+    /// Gives a pattern the palette color after its current one.
+    CycleColor {
+        index: usize,
+    },
+    /// Switches between a pattern coloring its matches and the whole lines they are in.
+    ToggleWholeLine {
+        index: usize,
+    },
+    /// Snoozes a pattern, or snoozes it longer, waking it up after the longest snooze.
+    Snooze {
+        index: usize,
+    },
+    /// Replaces all the patterns with those of a profile from the config file.
+    UseProfile {
+        name: String,
+    },
+    /// Replaces the patterns loaded from the patterns file, where they are, with those it has
+    /// now.
+    ReloadPatterns,
+    ToggleFilter,
+    /// Filters by a boolean expression over the patterns, or with `None` by any pattern again.
+    SetFilter {
+        expression: Option<String>,
+    },
+    /// Folds or unfolds the lines continuing entries.
+    ToggleGrouping,
+    /// Flips between oldest-first and newest-first order.
+    ToggleOrder,
+    /// Raises the minimum level shown, wrapping around to showing every level.
+    CycleMinLevel,
+    ToggleWrap {
+        viewport: Viewport,
+    },
+    ToggleLineNumbers,
+    /// Shows epoch times in lines as readable ones, or as they are.
+    ToggleEpochs,
+    CycleJsonView,
+    ToggleSource {
+        index: usize,
+    },
+    CycleFormat {
+        index: usize,
+    },
+    ToggleField {
+        key: String,
+    },
+    ClearLines,
+    Undo,
+    Redo,
+}
+
+impl Action {
+    /// Whether applying the action can change the patterns, and with them what lines match.
+    /// Undoing or redoing anything may, as may restoring lines matched by older patterns.
+    fn changes_patterns(&self) -> bool {
+        matches!(
+            self,
+            Action::AddPattern { .. }
+                | Action::RemovePattern { .. }
+                | Action::EditPattern { .. }
+                | Action::ToggleCase { .. }
+                | Action::ToggleLiteral { .. }
+                | Action::ToggleWord { .. }
+                | Action::ToggleExclude { .. }
+                | Action::ToggleEnabled { .. }
+                | Action::Snooze { .. }
+                | Action::UseProfile { .. }
+                | Action::ReloadPatterns
+                | Action::Undo
+                | Action::Redo
+        )
+    }
+}
+
+/// What it takes to revert an applied action.
+enum Revert {
+    RemovePattern {
+        index: usize,
+    },
+    InsertPattern {
+        index: usize,
+        pattern: PatternSpec,
+    },
+    ReplacePattern {
+        index: usize,
+        pattern: PatternSpec,
+    },
+    ToggleCase {
+        index: usize,
+    },
+    ToggleLiteral {
+        index: usize,
+    },
+    ToggleWord {
+        index: usize,
+    },
+    ToggleExclude {
+        index: usize,
+    },
+    ToggleEnabled {
+        index: usize,
+    },
+    SetColor {
+        index: usize,
+        color: Option<Color>,
+    },
+    ToggleWholeLine {
+        index: usize,
+    },
+    SetSnooze {
+        index: usize,
+        snooze: Option<Snooze>,
+    },
+    SetPatterns(Vec<PatternSpec>),
+    ReloadPatterns {
+        at: usize,
+        patterns: Vec<PatternSpec>,
+    },
+    ToggleFilter,
+    SetFilter {
+        expression: Option<FilterExpr>,
+        filter_only: bool,
+    },
+    ToggleGrouping,
+    ToggleOrder,
+    SetMinLevel(Option<Level>),
+    ToggleWrap {
+        viewport: Viewport,
+    },
+    ToggleLineNumbers,
+    ToggleEpochs,
+    SetJsonView(JsonView),
+    ToggleSource {
+        index: usize,
+    },
+    SetFormat {
+        index: usize,
+        format: Format,
+    },
+    ToggleField {
+        key: String,
+    },
+    RestoreLines {
+        lines: VecDeque<LogLine>,
+        /// Estimated memory used by the lines, see [`LogBuffer::estimated_bytes`].
+        bytes: usize,
+        /// Lines evicted before the clear, counted again once the lines are back.
+        evicted: usize,
+    },
+}
+
+impl Revert {
+    /// Estimated memory kept to revert the action, counting only the lines of a clear.
+    fn bytes(&self) -> usize {
+        match self {
+            Revert::RestoreLines { bytes, .. } => *bytes,
+            _ => 0,
+        }
+    }
+}
+
+/// Actions kept to be undone, the oldest forgotten beyond it.
+const MAX_UNDO: usize = 100;
+/// Estimated memory of the cleared lines kept to be restored by undoing, the oldest clears
+/// forgotten beyond it.
+const MAX_RESTORE_BYTES: usize = 64 << 20;
+/// Actions kept in the journal, the oldest dropped beyond it.
+const MAX_JOURNAL: usize = 10_000;
+
+#[derive(Default)]
+pub(crate) struct History {
+    done: VecDeque<(Action, Revert)>,
+    undone: Vec<Action>,
+    journal: VecDeque<Action>,
+}
+
+impl History {
+    /// The latest actions applied, in order, including undos and redos.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) fn journal(&self) -> &VecDeque<Action> {
+        &self.journal
+    }
+
+    /// Keeps an applied action to be undone, forgetting the oldest ones beyond [`MAX_UNDO`]
+    /// and, beyond [`MAX_RESTORE_BYTES`], the oldest clears along with the actions before them,
+    /// which can only be undone past them.
+    fn remember(&mut self, action: Action, revert: Revert) {
+        self.done.push_back((action, revert));
+        if self.done.len() > MAX_UNDO {
+            self.done.pop_front();
+        }
+        let mut bytes: usize = self.done.iter().map(|(_, revert)| revert.bytes()).sum();
+        while bytes > MAX_RESTORE_BYTES {
+            let Some(oldest) = self.done.iter().position(|(_, revert)| revert.bytes() > 0) else {
+                break;
+            };
+            bytes -= self.done[oldest].1.bytes();
+            self.done.drain(..=oldest);
+        }
+    }
+
+    /// Journals an applied action, dropping the oldest ones beyond [`MAX_JOURNAL`].
+    fn record(&mut self, action: Action) {
+        if self.journal.len() == MAX_JOURNAL {
+            self.journal.pop_front();
+        }
+        self.journal.push_back(action);
+    }
+}
+
+/// Applies `action`, recording it for undo and in the journal. Actions that don't apply (an
+/// undo with nothing to undo, an index out of range) are journaled but change nothing.
+pub(crate) fn apply(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    action: Action,
+) -> Result<(), LogrError> {
+    let shared = app
+        .share
+        .as_ref()
+        .filter(|_| action.changes_patterns())
+        .map(|_| share::texts(&app.patterns));
+    let entry = app.audit.is_some().then(|| audit_entry(app, &action));
+    match &action {
+        Action::Undo => {
+            if let Some((undone, revert)) = app.history.done.pop_back() {
+                apply_revert(app, lines, revert)?;
+                app.history.undone.push(undone);
+            }
+        }
+        Action::Redo => {
+            if let Some(redone) = app.history.undone.pop()
+                && let Some(revert) = perform(app, lines, &redone)?
+            {
+                app.history.remember(redone, revert);
+            }
+        }
+        _ => {
+            if let Some(revert) = perform(app, lines, &action)? {
+                app.history.remember(action.clone(), revert);
+                app.history.undone.clear();
+            }
+        }
+    }
+    if action.changes_patterns() {
+        refresh_matches(app, lines);
+    }
+    if let Some(before) = shared
+        && let Some(share) = &app.share
+    {
+        for change in share::changes(&before, &app.patterns) {
+            share.broadcast(&change);
+        }
+    }
+    if let Some(entry) = entry {
+        audit::record(app, &entry);
+    }
+    app.history.record(action);
+    Ok(())
+}
+
+/// How an action is recorded in the audit log, taken before it is applied. Undo and redo are
+/// recorded with the action undone or redone, its patterns named by their place as the ones
+/// there now may not be the ones it was about.
+fn audit_entry(app: &AppState, action: &Action) -> String {
+    match action {
+        Action::Undo => match app.history.done.back() {
+            Some((undone, _)) => format!("Undid \"{}\"", describe(undone, None)),
+            None => "Undid nothing".to_string(),
+        },
+        Action::Redo => match app.history.undone.last() {
+            Some(redone) => format!("Redid \"{}\"", describe(redone, None)),
+            None => "Redid nothing".to_string(),
+        },
+        _ => describe(action, Some(app)),
+    }
+}
+
+/// A line telling what an action does, naming the patterns and sources it is about by their
+/// text and name when `app` is given, or by their place otherwise.
+fn describe(action: &Action, app: Option<&AppState>) -> String {
+    let pattern = |index: usize| match app.and_then(|app| app.patterns.get(index)) {
+        Some(pattern) => format!("pattern {:?}", pattern.pattern),
+        None => format!("pattern #{}", index + 1),
+    };
+    let source = |index: usize| match app.and_then(|app| app.sources.get(index)) {
+        Some(source) => format!("source {:?}", source.name),
+        None => format!("source #{}", index + 1),
+    };
+    match action {
+        Action::AddPattern {
+            pattern,
+            case_sensitive,
+        } => {
+            let case = if *case_sensitive {
+                ""
+            } else {
+                " ignoring case"
+            };
+            format!("Added pattern {pattern:?}{case}")
+        }
+        Action::RemovePattern { index } => format!("Removed {}", pattern(*index)),
+        Action::EditPattern {
+            index,
+            pattern: text,
+        } => format!("Edited {} to {text:?}", pattern(*index)),
+        Action::ToggleCase { index } => {
+            format!("Toggled case sensitivity of {}", pattern(*index))
+        }
+        Action::ToggleLiteral { index } => {
+            format!("Toggled literal matching of {}", pattern(*index))
+        }
+        Action::ToggleWord { index } => {
+            format!("Toggled whole-word matching of {}", pattern(*index))
+        }
+        Action::ToggleExclude { index } => format!("Toggled excluding with {}", pattern(*index)),
+        Action::ToggleEnabled { index } => format!("Toggled {} on or off", pattern(*index)),
+        Action::CycleColor { index } => format!("Changed the color of {}", pattern(*index)),
+        Action::ToggleWholeLine { index } => {
+            format!("Toggled whole-line coloring of {}", pattern(*index))
+        }
+        Action::Snooze { index } => {
+            let current = app.and_then(|app| app.patterns.get(*index));
+            match current.map(|current| Snooze::next(current.snooze, Instant::now())) {
+                Some(Some(snooze)) => {
+                    format!("Snoozed {} for {} minutes", pattern(*index), snooze.minutes)
+                }
+                Some(None) => format!("Woke up {}", pattern(*index)),
+                None => format!("Changed the snooze of {}", pattern(*index)),
+            }
+        }
+        Action::UseProfile { name } => format!("Switched to profile {name:?}"),
+        Action::ReloadPatterns => "Reloaded the patterns file".to_string(),
+        Action::ToggleFilter => "Toggled the filter".to_string(),
+        Action::SetFilter {
+            expression: Some(expression),
+        } => format!("Filtered by {expression:?}"),
+        Action::SetFilter { expression: None } => "Filtered by any pattern".to_string(),
+        Action::ToggleGrouping => "Toggled folding entries".to_string(),
+        Action::ToggleOrder => "Toggled newest-first order".to_string(),
+        Action::CycleMinLevel => "Changed the minimum level".to_string(),
+        Action::ToggleWrap {
+            viewport: Viewport::Main,
+        } => "Toggled wrapping lines".to_string(),
+        Action::ToggleWrap {
+            viewport: Viewport::Detail,
+        } => "Toggled wrapping the detail popup".to_string(),
+        Action::ToggleWrap {
+            viewport: Viewport::Help,
+        } => "Toggled wrapping the help overlay".to_string(),
+        Action::ToggleLineNumbers => "Toggled line numbers".to_string(),
+        Action::ToggleEpochs => "Toggled readable epoch times".to_string(),
+        Action::CycleJsonView => "Changed the JSON view".to_string(),
+        Action::ToggleSource { index } => format!("Toggled {}", source(*index)),
+        Action::CycleFormat { index } => format!("Changed the format of {}", source(*index)),
+        Action::ToggleField { key } => format!("Toggled field {key:?}"),
+        Action::ClearLines => "Cleared the buffer".to_string(),
+        Action::Undo => "Undid an action".to_string(),
+        Action::Redo => "Redid an action".to_string(),
+    }
+}
+
+fn perform(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    action: &Action,
+) -> Result<Option<Revert>, LogrError> {
+    let revert = match action {
+        Action::AddPattern {
+            pattern,
+            case_sensitive,
+        } => {
+            app.patterns
+                .push(build_pattern(pattern.clone(), *case_sensitive)?);
+            Revert::RemovePattern {
+                index: app.patterns.len() - 1,
+            }
+        }
+        Action::RemovePattern { index } => {
+            if *index >= app.patterns.len() {
+                return Ok(None);
+            }
+            Revert::InsertPattern {
+                index: *index,
+                pattern: remove_pattern(app, *index),
+            }
+        }
+        Action::EditPattern { index, pattern } => {
+            let Some(old) = app.patterns.get(*index) else {
+                return Ok(None);
+            };
+            let mut edited = build_spec(pattern.clone(), old.case_sensitive, old.literal)?;
+            edited.exclude = old.exclude;
+            edited.enabled = old.enabled;
+            edited.snooze = old.snooze;
+            edited.sound = old.sound.clone();
+            edited.color = old.color;
+            edited.style = old.style;
+            edited.whole_line = old.whole_line;
+            edited.name = old.name.clone();
+            edited.from_file = old.from_file;
+            if old.word {
+                set_word(&mut edited, true)?;
+            }
+            Revert::ReplacePattern {
+                index: *index,
+                pattern: std::mem::replace(&mut app.patterns[*index], edited),
+            }
+        }
+        Action::ToggleCase { index } => {
+            let Some(pattern) = app.patterns.get_mut(*index) else {
+                return Ok(None);
+            };
+            toggle_case(pattern)?;
+            Revert::ToggleCase { index: *index }
+        }
+        Action::ToggleLiteral { index } => {
+            let Some(pattern) = app.patterns.get_mut(*index) else {
+                return Ok(None);
+            };
+            toggle_literal(pattern)?;
+            Revert::ToggleLiteral { index: *index }
+        }
+        Action::ToggleWord { index } => {
+            let Some(pattern) = app.patterns.get_mut(*index) else {
+                return Ok(None);
+            };
+            set_word(pattern, !pattern.word)?;
+            Revert::ToggleWord { index: *index }
+        }
+        Action::ToggleExclude { index } => {
+            let Some(pattern) = app.patterns.get_mut(*index) else {
+                return Ok(None);
+            };
+            pattern.exclude = !pattern.exclude;
+            Revert::ToggleExclude { index: *index }
+        }
+        Action::ToggleEnabled { index } => {
+            let Some(pattern) = app.patterns.get_mut(*index) else {
+                return Ok(None);
+            };
+            pattern.enabled = !pattern.enabled;
+            Revert::ToggleEnabled { index: *index }
+        }
+        Action::CycleColor { index } => {
+            if *index >= app.patterns.len() {
+                return Ok(None);
+            }
+            let color = next_pattern_color(pattern_color(&app.patterns, *index));
+            Revert::SetColor {
+                index: *index,
+                color: app.patterns[*index].color.replace(color),
+            }
+        }
+        Action::ToggleWholeLine { index } => {
+            let Some(pattern) = app.patterns.get_mut(*index) else {
+                return Ok(None);
+            };
+            pattern.whole_line = !pattern.whole_line;
+            Revert::ToggleWholeLine { index: *index }
+        }
+        Action::Snooze { index } => {
+            let Some(pattern) = app.patterns.get_mut(*index) else {
+                return Ok(None);
+            };
+            let snooze = Snooze::next(pattern.snooze, Instant::now());
+            Revert::SetSnooze {
+                index: *index,
+                snooze: std::mem::replace(&mut pattern.snooze, snooze),
+            }
+        }
+        Action::UseProfile { name } => {
+            let Some(profile) = app.profiles.get(name) else {
+                return Ok(None);
+            };
+            // Built in full before any is replaced, so a broken profile changes nothing.
+            let patterns = profile.build(app.ignore_case)?;
+            Revert::SetPatterns(set_patterns(app, patterns))
+        }
+        Action::ReloadPatterns => {
+            let Some(path) = &app.patterns_file else {
+                return Ok(None);
+            };
+            // Loaded in full before any is replaced, so a broken file changes nothing.
+            let patterns = pattern_file::load(path, app.ignore_case)?;
+            let (at, patterns) = replace_file_patterns(app, 0, patterns);
+            Revert::ReloadPatterns { at, patterns }
+        }
+        Action::ToggleFilter => {
+            toggle_filter(app, lines);
+            Revert::ToggleFilter
+        }
+        Action::SetFilter { expression } => {
+            let expression = match expression {
+                Some(text) => {
+                    let expression = FilterExpr::parse(text).map_err(LogrError::FilterError)?;
+                    if let Some(unknown) = expression.unknown(&app.patterns) {
+                        return Err(LogrError::FilterError(unknown));
+                    }
+                    Some(expression)
+                }
+                None => None,
+            };
+            let revert = Revert::SetFilter {
+                expression: app.filter_expr.clone(),
+                filter_only: app.filter_only,
+            };
+            // An expression is entered to filter by it.
+            let filter_only = expression.is_some() || app.filter_only;
+            set_filter(app, lines, expression, filter_only);
+            revert
+        }
+        Action::ToggleGrouping => {
+            toggle_grouping(app);
+            Revert::ToggleGrouping
+        }
+        Action::ToggleOrder => {
+            toggle_order(app);
+            Revert::ToggleOrder
+        }
+        Action::CycleMinLevel => {
+            let min_level = app.min_level;
+            set_min_level(app, Level::next_minimum(min_level));
+            Revert::SetMinLevel(min_level)
+        }
+        Action::ToggleWrap { viewport } => {
+            toggle_wrap(app, *viewport);
+            Revert::ToggleWrap {
+                viewport: *viewport,
+            }
+        }
+        Action::ToggleLineNumbers => {
+            app.line_numbers = !app.line_numbers;
+            Revert::ToggleLineNumbers
+        }
+        Action::ToggleEpochs => {
+            app.human_epochs = !app.human_epochs;
+            Revert::ToggleEpochs
+        }
+        Action::CycleJsonView => {
+            let view = app.json_view;
+            app.json_view = view.next();
+            Revert::SetJsonView(view)
+        }
+        Action::ToggleSource { index } => {
+            let Some(source) = app.sources.get_mut(*index) else {
+                return Ok(None);
+            };
+            source.hidden = !source.hidden;
+            Revert::ToggleSource { index: *index }
+        }
+        Action::CycleFormat { index } => {
+            let Some(source) = app.sources.get_mut(*index) else {
+                return Ok(None);
+            };
+            let format = source.format;
+            source.format = format.next();
+            Revert::SetFormat {
+                index: *index,
+                format,
+            }
+        }
+        Action::ToggleField { key } => {
+            toggle_field(app, key.clone());
+            Revert::ToggleField { key: key.clone() }
+        }
+        Action::ClearLines => {
+            if let Some(pause) = &mut app.pause {
+                pause.end = 0;
+            }
+            app.detail_line = None;
+            app.search_match = None;
+            app.selection = None;
+            app.scroll = 0;
+            app.follow = true;
+            // The next line starts a fresh day or hour, marked with its separator.
+            app.last_date_hour = None;
+            Revert::RestoreLines {
+                bytes: lines.estimated_bytes(),
+                evicted: lines.evicted(),
+                lines: lines.take(),
+            }
+        }
+        Action::Undo | Action::Redo => return Ok(None),
+    };
+    Ok(Some(revert))
+}
+
+fn apply_revert(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    revert: Revert,
+) -> Result<(), LogrError> {
+    match revert {
+        Revert::RemovePattern { index } => {
+            if index < app.patterns.len() {
+                remove_pattern(app, index);
+            }
+        }
+        Revert::InsertPattern { index, pattern } => {
+            app.patterns.insert(index.min(app.patterns.len()), pattern);
+        }
+        Revert::ReplacePattern { index, pattern } => {
+            if let Some(edited) = app.patterns.get_mut(index) {
+                *edited = pattern;
+            }
+        }
+        Revert::ToggleCase { index } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                toggle_case(pattern)?;
+            }
+        }
+        Revert::ToggleLiteral { index } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                toggle_literal(pattern)?;
+            }
+        }
+        Revert::ToggleWord { index } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                set_word(pattern, !pattern.word)?;
+            }
+        }
+        Revert::ToggleExclude { index } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                pattern.exclude = !pattern.exclude;
+            }
+        }
+        Revert::ToggleEnabled { index } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                pattern.enabled = !pattern.enabled;
+            }
+        }
+        Revert::SetColor { index, color } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                pattern.color = color;
+            }
+        }
+        Revert::ToggleWholeLine { index } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                pattern.whole_line = !pattern.whole_line;
+            }
+        }
+        Revert::SetSnooze { index, snooze } => {
+            if let Some(pattern) = app.patterns.get_mut(index) {
+                pattern.snooze = snooze;
+            }
+        }
+        Revert::SetPatterns(patterns) => {
+            set_patterns(app, patterns);
+        }
+        Revert::ReloadPatterns { at, patterns } => {
+            replace_file_patterns(app, at, patterns);
+        }
+        Revert::ToggleFilter => toggle_filter(app, lines),
+        Revert::SetFilter {
+            expression,
+            filter_only,
+        } => set_filter(app, lines, expression, filter_only),
+        Revert::ToggleGrouping => toggle_grouping(app),
+        Revert::ToggleOrder => toggle_order(app),
+        Revert::SetMinLevel(min_level) => set_min_level(app, min_level),
+        Revert::ToggleWrap { viewport } => toggle_wrap(app, viewport),
+        Revert::ToggleLineNumbers => app.line_numbers = !app.line_numbers,
+        Revert::ToggleEpochs => app.human_epochs = !app.human_epochs,
+        Revert::SetJsonView(view) => app.json_view = view,
+        Revert::ToggleSource { index } => {
+            if let Some(source) = app.sources.get_mut(index) {
+                source.hidden = !source.hidden;
+            }
+        }
+        Revert::SetFormat { index, format } => {
+            if let Some(source) = app.sources.get_mut(index) {
+                source.format = format;
+            }
+        }
+        Revert::ToggleField { key } => toggle_field(app, key),
+        Revert::RestoreLines {
+            lines: cleared,
+            evicted,
+            ..
+        } => lines.restore(cleared, evicted),
+    }
+    Ok(())
+}
+
+fn remove_pattern(app: &mut AppState, index: usize) -> PatternSpec {
+    let pattern = app.patterns.remove(index);
+    if app.selected > app.patterns.len() {
+        app.selected = app.patterns.len();
+    }
+    pattern
+}
+
+/// Replaces the patterns, returning the ones replaced.
+fn set_patterns(app: &mut AppState, patterns: Vec<PatternSpec>) -> Vec<PatternSpec> {
+    app.selected = 0;
+    std::mem::replace(&mut app.patterns, patterns)
+}
+
+/// Replaces the patterns loaded from the patterns file, which are next to each other, with
+/// `patterns`, put at `at` when there were none. Returns where they were and the ones replaced.
+fn replace_file_patterns(
+    app: &mut AppState,
+    at: usize,
+    patterns: Vec<PatternSpec>,
+) -> (usize, Vec<PatternSpec>) {
+    let at = app
+        .patterns
+        .iter()
+        .position(|pattern| pattern.from_file)
+        .unwrap_or(at)
+        .min(app.patterns.len());
+    let (replaced, mut kept): (Vec<_>, Vec<_>) = std::mem::take(&mut app.patterns)
+        .into_iter()
+        .partition(|pattern| pattern.from_file);
+    kept.splice(at..at, patterns);
+    set_patterns(app, kept);
+    (at, replaced)
+}
+
+fn toggle_case(pattern: &mut PatternSpec) -> Result<(), LogrError> {
+    let case_sensitive = !pattern.case_sensitive;
+    pattern.regex = pattern_regex(
+        &pattern.pattern,
+        case_sensitive,
+        pattern.literal,
+        pattern.word,
+    )?;
+    pattern.case_sensitive = case_sensitive;
+    pattern.cost = MatchCost::default();
+    Ok(())
+}
+
+/// Fails, changing nothing, for a literal pattern whose text isn't a valid regex.
+fn toggle_literal(pattern: &mut PatternSpec) -> Result<(), LogrError> {
+    let literal = !pattern.literal;
+    pattern.regex = pattern_regex(
+        &pattern.pattern,
+        pattern.case_sensitive,
+        literal,
+        pattern.word,
+    )?;
+    pattern.groups = pattern.regex.captures_len() > 1;
+    pattern.literal = literal;
+    pattern.cost = MatchCost::default();
+    Ok(())
+}
+
+fn toggle_wrap(app: &mut AppState, viewport: Viewport) {
+    let wrap = app.wrap_mut(viewport);
+    *wrap = !*wrap;
+}
+
+fn toggle_field(app: &mut AppState, key: String) {
+    if !app.hidden_fields.remove(&key) {
+        app.hidden_fields.insert(key);
+    }
+}
+
+/// Flips the order, following the newest lines at their new end of the view.
+fn toggle_order(app: &mut AppState) {
+    app.follow_at = app.follow_at.flipped();
+    app.follow = true;
+}
+
+fn set_min_level(app: &mut AppState, min_level: Option<Level>) {
+    app.min_level = min_level;
+    app.follow = true;
+    app.scroll = 0;
+}
+
+fn toggle_filter(app: &mut AppState, lines: &LogBuffer) {
+    keep_anchor(app, lines, |app| app.filter_only = !app.filter_only);
+}
+
+fn set_filter(
+    app: &mut AppState,
+    lines: &LogBuffer,
+    expression: Option<FilterExpr>,
+    filter_only: bool,
+) {
+    keep_anchor(app, lines, |app| {
+        app.filter_expr = expression;
+        app.filter_only = filter_only;
+    });
+}
+
+/// Changes which lines the view shows, keeping the line it starts at (or the next one still
+/// shown) where it is, since the scroll offset counts the lines shown. A view following new
+/// lines, or scrolled past its lines, follows them afterwards.
+fn keep_anchor(app: &mut AppState, lines: &LogBuffer, change: impl FnOnce(&mut AppState)) {
+    let anchor = if app.follow {
+        None
+    } else {
+        visible_line_index(lines, app, app.scroll)
+    };
+    change(app);
+    match anchor {
+        Some(anchor) => {
+            app.scroll = visible_indices(lines, app)
+                .take_while(|&index| index < anchor)
+                .count();
+        }
+        None => {
+            app.follow = true;
+            app.scroll = 0;
+        }
+    }
+}
+
+fn toggle_grouping(app: &mut AppState) {
+    app.group_entries = !app.group_entries;
+    app.follow = true;
+    app.scroll = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, fs};
+
+    use super::{Action, History, MAX_JOURNAL, MAX_RESTORE_BYTES, MAX_UNDO, Revert, apply};
+    use crate::{
+        AppState, LogLine, Viewport,
+        audit::Audit,
+        buffer::LogBuffer,
+        build_pattern,
+        config::{Profile, ProfilePattern},
+        json::JsonView,
+        pattern_file, push_line, visible_line_index,
+    };
+
+    type State = (Vec<(String, bool)>, bool, (bool, bool), Vec<String>);
+
+    fn state(app: &AppState, lines: &LogBuffer) -> State {
+        let patterns = app
+            .patterns
+            .iter()
+            .map(|pattern| (pattern.pattern.clone(), pattern.case_sensitive))
+            .collect();
+        let texts = lines.iter().map(|line| line.text.clone()).collect();
+        (
+            patterns,
+            app.filter_only,
+            (app.wrap, app.detail_wrap),
+            texts,
+        )
+    }
+
+    fn add(pattern: &str) -> Action {
+        Action::AddPattern {
+            pattern: pattern.to_string(),
+            case_sensitive: true,
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_revert_and_reapply_actions() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::from(vec![LogLine::output("a".to_string())]);
+
+        for action in [add("foo"), add("bar"), Action::ToggleCase { index: 0 }] {
+            apply(&mut app, &mut lines, action).expect("apply failed");
+        }
+        apply(&mut app, &mut lines, Action::ToggleExclude { index: 0 }).expect("apply failed");
+        assert!(app.patterns[0].exclude);
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert!(!app.patterns[0].exclude);
+        apply(&mut app, &mut lines, Action::RemovePattern { index: 1 }).expect("apply failed");
+        apply(&mut app, &mut lines, Action::ClearLines).expect("apply failed");
+        assert_eq!(lines.len(), 0);
+
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(lines[0].text, "a");
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(app.patterns[1].pattern, "bar");
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert!(app.patterns[0].case_sensitive);
+        assert!(!app.patterns[0].regex.is_match("FOO"));
+
+        apply(&mut app, &mut lines, Action::Redo).expect("redo failed");
+        assert!(!app.patterns[0].case_sensitive);
+        assert!(app.patterns[0].regex.is_match("FOO"));
+
+        apply(&mut app, &mut lines, Action::CycleJsonView).expect("apply failed");
+        assert_eq!(app.json_view, JsonView::Flat);
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(app.json_view, JsonView::Colored);
+
+        let wrap = Action::ToggleWrap {
+            viewport: Viewport::Main,
+        };
+        apply(&mut app, &mut lines, wrap).expect("apply failed");
+        apply(&mut app, &mut lines, Action::Redo).expect("redo failed");
+        assert_eq!(app.patterns.len(), 2, "a new action drops the redo stack");
+    }
+
+    #[test]
+    fn replaying_the_journal_reproduces_the_session() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::from(vec![LogLine::output("a".to_string())]);
+        for action in [
+            add("foo"),
+            Action::ToggleFilter,
+            add("bar"),
+            Action::Undo,
+            Action::ClearLines,
+            Action::Undo,
+            Action::Redo,
+            Action::ToggleWrap {
+                viewport: Viewport::Detail,
+            },
+            Action::RemovePattern { index: 5 },
+        ] {
+            apply(&mut app, &mut lines, action).expect("apply failed");
+        }
+
+        let mut replayed = AppState::new(Vec::new(), false);
+        let mut replayed_lines = LogBuffer::from(vec![LogLine::output("a".to_string())]);
+        for action in app.history.journal().clone() {
+            apply(&mut replayed, &mut replayed_lines, action).expect("replay failed");
+        }
+
+        assert_eq!(app.history.journal().len(), 9);
+        assert_eq!(state(&replayed, &replayed_lines), state(&app, &lines));
+    }
+
+    #[test]
+    fn the_oldest_actions_and_clears_are_forgotten_beyond_the_limits() {
+        let mut history = History::default();
+        for _ in 0..MAX_UNDO + 5 {
+            history.remember(Action::ToggleFilter, Revert::ToggleFilter);
+            history.record(Action::ToggleFilter);
+        }
+        assert_eq!(history.done.len(), MAX_UNDO);
+
+        let clear = || Revert::RestoreLines {
+            lines: VecDeque::new(),
+            bytes: MAX_RESTORE_BYTES / 2 + 1,
+            evicted: 0,
+        };
+        history.remember(Action::ClearLines, clear());
+        history.remember(
+            Action::ToggleWrap {
+                viewport: Viewport::Main,
+            },
+            Revert::ToggleFilter,
+        );
+        history.remember(Action::ClearLines, clear());
+        let kept: Vec<&Action> = history.done.iter().map(|(action, _)| action).collect();
+        assert_eq!(
+            kept,
+            [
+                &Action::ToggleWrap {
+                    viewport: Viewport::Main
+                },
+                &Action::ClearLines
+            ]
+        );
+
+        for _ in 0..MAX_JOURNAL {
+            history.record(Action::ToggleLineNumbers);
+        }
+        assert_eq!(history.journal().len(), MAX_JOURNAL);
+        assert!(
+            history
+                .journal()
+                .iter()
+                .all(|action| *action == Action::ToggleLineNumbers)
+        );
+    }
+
+    #[test]
+    fn pattern_changes_refresh_cached_matches() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::default();
+        push_line(&mut app, &mut lines, LogLine::output("error".to_string()));
+        let matched = |lines: &LogBuffer| lines[0].matches.as_ref().map(Vec::len);
+        assert_eq!(matched(&lines), Some(0));
+
+        apply(&mut app, &mut lines, add("err")).expect("apply failed");
+        assert_eq!(matched(&lines), Some(1));
+        apply(&mut app, &mut lines, Action::ToggleEnabled { index: 0 }).expect("apply failed");
+        assert_eq!(matched(&lines), Some(0));
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(matched(&lines), Some(1));
+
+        apply(&mut app, &mut lines, Action::Snooze { index: 0 }).expect("apply failed");
+        assert_eq!(matched(&lines), Some(0));
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(matched(&lines), Some(1));
+        assert!(app.patterns[0].snooze.is_none());
+    }
+
+    #[test]
+    fn profiles_replace_the_patterns_as_a_whole() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::default();
+        apply(&mut app, &mut lines, add("foo")).expect("apply failed");
+        let profile = |patterns: &[&str]| Profile {
+            patterns: patterns
+                .iter()
+                .map(|pattern| ProfilePattern::Text(pattern.to_string()))
+                .collect(),
+            exclude: vec!["health".to_string()],
+            ..Profile::default()
+        };
+        app.profiles
+            .insert("nginx".to_string(), profile(&["5\\d\\d", "timed out"]));
+        app.profiles
+            .insert("broken".to_string(), profile(&["ok", "("]));
+        let use_profile = |name: &str| Action::UseProfile {
+            name: name.to_string(),
+        };
+
+        apply(&mut app, &mut lines, use_profile("nginx")).expect("apply failed");
+        let patterns = state(&app, &lines).0;
+        assert_eq!(
+            patterns
+                .iter()
+                .map(|(pattern, _)| pattern)
+                .collect::<Vec<_>>(),
+            ["5\\d\\d", "timed out", "health"]
+        );
+        assert!(app.patterns[2].exclude);
+
+        assert!(apply(&mut app, &mut lines, use_profile("broken")).is_err());
+        assert_eq!(app.patterns.len(), 3);
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(state(&app, &lines).0, [("foo".to_string(), true)]);
+    }
+
+    #[test]
+    fn reloading_replaces_the_patterns_of_the_file_where_they_are() {
+        let path = std::env::temp_dir().join(format!("logr-reload-{}.txt", std::process::id()));
+        fs::write(&path, "one\ntwo\n").expect("write failed");
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::default();
+        apply(&mut app, &mut lines, add("before")).expect("apply failed");
+        app.patterns
+            .extend(pattern_file::load(&path, false).expect("load failed"));
+        apply(&mut app, &mut lines, add("after")).expect("apply failed");
+        app.patterns_file = Some(path.clone());
+        let texts = |app: &AppState| -> Vec<String> {
+            app.patterns
+                .iter()
+                .map(|pattern| pattern.pattern.clone())
+                .collect()
+        };
+
+        fs::write(&path, "three\n").expect("write failed");
+        apply(&mut app, &mut lines, Action::ReloadPatterns).expect("reload failed");
+        assert_eq!(texts(&app), ["before", "three", "after"]);
+        fs::write(&path, "(\n").expect("write failed");
+        assert!(apply(&mut app, &mut lines, Action::ReloadPatterns).is_err());
+        assert_eq!(texts(&app), ["before", "three", "after"]);
+
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(texts(&app), ["before", "one", "two", "after"]);
+        fs::remove_file(&path).expect("remove failed");
+    }
+
+    #[test]
+    fn toggling_the_filter_keeps_the_line_at_the_top_in_view() {
+        let pattern = build_pattern("ERROR".to_string(), true).expect("pattern build failed");
+        let mut app = AppState::new(vec![pattern], false);
+        let mut lines = LogBuffer::default();
+        for i in 0..20 {
+            let text = if i % 5 == 0 { "ERROR" } else { "ok" };
+            push_line(&mut app, &mut lines, LogLine::output(format!("{text} {i}")));
+        }
+        app.follow = false;
+        let top = |app: &AppState, lines: &LogBuffer| {
+            let index = visible_line_index(lines, app, app.scroll).expect("no line at the top");
+            lines[index].text.clone()
+        };
+
+        app.scroll = 10;
+        apply(&mut app, &mut lines, Action::ToggleFilter).expect("apply failed");
+        assert_eq!((app.scroll, top(&app, &lines)), (2, "ERROR 10".to_string()));
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(
+            (app.scroll, top(&app, &lines)),
+            (10, "ERROR 10".to_string())
+        );
+
+        app.scroll = 11;
+        apply(&mut app, &mut lines, Action::ToggleFilter).expect("apply failed");
+        assert_eq!(top(&app, &lines), "ERROR 15");
+        assert!(!app.follow);
+    }
+
+    #[test]
+    fn applied_actions_are_audited_with_what_they_are_about() {
+        let path = std::env::temp_dir().join(format!("logr-audit-{}.log", std::process::id()));
+        let file = fs::File::create(&path).expect("create failed");
+        let mut app = AppState::new(Vec::new(), false);
+        app.audit = Some(Audit::new(Box::new(file)));
+        let mut lines = LogBuffer::default();
+
+        apply(&mut app, &mut lines, add("ERROR")).expect("apply failed");
+        apply(&mut app, &mut lines, Action::ToggleEnabled { index: 0 }).expect("apply failed");
+        apply(&mut app, &mut lines, Action::Snooze { index: 0 }).expect("apply failed");
+        apply(&mut app, &mut lines, Action::ToggleFilter).expect("apply failed");
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+
+        let audited = fs::read_to_string(&path).expect("read failed");
+        let entries: Vec<&str> = audited
+            .lines()
+            .map(|line| line.split_once(' ').expect("no timestamp").1)
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                r#"Added pattern "ERROR""#,
+                r#"Toggled pattern "ERROR" on or off"#,
+                r#"Snoozed pattern "ERROR" for 5 minutes"#,
+                "Toggled the filter",
+                r#"Undid "Toggled the filter""#,
+                r#"Undid "Changed the snooze of pattern #1""#,
+            ]
+        );
+        fs::remove_file(&path).expect("remove failed");
+    }
+}
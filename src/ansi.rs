@@ -0,0 +1,95 @@
+use std::fmt::Write as _;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Line,
+};
+
+/// The text of a line with ANSI escape codes for the styles of its spans, for printing it
+/// outside the TUI.
+pub(crate) fn line_to_ansi(line: &Line) -> String {
+    let mut text = String::new();
+    for span in &line.spans {
+        let codes = sgr_codes(line.style.patch(span.style));
+        if codes.is_empty() {
+            text.push_str(&span.content);
+        } else {
+            let _ = write!(text, "\x1b[{}m{}\x1b[0m", codes.join(";"), span.content);
+        }
+    }
+    text
+}
+
+fn sgr_codes(style: Style) -> Vec<String> {
+    let mut codes: Vec<String> = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ]
+    .into_iter()
+    .filter(|(modifier, _)| style.add_modifier.contains(*modifier))
+    .map(|(_, code)| code.to_string())
+    .collect();
+    codes.extend(style.fg.and_then(|color| color_code(color, false)));
+    codes.extend(style.bg.and_then(|color| color_code(color, true)));
+    codes
+}
+
+fn color_code(color: Color, background: bool) -> Option<String> {
+    let offset = if background { 10 } else { 0 };
+    let code = match color {
+        Color::Reset => return None,
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::Gray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::LightYellow => 93,
+        Color::LightBlue => 94,
+        Color::LightMagenta => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+        Color::Rgb(r, g, b) => return Some(format!("{};2;{r};{g};{b}", 38 + offset)),
+        Color::Indexed(index) => return Some(format!("{};5;{index}", 38 + offset)),
+    };
+    Some((code + offset).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::line_to_ansi;
+    use ratatui::{
+        style::{Color, Modifier, Style},
+        text::{Line, Span},
+    };
+
+    #[test]
+    fn styled_spans_get_escape_codes_and_plain_ones_none() {
+        let line = Line::from(vec![
+            Span::raw("GET /a "),
+            Span::styled(
+                "error",
+                Style::default()
+                    .fg(Color::Red)
+                    .bg(Color::Rgb(1, 2, 3))
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]);
+        assert_eq!(
+            line_to_ansi(&line),
+            "GET /a \x1b[1;31;48;2;1;2;3merror\x1b[0m"
+        );
+
+        let dimmed = Line::from("preview").style(Style::default().add_modifier(Modifier::DIM));
+        assert_eq!(line_to_ansi(&dimmed), "\x1b[2mpreview\x1b[0m");
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    io::{self, Write},
+    time::SystemTime,
+};
+
+use crate::{AppState, parser::epoch_timestamp};
+
+/// The audit log kept with `--audit`: one line per interactive action, like a pattern added,
+/// the filter toggled or an export written, after the time it was taken, so what the operator
+/// did while viewing the logs can be attached to an incident record.
+pub(crate) struct Audit {
+    out: Box<dyn Write>,
+}
+
+impl Audit {
+    pub(crate) fn new(out: Box<dyn Write>) -> Self {
+        Audit { out }
+    }
+
+    /// Writes an entry through to the file, so none is lost if logr is killed.
+    fn write(&mut self, what: &str, at: SystemTime) -> io::Result<()> {
+        writeln!(self.out, "{}", entry(what, at))?;
+        self.out.flush()
+    }
+}
+
+/// An entry of the audit log, like `2024-05-01T12:00:00.000000Z Added pattern "error"`, its
+/// lines joined so each entry is a line.
+fn entry(what: &str, at: SystemTime) -> String {
+    let micros = at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| {
+            i64::try_from(since.as_micros()).unwrap_or(i64::MAX)
+        });
+    format!("{} {}", epoch_timestamp(micros), what.replace('\n', " "))
+}
+
+/// Records an action in the audit log, if one is kept. Recording stops at the first error,
+/// telling why in the status bar.
+pub(crate) fn record(app: &mut AppState, what: &str) {
+    let Some(audit) = &mut app.audit else {
+        return;
+    };
+    if let Err(err) = audit.write(what, SystemTime::now()) {
+        app.audit = None;
+        app.status = Some((app.messages.audit_stopped)(&err.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::entry;
+
+    #[test]
+    fn entries_are_single_lines_after_the_time() {
+        let at = SystemTime::UNIX_EPOCH + Duration::from_micros(1_714_564_800_000_250);
+        assert_eq!(
+            entry("Ran :write out.log\n(Wrote 3 lines)", at),
+            "2024-05-01T12:00:00.000250Z Ran :write out.log (Wrote 3 lines)"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    fs,
+    hint::black_box,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use encoding_rs::UTF_8;
+
+use crate::{
+    AppState, LogrError, PatternFileArgs, PatternSpec, buffer::LogBuffer, build_patterns,
+    line_matches_patterns, push_line, push_segment, reader::SegmentReader, ui::highlight_line,
+};
+
+struct Stage {
+    name: String,
+    elapsed: Duration,
+}
+
+struct Report {
+    lines: usize,
+    bytes: usize,
+    stages: Vec<Stage>,
+}
+
+pub(crate) async fn run(args: &PatternFileArgs) -> Result<(), LogrError> {
+    let patterns = build_patterns(&args.patterns, args.ignore_case)?;
+    let bytes = fs::read(&args.file)?;
+    let report = bench(&bytes, patterns).await?;
+    write_report(&report, &mut io::stdout().lock())?;
+    Ok(())
+}
+
+/// Runs the input through the same stages as the TUI: reading and buffering lines, matching
+/// them against the patterns (all of them, then each on its own) and highlighting them.
+async fn bench(bytes: &[u8], patterns: Vec<PatternSpec>) -> io::Result<Report> {
+    let mut app = AppState::new(patterns, false);
+    let mut lines = LogBuffer::new(None);
+    let mut stages = Vec::new();
+
+    let started = Instant::now();
+    let mut reader = SegmentReader::new(bytes, UTF_8);
+    while let Some(segment) = reader.next_segment().await? {
+        if let Some(line) = push_segment(&mut lines, None, segment) {
+            push_line(&mut app, &mut lines, line);
+        }
+    }
+    stages.push(Stage {
+        name: "ingest".to_string(),
+        elapsed: started.elapsed(),
+    });
+
+    let started = Instant::now();
+    for line in lines.iter() {
+        black_box(line_matches_patterns(&line.text, &app.patterns));
+    }
+    stages.push(Stage {
+        name: "match".to_string(),
+        elapsed: started.elapsed(),
+    });
+
+    for pattern in &app.patterns {
+        let started = Instant::now();
+        for line in lines.iter() {
+            black_box(pattern.regex.is_match(&line.text));
+        }
+        stages.push(Stage {
+            name: format!("  {}", pattern.pattern),
+            elapsed: started.elapsed(),
+        });
+    }
+
+    let started = Instant::now();
+    for line in lines.iter() {
+        black_box(highlight_line(&line.text, &app.patterns));
+    }
+    stages.push(Stage {
+        name: "highlight".to_string(),
+        elapsed: started.elapsed(),
+    });
+
+    Ok(Report {
+        lines: lines.len(),
+        bytes: bytes.len(),
+        stages,
+    })
+}
+
+fn write_report(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{} lines, {} bytes", report.lines, report.bytes)?;
+    for stage in &report.stages {
+        let elapsed = format!("{:.2?}", stage.elapsed);
+        let rate = report.lines as f64 / stage.elapsed.as_secs_f64();
+        writeln!(out, "{:<20} {elapsed:>12} {rate:>14.0} lines/s", stage.name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bench, write_report};
+    use crate::build_patterns;
+
+    #[tokio::test]
+    async fn bench_reports_every_stage() {
+        let patterns = build_patterns(&["error".to_string(), "warn".to_string()], false)
+            .expect("pattern build failed");
+        let input = "info start\nerror one\nwarn two\n".repeat(10);
+
+        let report = bench(input.as_bytes(), patterns)
+            .await
+            .expect("bench failed");
+        let mut out = Vec::new();
+        write_report(&report, &mut out).expect("write failed");
+        let out = String::from_utf8(out).expect("output is not UTF-8");
+
+        assert_eq!(report.lines, 30);
+        let names: Vec<&str> = report
+            .stages
+            .iter()
+            .map(|stage| stage.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["ingest", "match", "  error", "  warn", "highlight"]
+        );
+        assert!(out.starts_with(&format!("30 lines, {} bytes\n", input.len())));
+        assert_eq!(out.lines().count(), 6);
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::{VecDeque, vec_deque},
+    num::NonZeroUsize,
+    ops::Index,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{LineKind, LogLine};
+
+/// The buffered lines, optionally bounded to the most recent `max_lines` with older lines
+/// evicted as new ones arrive.
+pub(crate) struct LogBuffer {
+    lines: VecDeque<LogLine>,
+    max_lines: Option<NonZeroUsize>,
+    evicted: usize,
+    /// Estimated memory used by the lines, see [`estimated_size`].
+    bytes: usize,
+    /// The id of the next line pushed, see [`LogLine::id`].
+    next_id: u64,
+    /// Positions of the lines filtering by the patterns can show, see [`filterable`], counted
+    /// from the first line pushed so evicting lines doesn't move them. Kept as lines come and
+    /// go, and rebuilt with [`LogBuffer::reindex`] when their matches change.
+    filterable: VecDeque<usize>,
+    /// Changes whenever lines already buffered may have changed, see [`LogBuffer::generation`].
+    generation: u64,
+}
+
+/// Source of the buffers' generations, shared by all of them so no two ever have the same one.
+static GENERATIONS: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    GENERATIONS.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Whether filtering by the patterns can show a line: one that isn't output, like a run
+/// separator, or that a pattern matches without excluding it, or that wasn't matched yet.
+fn filterable(line: &LogLine) -> bool {
+    line.kind != LineKind::Output
+        || line
+            .matches
+            .as_ref()
+            .is_none_or(|matches| matches.iter().any(|found| !found.exclude))
+}
+
+/// Rough memory footprint of a line: the struct itself and its text. Good enough to warn
+/// about a buffer growing large, not an exact account.
+fn estimated_size(line: &LogLine) -> usize {
+    size_of::<LogLine>() + line.text.len()
+}
+
+impl LogBuffer {
+    pub(crate) fn new(max_lines: Option<NonZeroUsize>) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            max_lines,
+            evicted: 0,
+            bytes: 0,
+            next_id: 0,
+            filterable: VecDeque::new(),
+            generation: next_generation(),
+        }
+    }
+
+    /// Appends a line, returning the oldest line if it had to be evicted to make room.
+    pub(crate) fn push(&mut self, mut line: LogLine) -> Option<LogLine> {
+        line.id = self.next_id;
+        self.next_id += 1;
+        self.bytes += estimated_size(&line);
+        if filterable(&line) {
+            self.filterable.push_back(self.evicted + self.lines.len());
+        }
+        self.lines.push_back(line);
+        match self.max_lines {
+            Some(max_lines) if self.lines.len() > max_lines.get() => self.evict(),
+            _ => None,
+        }
+    }
+
+    fn evict(&mut self) -> Option<LogLine> {
+        let line = self.lines.pop_front()?;
+        if self.filterable.front() == Some(&self.evicted) {
+            self.filterable.pop_front();
+        }
+        self.evicted += 1;
+        self.bytes = self.bytes.saturating_sub(estimated_size(&line));
+        if line.continuations > 0
+            && let Some(next) = self.lines.front_mut()
+        {
+            next.promote(&line);
+        }
+        Some(line)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.lines.clear();
+        self.evicted = 0;
+        self.bytes = 0;
+        self.filterable.clear();
+        self.generation = next_generation();
+    }
+
+    /// Removes all lines, returning them. Like [`LogBuffer::clear`], starts the eviction count
+    /// over, but keeps giving lines new ids.
+    pub(crate) fn take(&mut self) -> VecDeque<LogLine> {
+        self.evicted = 0;
+        self.bytes = 0;
+        self.filterable.clear();
+        self.generation = next_generation();
+        std::mem::take(&mut self.lines)
+    }
+
+    /// Puts previously taken lines back in front of the current ones, along with the number of
+    /// lines evicted before they were taken, evicting the oldest lines beyond the limit.
+    pub(crate) fn restore(&mut self, mut lines: VecDeque<LogLine>, evicted: usize) {
+        self.evicted += evicted;
+        self.bytes += lines.iter().map(estimated_size).sum::<usize>();
+        lines.append(&mut self.lines);
+        self.lines = lines;
+        self.reindex();
+        self.evict_beyond_limit();
+    }
+
+    /// Rebuilds the positions of the lines filtering can show, after their matches changed.
+    pub(crate) fn reindex(&mut self) {
+        self.generation = next_generation();
+        self.filterable = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| filterable(line))
+            .map(|(index, _)| self.evicted + index)
+            .collect();
+    }
+
+    /// Updates whether filtering can show the last line, after its matches changed.
+    pub(crate) fn reindex_last(&mut self) {
+        let Some(last) = self.lines.len().checked_sub(1) else {
+            return;
+        };
+        let position = self.evicted + last;
+        if self.filterable.back() == Some(&position) {
+            self.filterable.pop_back();
+        }
+        if filterable(&self.lines[last]) {
+            self.filterable.push_back(position);
+        }
+    }
+
+    /// Index of the line with this id or, if it is gone, of the first line after it. Restored
+    /// lines keep their older ids, so ids increase in buffer order.
+    pub(crate) fn index_from(&self, id: u64) -> usize {
+        self.lines.partition_point(|line| line.id < id)
+    }
+
+    /// Index of the line with this id, unless it is gone.
+    pub(crate) fn position(&self, id: u64) -> Option<usize> {
+        let index = self.index_from(id);
+        self.lines
+            .get(index)
+            .is_some_and(|line| line.id == id)
+            .then_some(index)
+    }
+
+    /// Indices of the lines filtering by the patterns can show, oldest first. The others are
+    /// hidden while filtering, so only these need to be looked at.
+    pub(crate) fn filterable(&self) -> impl Iterator<Item = usize> + '_ {
+        self.filterable
+            .iter()
+            .map(|position| position - self.evicted)
+    }
+
+    /// Bounds the buffer to the most recent `max_lines` from now on, evicting older lines.
+    pub(crate) fn set_max_lines(&mut self, max_lines: NonZeroUsize) {
+        self.max_lines = Some(max_lines);
+        self.evict_beyond_limit();
+    }
+
+    fn evict_beyond_limit(&mut self) {
+        if let Some(max_lines) = self.max_lines {
+            while self.lines.len() > max_lines.get() {
+                self.evict();
+            }
+        }
+    }
+
+    pub(crate) fn is_bounded(&self) -> bool {
+        self.max_lines.is_some()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&LogLine> {
+        self.lines.get(index)
+    }
+
+    /// A line to count continuations of its entry on. Changing whether it is shown goes through
+    /// [`LogBuffer::iter_mut`] instead.
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut LogLine> {
+        self.lines.get_mut(index)
+    }
+
+    /// The last line, to update while it is still coming in.
+    pub(crate) fn last_mut(&mut self) -> Option<&mut LogLine> {
+        self.lines.back_mut()
+    }
+
+    pub(crate) fn iter(&self) -> vec_deque::Iter<'_, LogLine> {
+        self.lines.iter()
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> vec_deque::IterMut<'_, LogLine> {
+        self.generation = next_generation();
+        self.lines.iter_mut()
+    }
+
+    /// Number of lines evicted since the buffer was created (or last cleared).
+    pub(crate) fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// Estimated memory used by the buffered lines, in bytes.
+    pub(crate) fn estimated_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Changes whenever lines already buffered may have changed other than by lines being
+    /// pushed and evicted, or the last line updated: when lines are changed all at once, or
+    /// cleared, taken and restored.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Index<usize> for LogBuffer {
+    type Output = LogLine;
+
+    fn index(&self, index: usize) -> &LogLine {
+        &self.lines[index]
+    }
+}
+
+impl From<Vec<LogLine>> for LogBuffer {
+    fn from(lines: Vec<LogLine>) -> Self {
+        let mut buffer = Self {
+            bytes: lines.iter().map(estimated_size).sum(),
+            next_id: lines.len() as u64,
+            lines: lines
+                .into_iter()
+                .zip(0..)
+                .map(|(line, id)| LogLine { id, ..line })
+                .collect(),
+            max_lines: None,
+            evicted: 0,
+            filterable: VecDeque::new(),
+            generation: 0,
+        };
+        buffer.reindex();
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LogBuffer, estimated_size};
+    use crate::{LogLine, PatternMatch};
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn push_evicts_oldest_lines_beyond_limit() {
+        let mut buffer = LogBuffer::new(NonZeroUsize::new(2));
+
+        assert!(buffer.push(LogLine::output("a".to_string())).is_none());
+        assert!(buffer.push(LogLine::output("b".to_string())).is_none());
+        let evicted = buffer.push(LogLine::output("c".to_string()));
+
+        assert_eq!(evicted.map(|line| line.text), Some("a".to_string()));
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.evicted(), 1);
+        assert_eq!(buffer[0].text, "b");
+        assert_eq!(buffer[1].text, "c");
+    }
+
+    #[test]
+    fn restore_puts_taken_lines_before_new_ones() {
+        let mut buffer = LogBuffer::new(NonZeroUsize::new(3));
+        for text in ["a", "b", "c", "d"] {
+            buffer.push(LogLine::output(text.to_string()));
+        }
+
+        let evicted = buffer.evicted();
+        let taken = buffer.take();
+        assert_eq!(buffer.evicted(), 0);
+        buffer.push(LogLine::output("e".to_string()));
+        buffer.restore(taken, evicted);
+
+        let texts: Vec<&str> = buffer.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, vec!["c", "d", "e"]);
+        assert_eq!(buffer.evicted(), 2);
+    }
+
+    #[test]
+    fn filterable_lines_are_indexed_as_lines_come_and_go() {
+        let matched = |text: &str, exclude| {
+            let mut line = LogLine::output(text.to_string());
+            line.matches = Some(vec![PatternMatch {
+                start: 0,
+                end: 1,
+                pattern: 0,
+                exclude,
+            }]);
+            line
+        };
+        let unmatched = |text: &str| {
+            let mut line = LogLine::output(text.to_string());
+            line.matches = Some(Vec::new());
+            line
+        };
+        let mut buffer = LogBuffer::new(NonZeroUsize::new(4));
+        buffer.push(matched("a", false));
+        buffer.push(unmatched("b"));
+        buffer.push(LogLine::run_separator("--- run 2 ---".to_string()));
+        buffer.push(matched("c", true));
+        buffer.push(matched("d", false));
+        assert_eq!(buffer.filterable().collect::<Vec<_>>(), [1, 3]);
+
+        if let Some(last) = buffer.last_mut() {
+            last.matches = Some(Vec::new());
+        }
+        buffer.reindex_last();
+        assert_eq!(buffer.filterable().collect::<Vec<_>>(), [1]);
+
+        let taken = buffer.take();
+        buffer.push(matched("e", false));
+        buffer.restore(taken, 0);
+        assert_eq!(buffer.filterable().collect::<Vec<_>>(), [0, 3]);
+    }
+
+    #[test]
+    fn unbounded_buffer_never_evicts() {
+        let mut buffer = LogBuffer::new(None);
+        for i in 0..1000 {
+            assert!(buffer.push(LogLine::output(i.to_string())).is_none());
+        }
+        assert_eq!(buffer.len(), 1000);
+        assert_eq!(buffer.evicted(), 0);
+    }
+
+    #[test]
+    fn bounding_evicts_oldest_lines_and_their_size() {
+        let mut buffer = LogBuffer::new(None);
+        for text in ["a", "bb", "ccc"] {
+            buffer.push(LogLine::output(text.to_string()));
+        }
+        let size = |text: &str| estimated_size(&LogLine::output(text.to_string()));
+        assert!(!buffer.is_bounded());
+        assert_eq!(
+            buffer.estimated_bytes(),
+            size("a") + size("bb") + size("ccc")
+        );
+
+        buffer.set_max_lines(NonZeroUsize::new(2).expect("non-zero"));
+        buffer.push(LogLine::output("dddd".to_string()));
+
+        let texts: Vec<&str> = buffer.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, vec!["ccc", "dddd"]);
+        assert!(buffer.is_bounded());
+        assert_eq!(buffer.evicted(), 2);
+        assert_eq!(buffer.estimated_bytes(), size("ccc") + size("dddd"));
+    }
+}
@@ -0,0 +1,178 @@
+use std::{collections::VecDeque, ops::ControlFlow};
+
+use crossterm::event::KeyEvent;
+
+use crate::{AppState, InputEvent, LogrError, buffer::LogBuffer, enrich::Resolved, share::Change};
+
+/// Everything the main loop reacts to, published by producers (the terminal, the input
+/// source, the file watcher, the timers) and dispatched to the subsystems consuming them.
+pub(crate) enum AppEvent {
+    /// A key pressed in the terminal.
+    Key(KeyEvent),
+    /// Output of the input source (stdin, a followed file or the command) or a run finishing.
+    Input(InputEvent),
+    /// The user asked for the command to be re-run.
+    RerunRequested,
+    /// Files matching `--watch` changed.
+    FilesChanged,
+    /// Another instance sharing patterns with `--share` added or removed one.
+    Shared(Change),
+    /// A reverse lookup of an address in a line finished, with `--enrich`.
+    Resolved(Resolved),
+    /// The terminal was resized.
+    Resize,
+    /// A second passed while patterns are snoozed, to wake them up and count down.
+    SnoozeTick,
+    /// A second passed with `--idle`, to check whether the screen went idle.
+    IdleTick,
+    /// Time for the next bell of the alert being sounded.
+    BellTick,
+    /// The frame timer fired while the screen is out of date, time to draw the frame.
+    Tick,
+    /// Something on screen changed, so the frame is out of date.
+    Changed,
+    /// The user asked to quit.
+    Quit,
+}
+
+/// A subsystem consuming events: the reader, sharing, enrichment, the timers or the screen.
+pub(crate) trait Consumer {
+    /// Handles `event` if it is one the subsystem subscribes to, publishing on `bus` what
+    /// follows from it, or hands it back for the next consumer.
+    fn consume(
+        &mut self,
+        event: AppEvent,
+        app: &mut AppState,
+        lines: &mut LogBuffer,
+        bus: &mut EventBus,
+    ) -> Result<Option<AppEvent>, LogrError>;
+}
+
+/// Queue of events waiting to be dispatched, in the order they were published.
+#[derive(Default)]
+pub(crate) struct EventBus {
+    events: VecDeque<AppEvent>,
+}
+
+impl EventBus {
+    pub(crate) fn publish(&mut self, event: AppEvent) {
+        self.events.push_back(event);
+    }
+
+    /// Takes the oldest pending event. Events published while handling it are queued behind
+    /// the already pending ones.
+    pub(crate) fn pop(&mut self) -> Option<AppEvent> {
+        self.events.pop_front()
+    }
+
+    /// Hands each pending event, and those published while handling them, to the consumers in
+    /// turn until one takes it. Breaks on [`AppEvent::Quit`].
+    pub(crate) fn dispatch(
+        &mut self,
+        consumers: &mut [&mut dyn Consumer],
+        app: &mut AppState,
+        lines: &mut LogBuffer,
+    ) -> Result<ControlFlow<()>, LogrError> {
+        while let Some(event) = self.pop() {
+            if matches!(event, AppEvent::Quit) {
+                return Ok(ControlFlow::Break(()));
+            }
+            let mut event = Some(event);
+            for consumer in consumers.iter_mut() {
+                let Some(pending) = event.take() else {
+                    break;
+                };
+                event = consumer.consume(pending, app, lines, self)?;
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::{AppEvent, Consumer, EventBus};
+    use crate::{AppState, LogrError, buffer::LogBuffer};
+
+    #[test]
+    fn events_are_dispatched_in_publish_order() {
+        let mut bus = EventBus::default();
+        bus.publish(AppEvent::FilesChanged);
+        bus.publish(AppEvent::Tick);
+        assert!(matches!(bus.pop(), Some(AppEvent::FilesChanged)));
+
+        bus.publish(AppEvent::RerunRequested);
+        assert!(matches!(bus.pop(), Some(AppEvent::Tick)));
+        assert!(matches!(bus.pop(), Some(AppEvent::RerunRequested)));
+        assert!(bus.pop().is_none());
+    }
+
+    /// Draws on ticks, asking for a tick whenever something changed.
+    #[derive(Default)]
+    struct Screen {
+        drawn: usize,
+    }
+
+    impl Consumer for Screen {
+        fn consume(
+            &mut self,
+            event: AppEvent,
+            _app: &mut AppState,
+            _lines: &mut LogBuffer,
+            bus: &mut EventBus,
+        ) -> Result<Option<AppEvent>, LogrError> {
+            match event {
+                AppEvent::Changed => bus.publish(AppEvent::Tick),
+                AppEvent::Tick => self.drawn += 1,
+                event => return Ok(Some(event)),
+            }
+            Ok(None)
+        }
+    }
+
+    /// Takes the files changing, telling that something changed.
+    struct Watcher;
+
+    impl Consumer for Watcher {
+        fn consume(
+            &mut self,
+            event: AppEvent,
+            _app: &mut AppState,
+            _lines: &mut LogBuffer,
+            bus: &mut EventBus,
+        ) -> Result<Option<AppEvent>, LogrError> {
+            match event {
+                AppEvent::FilesChanged => bus.publish(AppEvent::Changed),
+                event => return Ok(Some(event)),
+            }
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn events_go_to_the_consumer_subscribing_to_them() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::new(None);
+        let (mut watcher, mut screen) = (Watcher, Screen::default());
+        let mut bus = EventBus::default();
+
+        bus.publish(AppEvent::FilesChanged);
+        bus.publish(AppEvent::Resize);
+        let flow = bus
+            .dispatch(&mut [&mut watcher, &mut screen], &mut app, &mut lines)
+            .expect("dispatch failed");
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert_eq!(screen.drawn, 1);
+        assert!(bus.pop().is_none());
+
+        bus.publish(AppEvent::Quit);
+        bus.publish(AppEvent::Tick);
+        let flow = bus
+            .dispatch(&mut [&mut watcher, &mut screen], &mut app, &mut lines)
+            .expect("dispatch failed");
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(screen.drawn, 1);
+    }
+}
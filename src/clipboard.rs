@@ -0,0 +1,50 @@
+use std::io::{self, Write};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Puts text on the system clipboard with an OSC 52 escape sequence, which the terminal (even
+/// one at the other end of an SSH session) picks up. Terminals that don't support it ignore
+/// it, and tmux needs `set-clipboard on` to pass it on.
+pub(crate) fn copy(text: &str) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    out.write_all(osc52(text).as_bytes())?;
+    out.flush()
+}
+
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (u32::from(byte) << (16 - 8 * i))
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(
+                    BASE64[((group >> (18 - 6 * i)) & 0x3f) as usize],
+                ));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, osc52};
+
+    #[test]
+    fn osc52_carries_the_text_base64_encoded() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64("logr ✓\n".as_bytes()), "bG9nciDinJMK");
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+    }
+}
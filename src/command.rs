@@ -0,0 +1,226 @@
+use std::{
+    io,
+    process::{ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+
+use encoding_rs::Encoding;
+use tokio::{
+    process::{Child, Command},
+    sync::mpsc,
+};
+
+use crate::{
+    InputEvent, LineKind, LogLine, PatternSpec,
+    buffer::LogBuffer,
+    reader::{CHANNEL_CAPACITY, Segment, forward_segments},
+};
+
+pub(crate) struct CommandRunner {
+    command: Vec<String>,
+    encoding: &'static Encoding,
+    child: Option<Child>,
+    output: mpsc::Receiver<Segment>,
+    run: usize,
+    started: Instant,
+    lines: usize,
+    finished: bool,
+}
+
+pub(crate) struct RunExit {
+    run: usize,
+    status: Option<ExitStatus>,
+    duration: Duration,
+    lines: usize,
+}
+
+pub(crate) struct RunSummary {
+    pub(crate) text: String,
+    pub(crate) success: bool,
+}
+
+impl RunExit {
+    pub(crate) fn success(&self) -> bool {
+        self.status.is_some_and(|status| status.success())
+    }
+
+    /// Summarizes the run, counting matches per pattern over the lines emitted since the
+    /// latest run separator.
+    pub(crate) fn summary(&self, lines: &LogBuffer, patterns: &[PatternSpec]) -> RunSummary {
+        let run_lines: Vec<&LogLine> = lines
+            .iter()
+            .rev()
+            .take_while(|line| line.kind != LineKind::RunSeparator)
+            .collect();
+
+        let status = match self.status.map(|status| status.code()) {
+            Some(Some(code)) => format!("exited with code {code}"),
+            Some(None) => "terminated by signal".to_string(),
+            None => "exit status unknown".to_string(),
+        };
+        let mut text = format!(
+            "--- run {} {status} in {:.1?}, {} lines",
+            self.run, self.duration, self.lines
+        );
+        for pattern in patterns {
+            let count = run_lines
+                .iter()
+                .filter(|line| line.kind == LineKind::Output && pattern.regex.is_match(&line.text))
+                .count();
+            text.push_str(&format!(", {}: {count}", pattern.pattern));
+        }
+        text.push_str(" ---");
+
+        RunSummary {
+            text,
+            success: self.success(),
+        }
+    }
+}
+
+impl CommandRunner {
+    pub(crate) fn spawn(
+        command: Vec<String>,
+        encoding: &'static Encoding,
+    ) -> Result<Self, io::Error> {
+        let (child, output) = spawn_child(&command, encoding)?;
+        Ok(Self {
+            command,
+            encoding,
+            child: Some(child),
+            output,
+            run: 1,
+            started: Instant::now(),
+            lines: 0,
+            finished: false,
+        })
+    }
+
+    /// Kills the current child (if still running) and starts the command again.
+    pub(crate) fn rerun(&mut self) -> Result<(), io::Error> {
+        if let Some(mut child) = self.child.take() {
+            // The child may have already exited, in which case there is nothing to kill.
+            let _ = child.start_kill();
+        }
+        let (child, output) = spawn_child(&self.command, self.encoding)?;
+        self.child = Some(child);
+        self.output = output;
+        self.run += 1;
+        self.started = Instant::now();
+        self.lines = 0;
+        self.finished = false;
+        Ok(())
+    }
+
+    pub(crate) fn separator(&self) -> LogLine {
+        LogLine::run_separator(format!(
+            "--- run {}: {} ---",
+            self.run,
+            self.command.join(" ")
+        ))
+    }
+
+    /// Returns the next line of output, followed by a single exit event once the current run's
+    /// output is exhausted. After that it never resolves until the command is re-run.
+    pub(crate) async fn next_event(&mut self) -> Option<InputEvent> {
+        if self.finished {
+            return std::future::pending().await;
+        }
+        if let Some(segment) = self.output.recv().await {
+            return Some(self.segment_event(segment));
+        }
+
+        // Only mark the run finished once the status is known, so a timeout cancelling the wait
+        // doesn't lose the exit event.
+        let status = match &mut self.child {
+            Some(child) => child.wait().await.ok(),
+            None => None,
+        };
+        self.finished = true;
+        Some(InputEvent::RunExited(RunExit {
+            run: self.run,
+            status,
+            duration: self.started.elapsed(),
+            lines: self.lines,
+        }))
+    }
+
+    /// Returns the next already received line of output without waiting. The exit event is
+    /// only ever returned by `next_event`.
+    pub(crate) fn try_next_event(&mut self) -> Option<InputEvent> {
+        if self.finished {
+            return None;
+        }
+        let segment = self.output.try_recv().ok()?;
+        Some(self.segment_event(segment))
+    }
+
+    fn segment_event(&mut self, segment: Segment) -> InputEvent {
+        if !matches!(segment, Segment::Progress(_)) {
+            self.lines += 1;
+        }
+        InputEvent::Segment(segment)
+    }
+}
+
+fn spawn_child(
+    command: &[String],
+    encoding: &'static Encoding,
+) -> Result<(Child, mpsc::Receiver<Segment>), io::Error> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_segments(stdout, encoding, tx.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_segments(stderr, encoding, tx));
+    }
+
+    Ok((child, rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunExit;
+    use crate::{LogLine, buffer::LogBuffer, build_pattern};
+    use std::time::Duration;
+
+    #[test]
+    fn summary_counts_matches_in_latest_run_only() {
+        let patterns = vec![
+            build_pattern("FAILED".to_string(), true).expect("pattern build failed"),
+            build_pattern("ok".to_string(), true).expect("pattern build failed"),
+        ];
+        let lines = LogBuffer::from(vec![
+            LogLine::run_separator("--- run 1 ---".to_string()),
+            LogLine::output("test a ... FAILED".to_string()),
+            LogLine::run_separator("--- run 2 ---".to_string()),
+            LogLine::output("test a ... ok".to_string()),
+            LogLine::output("test b ... ok".to_string()),
+        ]);
+        let exit = RunExit {
+            run: 2,
+            status: None,
+            duration: Duration::from_millis(1500),
+            lines: 2,
+        };
+
+        let summary = exit.summary(&lines, &patterns);
+
+        assert!(!summary.success);
+        assert_eq!(
+            summary.text,
+            "--- run 2 exit status unknown in 1.5s, 2 lines, FAILED: 0, ok: 2 ---"
+        );
+    }
+}
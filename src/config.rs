@@ -0,0 +1,221 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    LogrError, PatternSpec, add_alerts, build_filters, build_text, keys::KeyBindings,
+    pattern_spec::PatternDef, redact::Redaction, rewrite::Rewrite, sound::Sound,
+};
+
+/// The config file, e.g.
+///
+/// ```toml
+/// redactions = ["tokens", "emails", 'session=(\w+)']
+///
+/// [profiles.nginx]
+/// patterns = ['" 5\d\d ', "upstream timed out", { pattern = "crit", scope = "line" }]
+/// exclude = ["GET /health"]
+/// alerts = { "upstream timed out" = "2" }
+/// rewrites = ['s/(\d{13})/<ts:$1>/']
+///
+/// [keys]
+/// quit = ["Esc", "Ctrl-c"]
+/// ```
+#[derive(Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    pub(crate) profiles: BTreeMap<String, Profile>,
+    /// Rules masking secrets in lines, like those of `--redact`, whatever the profile.
+    pub(crate) redactions: Vec<Redaction>,
+    /// Keys of the main view by the name of the command they are bound to.
+    keys: BTreeMap<String, Vec<String>>,
+    #[serde(skip)]
+    pub(crate) bindings: KeyBindings,
+}
+
+/// A named set of patterns, used instead of the current ones when picked.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Profile {
+    pub(crate) patterns: Vec<ProfilePattern>,
+    pub(crate) exclude: Vec<String>,
+    /// Sounds of the patterns, like those of `--alert`.
+    pub(crate) alerts: BTreeMap<String, Sound>,
+    /// Rules rewriting lines as shown, like those of `--rewrite`, used when the profile is
+    /// picked with `--profile`.
+    pub(crate) rewrites: Vec<Rewrite>,
+}
+
+/// A pattern of a profile: given like with `--patterns`, or as a table with all its settings.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub(crate) enum ProfilePattern {
+    Text(String),
+    Table(PatternDef),
+}
+
+/// Shown in the profiles popup like the patterns dialog shows them, excluding ones after a `!`.
+impl fmt::Display for ProfilePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfilePattern::Text(text) => f.write_str(text),
+            ProfilePattern::Table(table) => table.fmt(f),
+        }
+    }
+}
+
+impl Profile {
+    pub(crate) fn build(&self, ignore_case: bool) -> Result<Vec<PatternSpec>, LogrError> {
+        let mut patterns = self
+            .patterns
+            .iter()
+            .map(|pattern| match pattern {
+                ProfilePattern::Text(text) => build_text(text, ignore_case, false),
+                ProfilePattern::Table(table) => table.clone().build(ignore_case),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        patterns.extend(build_filters(&[], &self.exclude, ignore_case, false)?);
+        let alerts: Vec<(String, Sound)> = self.alerts.clone().into_iter().collect();
+        add_alerts(&mut patterns, &alerts, ignore_case, false)?;
+        Ok(patterns)
+    }
+}
+
+impl Config {
+    /// Reads the config from `path`, or from the default location if not given, where it
+    /// doesn't have to exist.
+    pub(crate) fn load(path: Option<&Path>) -> Result<Self, LogrError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Config::default());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Self::parse(&text)
+            .map_err(|err| LogrError::ConfigError(format!("{}: {err}", path.display())))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config: Config = toml::from_str(text).map_err(|err| err.to_string())?;
+        config.bindings = KeyBindings::new(&config.keys)?;
+        Ok(config)
+    }
+
+    pub(crate) fn profile(&self, name: &str) -> Result<&Profile, LogrError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| LogrError::ProfileError(name.to_string()))
+    }
+}
+
+/// `logr/config.toml` in `$XDG_CONFIG_HOME`, or in `~/.config` when that isn't set.
+fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("logr").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+
+    use super::{Config, Profile, ProfilePattern};
+    use crate::sound::Sound;
+
+    #[test]
+    fn profiles_are_read_by_name() {
+        let config = Config::parse(
+            r#"
+            [profiles.nginx]
+            patterns = ['" 5\d\d ', "upstream timed out"]
+            exclude = ["GET /health"]
+
+            [profiles.java]
+            patterns = ["Exception"]
+            "#,
+        )
+        .expect("parse failed");
+
+        assert_eq!(
+            config.profile("java").expect("no profile"),
+            &Profile {
+                patterns: vec![ProfilePattern::Text("Exception".to_string())],
+                exclude: Vec::new(),
+                ..Profile::default()
+            }
+        );
+        let nginx = config.profile("nginx").expect("no profile");
+        let patterns = nginx.build(false).expect("build failed");
+        assert_eq!(patterns.len(), 3);
+        assert!(patterns[2].exclude);
+        let config = Config::parse(
+            r#"
+            [profiles.disk]
+            patterns = [{ pattern = "disk full", literal = true, scope = "line" }, "ERROR:red"]
+            "#,
+        )
+        .expect("parse failed");
+        let patterns = config
+            .profile("disk")
+            .expect("no profile")
+            .build(true)
+            .expect("build failed");
+        assert!(patterns[0].literal && patterns[0].whole_line && !patterns[0].case_sensitive);
+        assert_eq!(patterns[1].color, Some(Color::Red));
+        assert!(config.profile("rails").is_err());
+        assert!(Config::parse("[profiles.x]\npaterns = []").is_err());
+        assert!(Config::parse("[keys]\nquit = [\"Esc\"]").is_ok());
+        let config = Config::parse("redactions = [\"tokens\", 'id=(\\d+)']").expect("parse failed");
+        assert_eq!(config.redactions.len(), 2);
+        assert!(Config::parse("redactions = [\"(\"]").is_err());
+    }
+
+    #[test]
+    fn profile_alerts_give_patterns_their_sounds() {
+        let config = Config::parse(
+            r#"
+            [profiles.deploys]
+            patterns = ["ERROR"]
+            alerts = { ERROR = "2", "deploy finished" = "complete" }
+            "#,
+        )
+        .expect("parse failed");
+
+        let patterns = config
+            .profile("deploys")
+            .expect("no profile")
+            .build(false)
+            .expect("build failed");
+        let sounds: Vec<(&str, Option<&Sound>)> = patterns
+            .iter()
+            .map(|pattern| (pattern.pattern.as_str(), pattern.sound.as_ref()))
+            .collect();
+        assert_eq!(
+            sounds,
+            [
+                ("ERROR", Some(&Sound::Bells(2))),
+                (
+                    "deploy finished",
+                    Some(&Sound::Named("complete".to_string()))
+                ),
+            ]
+        );
+        assert!(Config::parse("[profiles.x]\nalerts = { ERROR = \"0\" }").is_err());
+        assert!(Config::parse("[keys]\nleave = [\"Esc\"]").is_err());
+        assert!(Config::parse("[profiles.x]\nrewrites = [\"s/a/b\"]").is_err());
+    }
+}
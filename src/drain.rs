@@ -0,0 +1,264 @@
+use std::{net::SocketAddr, sync::LazyLock, time::Duration};
+
+use regex::Regex;
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::mpsc,
+    time::timeout,
+};
+
+use crate::reader::{CHANNEL_CAPACITY, Segment, accept};
+
+/// The largest request body taken, well above the batches drains post.
+const MAX_BODY: usize = 1 << 20;
+
+/// The longest request or header line taken, headers of drains being a few short ones.
+const MAX_LINE: u64 = 8 << 10;
+
+/// The most headers a request may have.
+const MAX_HEADERS: usize = 64;
+
+/// How long a request may take to arrive, and an idle connection kept open for the next one.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An RFC 5424 syslog message, capturing its timestamp, app name, process id and text, e.g.
+/// `<190>1 2026-10-16T12:00:01.123456+00:00 host app web.1 - State changed from up to down`.
+/// Heroku's logplex leaves out the structured data, as in the example, while Cloud Foundry
+/// gives it.
+static SYSLOG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?s)^<\d{1,3}>1 (\S+) \S+ (\S+) (\S+) \S+ ",
+        r"(?:(?:-|(?:\[(?:[^\]\\]|\\.)*\])+)(?: |$))?(.*)$"
+    ))
+    .expect("syslog regex is valid")
+});
+
+/// Listens for the HTTP log drains of Heroku or Cloud Foundry on `addr`, taking the syslog
+/// messages they post as lines. Only plain HTTP is served: the drains post over HTTPS, which is
+/// left to a TLS-terminating proxy or tunnel in front of the address, as the README shows.
+pub(crate) async fn listen(addr: SocketAddr) -> io::Result<mpsc::Receiver<Segment>> {
+    let listener = TcpListener::bind(addr).await?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    accept(listener, move |stream| serve(stream, tx.clone()));
+    Ok(rx)
+}
+
+/// The head of a request, as far as telling its body goes.
+struct Head {
+    post: bool,
+    length: Option<usize>,
+    close: bool,
+}
+
+/// Answers the requests of a connection until it is closed.
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    tx: mpsc::Sender<Segment>,
+) -> io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    while let Some(head) = within(read_head(&mut stream)).await? {
+        let status = match (head.post, head.length) {
+            (false, _) => "405 Method Not Allowed",
+            (true, None) => "411 Length Required",
+            (true, Some(length)) if length > MAX_BODY => "413 Content Too Large",
+            (true, Some(length)) => {
+                let mut body = vec![0; length];
+                within(stream.read_exact(&mut body)).await?;
+                for message in messages(&String::from_utf8_lossy(&body)) {
+                    for line in format(message).lines() {
+                        if tx.send(Segment::Line(line.to_string())).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                "200 OK"
+            }
+        };
+        let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n");
+        stream.write_all(response.as_bytes()).await?;
+        // The body of a request turned down wasn't read, so the connection can't go on.
+        if head.close || !status.starts_with("200") {
+            break;
+        }
+    }
+    stream.shutdown().await
+}
+
+/// Reads from the connection, failing once [`READ_TIMEOUT`] passes first.
+async fn within<T>(read: impl Future<Output = io::Result<T>>) -> io::Result<T> {
+    timeout(READ_TIMEOUT, read)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "request timed out"))?
+}
+
+/// Reads the request line and headers, or `None` once the connection is closed. Lines longer
+/// than [`MAX_LINE`] or more than [`MAX_HEADERS`] headers fail the request.
+async fn read_head<R: AsyncRead + Unpin>(stream: &mut BufReader<R>) -> io::Result<Option<Head>> {
+    let mut line = String::new();
+    if read_line(stream, &mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut head = Head {
+        post: line.starts_with("POST "),
+        length: None,
+        close: line.trim_end().ends_with("HTTP/1.0"),
+    };
+    for _ in 0..=MAX_HEADERS {
+        line.clear();
+        if read_line(stream, &mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(Some(head));
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            head.length = value.parse().ok();
+        } else if name.eq_ignore_ascii_case("connection") {
+            head.close = value.eq_ignore_ascii_case("close");
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "too many headers",
+    ))
+}
+
+/// Reads a line of at most [`MAX_LINE`] bytes, returning how many were read.
+async fn read_line<R: AsyncRead + Unpin>(
+    stream: &mut BufReader<R>,
+    line: &mut String,
+) -> io::Result<usize> {
+    let read = (&mut *stream).take(MAX_LINE).read_line(line).await?;
+    if read as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+    Ok(read)
+}
+
+/// The syslog messages of a request body: octet-counted the way Heroku frames them, like
+/// `83 <190>1 2026-10-16T12:00:01+00:00 ...`, or one per line as Cloud Foundry posts them.
+fn messages(body: &str) -> Vec<&str> {
+    let mut messages = Vec::new();
+    let mut rest = body.trim_start();
+    while !rest.is_empty() {
+        let framed = rest.split_once(' ').and_then(|(length, after)| {
+            let length: usize = length.parse().ok()?;
+            Some((after.get(..length)?, &after[length..]))
+        });
+        let Some((message, after)) = framed else {
+            return body.lines().filter(|line| !line.is_empty()).collect();
+        };
+        messages.push(message.trim_end());
+        rest = after.trim_start();
+    }
+    messages
+}
+
+/// A syslog message the way `heroku logs` shows it, like
+/// `2026-10-16T12:00:01.123456+00:00 app[web.1]: State changed from up to down`. Messages
+/// that aren't syslog are taken as they are.
+fn format(message: &str) -> String {
+    let Some(captures) = SYSLOG.captures(message) else {
+        return message.to_string();
+    };
+    let (timestamp, app, process, text) = (&captures[1], &captures[2], &captures[3], &captures[4]);
+    if process == "-" {
+        format!("{timestamp} {app}: {text}")
+    } else {
+        format!("{timestamp} {app}[{process}]: {text}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        sync::mpsc,
+    };
+
+    use super::{MAX_HEADERS, MAX_LINE, format, messages, serve};
+    use crate::reader::Segment;
+
+    #[test]
+    fn octet_counted_and_plain_bodies_are_split_into_messages() {
+        let first = "<190>1 2026-10-16T12:00:01+00:00 host app web.1 - Starting process";
+        let second = "<158>1 2026-10-16T12:00:02+00:00 host heroku router - at=info status=200";
+        let body = format!("{} {first}\n{} {second}", first.len() + 1, second.len());
+        assert_eq!(messages(&body), [first, second]);
+        assert_eq!(messages(&format!("{first}\n{second}\n")), [first, second]);
+
+        assert_eq!(
+            format(first),
+            "2026-10-16T12:00:01+00:00 app[web.1]: Starting process"
+        );
+        assert_eq!(
+            format("<14>1 2026-10-16T12:00:03Z host api - - [origin ip=\"10.0.0.1\"] Ready"),
+            "2026-10-16T12:00:03Z api: Ready"
+        );
+        assert_eq!(format("not syslog"), "not syslog");
+    }
+
+    #[tokio::test]
+    async fn posted_messages_become_lines() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (tx, mut rx) = mpsc::channel(16);
+        let served = tokio::spawn(serve(server, tx));
+
+        let message = "<190>1 2026-10-16T12:00:01+00:00 host app web.1 - ERROR disk full";
+        let body = format!("{} {message}", message.len());
+        let request = format!(
+            "POST /logs HTTP/1.1\r\nContent-Type: application/logplex-1\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let (mut reader, mut writer) = tokio::io::split(client);
+        writer
+            .write_all(request.as_bytes())
+            .await
+            .expect("write failed");
+        let mut response = String::new();
+        reader
+            .read_to_string(&mut response)
+            .await
+            .expect("read failed");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        served.await.expect("join failed").expect("serve failed");
+
+        let Some(Segment::Line(line)) = rx.recv().await else {
+            panic!("no line");
+        };
+        assert_eq!(
+            line,
+            "2026-10-16T12:00:01+00:00 app[web.1]: ERROR disk full"
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_heads_close_the_connection() {
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE as usize));
+        let many_headers = format!(
+            "POST /logs HTTP/1.1\r\n{}\r\n",
+            "X-Padding: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        for request in [long_line, many_headers] {
+            let (mut client, server) = tokio::io::duplex(1 << 16);
+            let (tx, _rx) = mpsc::channel(16);
+            let served = tokio::spawn(serve(server, tx));
+            client
+                .write_all(request.as_bytes())
+                .await
+                .expect("write failed");
+            let served = served.await.expect("join failed");
+            assert_eq!(
+                served.expect_err("serve succeeded").kind(),
+                std::io::ErrorKind::InvalidData
+            );
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs, io,
+    net::{IpAddr, Ipv4Addr},
+    ops::Range,
+    path::Path,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use regex::Regex;
+use tokio::{
+    sync::{Semaphore, mpsc},
+    task,
+    time::timeout,
+};
+
+/// IPv4 addresses, and runs of hex digits and colons that may be IPv6 ones.
+static ADDRESS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b|[0-9A-Fa-f]*:[0-9A-Fa-f:]*:[0-9A-Fa-f]*")
+        .expect("address regex is valid")
+});
+
+/// How long a reverse lookup may take before the address is left without a name.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Lookups in flight at once, so a burst of new addresses doesn't flood the resolver or take
+/// up the blocking threads.
+const MAX_LOOKUPS: usize = 8;
+
+/// Addresses looked up at most in a session, after which new ones are left as they are.
+const MAX_ADDRESSES: usize = 10_000;
+
+/// Where the names and countries of the addresses in lines are shown.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Enrich {
+    /// In the detail popup of a line.
+    Detail,
+    /// After each address in the lines too, like `10.0.0.7 [db-1.lan, DE]`.
+    Inline,
+}
+
+/// A reverse lookup that finished: the address and its name, if it has one.
+pub(crate) type Resolved = (IpAddr, Option<String>);
+
+/// What is known of an address so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Enrichment {
+    host: Option<String>,
+    country: Option<String>,
+    /// The reverse lookup hasn't finished yet.
+    pending: bool,
+}
+
+impl Enrichment {
+    fn label(&self) -> Option<String> {
+        let parts: Vec<&str> = self
+            .host
+            .iter()
+            .chain(&self.country)
+            .map(String::as_str)
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// Annotates the IP addresses in lines with their reverse DNS names, looked up in the
+/// background with the system resolver, and their countries from a GeoIP file. Each address
+/// is looked up once.
+pub(crate) struct Enricher {
+    mode: Enrich,
+    geoip: Option<GeoIp>,
+    /// Whether names are looked up, which they are where there is a system resolver to ask.
+    resolves: bool,
+    known: HashMap<IpAddr, Enrichment>,
+    lookups: Arc<Semaphore>,
+    resolved: mpsc::Sender<Resolved>,
+    /// Lookups finished so far, each of which may change how lines are annotated.
+    finished: usize,
+}
+
+impl Enricher {
+    /// Starts enriching, with the countries of the GeoIP file if given, returning the reverse
+    /// lookups as they finish.
+    pub(crate) fn new(
+        mode: Enrich,
+        geoip: Option<&Path>,
+    ) -> io::Result<(Self, mpsc::Receiver<Resolved>)> {
+        let geoip = geoip.map(GeoIp::load).transpose()?;
+        let (tx, rx) = mpsc::channel(64);
+        let enricher = Enricher {
+            mode,
+            geoip,
+            resolves: cfg!(unix),
+            known: HashMap::new(),
+            lookups: Arc::new(Semaphore::new(MAX_LOOKUPS)),
+            resolved: tx,
+            finished: 0,
+        };
+        Ok((enricher, rx))
+    }
+
+    /// Looks up the addresses in a line that weren't looked up before.
+    pub(crate) fn request(&mut self, text: &str) {
+        for (_, address) in find_addresses(text) {
+            if self.known.len() >= MAX_ADDRESSES || self.known.contains_key(&address) {
+                continue;
+            }
+            let country = self
+                .geoip
+                .as_ref()
+                .and_then(|geoip| geoip.country(address))
+                .map(str::to_string);
+            let enrichment = Enrichment {
+                host: None,
+                country,
+                pending: self.resolves,
+            };
+            self.known.insert(address, enrichment);
+            if !self.resolves {
+                continue;
+            }
+            let lookups = Arc::clone(&self.lookups);
+            let resolved = self.resolved.clone();
+            tokio::spawn(async move {
+                let Ok(permit) = lookups.acquire_owned().await else {
+                    return;
+                };
+                // The permit is held until the lookup returns, even after it timed out, so
+                // stuck lookups still count against the limit.
+                let lookup = task::spawn_blocking(move || {
+                    let _permit = permit;
+                    reverse_lookup(address)
+                });
+                let host = timeout(LOOKUP_TIMEOUT, lookup)
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+                    .flatten();
+                let _ = resolved.send((address, host)).await;
+            });
+        }
+    }
+
+    pub(crate) fn resolved(&mut self, (address, host): Resolved) {
+        self.finished += 1;
+        if let Some(enrichment) = self.known.get_mut(&address) {
+            enrichment.host = host;
+            enrichment.pending = false;
+        }
+    }
+
+    pub(crate) fn finished(&self) -> usize {
+        self.finished
+    }
+
+    /// Each address in a text with what is known of it, like `10.0.0.7  db-1.lan, DE`.
+    pub(crate) fn summaries(&self, text: &str) -> Vec<String> {
+        let mut addresses: Vec<IpAddr> = Vec::new();
+        for (_, address) in find_addresses(text) {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        addresses
+            .into_iter()
+            .map(|address| {
+                let enrichment = self.known.get(&address);
+                let known = match enrichment.and_then(Enrichment::label) {
+                    Some(label) => label,
+                    None if enrichment.is_some_and(|enrichment| enrichment.pending) => {
+                        "resolving…".to_string()
+                    }
+                    None => "unknown".to_string(),
+                };
+                format!("{address}  {known}")
+            })
+            .collect()
+    }
+
+    /// The text with what is known of each address in it after the address, when shown inline.
+    /// Borrowed when there is nothing to add.
+    pub(crate) fn annotate<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        if self.mode != Enrich::Inline {
+            return text;
+        }
+        let mut annotated = String::new();
+        let mut cursor = 0;
+        for (range, address) in find_addresses(&text) {
+            let Some(label) = self.known.get(&address).and_then(Enrichment::label) else {
+                continue;
+            };
+            annotated.push_str(&text[cursor..range.end]);
+            annotated.push_str(" [");
+            annotated.push_str(&label);
+            annotated.push(']');
+            cursor = range.end;
+        }
+        if cursor == 0 {
+            return text;
+        }
+        annotated.push_str(&text[cursor..]);
+        Cow::Owned(annotated)
+    }
+}
+
+/// The addresses in a text and where they are, leaving out those inside words, like the
+/// `d::f` of `std::fmt`.
+fn find_addresses(text: &str) -> impl Iterator<Item = (Range<usize>, IpAddr)> + '_ {
+    let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    ADDRESS.find_iter(text).filter_map(move |found| {
+        let before = text[..found.start()].chars().next_back();
+        let after = text[found.end()..].chars().next();
+        if word(before) || word(after) {
+            return None;
+        }
+        let address: IpAddr = found.as_str().parse().ok()?;
+        (!address.is_unspecified()).then_some((found.range(), address))
+    })
+}
+
+/// The name of an address from the system resolver, which asks `/etc/hosts`, DNS or whatever
+/// else nsswitch is set up with, or `None` when it has none. Blocks until it answers.
+#[cfg(unix)]
+fn reverse_lookup(address: IpAddr) -> Option<String> {
+    /// Longest name `getnameinfo` writes, with its null.
+    const MAX_HOST: usize = 1025;
+
+    let address = socket2::SockAddr::from(std::net::SocketAddr::new(address, 0));
+    let mut host = [0 as libc::c_char; MAX_HOST];
+    // SAFETY: the address and the host buffer are valid for the lengths given, and on success
+    // getnameinfo leaves a null-terminated name in the buffer.
+    let name = unsafe {
+        let status = libc::getnameinfo(
+            address.as_ptr().cast(),
+            address.len(),
+            host.as_mut_ptr(),
+            MAX_HOST as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        );
+        if status != 0 {
+            return None;
+        }
+        std::ffi::CStr::from_ptr(host.as_ptr())
+    };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(unix))]
+fn reverse_lookup(_: IpAddr) -> Option<String> {
+    None
+}
+
+/// The countries of address ranges, from a CSV file of `first,last,country` rows like DB-IP's
+/// free country database. IPv4 addresses may also be given as numbers, as in IP2Location's.
+struct GeoIp {
+    /// The first and last addresses of each range, IPv4 ones mapped to IPv6, and its country.
+    ranges: Vec<(u128, u128, String)>,
+}
+
+impl GeoIp {
+    fn load(path: &Path) -> io::Result<Self> {
+        GeoIp::parse(&fs::read_to_string(path)?).map_err(|line| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: invalid address range on line {line}", path.display()),
+            )
+        })
+    }
+
+    /// Parses the rows, failing with the number of the first invalid one. A header row is
+    /// skipped.
+    fn parse(text: &str) -> Result<Self, usize> {
+        let mut ranges = Vec::new();
+        for (number, row) in text.lines().enumerate() {
+            if row.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = row
+                .split(',')
+                .map(|field| field.trim().trim_matches('"'))
+                .collect();
+            let range = match fields.as_slice() {
+                [first, last, country, ..] => range_key(first)
+                    .zip(range_key(last))
+                    .map(|(first, last)| (first, last, country.to_string())),
+                _ => None,
+            };
+            match range {
+                Some(range) => ranges.push(range),
+                None if number == 0 => {}
+                None => return Err(number + 1),
+            }
+        }
+        ranges.sort_unstable_by_key(|(first, ..)| *first);
+        Ok(GeoIp { ranges })
+    }
+
+    fn country(&self, address: IpAddr) -> Option<&str> {
+        let key = address_key(address);
+        let after = self.ranges.partition_point(|(first, ..)| *first <= key);
+        let (_, last, country) = self.ranges.get(after.checked_sub(1)?)?;
+        (key <= *last).then_some(country.as_str())
+    }
+}
+
+fn address_key(address: IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(address) => u128::from(address.to_ipv6_mapped()),
+        IpAddr::V6(address) => u128::from(address),
+    }
+}
+
+fn range_key(field: &str) -> Option<u128> {
+    match field.parse::<IpAddr>() {
+        Ok(address) => Some(address_key(address)),
+        Err(_) => field
+            .parse::<u32>()
+            .ok()
+            .map(|number| address_key(Ipv4Addr::from(number).into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        collections::HashMap,
+        net::{IpAddr, Ipv4Addr},
+        sync::Arc,
+    };
+
+    use tokio::sync::{Semaphore, mpsc};
+
+    use super::{Enrich, Enricher, GeoIp, reverse_lookup};
+
+    #[cfg(unix)]
+    #[test]
+    fn reverse_lookups_ask_the_system_resolver() {
+        // Named in the hosts file, so the lookup doesn't depend on DNS being reachable.
+        let host = reverse_lookup(Ipv4Addr::LOCALHOST.into()).expect("loopback has a name");
+        assert!(
+            !host.is_empty() && host.parse::<IpAddr>().is_err(),
+            "{host}"
+        );
+    }
+
+    #[test]
+    fn addresses_are_annotated_with_their_names_and_countries() {
+        let geoip = GeoIp::parse(
+            "first,last,country\n192.0.2.0,192.0.2.255,DE\n\"3221226240\",\"3221226495\",FR\n",
+        )
+        .expect("parse failed");
+        assert_eq!(GeoIp::parse("1.0.0.0,1.0.0.255,AU\nnope\n").err(), Some(2));
+        let (resolved, _) = mpsc::channel(1);
+        let mut enricher = Enricher {
+            mode: Enrich::Inline,
+            geoip: Some(geoip),
+            resolves: false,
+            known: HashMap::new(),
+            lookups: Arc::new(Semaphore::new(1)),
+            resolved,
+            finished: 0,
+        };
+        let text = "GET / from 192.0.2.7 via 192.0.3.1:443 and 198.51.100.1 in std::fmt";
+        enricher.request(text);
+        enricher.resolved((
+            "192.0.2.7".parse().expect("parse failed"),
+            Some("db-1.lan".to_string()),
+        ));
+
+        assert_eq!(
+            enricher.annotate(Cow::Borrowed(text)),
+            "GET / from 192.0.2.7 [db-1.lan, DE] via 192.0.3.1 [FR]:443 and 198.51.100.1 in \
+             std::fmt"
+        );
+        assert_eq!(
+            enricher.summaries(text),
+            [
+                "192.0.2.7  db-1.lan, DE",
+                "192.0.3.1  FR",
+                "198.51.100.1  unknown"
+            ]
+        );
+        assert!(matches!(
+            enricher.annotate(Cow::Borrowed("no addresses here")),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -1,69 +1,149 @@
-use std::time::Duration;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers};
+use futures::StreamExt as _;
+use std::{borrow::Cow, io, num::NonZeroUsize};
 
-use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
-
-use crate::{build_pattern, build_regex, max_start, AppState, LogrError};
+use crate::{
+    action::{apply, Action},
+    anchor_scroll, audit,
+    buffer::LogBuffer,
+    build_regex,
+    bus::{AppEvent, EventBus},
+    clipboard, create_spill, entry_first_line, filtered_line_count, line_visible, logfmt,
+    max_row_start, max_start,
+    preset::Outcome,
+    prompt::{parse_command, parse_filter, parse_go_to, run_command, GoTo},
+    redact::redact,
+    scroll_rows, scrolls_rows, snapshot, spill_path, start_for_top, top_line,
+    ui::visual_rows,
+    visible_indices, visible_line_index, visible_lines, AppState, FollowAt, LineKind, LogLine,
+    LogrError, Pause, Selection, Viewport,
+};
 
 pub(crate) struct EventResult {
     pub exit: bool,
-    pub(crate) redraw: bool,
+    pub(crate) rerun: bool,
+}
+
+/// Events read from the terminal without blocking the runtime.
+pub(crate) struct TerminalEvents {
+    stream: EventStream,
+}
+
+impl TerminalEvents {
+    pub(crate) fn new() -> Self {
+        Self {
+            stream: EventStream::new(),
+        }
+    }
+
+    /// Waits for the next terminal event the app reacts to and publishes it. Never resolves once
+    /// the terminal is gone. Cancel safe.
+    pub(crate) async fn publish_next(&mut self, bus: &mut EventBus) {
+        loop {
+            let event = match self.stream.next().await {
+                Some(Ok(Event::Key(key))) => AppEvent::Key(key),
+                Some(Ok(Event::Resize(..))) => AppEvent::Resize,
+                Some(_) => continue,
+                None => return std::future::pending().await,
+            };
+            bus.publish(event);
+            return;
+        }
+    }
 }
 
-pub(crate) fn handle_event(
+/// Routes a key to the large-buffer prompt, the help overlay, the detail popup, the pattern or
+/// sources dialog, the search bar, the line selection or the main view, whichever is on top.
+pub(crate) fn handle_key(
     app: &mut AppState,
+    lines: &mut LogBuffer,
     total_lines: usize,
     view_height: usize,
+    key: KeyEvent,
 ) -> Result<EventResult, LogrError> {
-    let mut redraw = false;
-    while crossterm::event::poll(Duration::from_millis(0)).unwrap_or(false) {
-        if let Ok(Event::Key(KeyEvent {
-            code, modifiers, ..
-        })) = read()
-        {
-            redraw = true;
-            if app.dialog_open {
-                if let Some(result) = handle_dialog_event(app, code, modifiers, redraw)? {
-                    return Ok(result);
-                }
-                continue;
-            }
-
-            if let Some(result) =
-                handle_main_event(app, total_lines, view_height, code, modifiers, redraw)
-            {
-                return Ok(result);
-            }
-        }
-    }
+    let KeyEvent {
+        code, modifiers, ..
+    } = key;
+    let result = if app.large_buffer_open {
+        handle_large_buffer_event(app, lines, code, modifiers)
+    } else if app.reveal_open {
+        handle_reveal_event(app, code)
+    } else if app.help_open {
+        handle_help_event(app, lines, code, modifiers)
+    } else if app.detail_line.is_some() {
+        handle_detail_event(app, lines, code, modifiers)
+    } else if app.dialog_open {
+        handle_dialog_event(app, lines, code, modifiers)?
+    } else if app.sources_open {
+        handle_sources_event(app, lines, code, modifiers)
+    } else if app.fields_open {
+        handle_fields_event(app, lines, code, modifiers)
+    } else if app.profiles_open {
+        handle_profiles_event(app, lines, code, modifiers)
+    } else if app.failures_open {
+        handle_failures_event(app, lines, total_lines, view_height, code, modifiers)
+    } else if app.search_open {
+        handle_search_event(app, lines, total_lines, view_height, code, modifiers)
+    } else if app.prompt_open {
+        handle_prompt_event(app, lines, total_lines, view_height, code, modifiers)
+    } else if app.selection.is_some() {
+        handle_selection_event(app, lines, total_lines, view_height, code, modifiers)
+    } else {
+        handle_main_event(app, lines, total_lines, view_height, code, modifiers)
+    };
 
-    Ok(EventResult {
+    anchor_scroll(app, lines);
+    Ok(result.unwrap_or(EventResult {
         exit: false,
-        redraw,
-    })
+        rerun: false,
+    }))
 }
 
 fn handle_dialog_event(
     app: &mut AppState,
+    lines: &mut LogBuffer,
     code: KeyCode,
     modifiers: KeyModifiers,
-    redraw: bool,
 ) -> Result<Option<EventResult>, LogrError> {
     match code {
         KeyCode::Esc => {
             app.dialog_open = false;
             app.input.clear();
+            app.after_cursor = 0;
+            app.editing = None;
             app.pattern_error = None;
         }
         KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-            return Ok(Some(EventResult { exit: true, redraw }));
+            return Ok(Some(EventResult {
+                exit: true,
+                rerun: false,
+            }));
+        }
+        KeyCode::Enter if app.input.is_empty() && app.selected < app.patterns.len() => {
+            app.input = app.patterns[app.selected].pattern.clone();
+            app.after_cursor = 0;
+            app.editing = Some(app.selected);
+            app.selected = app.patterns.len();
+            app.pattern_error = None;
         }
         KeyCode::Enter => {
             if !app.input.trim().is_empty() {
-                match build_pattern(app.input.clone(), !app.ignore_case) {
-                    Ok(pattern) => {
-                        app.patterns.push(pattern);
+                let action = match app.editing {
+                    Some(index) => Action::EditPattern {
+                        index,
+                        pattern: app.input.clone(),
+                    },
+                    None => Action::AddPattern {
+                        pattern: app.input.clone(),
+                        case_sensitive: !app.ignore_case,
+                    },
+                };
+                match apply(app, lines, action) {
+                    Ok(()) => {
                         app.dialog_open = false;
                         app.input.clear();
+                        app.after_cursor = 0;
+                        app.editing = None;
                         app.pattern_error = None;
                     }
                     Err(err) => {
@@ -72,143 +152,266 @@ fn handle_dialog_event(
                 }
             } else {
                 app.dialog_open = false;
+                app.editing = None;
                 app.pattern_error = None;
             }
         }
-        KeyCode::Up => {
-            if app.selected > 0 {
-                app.selected -= 1;
-            }
+        KeyCode::Up if app.selected > 0 => {
+            app.selected -= 1;
         }
-        KeyCode::Down => {
-            if app.selected < app.patterns.len() {
-                app.selected += 1;
-            }
+        KeyCode::Down if app.selected < app.patterns.len() => {
+            app.selected += 1;
         }
-        KeyCode::Left | KeyCode::Right => {
-            if app.selected < app.patterns.len() {
-                let case_sensitive = !app.patterns[app.selected].case_sensitive;
-                match build_regex(&app.patterns[app.selected].pattern, case_sensitive) {
-                    Ok(regex) => {
-                        app.patterns[app.selected].case_sensitive = case_sensitive;
-                        app.patterns[app.selected].regex = regex;
-                    }
-                    Err(err) => {
-                        app.pattern_error = Some(format!("Invalid pattern: {err}"));
-                    }
-                }
-            }
+        KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => {
+            let cursor = input_cursor(app);
+            let moved = match code {
+                KeyCode::Left if control(modifiers) => word_start(&app.input, cursor),
+                KeyCode::Right if control(modifiers) => word_end(&app.input, cursor),
+                KeyCode::Left => app.input[..cursor]
+                    .chars()
+                    .next_back()
+                    .map_or(cursor, |c| cursor - c.len_utf8()),
+                KeyCode::Right => app.input[cursor..]
+                    .chars()
+                    .next()
+                    .map_or(cursor, |c| cursor + c.len_utf8()),
+                KeyCode::Home => 0,
+                _ => app.input.len(),
+            };
+            app.after_cursor = app.input.len() - moved;
+            app.selected = app.patterns.len();
+        }
+        KeyCode::Tab if app.selected < app.patterns.len() => {
+            let action = Action::ToggleExclude {
+                index: app.selected,
+            };
+            apply(app, lines, action)?;
         }
         KeyCode::Delete => {
             if app.selected < app.patterns.len() {
-                app.patterns.remove(app.selected);
-                if app.selected > app.patterns.len() {
-                    app.selected = app.patterns.len();
-                }
-                if app.patterns.is_empty() {
-                    app.selected = 0;
+                let action = Action::RemovePattern {
+                    index: app.selected,
+                };
+                apply(app, lines, action)?;
+                // The text being edited stays in the input line, to be added as a new pattern.
+                app.editing = None;
+            } else {
+                let cursor = input_cursor(app);
+                if cursor < app.input.len() {
+                    let removed = app.input.remove(cursor);
+                    app.after_cursor -= removed.len_utf8();
                 }
             }
         }
         KeyCode::Backspace => {
-            app.input.pop();
+            let cursor = input_cursor(app);
+            if let Some(c) = app.input[..cursor].chars().next_back() {
+                app.input.remove(cursor - c.len_utf8());
+            }
             app.selected = app.patterns.len();
         }
-        KeyCode::Char(c) => {
-            if !modifiers.contains(KeyModifiers::CONTROL) {
-                app.input.push(c);
-                app.selected = app.patterns.len();
+        KeyCode::Char('t') if control(modifiers) && app.selected < app.patterns.len() => {
+            let action = Action::ToggleCase {
+                index: app.selected,
+            };
+            if let Err(err) = apply(app, lines, action) {
+                app.pattern_error = Some(format!("Invalid pattern: {err}"));
+            }
+        }
+        KeyCode::Char('f') if control(modifiers) && app.selected < app.patterns.len() => {
+            let action = Action::ToggleLiteral {
+                index: app.selected,
+            };
+            if let Err(err) = apply(app, lines, action) {
+                app.pattern_error = Some(format!("Invalid pattern: {err}"));
+            }
+        }
+        KeyCode::Char('s')
+            if modifiers.contains(KeyModifiers::CONTROL) && app.selected < app.patterns.len() =>
+        {
+            let action = Action::Snooze {
+                index: app.selected,
+            };
+            apply(app, lines, action)?;
+        }
+        KeyCode::Char(' ') if app.selected < app.patterns.len() => {
+            let action = Action::ToggleEnabled {
+                index: app.selected,
+            };
+            apply(app, lines, action)?;
+        }
+        KeyCode::Char('c') if app.selected < app.patterns.len() => {
+            let action = Action::CycleColor {
+                index: app.selected,
+            };
+            apply(app, lines, action)?;
+        }
+        KeyCode::Char('l') if app.selected < app.patterns.len() => {
+            let action = Action::ToggleWholeLine {
+                index: app.selected,
+            };
+            apply(app, lines, action)?;
+        }
+        KeyCode::Char('w') if app.selected < app.patterns.len() => {
+            let action = Action::ToggleWord {
+                index: app.selected,
+            };
+            if let Err(err) = apply(app, lines, action) {
+                app.pattern_error = Some(format!("Invalid pattern: {err}"));
             }
         }
+        KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+            let cursor = input_cursor(app);
+            app.input.insert(cursor, c);
+            app.selected = app.patterns.len();
+        }
         _ => {}
     }
 
     Ok(None)
 }
 
-fn handle_main_event(
+/// Where the text cursor is in the input line, as a byte index.
+pub(crate) fn input_cursor(app: &AppState) -> usize {
+    app.input.len().saturating_sub(app.after_cursor)
+}
+
+/// The start of the word before the cursor, past any whitespace right before it.
+fn word_start(text: &str, cursor: usize) -> usize {
+    let before = text[..cursor].trim_end();
+    before
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+/// The end of the word after the cursor, past any whitespace right after it.
+fn word_end(text: &str, cursor: usize) -> usize {
+    let after = &text[cursor..];
+    let word = after.trim_start();
+    let start = cursor + after.len() - word.len();
+    start + word.find(char::is_whitespace).unwrap_or(word.len())
+}
+
+fn handle_selection_event(
     app: &mut AppState,
+    lines: &LogBuffer,
     total_lines: usize,
     view_height: usize,
     code: KeyCode,
     modifiers: KeyModifiers,
-    redraw: bool,
 ) -> Option<EventResult> {
+    app.status = None;
+    let (code, modifiers) = oriented(app.follow_at, code, modifiers);
     match code {
-        KeyCode::Char('q') => return Some(EventResult { exit: true, redraw }),
-        KeyCode::Char('p') => {
-            app.dialog_open = true;
-            app.input.clear();
-            app.pattern_error = None;
-            app.selected = 0;
+        KeyCode::Char('c') if control(modifiers) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
         }
-        KeyCode::Char('w') => {
-            app.wrap = !app.wrap;
+        KeyCode::Down | KeyCode::Char('j') => {
+            move_selection(app, lines, total_lines, view_height, true);
         }
-        KeyCode::Char('f') => {
-            app.filter_only = !app.filter_only;
-            app.follow = true;
-            app.scroll = 0;
+        KeyCode::Up | KeyCode::Char('k') => {
+            move_selection(app, lines, total_lines, view_height, false);
         }
-        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-            return Some(EventResult { exit: true, redraw });
+        KeyCode::Char('y') if app.read_only => {
+            app.selection = None;
+            app.status = Some(app.messages.not_copying.to_string());
         }
-        KeyCode::Up | KeyCode::Char('k') => {
-            if total_lines > 0 {
-                let max_start = max_start(total_lines, view_height);
-                if app.follow {
-                    app.follow = false;
-                    app.scroll = max_start;
-                }
-                if app.scroll > 0 {
-                    app.scroll -= 1;
+        KeyCode::Char('y') => {
+            let (count, text) = selected_text(app, lines);
+            app.selection = None;
+            app.status = Some(match clipboard::copy(&text) {
+                Ok(()) => {
+                    audit::record(app, &format!("Copied {count} lines"));
+                    (app.messages.copied)(count)
                 }
-            }
+                Err(err) => format!("Can't copy: {err}"),
+            });
         }
-        KeyCode::Down | KeyCode::Char('j') => {
-            if total_lines > 0 {
-                let max_start = max_start(total_lines, view_height);
-                if app.follow {
-                    app.scroll = max_start;
-                }
-                if app.scroll < max_start {
-                    app.scroll += 1;
-                } else {
-                    app.follow = true;
-                }
-            }
+        KeyCode::Esc | KeyCode::Char('v' | 'q') => {
+            app.selection = None;
         }
-        KeyCode::PageUp | KeyCode::Char('u') if modifiers.contains(KeyModifiers::CONTROL) => {
-            if total_lines > 0 {
-                let max_start = max_start(total_lines, view_height);
-                let delta = usize::max(1, view_height / 2);
-                if app.follow {
-                    app.follow = false;
-                    app.scroll = max_start;
-                }
-                app.scroll = app.scroll.saturating_sub(delta);
+        _ => {}
+    }
+
+    None
+}
+
+/// Closes the help overlay on any key but `w`, which wraps its lines or not apart from the main
+/// view, and Ctrl-c, which quits as everywhere else.
+fn handle_help_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if control(modifiers) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Char('w') => {
+            let action = Action::ToggleWrap {
+                viewport: Viewport::Help,
+            };
+            apply_main(app, lines, action);
+        }
+        _ => app.help_open = false,
+    }
+    None
+}
+
+fn handle_large_buffer_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Char('b') => {
+            app.large_buffer_open = false;
+            if let Some(max_lines) = NonZeroUsize::new(lines.len()) {
+                lines.set_max_lines(max_lines);
+                app.status = Some((app.messages.keeping)(max_lines.get()));
             }
         }
-        KeyCode::PageDown | KeyCode::Char('d') if modifiers.contains(KeyModifiers::CONTROL) => {
-            if total_lines > 0 {
-                let max_start = max_start(total_lines, view_height);
-                let delta = usize::max(1, view_height / 2);
-                if app.follow {
-                    app.scroll = max_start;
-                }
-                app.scroll = usize::min(app.scroll + delta, max_start);
-                if app.scroll == max_start {
-                    app.follow = true;
+        KeyCode::Char('s') => {
+            app.large_buffer_open = false;
+            let max_lines = NonZeroUsize::new(lines.len())?;
+            let path = spill_path();
+            let path_text = path.display().to_string();
+            app.status = Some(if app.read_only {
+                (app.messages.not_writing)(&path_text)
+            } else {
+                match create_spill(&path) {
+                    Ok(file) => {
+                        app.spill = Some(Box::new(io::LineWriter::new(file)));
+                        lines.set_max_lines(max_lines);
+                        (app.messages.spilling)(max_lines.get(), &path_text)
+                    }
+                    Err(err) => (app.messages.spill_stopped)(&err.to_string()),
                 }
-            }
+            });
         }
-        KeyCode::Home | KeyCode::Char('g') if !modifiers.contains(KeyModifiers::SHIFT) => {
-            app.follow = false;
-            app.scroll = 0;
+        KeyCode::Char('d') => {
+            app.large_buffer_open = false;
+            app.dedup = true;
+            app.status = Some(app.messages.deduplicating.to_string());
         }
-        KeyCode::End | KeyCode::Char('G') => {
-            app.follow = true;
-            app.scroll = max_start(total_lines, view_height);
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => {
+            app.large_buffer_open = false;
         }
         _ => {}
     }
@@ -216,201 +419,2564 @@ fn handle_main_event(
     None
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{handle_dialog_event, handle_main_event};
-    use crate::{build_pattern, max_start, AppState};
-    use crossterm::event::{KeyCode, KeyModifiers};
-
-    fn app_with_patterns(ignore_case: bool) -> AppState {
-        let patterns = vec![
-            build_pattern("foo".to_string(), true).expect("pattern build failed"),
-            build_pattern("bar".to_string(), true).expect("pattern build failed"),
-        ];
-        AppState::new(patterns, ignore_case)
+fn handle_sources_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('s') => {
+            app.sources_open = false;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.source_selected = app.source_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.source_selected + 1 < app.sources.len() => {
+            app.source_selected += 1;
+        }
+        KeyCode::Char(' ') | KeyCode::Enter => {
+            let action = Action::ToggleSource {
+                index: app.source_selected,
+            };
+            apply_main(app, lines, action);
+        }
+        KeyCode::Left | KeyCode::Right => {
+            let action = Action::CycleFormat {
+                index: app.source_selected,
+            };
+            apply_main(app, lines, action);
+        }
+        _ => {}
     }
 
-    #[test]
-    fn dialog_enter_adds_pattern_and_closes() {
-        let mut app = app_with_patterns(false);
-        app.dialog_open = true;
-        app.input = "new".to_string();
-
-        let result = handle_dialog_event(&mut app, KeyCode::Enter, KeyModifiers::empty(), true)
-            .expect("dialog handler failed");
+    None
+}
 
-        assert!(result.is_none());
-        assert!(!app.dialog_open);
-        assert!(app.input.is_empty());
-        assert!(app.pattern_error.is_none());
-        assert_eq!(app.patterns.len(), 3);
-        assert_eq!(app.patterns[2].pattern, "new");
-        assert!(app.patterns[2].case_sensitive);
+fn handle_fields_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('F') => {
+            app.fields_open = false;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.field_selected = app.field_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.field_selected + 1 < app.field_keys.len() => {
+            app.field_selected += 1;
+        }
+        KeyCode::Char(' ') | KeyCode::Enter => {
+            if let Some(key) = app.field_keys.get(app.field_selected) {
+                let action = Action::ToggleField { key: key.clone() };
+                apply_main(app, lines, action);
+            }
+        }
+        _ => {}
     }
 
-    #[test]
-    fn dialog_toggle_case_sensitive() {
-        let mut app = app_with_patterns(false);
-        app.dialog_open = true;
-        app.selected = 0;
-
-        let result = handle_dialog_event(&mut app, KeyCode::Left, KeyModifiers::empty(), true)
-            .expect("dialog handler failed");
+    None
+}
 
-        assert!(result.is_none());
-        assert!(!app.patterns[0].case_sensitive);
-        assert!(app.patterns[0].regex.is_match("FOO"));
+fn handle_profiles_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.profiles_open = false;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.profile_selected = app.profile_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.profile_selected + 1 < app.profiles.len() => {
+            app.profile_selected += 1;
+        }
+        KeyCode::Enter => {
+            if let Some(name) = app.profiles.keys().nth(app.profile_selected) {
+                let action = Action::UseProfile { name: name.clone() };
+                app.profiles_open = false;
+                if let Err(err) = apply(app, lines, action) {
+                    app.status = Some((app.messages.cant_use_profile)(&err.to_string()));
+                }
+            }
+        }
+        _ => {}
     }
 
-    #[test]
-    fn dialog_delete_removes_pattern() {
-        let mut app = app_with_patterns(false);
-        app.dialog_open = true;
-        app.selected = 0;
-
-        let result = handle_dialog_event(&mut app, KeyCode::Delete, KeyModifiers::empty(), true)
-            .expect("dialog handler failed");
+    None
+}
 
-        assert!(result.is_none());
-        assert_eq!(app.patterns.len(), 1);
-        assert_eq!(app.patterns[0].pattern, "bar");
-        assert_eq!(app.selected, 0);
+fn handle_failures_event(
+    app: &mut AppState,
+    lines: &LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('!') => {
+            app.failures_open = false;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.failure_selected = app.failure_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.failure_selected + 1 < app.failures.len() => {
+            app.failure_selected += 1;
+        }
+        KeyCode::Enter => {
+            if let Some(&(number, _)) = app.failures.get(app.failure_selected) {
+                app.failures_open = false;
+                app.status = go_to(app, lines, total_lines, view_height, GoTo::Line(number));
+            }
+        }
+        _ => {}
     }
 
-    #[test]
-    fn dialog_backspace_moves_selection_to_input() {
-        let mut app = app_with_patterns(false);
-        app.dialog_open = true;
-        app.selected = 0;
-        app.input = "ab".to_string();
+    None
+}
 
-        let result = handle_dialog_event(&mut app, KeyCode::Backspace, KeyModifiers::empty(), true)
-            .expect("dialog handler failed");
+fn handle_search_event(
+    app: &mut AppState,
+    lines: &LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Esc => {
+            app.search_open = false;
+            app.search_input.clear();
+            app.search_error = None;
+        }
+        KeyCode::Enter => {
+            if app.search_input.is_empty() {
+                app.search = None;
+                app.search_match = None;
+                app.search_open = false;
+                app.search_error = None;
+                return None;
+            }
+            match build_regex(&app.search_input, !app.ignore_case) {
+                Ok(regex) => {
+                    app.search = Some(regex);
+                    app.search_match = None;
+                    jump_to_match(app, lines, total_lines, view_height, true);
+                    if app.search_match.is_some() {
+                        app.search_open = false;
+                        app.search_input.clear();
+                        app.search_error = None;
+                    } else {
+                        app.search_error = Some("Pattern not found".to_string());
+                    }
+                }
+                Err(err) => {
+                    app.search_error = Some(format!("Invalid pattern: {err}"));
+                }
+            }
+        }
+        KeyCode::Backspace => {
+            app.search_input.pop();
+        }
+        KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+            app.search_input.push(c);
+        }
+        _ => {}
+    }
+
+    None
+}
+
+fn handle_prompt_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCode::Esc => {
+            app.prompt_open = false;
+            app.prompt_input.clear();
+            app.prompt_error = None;
+        }
+        KeyCode::Enter => {
+            if app.prompt_input.trim().is_empty() {
+                app.prompt_open = false;
+                app.prompt_error = None;
+                return None;
+            }
+            let result = if let Some(expression) = parse_filter(&app.prompt_input) {
+                apply(app, lines, Action::SetFilter { expression })
+                    .map(|()| None)
+                    .map_err(|err| err.to_string())
+            } else {
+                match parse_go_to(&app.prompt_input) {
+                    Some(target) => Ok(go_to(app, lines, total_lines, view_height, target)),
+                    None => parse_command(&app.prompt_input)
+                        .and_then(|command| run_command(app, lines, command))
+                        .inspect(|status| {
+                            let entry = format!("Ran :{} ({status})", app.prompt_input.trim());
+                            audit::record(app, &entry);
+                        })
+                        .map(Some),
+                }
+            };
+            match result {
+                Ok(status) => {
+                    app.status = status;
+                    app.prompt_open = false;
+                    app.prompt_input.clear();
+                    app.prompt_error = None;
+                }
+                Err(err) => app.prompt_error = Some(err),
+            }
+        }
+        KeyCode::Backspace => {
+            app.prompt_input.pop();
+        }
+        KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+            app.prompt_input.push(c);
+        }
+        _ => {}
+    }
+
+    None
+}
+
+fn handle_detail_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    match code {
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+            app.detail_line = None;
+        }
+        KeyCode::Char('x') => {
+            app.detail_hex = !app.detail_hex;
+        }
+        KeyCode::Char('w') => {
+            let action = Action::ToggleWrap {
+                viewport: Viewport::Detail,
+            };
+            apply_main(app, lines, action);
+        }
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        _ => {}
+    }
+
+    None
+}
+
+/// What a key of the main view does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyCommand {
+    Quit,
+    Help,
+    Redo,
+    Undo,
+    Rerun,
+    PreviousRun,
+    NextRun,
+    PreviousFailure,
+    NextFailure,
+    Failures,
+    Profiles,
+    ReloadPatterns,
+    Patterns,
+    Wrap,
+    LineNumbers,
+    Epochs,
+    Reveal,
+    Sources,
+    Fields,
+    Search,
+    Prompt,
+    Write,
+    NextMatch,
+    PreviousMatch,
+    Detail,
+    Select,
+    Filter,
+    FilterExpression,
+    Pause,
+    Grouping,
+    Entry,
+    Order,
+    MinLevel,
+    JsonView,
+    Clear,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
+
+/// A key of the main view, with how the help overlay lists it.
+pub(crate) struct Binding {
+    /// What the config file calls it, to bind other keys to it.
+    pub(crate) name: &'static str,
+    pub(crate) keys: &'static str,
+    /// Whether it takes a key, after [`oriented`] swapped the scrolling keys around.
+    takes: fn(KeyCode, KeyModifiers) -> bool,
+    command: KeyCommand,
+}
+
+/// The keys of the main view, tried in order after the ones bound in the config file, which
+/// replace the keys of the bindings they name. The view looks keys up here and the help overlay
+/// lists them from here, so the two can't drift apart. What each does is in
+/// [`Messages::key_help`](crate::messages::Messages::key_help), in the same order.
+pub(crate) const KEYMAP: &[Binding] = &[
+    Binding {
+        name: "quit",
+        keys: "q, Ctrl-c",
+        takes: |code, modifiers| {
+            code == KeyCode::Char('q') || (code == KeyCode::Char('c') && control(modifiers))
+        },
+        command: KeyCommand::Quit,
+    },
+    Binding {
+        name: "help",
+        keys: "?",
+        takes: |code, _| code == KeyCode::Char('?'),
+        command: KeyCommand::Help,
+    },
+    Binding {
+        name: "undo",
+        keys: "u",
+        takes: |code, modifiers| code == KeyCode::Char('u') && !control(modifiers),
+        command: KeyCommand::Undo,
+    },
+    Binding {
+        name: "redo",
+        keys: "Ctrl-r",
+        takes: |code, modifiers| code == KeyCode::Char('r') && control(modifiers),
+        command: KeyCommand::Redo,
+    },
+    Binding {
+        name: "rerun",
+        keys: "r",
+        takes: |code, _| code == KeyCode::Char('r'),
+        command: KeyCommand::Rerun,
+    },
+    Binding {
+        name: "previous-run",
+        keys: "[",
+        takes: |code, _| code == KeyCode::Char('['),
+        command: KeyCommand::PreviousRun,
+    },
+    Binding {
+        name: "next-run",
+        keys: "]",
+        takes: |code, _| code == KeyCode::Char(']'),
+        command: KeyCommand::NextRun,
+    },
+    Binding {
+        name: "previous-failure",
+        keys: "{",
+        takes: |code, _| code == KeyCode::Char('{'),
+        command: KeyCommand::PreviousFailure,
+    },
+    Binding {
+        name: "next-failure",
+        keys: "}",
+        takes: |code, _| code == KeyCode::Char('}'),
+        command: KeyCommand::NextFailure,
+    },
+    Binding {
+        name: "failures",
+        keys: "!",
+        takes: |code, _| code == KeyCode::Char('!'),
+        command: KeyCommand::Failures,
+    },
+    Binding {
+        name: "profiles",
+        keys: "Ctrl-p",
+        takes: |code, modifiers| code == KeyCode::Char('p') && control(modifiers),
+        command: KeyCommand::Profiles,
+    },
+    Binding {
+        name: "reload-patterns",
+        keys: "R",
+        takes: |code, _| code == KeyCode::Char('R'),
+        command: KeyCommand::ReloadPatterns,
+    },
+    Binding {
+        name: "patterns",
+        keys: "p",
+        takes: |code, _| code == KeyCode::Char('p'),
+        command: KeyCommand::Patterns,
+    },
+    Binding {
+        name: "wrap",
+        keys: "w",
+        takes: |code, _| code == KeyCode::Char('w'),
+        command: KeyCommand::Wrap,
+    },
+    Binding {
+        name: "line-numbers",
+        keys: "#",
+        takes: |code, _| code == KeyCode::Char('#'),
+        command: KeyCommand::LineNumbers,
+    },
+    Binding {
+        name: "epochs",
+        keys: "T",
+        takes: |code, _| code == KeyCode::Char('T'),
+        command: KeyCommand::Epochs,
+    },
+    Binding {
+        name: "reveal",
+        keys: "X",
+        takes: |code, _| code == KeyCode::Char('X'),
+        command: KeyCommand::Reveal,
+    },
+    Binding {
+        name: "sources",
+        keys: "s",
+        takes: |code, _| code == KeyCode::Char('s'),
+        command: KeyCommand::Sources,
+    },
+    Binding {
+        name: "fields",
+        keys: "F",
+        takes: |code, _| code == KeyCode::Char('F'),
+        command: KeyCommand::Fields,
+    },
+    Binding {
+        name: "search",
+        keys: "/",
+        takes: |code, _| code == KeyCode::Char('/'),
+        command: KeyCommand::Search,
+    },
+    Binding {
+        name: "next-match",
+        keys: "n",
+        takes: |code, _| code == KeyCode::Char('n'),
+        command: KeyCommand::NextMatch,
+    },
+    Binding {
+        name: "previous-match",
+        keys: "N",
+        takes: |code, _| code == KeyCode::Char('N'),
+        command: KeyCommand::PreviousMatch,
+    },
+    Binding {
+        name: "prompt",
+        keys: ":",
+        takes: |code, _| code == KeyCode::Char(':'),
+        command: KeyCommand::Prompt,
+    },
+    Binding {
+        name: "write",
+        keys: "W",
+        takes: |code, _| code == KeyCode::Char('W'),
+        command: KeyCommand::Write,
+    },
+    Binding {
+        name: "detail",
+        keys: "Enter",
+        takes: |code, _| code == KeyCode::Enter,
+        command: KeyCommand::Detail,
+    },
+    Binding {
+        name: "select",
+        keys: "v",
+        takes: |code, _| code == KeyCode::Char('v'),
+        command: KeyCommand::Select,
+    },
+    Binding {
+        name: "filter",
+        keys: "f",
+        takes: |code, _| code == KeyCode::Char('f'),
+        command: KeyCommand::Filter,
+    },
+    Binding {
+        name: "filter-expression",
+        keys: "|",
+        takes: |code, _| code == KeyCode::Char('|'),
+        command: KeyCommand::FilterExpression,
+    },
+    Binding {
+        name: "pause",
+        keys: "Space, P",
+        takes: |code, _| matches!(code, KeyCode::Char(' ' | 'P')),
+        command: KeyCommand::Pause,
+    },
+    Binding {
+        name: "group-entries",
+        keys: "E",
+        takes: |code, _| code == KeyCode::Char('E'),
+        command: KeyCommand::Grouping,
+    },
+    Binding {
+        name: "fold-entry",
+        keys: "z",
+        takes: |code, _| code == KeyCode::Char('z'),
+        command: KeyCommand::Entry,
+    },
+    Binding {
+        name: "order",
+        keys: "o",
+        takes: |code, _| code == KeyCode::Char('o'),
+        command: KeyCommand::Order,
+    },
+    Binding {
+        name: "min-level",
+        keys: "L",
+        takes: |code, _| code == KeyCode::Char('L'),
+        command: KeyCommand::MinLevel,
+    },
+    Binding {
+        name: "json-view",
+        keys: "J",
+        takes: |code, _| code == KeyCode::Char('J'),
+        command: KeyCommand::JsonView,
+    },
+    Binding {
+        name: "clear",
+        keys: "c, C",
+        takes: |code, _| matches!(code, KeyCode::Char('c' | 'C')),
+        command: KeyCommand::Clear,
+    },
+    Binding {
+        name: "up",
+        keys: "k, Up",
+        takes: |code, _| matches!(code, KeyCode::Up | KeyCode::Char('k')),
+        command: KeyCommand::Up,
+    },
+    Binding {
+        name: "down",
+        keys: "j, Down",
+        takes: |code, _| matches!(code, KeyCode::Down | KeyCode::Char('j')),
+        command: KeyCommand::Down,
+    },
+    Binding {
+        name: "page-up",
+        keys: "Ctrl-u, PageUp",
+        takes: |code, modifiers| {
+            matches!(code, KeyCode::PageUp | KeyCode::Char('u')) && control(modifiers)
+        },
+        command: KeyCommand::PageUp,
+    },
+    Binding {
+        name: "page-down",
+        keys: "Ctrl-d, PageDown",
+        takes: |code, modifiers| {
+            matches!(code, KeyCode::PageDown | KeyCode::Char('d')) && control(modifiers)
+        },
+        command: KeyCommand::PageDown,
+    },
+    Binding {
+        name: "top",
+        keys: "g, Home",
+        takes: |code, modifiers| {
+            matches!(code, KeyCode::Home | KeyCode::Char('g'))
+                && !modifiers.contains(KeyModifiers::SHIFT)
+        },
+        command: KeyCommand::Top,
+    },
+    Binding {
+        name: "bottom",
+        keys: "G, End",
+        takes: |code, _| matches!(code, KeyCode::End | KeyCode::Char('G')),
+        command: KeyCommand::Bottom,
+    },
+];
+
+fn control(modifiers: KeyModifiers) -> bool {
+    modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// The command a key runs in the main view, as bound in the config file or else the keymap.
+fn main_command(app: &AppState, code: KeyCode, modifiers: KeyModifiers) -> Option<KeyCommand> {
+    match app.keys.binding(code, modifiers) {
+        Some(binding) => Some(flipped(app.follow_at, binding.command)),
+        None => {
+            let (code, modifiers) = oriented(app.follow_at, code, modifiers);
+            KEYMAP
+                .iter()
+                .filter(|binding| !app.keys.rebinds(binding))
+                .find(|binding| (binding.takes)(code, modifiers))
+                .map(|binding| binding.command)
+        }
+    }
+}
+
+/// The commands of the main view an embedded [`LogView`](crate::LogView) handles: those
+/// changing what it shows and where it is scrolled to, rather than opening a popup.
+const VIEW_COMMANDS: &[KeyCommand] = &[
+    KeyCommand::Undo,
+    KeyCommand::Redo,
+    KeyCommand::Wrap,
+    KeyCommand::Epochs,
+    KeyCommand::Filter,
+    KeyCommand::JsonView,
+    KeyCommand::Clear,
+    KeyCommand::Up,
+    KeyCommand::Down,
+    KeyCommand::PageUp,
+    KeyCommand::PageDown,
+    KeyCommand::Top,
+    KeyCommand::Bottom,
+];
+
+/// Handles a key of an embedded [`LogView`](crate::LogView) like the main view does, if it is
+/// bound to one of the [`VIEW_COMMANDS`]. Returns whether it was, leaving any other key to the
+/// app embedding the view.
+pub(crate) fn handle_view_key(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    view_height: usize,
+    key: KeyEvent,
+) -> bool {
+    if !main_command(app, key.code, key.modifiers)
+        .is_some_and(|command| VIEW_COMMANDS.contains(&command))
+    {
+        return false;
+    }
+    let total_lines = filtered_line_count(lines, app);
+    handle_main_event(
+        app,
+        lines,
+        total_lines,
+        view_height,
+        key.code,
+        key.modifiers,
+    );
+    anchor_scroll(app, lines);
+    true
+}
+
+fn handle_main_event(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<EventResult> {
+    app.status = None;
+    match main_command(app, code, modifiers)? {
+        KeyCommand::Quit => {
+            return Some(EventResult {
+                exit: true,
+                rerun: false,
+            });
+        }
+        KeyCommand::Help => {
+            app.help_open = true;
+        }
+        KeyCommand::Redo => {
+            apply_main(app, lines, Action::Redo);
+        }
+        KeyCommand::Undo => {
+            apply_main(app, lines, Action::Undo);
+        }
+        KeyCommand::Rerun => {
+            return Some(EventResult {
+                exit: false,
+                rerun: true,
+            });
+        }
+        KeyCommand::PreviousRun => {
+            jump_to(app, lines, total_lines, view_height, false, is_run_start);
+        }
+        KeyCommand::NextRun => {
+            jump_to(app, lines, total_lines, view_height, true, is_run_start);
+        }
+        KeyCommand::PreviousFailure => {
+            jump_to(app, lines, total_lines, view_height, false, is_failure);
+        }
+        KeyCommand::NextFailure => {
+            jump_to(app, lines, total_lines, view_height, true, is_failure);
+        }
+        KeyCommand::Failures => {
+            open_failures(app, lines);
+        }
+        KeyCommand::Profiles => {
+            if app.profiles.is_empty() {
+                app.status = Some(app.messages.no_profiles.to_string());
+            } else {
+                app.profile_selected = 0;
+                app.profiles_open = true;
+            }
+        }
+        KeyCommand::ReloadPatterns => {
+            app.status = Some(if app.patterns_file.is_none() {
+                app.messages.no_patterns_file.to_string()
+            } else {
+                match apply(app, lines, Action::ReloadPatterns) {
+                    Ok(_) => app.messages.reloaded_patterns.to_string(),
+                    Err(err) => (app.messages.cant_reload_patterns)(&err.to_string()),
+                }
+            });
+        }
+        KeyCommand::Patterns => {
+            app.dialog_open = true;
+            app.input.clear();
+            app.after_cursor = 0;
+            app.pattern_error = None;
+            app.selected = 0;
+        }
+        KeyCommand::Wrap => {
+            let action = Action::ToggleWrap {
+                viewport: Viewport::Main,
+            };
+            apply_main(app, lines, action);
+        }
+        KeyCommand::LineNumbers => {
+            apply_main(app, lines, Action::ToggleLineNumbers);
+        }
+        KeyCommand::Epochs => {
+            apply_main(app, lines, Action::ToggleEpochs);
+        }
+        KeyCommand::Reveal => {
+            if app.revealed {
+                app.revealed = false;
+                audit::record(app, "Masked the secrets");
+            } else if app.redactions.is_empty() {
+                app.status = Some(app.messages.no_secrets.to_string());
+            } else {
+                app.reveal_open = true;
+            }
+        }
+        KeyCommand::Sources => {
+            if !app.sources.is_empty() {
+                app.sources_open = true;
+            }
+        }
+        KeyCommand::Fields => {
+            open_fields(app, lines);
+        }
+        KeyCommand::Search => {
+            app.search_open = true;
+            app.search_input.clear();
+            app.search_error = None;
+        }
+        KeyCommand::Prompt => {
+            app.prompt_open = true;
+            app.prompt_input.clear();
+            app.prompt_error = None;
+        }
+        KeyCommand::Write => {
+            app.prompt_open = true;
+            app.prompt_input = "write ".to_string();
+            app.prompt_error = None;
+        }
+        KeyCommand::NextMatch => {
+            jump_to_match(app, lines, total_lines, view_height, true);
+        }
+        KeyCommand::PreviousMatch => {
+            jump_to_match(app, lines, total_lines, view_height, false);
+        }
+        KeyCommand::Detail => {
+            open_detail(app, lines, total_lines, view_height);
+        }
+        KeyCommand::Select => {
+            start_selection(app, lines, total_lines, view_height);
+        }
+        KeyCommand::Filter => {
+            apply_main(app, lines, Action::ToggleFilter);
+        }
+        KeyCommand::FilterExpression => {
+            app.prompt_open = true;
+            app.prompt_input = match &app.filter_expr {
+                Some(expression) => format!("filter {}", expression.text()),
+                None => "filter ".to_string(),
+            };
+            app.prompt_error = None;
+        }
+        KeyCommand::Pause => {
+            toggle_pause(app, lines);
+        }
+        KeyCommand::Grouping => {
+            apply_main(app, lines, Action::ToggleGrouping);
+        }
+        KeyCommand::Entry => {
+            toggle_entry(app, lines, total_lines, view_height);
+        }
+        KeyCommand::Order => {
+            apply_main(app, lines, Action::ToggleOrder);
+        }
+        KeyCommand::MinLevel => {
+            apply_main(app, lines, Action::CycleMinLevel);
+        }
+        KeyCommand::JsonView => {
+            apply_main(app, lines, Action::CycleJsonView);
+        }
+        KeyCommand::Clear => {
+            apply_main(app, lines, Action::ClearLines);
+        }
+        KeyCommand::Up if scrolls_rows(app) => scroll_wrapped(app, lines, view_height, -1),
+        KeyCommand::Down if scrolls_rows(app) => scroll_wrapped(app, lines, view_height, 1),
+        KeyCommand::PageUp if scrolls_rows(app) => {
+            let delta = usize::max(1, view_height / 2) as isize;
+            scroll_wrapped(app, lines, view_height, -delta);
+        }
+        KeyCommand::PageDown if scrolls_rows(app) => {
+            let delta = usize::max(1, view_height / 2) as isize;
+            scroll_wrapped(app, lines, view_height, delta);
+        }
+        KeyCommand::Up => {
+            if total_lines > 0 {
+                let max_start = max_start(total_lines, view_height);
+                if app.follow {
+                    app.follow = false;
+                    app.scroll = max_start;
+                }
+                if app.scroll > 0 {
+                    app.scroll -= 1;
+                }
+            }
+        }
+        KeyCommand::Down => {
+            if total_lines > 0 {
+                let max_start = max_start(total_lines, view_height);
+                if app.follow {
+                    app.scroll = max_start;
+                }
+                if app.scroll < max_start {
+                    app.scroll += 1;
+                } else {
+                    app.follow = true;
+                }
+            }
+        }
+        KeyCommand::PageUp => {
+            if total_lines > 0 {
+                let max_start = max_start(total_lines, view_height);
+                let delta = usize::max(1, view_height / 2);
+                if app.follow {
+                    app.follow = false;
+                    app.scroll = max_start;
+                }
+                app.scroll = app.scroll.saturating_sub(delta);
+            }
+        }
+        KeyCommand::PageDown => {
+            if total_lines > 0 {
+                let max_start = max_start(total_lines, view_height);
+                let delta = usize::max(1, view_height / 2);
+                if app.follow {
+                    app.scroll = max_start;
+                }
+                app.scroll = usize::min(app.scroll + delta, max_start);
+                if app.scroll == max_start {
+                    app.follow = true;
+                }
+            }
+        }
+        KeyCommand::Top => {
+            app.follow = false;
+            app.scroll = 0;
+            app.row_offset = 0;
+        }
+        KeyCommand::Bottom => {
+            app.follow = true;
+            app.scroll = max_start(total_lines, view_height);
+        }
+    }
+
+    None
+}
+
+/// Scrolls a view of wrapped lines by `delta` rows rather than lines, following new lines again
+/// once scrolled down to them.
+fn scroll_wrapped(app: &mut AppState, lines: &LogBuffer, view_height: usize, delta: isize) {
+    let visible: Vec<usize> = visible_indices(lines, app).collect();
+    let width = app.wrap_width.get();
+    let rows = |position: usize| visual_rows(&lines[visible[position]], app, width);
+    let newest = max_row_start(visible.len(), view_height, rows);
+    let current = if app.follow {
+        newest
+    } else {
+        (app.scroll, app.row_offset)
+    };
+    let (scroll, row_offset) = scroll_rows(current, delta, visible.len(), rows).min(newest);
+    app.follow = delta > 0 && (scroll, row_offset) == newest;
+    app.scroll = scroll;
+    anchor_scroll(app, lines);
+    app.row_offset = row_offset;
+}
+
+/// [`oriented`] for the keys bound in the config file, which swaps the scrolling commands
+/// rather than the keys.
+fn flipped(follow_at: FollowAt, command: KeyCommand) -> KeyCommand {
+    if follow_at == FollowAt::Bottom {
+        return command;
+    }
+    match command {
+        KeyCommand::Up => KeyCommand::Down,
+        KeyCommand::Down => KeyCommand::Up,
+        KeyCommand::PageUp => KeyCommand::PageDown,
+        KeyCommand::PageDown => KeyCommand::PageUp,
+        KeyCommand::Top => KeyCommand::Bottom,
+        KeyCommand::Bottom => KeyCommand::Top,
+        command => command,
+    }
+}
+
+/// Following at the top, the buffer runs up the screen, so the scroll keys are swapped to
+/// keep moving the view the way they point.
+fn oriented(
+    follow_at: FollowAt,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> (KeyCode, KeyModifiers) {
+    if follow_at == FollowAt::Bottom {
+        return (code, modifiers);
+    }
+    let control = modifiers.contains(KeyModifiers::CONTROL);
+    let code = match code {
+        KeyCode::Up => KeyCode::Down,
+        KeyCode::Down => KeyCode::Up,
+        KeyCode::Char('k') => KeyCode::Char('j'),
+        KeyCode::Char('j') => KeyCode::Char('k'),
+        KeyCode::PageUp => KeyCode::PageDown,
+        KeyCode::PageDown => KeyCode::PageUp,
+        KeyCode::Char('u') if control => KeyCode::Char('d'),
+        KeyCode::Char('d') if control => KeyCode::Char('u'),
+        KeyCode::Home => KeyCode::End,
+        KeyCode::End => KeyCode::Home,
+        KeyCode::Char('g') if !modifiers.contains(KeyModifiers::SHIFT) => KeyCode::End,
+        KeyCode::Char('G') => KeyCode::Home,
+        code => return (code, modifiers),
+    };
+    (code, modifiers.difference(KeyModifiers::SHIFT))
+}
+
+/// Applies an action from the main view. Only undoing or redoing a case toggle can fail, which
+/// is reported in the pattern dialog.
+fn apply_main(app: &mut AppState, lines: &mut LogBuffer, action: Action) {
+    if let Err(err) = apply(app, lines, action) {
+        app.pattern_error = Some(format!("Invalid pattern: {err}"));
+    }
+}
+
+/// Opens the detail popup for the line at the top of the view, defaulting to a hex dump for
+/// binary-looking lines.
+fn open_detail(app: &mut AppState, lines: &LogBuffer, total_lines: usize, view_height: usize) {
+    let max_start = max_start(total_lines, view_height);
+    let start = if app.follow {
+        max_start
+    } else {
+        app.scroll.min(max_start)
+    };
+    let top = top_line(start, total_lines, view_height, app.follow_at);
+    if let Some(index) = visible_line_index(lines, app, top) {
+        app.detail_hex = lines[index].is_binary();
+        app.detail_line = Some(lines[index].id);
+        if let Some(enricher) = &mut app.enricher {
+            enricher.request(&lines[index].text);
+        }
+    }
+}
+
+/// Starts selecting lines at the output line at the top of the view, or the first one below
+/// it.
+fn start_selection(app: &mut AppState, lines: &LogBuffer, total_lines: usize, view_height: usize) {
+    let max_start = max_start(total_lines, view_height);
+    let start = if app.follow {
+        max_start
+    } else {
+        app.scroll.min(max_start)
+    };
+    let top = top_line(start, total_lines, view_height, app.follow_at);
+    let numbers: Vec<Option<usize>> = visible_lines(lines, app).map(|line| line.number).collect();
+    let first = match app.follow_at {
+        FollowAt::Bottom => numbers.iter().skip(top).flatten().next(),
+        FollowAt::Top => numbers.iter().take(top + 1).rev().flatten().next(),
+    };
+    if let Some(&number) = first {
+        app.follow = false;
+        app.scroll = start;
+        app.selection = Some(Selection {
+            anchor: number,
+            cursor: number,
+        });
+    }
+}
+
+/// Moves the selection's cursor to the next (or previous) output line, scrolling to keep it in
+/// view.
+fn move_selection(
+    app: &mut AppState,
+    lines: &LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    forward: bool,
+) {
+    let Some(selection) = app.selection else {
+        return;
+    };
+    let numbers: Vec<Option<usize>> = visible_lines(lines, app).map(|line| line.number).collect();
+    let Some(current) = numbers
+        .iter()
+        .position(|&number| number == Some(selection.cursor))
+    else {
+        return;
+    };
+    let numbered = |(position, number): (usize, &Option<usize>)| Some((position, (*number)?));
+    let next = if forward {
+        numbers
+            .iter()
+            .enumerate()
+            .skip(current + 1)
+            .find_map(numbered)
+    } else {
+        numbers
+            .iter()
+            .enumerate()
+            .take(current)
+            .rev()
+            .find_map(numbered)
+    };
+    let Some((position, number)) = next else {
+        return;
+    };
+    app.selection = Some(Selection {
+        cursor: number,
+        ..selection
+    });
+    let max_start = max_start(total_lines, view_height);
+    let start = if app.follow {
+        max_start
+    } else {
+        app.scroll.min(max_start)
+    };
+    app.follow = false;
+    app.scroll = if position < start {
+        position
+    } else if position >= start + view_height {
+        position + 1 - view_height
+    } else {
+        start
+    }
+    .min(max_start);
+}
+
+/// The number of selected lines still buffered and shown, and their raw text, oldest first.
+fn selected_text(app: &AppState, lines: &LogBuffer) -> (usize, String) {
+    let selected: Vec<Cow<str>> = visible_lines(lines, app)
+        .filter(|line| {
+            line.number.is_some_and(|number| {
+                app.selection
+                    .is_some_and(|selection| selection.contains(number))
+            })
+        })
+        .map(|line| redact(&app.redactions, Cow::Borrowed(&line.text)))
+        .collect();
+    (selected.len(), selected.join("\n"))
+}
+
+/// Shows the secrets on screen only once asked to with `y`, so they aren't revealed by a stray
+/// key.
+fn handle_reveal_event(app: &mut AppState, code: KeyCode) -> Option<EventResult> {
+    app.reveal_open = false;
+    if code == KeyCode::Char('y') {
+        app.revealed = true;
+        audit::record(app, "Revealed the secrets");
+        app.status = Some((app.messages.revealed)(app.keys.key("reveal")));
+    }
+    None
+}
+
+/// Freezes the view while lines keep being buffered, or resumes following them.
+fn toggle_pause(app: &mut AppState, lines: &LogBuffer) {
+    match app.pause.take() {
+        Some(pause) => {
+            app.follow = true;
+            app.status = Some((app.messages.new_while_paused)(pause.received));
+        }
+        None => {
+            app.pause = Some(Pause {
+                end: lines.len(),
+                received: 0,
+            });
+        }
+    }
+}
+
+/// Expands or collapses the entry of the line at the top of the view, keeping the line
+/// starting it there while scrolled.
+fn toggle_entry(app: &mut AppState, lines: &mut LogBuffer, total_lines: usize, view_height: usize) {
+    if !app.group_entries {
+        app.status = Some((app.messages.not_grouping)(app.keys.key("group-entries")));
+        return;
+    }
+    let max_start = max_start(total_lines, view_height);
+    let start = if app.follow {
+        max_start
+    } else {
+        app.scroll.min(max_start)
+    };
+    let top = top_line(start, total_lines, view_height, app.follow_at);
+    let Some(index) = visible_line_index(lines, app, top) else {
+        return;
+    };
+    let first = entry_first_line(lines, index);
+    let continuations = lines[first].continuations;
+    if continuations == 0 {
+        return;
+    }
+    let expanded = !lines[first].expanded;
+    for line in lines.iter_mut().skip(first).take(continuations + 1) {
+        line.expanded = expanded;
+    }
+    if !app.follow {
+        let position = lines
+            .iter()
+            .take(first)
+            .filter(|line| line_visible(line, app))
+            .count();
+        app.scroll = start_for_top(position, view_height, app.follow_at);
+    }
+}
+
+/// Opens the failures panel listing the buffered lines reporting a failure.
+fn open_failures(app: &mut AppState, lines: &LogBuffer) {
+    app.failures = lines
+        .iter()
+        .filter(|line| is_failure(line))
+        .filter_map(|line| Some((line.number?, line.text.clone())))
+        .collect();
+    if app.failures.is_empty() {
+        app.status = Some(app.messages.no_failures.to_string());
+        return;
+    }
+    app.failure_selected = 0;
+    app.failures_open = true;
+}
+
+/// Opens the fields dialog listing the keys of the buffered logfmt lines, and any hidden ones
+/// no longer buffered so they can still be shown again.
+fn open_fields(app: &mut AppState, lines: &LogBuffer) {
+    let mut keys = app.hidden_fields.clone();
+    for line in lines.iter().filter(|line| line.kind == LineKind::Output) {
+        for pair in logfmt::parse(&line.text).unwrap_or_default() {
+            if !keys.contains(&line.text[pair.key.clone()]) {
+                keys.insert(line.text[pair.key].to_string());
+            }
+        }
+    }
+    if keys.is_empty() {
+        app.status = Some(app.messages.no_fields.to_string());
+        return;
+    }
+    app.field_keys = keys.into_iter().collect();
+    app.field_selected = 0;
+    app.fields_open = true;
+}
+
+fn is_run_start(line: &LogLine) -> bool {
+    line.kind == LineKind::RunSeparator
+}
+
+fn is_failure(line: &LogLine) -> bool {
+    line.outcome == Some(Outcome::Failed)
+}
+
+/// Scrolls to the previous (or next) line of a kind, like a run separator, relative to the top
+/// of the view.
+fn jump_to(
+    app: &mut AppState,
+    lines: &LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    forward: bool,
+    is_target: fn(&LogLine) -> bool,
+) {
+    let max_start = max_start(total_lines, view_height);
+    let current = if app.follow {
+        max_start
+    } else {
+        app.scroll.min(max_start)
+    };
+    let target = {
+        let mut starts = visible_lines(lines, app)
+            .enumerate()
+            .filter(|(_, line)| is_target(line))
+            .map(|(index, _)| index);
+        if forward {
+            starts.find(|&index| index > current)
+        } else {
+            starts.filter(|&index| index < current).last()
+        }
+    };
+    if let Some(target) = target {
+        app.follow = false;
+        app.scroll = target.min(max_start);
+    }
+}
+
+/// Makes the next (or previous) line matching the search the current match, wrapping around,
+/// and scrolls it to the top of the view. Without a current match, searches forward from the
+/// top of the view. Next is down the screen, so newest-first it is further back in the buffer.
+fn jump_to_match(
+    app: &mut AppState,
+    lines: &LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    forward: bool,
+) {
+    let Some(search) = &app.search else {
+        return;
+    };
+    let max_start = max_start(total_lines, view_height);
+    let start = if app.follow {
+        max_start
+    } else {
+        app.scroll.min(max_start)
+    };
+    let top = top_line(start, total_lines, view_height, app.follow_at);
+    let top = visible_line_index(lines, app, top).unwrap_or(0);
+    let forward = forward == (app.follow_at == FollowAt::Bottom);
+    let current = app.search_match.and_then(|id| lines.position(id));
+    let matches: Vec<(usize, usize)> = snapshot(lines, app)
+        .enumerate()
+        .filter(|(_, line)| line_visible(line, app))
+        .enumerate()
+        .filter(|(_, (_, line))| search.is_match(&line.text))
+        .map(|(position, (index, _))| (position, index))
+        .collect();
+    let target = if forward {
+        let start = current.map_or(top, |current| current + 1);
+        matches
+            .iter()
+            .find(|&&(_, index)| index >= start)
+            .or(matches.first())
+    } else {
+        let end = current.unwrap_or(top);
+        matches
+            .iter()
+            .rev()
+            .find(|&&(_, index)| index < end)
+            .or(matches.last())
+    };
+    if let Some(&(position, index)) = target {
+        app.search_match = Some(lines[index].id);
+        app.follow = false;
+        app.scroll = start_for_top(position, view_height, app.follow_at).min(max_start);
+    }
+}
+
+/// Stops following and puts the target line on top, or as close as the end of the lines allows,
+/// returning the status to show if the line isn't there.
+fn go_to(
+    app: &mut AppState,
+    lines: &LogBuffer,
+    total_lines: usize,
+    view_height: usize,
+    target: GoTo,
+) -> Option<String> {
+    let numbers: Vec<Option<usize>> = visible_lines(lines, app).map(|line| line.number).collect();
+    let last = numbers.len().checked_sub(1)?;
+    let position = match target {
+        GoTo::Line(number) => numbers
+            .iter()
+            .position(|shown| shown.is_some_and(|shown| shown >= number))
+            .unwrap_or(last),
+        GoTo::Percent(percent) => last * percent / 100,
+    };
+    app.follow = false;
+    app.scroll = start_for_top(position, view_height, app.follow_at)
+        .min(max_start(total_lines, view_height));
+    match target {
+        GoTo::Line(number) if numbers[position] != Some(number) => Some(format!(
+            "Line {number} isn't shown, went to the nearest one"
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        handle_detail_event, handle_dialog_event, handle_key, handle_main_event, input_cursor,
+        selected_text,
+    };
+    use crate::{
+        action::{apply, Action},
+        buffer::LogBuffer,
+        build_literal, build_pattern,
+        config::{Profile, ProfilePattern},
+        keys::KeyBindings,
+        max_start,
+        parser::Format,
+        preset::Tool,
+        push_line, push_output,
+        redact::Redaction,
+        source::Source,
+        visible_lines, AppState, FollowAt, LogLine, Selection,
+    };
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::style::Color;
+    use regex::Regex;
+    use std::{collections::BTreeMap, num::NonZeroUsize};
+
+    fn app_with_patterns(ignore_case: bool) -> AppState {
+        let patterns = vec![
+            build_pattern("foo".to_string(), true).expect("pattern build failed"),
+            build_pattern("bar".to_string(), true).expect("pattern build failed"),
+        ];
+        AppState::new(patterns, ignore_case)
+    }
+
+    #[test]
+    fn dialog_enter_adds_pattern_and_closes() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = true;
+        app.input = "new".to_string();
+
+        let result = handle_dialog_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            KeyCode::Enter,
+            KeyModifiers::empty(),
+        )
+        .expect("dialog handler failed");
+
+        assert!(result.is_none());
+        assert!(!app.dialog_open);
+        assert!(app.input.is_empty());
+        assert!(app.pattern_error.is_none());
+        assert_eq!(app.patterns.len(), 3);
+        assert_eq!(app.patterns[2].pattern, "new");
+        assert!(app.patterns[2].case_sensitive);
+    }
+
+    #[test]
+    fn dialog_enter_on_a_pattern_edits_it_in_place() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        app.dialog_open = true;
+        app.patterns[0].exclude = true;
+        app.selected = 0;
+        let mut press = |app: &mut AppState, code| {
+            handle_dialog_event(app, &mut lines, code, KeyModifiers::empty())
+                .expect("dialog handler failed");
+        };
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input, "foo");
+        assert_eq!(app.editing, Some(0));
+        press(&mut app, KeyCode::Char('d'));
+        press(&mut app, KeyCode::Enter);
+
+        assert!(!app.dialog_open);
+        assert_eq!(app.editing, None);
+        let patterns: Vec<&str> = app.patterns.iter().map(|p| p.pattern.as_str()).collect();
+        assert_eq!(patterns, ["food", "bar"]);
+        assert!(app.patterns[0].exclude);
+        assert!(app.patterns[0].regex.is_match("food"));
+
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(app.patterns[0].pattern, "foo");
+    }
+
+    #[test]
+    fn dialog_toggle_case_sensitive() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = true;
+        app.selected = 0;
+
+        let result = handle_dialog_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            KeyCode::Char('t'),
+            KeyModifiers::CONTROL,
+        )
+        .expect("dialog handler failed");
+
+        assert!(result.is_none());
+        assert!(!app.patterns[0].case_sensitive);
+        assert!(app.patterns[0].regex.is_match("FOO"));
+    }
+
+    #[test]
+    fn dialog_ctrl_f_matches_the_selected_pattern_as_text() {
+        let mut app = app_with_patterns(false);
+        app.patterns = vec![build_pattern("a.c".to_string(), true).expect("build failed")];
+        app.dialog_open = true;
+        app.selected = 0;
+        let mut lines = LogBuffer::default();
+        let mut ctrl_f = |app: &mut AppState| {
+            handle_dialog_event(app, &mut lines, KeyCode::Char('f'), KeyModifiers::CONTROL)
+                .expect("dialog handler failed");
+        };
+
+        ctrl_f(&mut app);
+        assert!(app.patterns[0].literal);
+        assert_eq!(app.patterns[0].pattern, "a.c");
+        assert!(!app.patterns[0].regex.is_match("abc"));
+        assert!(app.patterns[0].regex.is_match("a.c"));
+
+        app.patterns[0] = build_literal("[ERROR".to_string(), true).expect("build failed");
+        ctrl_f(&mut app);
+        assert!(app.patterns[0].literal);
+        assert!(app.pattern_error.is_some());
+    }
+
+    #[test]
+    fn dialog_w_matches_the_selected_pattern_as_whole_words() {
+        let mut app = app_with_patterns(false);
+        app.patterns = vec![build_pattern("err".to_string(), true).expect("build failed")];
+        app.dialog_open = true;
+        app.selected = 0;
+        let mut lines = LogBuffer::from(vec![LogLine::output("transferred".to_string())]);
+
+        handle_dialog_event(&mut app, &mut lines, KeyCode::Char('w'), KeyModifiers::NONE)
+            .expect("dialog handler failed");
+        assert!(app.patterns[0].word);
+        assert!(app.input.is_empty());
+        assert!(!app.patterns[0].regex.is_match("transferred"));
+        assert!(app.patterns[0].regex.is_match("err: disk full"));
+
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert!(!app.patterns[0].word);
+        assert!(app.patterns[0].regex.is_match("transferred"));
+    }
+
+    #[test]
+    fn dialog_tab_negates_the_selected_pattern() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = true;
+        app.filter_only = true;
+        app.selected = 1;
+        let mut lines = LogBuffer::default();
+        for text in ["foo", "foo bar", "bar", "baz"] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        let shown = |app: &AppState, lines: &LogBuffer| -> Vec<String> {
+            visible_lines(lines, app)
+                .map(|line| line.text.clone())
+                .collect()
+        };
+        assert_eq!(shown(&app, &lines), ["foo", "foo bar", "bar"]);
+
+        handle_dialog_event(&mut app, &mut lines, KeyCode::Tab, KeyModifiers::empty())
+            .expect("dialog handler failed");
+        assert!(!app.patterns[0].exclude);
+        assert!(app.patterns[1].exclude);
+        assert_eq!(shown(&app, &lines), ["foo"]);
+
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert!(!app.patterns[1].exclude);
+        assert_eq!(shown(&app, &lines), ["foo", "foo bar", "bar"]);
+    }
+
+    #[test]
+    fn dialog_space_toggles_selected_pattern_but_types_into_input() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = true;
+        app.selected = 0;
+        let mut lines = LogBuffer::default();
+
+        let space = |app: &mut AppState, lines: &mut LogBuffer| {
+            handle_dialog_event(app, lines, KeyCode::Char(' '), KeyModifiers::empty())
+                .expect("dialog handler failed");
+        };
+        space(&mut app, &mut lines);
+        assert!(!app.patterns[0].enabled);
+        assert!(app.input.is_empty());
+
+        app.selected = app.patterns.len();
+        space(&mut app, &mut lines);
+        assert_eq!(app.input, " ");
+        assert!(app.patterns[1].enabled);
+    }
+
+    #[test]
+    fn dialog_c_cycles_the_selected_patterns_color() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        app.dialog_open = true;
+        app.selected = 1;
+        let mut press = |app: &mut AppState| {
+            handle_dialog_event(app, &mut lines, KeyCode::Char('c'), KeyModifiers::empty())
+                .expect("dialog handler failed");
+        };
+
+        press(&mut app);
+        assert_eq!(app.patterns[1].color, Some(Color::Blue));
+        press(&mut app);
+        assert_eq!(app.patterns[1].color, Some(Color::Yellow));
+        assert_eq!(app.patterns[0].color, None);
+        assert!(app.input.is_empty());
+
+        app.selected = app.patterns.len();
+        press(&mut app);
+        assert_eq!(app.input, "c");
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert_eq!(app.patterns[1].color, Some(Color::Blue));
+    }
+
+    #[test]
+    fn dialog_ctrl_s_lengthens_the_snooze_until_it_wakes_the_pattern_up() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = true;
+        app.selected = 1;
+        let mut lines = LogBuffer::from(vec![LogLine::output("bar".to_string())]);
+
+        let mut minutes = Vec::new();
+        for _ in 0..4 {
+            handle_dialog_event(
+                &mut app,
+                &mut lines,
+                KeyCode::Char('s'),
+                KeyModifiers::CONTROL,
+            )
+            .expect("dialog handler failed");
+            minutes.push(app.patterns[1].snooze.map(|snooze| snooze.minutes));
+        }
+
+        assert_eq!(minutes, vec![Some(5), Some(15), Some(60), None]);
+        assert!(app.patterns[0].snooze.is_none());
+        assert!(app.input.is_empty());
+    }
+
+    #[test]
+    fn dialog_delete_removes_pattern() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = true;
+        app.selected = 0;
+
+        let result = handle_dialog_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            KeyCode::Delete,
+            KeyModifiers::empty(),
+        )
+        .expect("dialog handler failed");
+
+        assert!(result.is_none());
+        assert_eq!(app.patterns.len(), 1);
+        assert_eq!(app.patterns[0].pattern, "bar");
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn dialog_backspace_moves_selection_to_input() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = true;
+        app.selected = 0;
+        app.input = "ab".to_string();
+
+        let result = handle_dialog_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            KeyCode::Backspace,
+            KeyModifiers::empty(),
+        )
+        .expect("dialog handler failed");
+
+        assert!(result.is_none());
+        assert_eq!(app.input, "a");
+        assert_eq!(app.selected, app.patterns.len());
+    }
+
+    #[test]
+    fn dialog_input_is_edited_at_the_cursor() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        app.dialog_open = true;
+        app.selected = 0;
+        app.input = "disk full".to_string();
+        let mut press = |app: &mut AppState, code, modifiers| {
+            handle_dialog_event(app, &mut lines, code, modifiers).expect("dialog handler failed");
+        };
+
+        press(&mut app, KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(app.selected, app.patterns.len());
+        assert_eq!(input_cursor(&app), 5);
+        press(&mut app, KeyCode::Backspace, KeyModifiers::empty());
+        press(&mut app, KeyCode::Char('-'), KeyModifiers::empty());
+        assert_eq!(app.input, "disk-full");
+        press(&mut app, KeyCode::Home, KeyModifiers::empty());
+        press(&mut app, KeyCode::Delete, KeyModifiers::empty());
+        press(&mut app, KeyCode::Char('D'), KeyModifiers::empty());
+        assert_eq!(app.input, "Disk-full");
+        assert_eq!(input_cursor(&app), 1);
+        press(&mut app, KeyCode::Right, KeyModifiers::CONTROL);
+        assert_eq!(input_cursor(&app), 9);
+        press(&mut app, KeyCode::Left, KeyModifiers::empty());
+        press(&mut app, KeyCode::Left, KeyModifiers::empty());
+        press(&mut app, KeyCode::Char('ł'), KeyModifiers::empty());
+        press(&mut app, KeyCode::Left, KeyModifiers::empty());
+        press(&mut app, KeyCode::Delete, KeyModifiers::empty());
+        assert_eq!(app.input, "Disk-full");
+        press(&mut app, KeyCode::End, KeyModifiers::empty());
+        press(&mut app, KeyCode::Right, KeyModifiers::empty());
+        assert_eq!(input_cursor(&app), app.input.len());
+        assert_eq!(app.patterns.len(), 2);
+    }
+
+    #[test]
+    fn main_open_dialog_resets_state() {
+        let mut app = app_with_patterns(false);
+        app.dialog_open = false;
+        app.input = "x".to_string();
+        app.pattern_error = Some("err".to_string());
+
+        let result = handle_main_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            0,
+            0,
+            KeyCode::Char('p'),
+            KeyModifiers::empty(),
+        );
+
+        assert!(result.is_none());
+        assert!(app.dialog_open);
+        assert_eq!(app.selected, 0);
+        assert!(app.input.is_empty());
+        assert!(app.pattern_error.is_none());
+    }
+
+    #[test]
+    fn main_scroll_up_breaks_follow() {
+        let mut app = app_with_patterns(false);
+        let total_lines = 100;
+        let view_height = 10;
+        app.follow = true;
+        app.scroll = 0;
+
+        let result = handle_main_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            total_lines,
+            view_height,
+            KeyCode::Up,
+            KeyModifiers::empty(),
+        );
+
+        assert!(result.is_none());
+        assert!(!app.follow);
+        assert_eq!(
+            app.scroll,
+            max_start(total_lines, view_height).saturating_sub(1)
+        );
+    }
+
+    #[test]
+    fn main_scroll_down_to_follow() {
+        let mut app = app_with_patterns(false);
+        let total_lines = 100;
+        let view_height = 10;
+        let max_start = max_start(total_lines, view_height);
+        app.follow = false;
+        app.scroll = max_start;
+
+        let result = handle_main_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            total_lines,
+            view_height,
+            KeyCode::Down,
+            KeyModifiers::empty(),
+        );
+
+        assert!(result.is_none());
+        assert!(app.follow);
+        assert_eq!(app.scroll, max_start);
+    }
+
+    #[test]
+    fn main_home_end_positions() {
+        let mut app = app_with_patterns(false);
+        let total_lines = 40;
+        let view_height = 10;
+
+        let _ = handle_main_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            total_lines,
+            view_height,
+            KeyCode::Home,
+            KeyModifiers::empty(),
+        );
+        assert!(!app.follow);
+        assert_eq!(app.scroll, 0);
+
+        let _ = handle_main_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            total_lines,
+            view_height,
+            KeyCode::End,
+            KeyModifiers::empty(),
+        );
+        assert!(app.follow);
+        assert_eq!(app.scroll, max_start(total_lines, view_height));
+    }
+
+    #[test]
+    fn main_toggle_filter_mode() {
+        let mut app = app_with_patterns(false);
+        app.filter_only = false;
+        app.follow = false;
+        app.scroll = 5;
+
+        let result = handle_main_event(
+            &mut app,
+            &mut LogBuffer::default(),
+            20,
+            10,
+            KeyCode::Char('f'),
+            KeyModifiers::empty(),
+        );
+
+        assert!(result.is_none());
+        assert!(app.filter_only);
+        assert!(app.follow);
+        assert_eq!(app.scroll, 0);
+    }
+
+    fn lines_with_runs() -> LogBuffer {
+        let mut lines = Vec::new();
+        for run in 1..=3 {
+            lines.push(LogLine::run_separator(format!("--- run {run} ---")));
+            for i in 0..10 {
+                lines.push(LogLine::output(format!("line {i}")));
+            }
+        }
+        LogBuffer::from(lines)
+    }
+
+    #[test]
+    fn main_jump_to_previous_and_next_run() {
+        let mut app = app_with_patterns(false);
+        let mut lines = lines_with_runs();
+        let total_lines = lines.len();
+        let view_height = 5;
+
+        let _ = handle_main_event(
+            &mut app,
+            &mut lines,
+            total_lines,
+            view_height,
+            KeyCode::Char('['),
+            KeyModifiers::empty(),
+        );
+        assert!(!app.follow);
+        assert_eq!(app.scroll, 22);
+
+        let _ = handle_main_event(
+            &mut app,
+            &mut lines,
+            total_lines,
+            view_height,
+            KeyCode::Char('['),
+            KeyModifiers::empty(),
+        );
+        assert_eq!(app.scroll, 11);
+
+        let _ = handle_main_event(
+            &mut app,
+            &mut lines,
+            total_lines,
+            view_height,
+            KeyCode::Char(']'),
+            KeyModifiers::empty(),
+        );
+        assert_eq!(app.scroll, 22);
+    }
+
+    #[test]
+    fn failures_are_jumped_between_and_listed() {
+        let mut app = app_with_patterns(false);
+        app.outcomes = Some(Tool::Cargo.preset().outcomes());
+        let mut lines = LogBuffer::default();
+        for i in 0..20 {
+            let text = match i {
+                3 => "error[E0308]: mismatched types".to_string(),
+                12 => "test parser::tests::dates ... FAILED".to_string(),
+                _ => format!("   Compiling crate{i}"),
+            };
+            push_line(&mut app, &mut lines, LogLine::output(text));
+        }
+        let total_lines = lines.len();
+        let mut press = |app: &mut AppState, code| {
+            let key = KeyEvent::new(code, KeyModifiers::empty());
+            handle_key(app, &mut lines, total_lines, 5, key).expect("key handler failed");
+        };
+
+        press(&mut app, KeyCode::Char('{'));
+        assert!(!app.follow);
+        assert_eq!(app.scroll, 12);
+        press(&mut app, KeyCode::Char('{'));
+        assert_eq!(app.scroll, 3);
+        press(&mut app, KeyCode::Char('}'));
+        assert_eq!(app.scroll, 12);
 
-        assert!(result.is_none());
-        assert_eq!(app.input, "a");
-        assert_eq!(app.selected, app.patterns.len());
+        press(&mut app, KeyCode::Char('g'));
+        press(&mut app, KeyCode::Char('!'));
+        assert!(app.failures_open);
+        assert_eq!(app.failures.len(), 2);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        assert!(!app.failures_open);
+        assert_eq!(app.scroll, 12);
     }
 
     #[test]
-    fn main_open_dialog_resets_state() {
+    fn profile_picked_in_the_picker_replaces_the_patterns() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        for name in ["java", "nginx"] {
+            let profile = Profile {
+                patterns: vec![ProfilePattern::Text(format!("{name} error"))],
+                ..Profile::default()
+            };
+            app.profiles.insert(name.to_string(), profile);
+        }
+        let mut press = |app: &mut AppState, code, modifiers| {
+            let key = KeyEvent::new(code, modifiers);
+            handle_key(app, &mut lines, 0, 5, key).expect("key handler failed");
+        };
+
+        press(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+        assert!(app.profiles_open);
+        assert!(!app.dialog_open);
+        press(&mut app, KeyCode::Down, KeyModifiers::empty());
+        press(&mut app, KeyCode::Enter, KeyModifiers::empty());
+        assert!(!app.profiles_open);
+        let patterns: Vec<&str> = app.patterns.iter().map(|p| p.pattern.as_str()).collect();
+        assert_eq!(patterns, ["nginx error"]);
+    }
+
+    #[test]
+    fn keys_bound_in_the_config_replace_the_defaults() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        let keys = BTreeMap::from([
+            ("quit".to_string(), vec!["Esc".to_string()]),
+            ("page-down".to_string(), vec!["Ctrl-f".to_string()]),
+        ]);
+        app.keys = KeyBindings::new(&keys).expect("bindings failed");
+        app.follow = false;
+        let mut press = |app: &mut AppState, code, modifiers| {
+            let key = KeyEvent::new(code, modifiers);
+            handle_key(app, &mut lines, 100, 10, key).expect("key handler failed")
+        };
+
+        assert!(!press(&mut app, KeyCode::Char('q'), KeyModifiers::empty()).exit);
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL);
+        assert_eq!(app.scroll, 5);
+        press(&mut app, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(app.scroll, 5);
+        let result = press(&mut app, KeyCode::Esc, KeyModifiers::empty());
+        assert!(result.exit, "esc should quit");
+    }
+
+    #[test]
+    fn main_rerun_requests_rerun() {
         let mut app = app_with_patterns(false);
-        app.dialog_open = false;
-        app.input = "x".to_string();
-        app.pattern_error = Some("err".to_string());
 
         let result = handle_main_event(
             &mut app,
+            &mut LogBuffer::default(),
             0,
             0,
-            KeyCode::Char('p'),
+            KeyCode::Char('r'),
             KeyModifiers::empty(),
-            true,
-        );
+        )
+        .expect("rerun should return a result");
 
-        assert!(result.is_none());
-        assert!(app.dialog_open);
-        assert_eq!(app.selected, 0);
-        assert!(app.input.is_empty());
-        assert!(app.pattern_error.is_none());
+        assert!(result.rerun);
+        assert!(!result.exit);
     }
 
     #[test]
-    fn main_scroll_up_breaks_follow() {
+    fn main_enter_opens_detail_for_top_line() {
         let mut app = app_with_patterns(false);
-        let total_lines = 100;
-        let view_height = 10;
-        app.follow = true;
-        app.scroll = 0;
+        let mut lines = LogBuffer::from(vec![
+            LogLine::output("text".to_string()),
+            LogLine::output("raw\x00buffer".to_string()),
+        ]);
+        app.follow = false;
+        app.scroll = 1;
 
-        let result = handle_main_event(
+        let _ = handle_main_event(
             &mut app,
-            total_lines,
-            view_height,
-            KeyCode::Up,
+            &mut lines,
+            2,
+            1,
+            KeyCode::Enter,
             KeyModifiers::empty(),
-            true,
         );
+        assert_eq!(app.detail_line, Some(1));
+        assert!(app.detail_hex);
 
-        assert!(result.is_none());
-        assert!(!app.follow);
-        assert_eq!(
-            app.scroll,
-            max_start(total_lines, view_height).saturating_sub(1)
+        let _ = handle_detail_event(
+            &mut app,
+            &mut lines,
+            KeyCode::Char('x'),
+            KeyModifiers::empty(),
+        );
+        assert!(!app.detail_hex);
+
+        let _ = handle_detail_event(
+            &mut app,
+            &mut lines,
+            KeyCode::Char('w'),
+            KeyModifiers::empty(),
         );
+        assert!(!app.detail_wrap);
+        assert!(!app.wrap, "the main view keeps its own wrap setting");
+
+        let _ = handle_detail_event(&mut app, &mut lines, KeyCode::Esc, KeyModifiers::empty());
+        assert!(app.detail_line.is_none());
     }
 
     #[test]
-    fn main_scroll_down_to_follow() {
+    fn handle_key_routes_to_topmost_view() {
         let mut app = app_with_patterns(false);
-        let total_lines = 100;
-        let view_height = 10;
-        let max_start = max_start(total_lines, view_height);
-        app.follow = false;
-        app.scroll = max_start;
+        let mut lines = LogBuffer::from(vec![LogLine::output("text".to_string())]);
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
 
-        let result = handle_main_event(
-            &mut app,
-            total_lines,
-            view_height,
-            KeyCode::Down,
-            KeyModifiers::empty(),
-            true,
-        );
+        app.detail_line = Some(0);
+        let result = handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('q')))
+            .expect("key handler failed");
+        assert!(!result.exit);
+        assert!(app.detail_line.is_none());
 
-        assert!(result.is_none());
-        assert!(app.follow);
-        assert_eq!(app.scroll, max_start);
+        app.dialog_open = true;
+        let result = handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('q')))
+            .expect("key handler failed");
+        assert!(!result.exit);
+        assert_eq!(app.input, "q");
+
+        app.dialog_open = false;
+        let result = handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('q')))
+            .expect("key handler failed");
+        assert!(result.exit);
     }
 
     #[test]
-    fn main_home_end_positions() {
+    fn main_clear_and_undo_restore_lines() {
         let mut app = app_with_patterns(false);
-        let total_lines = 40;
-        let view_height = 10;
+        let mut lines = LogBuffer::from(vec![LogLine::output("text".to_string())]);
+
+        for (code, modifiers) in [
+            (KeyCode::Char('c'), KeyModifiers::empty()),
+            (KeyCode::Char('w'), KeyModifiers::empty()),
+            (KeyCode::Char('u'), KeyModifiers::empty()),
+        ] {
+            let result = handle_main_event(&mut app, &mut lines, 1, 1, code, modifiers);
+            assert!(result.is_none());
+        }
+        assert_eq!(lines.len(), 0);
+        assert!(!app.wrap);
 
         let _ = handle_main_event(
             &mut app,
-            total_lines,
-            view_height,
-            KeyCode::Home,
+            &mut lines,
+            0,
+            1,
+            KeyCode::Char('u'),
             KeyModifiers::empty(),
-            true,
         );
-        assert!(!app.follow);
-        assert_eq!(app.scroll, 0);
+        assert_eq!(lines[0].text, "text");
 
         let _ = handle_main_event(
             &mut app,
-            total_lines,
-            view_height,
-            KeyCode::End,
-            KeyModifiers::empty(),
-            true,
+            &mut lines,
+            1,
+            1,
+            KeyCode::Char('r'),
+            KeyModifiers::CONTROL,
+        );
+        assert_eq!(lines.len(), 0);
+
+        lines.push(LogLine::output("after restart".to_string()));
+        app.follow = false;
+        app.scroll = 1;
+        app.search_match = Some(0);
+        let _ = handle_main_event(
+            &mut app,
+            &mut lines,
+            1,
+            1,
+            KeyCode::Char('C'),
+            KeyModifiers::SHIFT,
         );
+        assert_eq!(lines.len(), 0);
         assert!(app.follow);
-        assert_eq!(app.scroll, max_start(total_lines, view_height));
+        assert_eq!((app.scroll, app.search_match), (0, None));
     }
 
     #[test]
-    fn main_toggle_filter_mode() {
+    fn sources_dialog_toggles_selected_source() {
         let mut app = app_with_patterns(false);
-        app.filter_only = false;
+        app.sources = ["api.log", "db.log"]
+            .into_iter()
+            .map(|name| Source::new(name.to_string(), Format::Plain))
+            .collect();
+        let mut lines = LogBuffer::default();
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        for code in [
+            KeyCode::Char('s'),
+            KeyCode::Down,
+            KeyCode::Down,
+            KeyCode::Char(' '),
+        ] {
+            handle_key(&mut app, &mut lines, 0, 1, key(code)).expect("key handler failed");
+        }
+        assert!(app.sources_open);
+        assert_eq!(app.source_selected, 1);
+        assert!(!app.sources[0].hidden);
+        assert!(app.sources[1].hidden);
+
+        handle_key(&mut app, &mut lines, 0, 1, key(KeyCode::Right)).expect("key handler failed");
+        assert_eq!(app.sources[1].format, Format::Json);
+
+        handle_key(&mut app, &mut lines, 0, 1, key(KeyCode::Esc)).expect("key handler failed");
+        assert!(!app.sources_open);
+    }
+
+    #[test]
+    fn search_jumps_to_matches_and_cycles() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::from(
+            ["boot", "error one", "ok", "error two", "ok", "ok"]
+                .into_iter()
+                .map(|text| LogLine::output(text.to_string()))
+                .collect::<Vec<_>>(),
+        );
         app.follow = false;
-        app.scroll = 5;
+        app.scroll = 2;
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
 
-        let result = handle_main_event(
+        let mut keys = vec![KeyCode::Char('/')];
+        keys.extend("error".chars().map(KeyCode::Char));
+        keys.push(KeyCode::Enter);
+        for code in keys {
+            handle_key(&mut app, &mut lines, 6, 2, key(code)).expect("key handler failed");
+        }
+        assert!(!app.search_open);
+        assert_eq!(app.search_match, Some(3));
+        assert_eq!(app.scroll, 3);
+
+        handle_key(&mut app, &mut lines, 6, 2, key(KeyCode::Char('n'))).expect("key failed");
+        assert_eq!(app.search_match, Some(1));
+        assert_eq!(app.scroll, 1);
+
+        handle_key(&mut app, &mut lines, 6, 2, key(KeyCode::Char('N'))).expect("key failed");
+        assert_eq!(app.search_match, Some(3));
+    }
+
+    #[test]
+    fn search_without_match_keeps_bar_open() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::from(vec![LogLine::output("text".to_string())]);
+        app.search_open = true;
+        app.search_input = "missing".to_string();
+
+        let result = handle_key(
             &mut app,
-            20,
+            &mut lines,
+            1,
+            1,
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::empty()),
+        )
+        .expect("key handler failed");
+
+        assert!(!result.exit);
+        assert!(app.search_open);
+        assert!(app.search_match.is_none());
+        assert_eq!(app.search_error.as_deref(), Some("Pattern not found"));
+    }
+
+    #[test]
+    fn count_prompt_reports_in_status_without_moving_view() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::from(
+            ["error one", "ok", "error two"]
+                .into_iter()
+                .map(|text| LogLine::output(text.to_string()))
+                .collect::<Vec<_>>(),
+        );
+        app.follow = false;
+        app.scroll = 1;
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        let mut keys = vec![KeyCode::Char(':')];
+        keys.extend("count error".chars().map(KeyCode::Char));
+        keys.push(KeyCode::Enter);
+        for code in keys {
+            handle_key(&mut app, &mut lines, 3, 1, key(code)).expect("key handler failed");
+        }
+        assert!(!app.prompt_open);
+        assert_eq!(app.status.as_deref(), Some("2 of 3 lines match /error/"));
+        assert_eq!(app.scroll, 1);
+        assert!(!app.follow);
+        assert_eq!(app.patterns.len(), 2);
+
+        handle_key(&mut app, &mut lines, 3, 1, key(KeyCode::Char('j'))).expect("key failed");
+        assert!(app.status.is_none());
+    }
+
+    #[test]
+    fn selection_extends_with_the_cursor_and_scrolls_along() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::new(None);
+        for index in 1..=10 {
+            push_line(
+                &mut app,
+                &mut lines,
+                LogLine::output(format!("line {index}")),
+            );
+        }
+        app.follow = false;
+        app.scroll = 2;
+        let press = |app: &mut AppState, lines: &mut LogBuffer, code| {
+            handle_key(
+                app,
+                lines,
+                10,
+                3,
+                KeyEvent::new(code, KeyModifiers::empty()),
+            )
+            .expect("key handler failed");
+        };
+
+        press(&mut app, &mut lines, KeyCode::Char('v'));
+        for _ in 0..3 {
+            press(&mut app, &mut lines, KeyCode::Char('j'));
+        }
+        assert_eq!(app.scroll, 3);
+        press(&mut app, &mut lines, KeyCode::Up);
+        assert_eq!(
+            app.selection,
+            Some(Selection {
+                anchor: 3,
+                cursor: 5
+            })
+        );
+        assert_eq!(
+            selected_text(&app, &lines),
+            (3, "line 3\nline 4\nline 5".to_string())
+        );
+
+        press(&mut app, &mut lines, KeyCode::Esc);
+        assert!(app.selection.is_none());
+        assert_eq!(app.scroll, 3);
+    }
+
+    #[test]
+    fn help_overlay_takes_the_next_key_to_close() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        handle_key(&mut app, &mut lines, 10, 5, key(KeyCode::Char('?'))).expect("key failed");
+        assert!(app.help_open);
+        let result =
+            handle_key(&mut app, &mut lines, 10, 5, key(KeyCode::Char('q'))).expect("key failed");
+        assert!(!app.help_open);
+        assert!(!result.exit);
+
+        handle_key(&mut app, &mut lines, 10, 5, key(KeyCode::Char('?'))).expect("key failed");
+        let result = handle_key(
+            &mut app,
+            &mut lines,
             10,
-            KeyCode::Char('f'),
-            KeyModifiers::empty(),
-            true,
+            5,
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+        )
+        .expect("key failed");
+        assert!(result.exit);
+    }
+
+    #[test]
+    fn help_overlay_wraps_apart_from_the_main_view() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+        let wrap = app.wrap;
+
+        handle_key(&mut app, &mut lines, 10, 5, key(KeyCode::Char('?'))).expect("key failed");
+        handle_key(&mut app, &mut lines, 10, 5, key(KeyCode::Char('w'))).expect("key failed");
+        assert!(app.help_open);
+        assert!(!app.help_wrap);
+        assert_eq!(app.wrap, wrap);
+
+        apply(&mut app, &mut lines, Action::Undo).expect("undo failed");
+        assert!(app.help_wrap);
+    }
+
+    #[test]
+    fn go_to_prompt_moves_the_view_to_a_line_or_percentage() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::new(None);
+        for index in 1..=20 {
+            push_line(
+                &mut app,
+                &mut lines,
+                LogLine::output(format!("line {index}")),
+            );
+        }
+        let mut run = |app: &mut AppState, command: &str| {
+            let mut keys = vec![KeyCode::Char(':')];
+            keys.extend(command.chars().map(KeyCode::Char));
+            keys.push(KeyCode::Enter);
+            for code in keys {
+                handle_key(
+                    app,
+                    &mut lines,
+                    20,
+                    5,
+                    KeyEvent::new(code, KeyModifiers::empty()),
+                )
+                .expect("key handler failed");
+            }
+        };
+
+        run(&mut app, "8");
+        assert!(!app.follow);
+        assert_eq!(app.scroll, 7);
+        assert!(app.status.is_none());
+
+        run(&mut app, "50%");
+        assert_eq!(app.scroll, 9);
+        run(&mut app, "19");
+        assert_eq!(app.scroll, 15);
+
+        run(&mut app, "99");
+        assert_eq!(app.scroll, 15);
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Line 99 isn't shown, went to the nearest one")
         );
+        assert!(!app.prompt_open);
+    }
 
-        assert!(result.is_none());
+    #[test]
+    fn filter_expression_prompt_shows_the_lines_it_holds_for() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::new(None);
+        for text in ["foo", "bar", "foo bar", "baz"] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        let press = |app: &mut AppState, lines: &mut LogBuffer, codes: Vec<KeyCode>| {
+            for code in codes {
+                handle_key(app, lines, 4, 5, KeyEvent::new(code, KeyModifiers::empty()))
+                    .expect("key handler failed");
+            }
+        };
+        let typed = |text: &str| -> Vec<KeyCode> {
+            let mut codes: Vec<KeyCode> = text.chars().map(KeyCode::Char).collect();
+            codes.push(KeyCode::Enter);
+            codes
+        };
+
+        let mut keys = vec![KeyCode::Char('|')];
+        keys.extend(typed("foo & !2"));
+        press(&mut app, &mut lines, keys);
         assert!(app.filter_only);
+        assert!(!app.prompt_open);
+        let shown: Vec<&str> = visible_lines(&lines, &app)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(shown, ["foo"]);
+
+        press(&mut app, &mut lines, vec![KeyCode::Char('|')]);
+        assert_eq!(app.prompt_input, "filter foo & !2");
+        press(&mut app, &mut lines, vec![KeyCode::Esc]);
+
+        press(
+            &mut app,
+            &mut lines,
+            [vec![KeyCode::Char(':')], typed("filter qux")].concat(),
+        );
+        assert!(app.prompt_open);
+        assert_eq!(
+            app.prompt_error.as_deref(),
+            Some("invalid filter expression: no pattern qux")
+        );
+        press(&mut app, &mut lines, vec![KeyCode::Esc]);
+
+        press(
+            &mut app,
+            &mut lines,
+            [vec![KeyCode::Char(':')], typed("filter")].concat(),
+        );
+        assert!(app.filter_expr.is_none());
+        assert_eq!(visible_lines(&lines, &app).count(), 3);
+    }
+
+    #[test]
+    fn secrets_are_revealed_on_confirmation_but_stay_masked_in_copies() {
+        let mut app = app_with_patterns(false);
+        app.redactions = vec![Redaction::parse("tokens").expect("parse failed")];
+        let mut lines = LogBuffer::new(None);
+        push_line(
+            &mut app,
+            &mut lines,
+            LogLine::output("login token=s3cr3t".to_string()),
+        );
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('X'))).expect("key failed");
+        assert!(app.reveal_open);
+        handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('X'))).expect("key failed");
+        assert!(!app.reveal_open);
+        assert!(!app.revealed, "only y reveals the secrets");
+
+        handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('X'))).expect("key failed");
+        handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('y'))).expect("key failed");
+        assert!(app.revealed);
+        app.selection = Some(Selection {
+            anchor: 1,
+            cursor: 1,
+        });
+        assert_eq!(
+            selected_text(&app, &lines),
+            (1, "login token=***".to_string())
+        );
+        app.selection = None;
+
+        handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('X'))).expect("key failed");
+        assert!(!app.revealed);
+    }
+
+    #[test]
+    fn large_buffer_prompt_bounds_the_buffer_and_takes_keys_first() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::default();
+        for text in ["a", "b", "c"] {
+            lines.push(LogLine::output(text.to_string()));
+        }
+        app.large_buffer_open = true;
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        handle_key(&mut app, &mut lines, 3, 1, key(KeyCode::Char('p'))).expect("key failed");
+        assert!(!app.dialog_open);
+        assert!(app.large_buffer_open);
+
+        handle_key(&mut app, &mut lines, 3, 1, key(KeyCode::Char('b'))).expect("key failed");
+        assert!(!app.large_buffer_open);
+        assert!(lines.is_bounded());
+        lines.push(LogLine::output("d".to_string()));
+        assert_eq!(lines.len(), 3);
+        assert_eq!(app.status.as_deref(), Some("Keeping the last 3 lines"));
+    }
+
+    #[test]
+    fn large_buffer_prompt_collapses_repeats_or_refuses_to_spill_when_read_only() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::from(vec![LogLine::output("a".to_string())]);
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        app.large_buffer_open = true;
+        handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('d'))).expect("key failed");
+        assert!(!app.large_buffer_open);
+        assert!(app.dedup);
+
+        app.large_buffer_open = true;
+        app.read_only = true;
+        handle_key(&mut app, &mut lines, 1, 1, key(KeyCode::Char('s'))).expect("key failed");
+        assert!(app.spill.is_none());
+        assert!(!lines.is_bounded());
+        assert!(app
+            .status
+            .as_deref()
+            .is_some_and(|status| status.ends_with("read-only (--read-only)")));
+    }
+
+    #[test]
+    fn scroll_keys_move_the_way_they_point_when_following_at_the_top() {
+        let mut app = app_with_patterns(false);
+        app.follow_at = FollowAt::Top;
+        let mut lines = LogBuffer::from(
+            (0..10)
+                .map(|i| LogLine::output(i.to_string()))
+                .collect::<Vec<_>>(),
+        );
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        handle_key(&mut app, &mut lines, 10, 4, key(KeyCode::Char('j'))).expect("key failed");
+        assert!(!app.follow);
+        assert_eq!(app.scroll, 5);
+
+        handle_key(&mut app, &mut lines, 10, 4, key(KeyCode::Enter)).expect("key failed");
+        assert_eq!(app.detail_line, Some(8));
+        app.detail_line = None;
+
+        for _ in 0..2 {
+            handle_key(&mut app, &mut lines, 10, 4, key(KeyCode::Char('k'))).expect("key failed");
+        }
+        assert!(app.follow);
+
+        let shift_g = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        handle_key(&mut app, &mut lines, 10, 4, shift_g).expect("key failed");
+        assert!(!app.follow);
+        assert_eq!(app.scroll, 0);
+    }
+
+    #[test]
+    fn fields_dialog_lists_logfmt_keys_and_hides_them() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::from(vec![
+            LogLine::output("level=info svc=auth".to_string()),
+            LogLine::output("plain line with a=b".to_string()),
+            LogLine::output("level=warn retry=3".to_string()),
+        ]);
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        handle_key(&mut app, &mut lines, 3, 3, key(KeyCode::Char('F'))).expect("key failed");
+        assert!(app.fields_open);
+        assert_eq!(app.field_keys, vec!["level", "retry", "svc"]);
+
+        for code in [
+            KeyCode::Down,
+            KeyCode::Down,
+            KeyCode::Char(' '),
+            KeyCode::Esc,
+        ] {
+            handle_key(&mut app, &mut lines, 3, 3, key(code)).expect("key failed");
+        }
+        assert!(!app.fields_open);
+        assert!(app.hidden_fields.contains("svc"));
+
+        handle_key(&mut app, &mut lines, 3, 3, key(KeyCode::Char('u'))).expect("key failed");
+        assert!(app.hidden_fields.is_empty());
+
+        let mut lines = LogBuffer::from(vec![LogLine::output("plain".to_string())]);
+        handle_key(&mut app, &mut lines, 1, 3, key(KeyCode::Char('F'))).expect("key failed");
+        assert!(!app.fields_open);
+        assert_eq!(app.status.as_deref(), Some("No logfmt fields"));
+    }
+
+    #[test]
+    fn search_moves_down_the_screen_in_newest_first_order() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::from(
+            (0..10)
+                .map(|i| LogLine::output(format!("line {i}")))
+                .collect::<Vec<_>>(),
+        );
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+        handle_key(&mut app, &mut lines, 10, 4, key(KeyCode::Char('o'))).expect("key failed");
+        assert_eq!(app.follow_at, FollowAt::Top);
+        app.search = Some(Regex::new("[37]").expect("regex build failed"));
+
+        handle_key(&mut app, &mut lines, 10, 4, key(KeyCode::Char('n'))).expect("key failed");
+        assert_eq!(app.search_match, Some(7));
+        assert_eq!(app.scroll, 4);
+        handle_key(&mut app, &mut lines, 10, 4, key(KeyCode::Char('n'))).expect("key failed");
+        assert_eq!(app.search_match, Some(3));
+        assert_eq!(app.scroll, 0);
+
+        handle_key(&mut app, &mut lines, 10, 4, key(KeyCode::Char('u'))).expect("key failed");
+        assert_eq!(app.follow_at, FollowAt::Bottom);
+    }
+
+    #[test]
+    fn pause_freezes_the_view_until_resumed() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::new(NonZeroUsize::new(4));
+        for i in 0..3 {
+            push_line(&mut app, &mut lines, LogLine::output(i.to_string()));
+        }
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        handle_key(&mut app, &mut lines, 3, 2, key(KeyCode::Char(' '))).expect("key failed");
+        for i in 3..5 {
+            push_output(&mut app, &mut lines, LogLine::output(i.to_string()));
+        }
+        let texts: Vec<&str> = visible_lines(&lines, &app)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["1", "2"]);
+
+        handle_key(&mut app, &mut lines, 2, 2, key(KeyCode::Char('P'))).expect("key failed");
+        assert!(app.pause.is_none());
         assert!(app.follow);
+        assert_eq!(app.status.as_deref(), Some("2 new lines while paused"));
+        assert_eq!(visible_lines(&lines, &app).count(), 4);
+    }
+
+    #[test]
+    fn entries_expand_and_collapse_at_the_top_of_the_view() {
+        let mut app = app_with_patterns(false);
+        let mut lines = LogBuffer::new(None);
+        for text in ["12:00:00 failed", "  at a", "  at b", "12:00:01 retrying"] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        let key = |code| KeyEvent::new(code, KeyModifiers::empty());
+
+        handle_key(&mut app, &mut lines, 4, 1, key(KeyCode::Char('z'))).expect("key failed");
+        assert_eq!(app.status.as_deref(), Some("Not grouping entries (E)"));
+        handle_key(&mut app, &mut lines, 4, 1, key(KeyCode::Char('E'))).expect("key failed");
+        assert_eq!(visible_lines(&lines, &app).count(), 2);
+
+        app.follow = false;
+        app.scroll = 0;
+        handle_key(&mut app, &mut lines, 2, 1, key(KeyCode::Char('z'))).expect("key failed");
+        assert!(lines.iter().take(3).all(|line| line.expanded));
+        assert_eq!(visible_lines(&lines, &app).count(), 4);
+
+        handle_key(&mut app, &mut lines, 4, 1, key(KeyCode::Char('j'))).expect("key failed");
+        handle_key(&mut app, &mut lines, 4, 1, key(KeyCode::Char('z'))).expect("key failed");
+        assert_eq!(visible_lines(&lines, &app).count(), 2);
         assert_eq!(app.scroll, 0);
     }
 }
@@ -0,0 +1,152 @@
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    LineKind, LogLine, json,
+    redact::{Redaction, redact},
+};
+
+/// Where the columns of an exported CSV file come from.
+pub(crate) enum Columns {
+    /// The capture groups of a regex, or the whole match if it has none.
+    Captures(Regex),
+    /// Fields of JSON lines, by their flattened key paths (e.g. `req.path`).
+    Fields(Vec<String>),
+}
+
+/// Writes a header and one CSV row per output line the columns are found in, returning the
+/// number of rows. The lines are masked with the redactions before their columns are taken.
+pub(crate) fn write_csv<'a>(
+    out: &mut impl Write,
+    columns: &Columns,
+    redactions: &[Redaction],
+    lines: impl Iterator<Item = &'a LogLine>,
+) -> io::Result<usize> {
+    write_row(out, &header(columns))?;
+    let mut rows = 0;
+    for line in lines.filter(|line| line.kind == LineKind::Output) {
+        if let Some(row) = row(columns, &redact(redactions, Cow::Borrowed(&line.text))) {
+            write_row(out, &row)?;
+            rows += 1;
+        }
+    }
+    Ok(rows)
+}
+
+fn header(columns: &Columns) -> Vec<String> {
+    match columns {
+        Columns::Captures(regex) if regex.captures_len() == 1 => vec!["match".to_string()],
+        Columns::Captures(regex) => regex
+            .capture_names()
+            .enumerate()
+            .skip(1)
+            .map(|(index, name)| name.map_or_else(|| index.to_string(), str::to_string))
+            .collect(),
+        Columns::Fields(fields) => fields.clone(),
+    }
+}
+
+/// The row for a line, or `None` if the regex doesn't match it or it has none of the fields.
+fn row(columns: &Columns, text: &str) -> Option<Vec<String>> {
+    match columns {
+        Columns::Captures(regex) => {
+            let captures = regex.captures(text)?;
+            let groups = if captures.len() == 1 {
+                0..1
+            } else {
+                1..captures.len()
+            };
+            Some(
+                groups
+                    .map(|group| {
+                        captures
+                            .get(group)
+                            .map_or_else(String::new, |found| found.as_str().to_string())
+                    })
+                    .collect(),
+            )
+        }
+        Columns::Fields(fields) => {
+            let flattened = json::flatten(text)?;
+            let values: Vec<Option<String>> = fields
+                .iter()
+                .map(|field| {
+                    let (_, value) = flattened.iter().find(|(key, _)| key == field)?;
+                    Some(match value {
+                        Value::String(text) => text.clone(),
+                        value => value.to_string(),
+                    })
+                })
+                .collect();
+            values
+                .iter()
+                .any(Option::is_some)
+                .then(|| values.into_iter().map(Option::unwrap_or_default).collect())
+        }
+    }
+}
+
+fn write_row(out: &mut impl Write, row: &[String]) -> io::Result<()> {
+    let fields: Vec<Cow<str>> = row.iter().map(|field| csv_field(field)).collect();
+    writeln!(out, "{}", fields.join(","))
+}
+
+/// Quotes a field if it contains anything that would break the row apart.
+fn csv_field(text: &str) -> Cow<'_, str> {
+    if text.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Columns, write_csv};
+    use crate::LogLine;
+    use regex::Regex;
+
+    fn export(columns: &Columns, texts: &[&str]) -> (usize, String) {
+        let lines: Vec<LogLine> = texts
+            .iter()
+            .map(|text| LogLine::output(text.to_string()))
+            .collect();
+        let mut out = Vec::new();
+        let rows = write_csv(&mut out, columns, &[], lines.iter()).expect("write failed");
+        (rows, String::from_utf8(out).expect("CSV is UTF-8"))
+    }
+
+    #[test]
+    fn capture_groups_become_columns() {
+        let regex = Regex::new(r"user=(?<user>\S+) took (\d+)ms").expect("regex build failed");
+        let (rows, csv) = export(
+            &Columns::Captures(regex),
+            &["user=a,b took 12ms", "no match", r#"user="x" took 7ms"#],
+        );
+
+        assert_eq!(rows, 2);
+        assert_eq!(csv, "user,2\n\"a,b\",12\n\"\"\"x\"\"\",7\n");
+    }
+
+    #[test]
+    fn json_fields_become_columns() {
+        let columns = Columns::Fields(vec!["level".to_string(), "req.ms".to_string()]);
+        let (rows, csv) = export(
+            &columns,
+            &[
+                r#"{"level":"info","req":{"ms":12}}"#,
+                r#"{"other":1}"#,
+                r#"{"level":"warn"}"#,
+                "plain",
+            ],
+        );
+
+        assert_eq!(rows, 2);
+        assert_eq!(csv, "level,req.ms\ninfo,12\nwarn,\n");
+    }
+}
@@ -0,0 +1,259 @@
+use crate::PatternSpec;
+
+/// A boolean expression over the patterns, like `(error | warn) & !healthcheck`, showing the
+/// lines it holds for while filtering instead of those matching any pattern. Patterns are
+/// referred to by their text or name, in double quotes when it has spaces or operators in it,
+/// or by their place in the patterns dialog, counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FilterExpr {
+    text: String,
+    root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Pattern(Reference),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Reference {
+    /// The place of a pattern in the list, from 0.
+    Place(usize),
+    Text(String),
+}
+
+impl Reference {
+    /// The index of the pattern referred to, if there is one.
+    fn resolve(&self, patterns: &[PatternSpec]) -> Option<usize> {
+        match self {
+            Reference::Place(index) => (*index < patterns.len()).then_some(*index),
+            Reference::Text(text) => patterns.iter().position(|pattern| {
+                pattern.pattern == *text || pattern.name.as_ref() == Some(text)
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Quoted(String),
+}
+
+impl FilterExpr {
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            next: 0,
+        };
+        let root = parser.or()?;
+        if parser.next < tokens.len() {
+            return Err(format!("unexpected {}", describe(&tokens[parser.next])));
+        }
+        Ok(FilterExpr {
+            text: text.trim().to_string(),
+            root,
+        })
+    }
+
+    /// The expression as it was entered.
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// A reference to a pattern not among `patterns`, to be reported when the expression is
+    /// entered. Patterns removed later just match no line.
+    pub(crate) fn unknown(&self, patterns: &[PatternSpec]) -> Option<String> {
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            match node {
+                Node::Pattern(reference) if reference.resolve(patterns).is_none() => {
+                    return Some(match reference {
+                        Reference::Place(index) => format!("no pattern #{}", index + 1),
+                        Reference::Text(text) => format!("no pattern {text}"),
+                    });
+                }
+                Node::Pattern(_) => {}
+                Node::Not(inner) => stack.push(inner),
+                Node::And(left, right) | Node::Or(left, right) => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether the expression holds for a line, given which patterns (by index) it matches.
+    pub(crate) fn holds(&self, patterns: &[PatternSpec], matched: impl Fn(usize) -> bool) -> bool {
+        eval(&self.root, patterns, &matched)
+    }
+}
+
+fn eval(node: &Node, patterns: &[PatternSpec], matched: &impl Fn(usize) -> bool) -> bool {
+    match node {
+        Node::Pattern(reference) => reference.resolve(patterns).is_some_and(matched),
+        Node::Not(inner) => !eval(inner, patterns, matched),
+        Node::And(left, right) => eval(left, patterns, matched) && eval(right, patterns, matched),
+        Node::Or(left, right) => eval(left, patterns, matched) || eval(right, patterns, matched),
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '!' => Token::Not,
+            '"' => {
+                let rest = &text[start + 1..];
+                let end = rest.find('"').ok_or("unclosed quote")?;
+                for _ in rest[..=end].chars() {
+                    chars.next();
+                }
+                Token::Quoted(rest[..end].to_string())
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(index, next)) = chars.peek() {
+                    if next.is_whitespace() || "()&|!\"".contains(next) {
+                        break;
+                    }
+                    end = index + next.len_utf8();
+                    chars.next();
+                }
+                Token::Word(text[start..end].to_string())
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::And => "&".to_string(),
+        Token::Or => "|".to_string(),
+        Token::Not => "!".to_string(),
+        Token::Word(word) => word.clone(),
+        Token::Quoted(text) => format!("\"{text}\""),
+    }
+}
+
+/// Parses `|` below `&` below `!`, by recursive descent.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    next: usize,
+}
+
+impl Parser<'_> {
+    fn take(&mut self, token: &Token) -> bool {
+        let taken = self.tokens.get(self.next) == Some(token);
+        self.next += usize::from(taken);
+        taken
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.take(&Token::Or) {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.not()?;
+        while self.take(&Token::And) {
+            node = Node::And(Box::new(node), Box::new(self.not()?));
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> Result<Node, String> {
+        if self.take(&Token::Not) {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        let Some(token) = self.tokens.get(self.next) else {
+            return Err("expected a pattern at the end".to_string());
+        };
+        self.next += 1;
+        match token {
+            Token::Open => {
+                let node = self.or()?;
+                if !self.take(&Token::Close) {
+                    return Err("unclosed (".to_string());
+                }
+                Ok(node)
+            }
+            Token::Word(word) if word.bytes().all(|byte| byte.is_ascii_digit()) => {
+                match word.parse::<usize>() {
+                    Ok(place @ 1..) => Ok(Node::Pattern(Reference::Place(place - 1))),
+                    _ => Err(format!("no pattern #{word}, they are counted from 1")),
+                }
+            }
+            Token::Word(text) | Token::Quoted(text) => {
+                Ok(Node::Pattern(Reference::Text(text.clone())))
+            }
+            token => Err(format!("expected a pattern, got {}", describe(token))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilterExpr;
+    use crate::build_patterns;
+
+    #[test]
+    fn expressions_combine_patterns_by_text_or_place() {
+        let texts = ["error", "warn", "healthcheck", "took \\d+ms"].map(String::from);
+        let patterns = build_patterns(&texts, false).expect("build failed");
+        let holds = |text: &str, matched: &[usize]| {
+            FilterExpr::parse(text)
+                .expect("parse failed")
+                .holds(&patterns, |index| matched.contains(&index))
+        };
+
+        let expr = "(error | warn) & !healthcheck";
+        assert!(holds(expr, &[0]));
+        assert!(holds(expr, &[1]));
+        assert!(!holds(expr, &[1, 2]));
+        assert!(!holds(expr, &[3]));
+        assert!(holds("error | warn & healthcheck", &[0]));
+        assert!(holds("!!1 & \"took \\d+ms\"", &[0, 3]));
+        assert!(!holds("2 & 5", &[1]));
+
+        let parsed = FilterExpr::parse(" 2 & nope ").expect("parse failed");
+        assert_eq!(parsed.text(), "2 & nope");
+        assert_eq!(
+            parsed.unknown(&patterns),
+            Some("no pattern nope".to_string())
+        );
+        assert_eq!(
+            FilterExpr::parse("4")
+                .expect("parse failed")
+                .unknown(&patterns),
+            None
+        );
+
+        for invalid in ["", "error &", "(error", "error)", "0", "\"error", "& error"] {
+            assert!(FilterExpr::parse(invalid).is_err(), "{invalid} parsed");
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    io::{BufReader, ErrorKind, Read},
+    net::SocketAddr,
+};
+
+use rmpv::{
+    Value as Object,
+    decode::{self, read_value},
+    encode,
+};
+use serde_json::Value;
+use tokio::{
+    io::{self, AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+    task,
+};
+use tokio_util::io::SyncIoBridge;
+
+use crate::{
+    gelf::decompress,
+    parser::epoch_timestamp,
+    reader::{CHANNEL_CAPACITY, Segment, accept},
+};
+
+/// The largest message taken, the connection being dropped on a longer one.
+const MAX_MESSAGE: usize = 64 << 20;
+
+/// Listens for the Fluentd forward protocol on `addr`, as sent by the `forward` output of
+/// Fluentd or Fluent Bit, taking each record as a JSON line of its time and tag followed by its
+/// fields. Connections asking for the shared key handshake aren't served.
+pub(crate) async fn listen(addr: SocketAddr) -> io::Result<mpsc::Receiver<Segment>> {
+    let listener = TcpListener::bind(addr).await?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    accept(listener, move |stream| serve(stream, tx.clone()));
+    Ok(rx)
+}
+
+/// Reads the messages of a connection, acknowledging those that ask for it, until it is closed
+/// or sends something that isn't MessagePack.
+async fn serve<S: AsyncRead + AsyncWrite + Send + 'static>(
+    stream: S,
+    tx: mpsc::Sender<Segment>,
+) -> io::Result<()> {
+    let (reader, mut writer) = io::split(stream);
+    let (decoded, mut messages) = mpsc::channel(1);
+    let decoding = task::spawn_blocking(move || decode(SyncIoBridge::new(reader), decoded));
+    while let Some(message) = messages.recv().await {
+        let Some(event) = Forwarded::parse(message) else {
+            continue;
+        };
+        for line in event.lines() {
+            if tx.send(Segment::Line(line)).await.is_err() {
+                return Ok(());
+            }
+        }
+        if let Some(chunk) = event.chunk {
+            writer.write_all(&ack(&chunk)).await?;
+        }
+    }
+    decoding.await?
+}
+
+/// Decodes the messages of a connection as they are read, each at most [`MAX_MESSAGE`] bytes
+/// long, until it is closed between two of them.
+fn decode(reader: impl Read, decoded: mpsc::Sender<Object>) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    loop {
+        let message = match read_value(&mut (&mut reader).take(MAX_MESSAGE as u64)) {
+            Ok(message) => message,
+            Err(decode::Error::InvalidMarkerRead(error))
+                if error.kind() == ErrorKind::UnexpectedEof =>
+            {
+                return Ok(());
+            }
+            Err(error) => return Err(io::Error::new(ErrorKind::InvalidData, error)),
+        };
+        if decoded.blocking_send(message).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// The answer to a message with a `chunk` option: `{"ack": chunk}`.
+fn ack(chunk: &str) -> Vec<u8> {
+    let mut ack = Vec::new();
+    let answer = Object::Map(vec![("ack".into(), chunk.into())]);
+    encode::write_value(&mut ack, &answer).expect("writing to a Vec doesn't fail");
+    ack
+}
+
+/// The time and record of an entry.
+type Entry = (Object, Object);
+
+/// A message of the forward protocol, in any of its modes, as its tag and entries.
+struct Forwarded {
+    tag: String,
+    entries: Vec<Entry>,
+    chunk: Option<String>,
+}
+
+impl Forwarded {
+    /// Reads `[tag, time, record]` (Message mode), `[tag, [[time, record], ...]]` (Forward
+    /// mode) or `[tag, entries]` with the entries packed into a string, gzip compressed or not
+    /// (PackedForward and CompressedPackedForward modes), each with an optional map of options.
+    fn parse(message: Object) -> Option<Self> {
+        let Object::Array(items) = message else {
+            return None;
+        };
+        let mut items = items.into_iter();
+        let tag = text(&items.next()?)?;
+        let (entries, options) = match items.next()? {
+            Object::Array(entries) => (
+                entries
+                    .into_iter()
+                    .filter_map(|entry| match entry {
+                        Object::Array(pair) => pair_of(pair),
+                        _ => None,
+                    })
+                    .collect(),
+                items.next(),
+            ),
+            Object::String(packed) => Self::unpack(packed.into_bytes(), items.next())?,
+            Object::Binary(packed) => Self::unpack(packed, items.next())?,
+            time => {
+                let record = items.next()?;
+                (vec![(time, record)], items.next())
+            }
+        };
+        let chunk = options.and_then(|options| text(get(&options, "chunk")?));
+        Some(Forwarded {
+            tag,
+            entries,
+            chunk,
+        })
+    }
+
+    fn unpack(packed: Vec<u8>, options: Option<Object>) -> Option<(Vec<Entry>, Option<Object>)> {
+        let compressed = options
+            .as_ref()
+            .and_then(|options| text(get(options, "compressed")?))
+            .is_some_and(|compression| compression == "gzip");
+        let packed = if compressed {
+            decompress(&packed)?
+        } else {
+            packed
+        };
+        let mut entries = Vec::new();
+        let mut rest = &packed[..];
+        while !rest.is_empty() {
+            if let Object::Array(pair) = read_value(&mut rest).ok()?
+                && let Some(pair) = pair_of(pair)
+            {
+                entries.push(pair);
+            }
+        }
+        Some((entries, options))
+    }
+
+    /// Each entry as a JSON line, its time in ISO 8601 and its tag first.
+    fn lines(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|(time, record)| {
+                let mut parts = Vec::new();
+                if let Some(micros) = micros(time) {
+                    parts.push(format!("\"timestamp\":\"{}\"", epoch_timestamp(micros)));
+                }
+                parts.push(format!("\"tag\":{}", Value::String(self.tag.clone())));
+                if let Object::Map(fields) = record {
+                    for (key, value) in fields {
+                        let key = text(key).unwrap_or_else(|| to_json(key).to_string());
+                        parts.push(format!("{}:{}", Value::String(key), to_json(value)));
+                    }
+                }
+                format!("{{{}}}", parts.join(","))
+            })
+            .collect()
+    }
+}
+
+fn pair_of(pair: Vec<Object>) -> Option<(Object, Object)> {
+    let mut pair = pair.into_iter();
+    Some((pair.next()?, pair.next()?))
+}
+
+/// The text of a string or binary object.
+fn text(object: &Object) -> Option<String> {
+    match object {
+        Object::String(text) => Some(String::from_utf8_lossy(text.as_bytes()).into_owned()),
+        Object::Binary(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        _ => None,
+    }
+}
+
+fn get<'a>(object: &'a Object, key: &str) -> Option<&'a Object> {
+    object
+        .as_map()?
+        .iter()
+        .find(|(name, _)| text(name).as_deref() == Some(key))
+        .map(|(_, value)| value)
+}
+
+/// An event time in microseconds since the Unix epoch: whole seconds, or the `EventTime`
+/// extension of seconds and nanoseconds.
+fn micros(time: &Object) -> Option<i64> {
+    match time {
+        Object::Integer(seconds) => seconds.as_i64()?.checked_mul(1_000_000),
+        Object::F32(seconds) => Some((f64::from(*seconds) * 1_000_000.0).round() as i64),
+        Object::F64(seconds) => Some((seconds * 1_000_000.0).round() as i64),
+        Object::Ext(0, bytes) if bytes.len() == 8 => {
+            let seconds = u32::from_be_bytes(bytes[..4].try_into().ok()?);
+            let nanos = u32::from_be_bytes(bytes[4..].try_into().ok()?);
+            Some(i64::from(seconds) * 1_000_000 + i64::from(nanos / 1000))
+        }
+        _ => None,
+    }
+}
+
+fn to_json(object: &Object) -> Value {
+    match object {
+        Object::Nil => Value::Null,
+        Object::Boolean(value) => Value::Bool(*value),
+        Object::Integer(value) => match value.as_i64() {
+            Some(value) => Value::from(value),
+            None => Value::from(value.as_u64()),
+        },
+        Object::F32(value) => Value::from(*value),
+        Object::F64(value) => Value::from(*value),
+        Object::String(_) | Object::Binary(_) => Value::String(text(object).unwrap_or_default()),
+        Object::Array(items) => Value::Array(items.iter().map(to_json).collect()),
+        Object::Map(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| {
+                    let key = text(key).unwrap_or_else(|| to_json(key).to_string());
+                    (key, to_json(value))
+                })
+                .collect(),
+        ),
+        Object::Ext(..) => match micros(object) {
+            Some(micros) => Value::String(epoch_timestamp(micros)),
+            None => Value::Null,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        sync::mpsc,
+    };
+
+    use rmpv::decode::read_value;
+
+    use super::{Forwarded, MAX_MESSAGE, ack, serve};
+    use crate::reader::Segment;
+
+    /// `["app.web", EventTime(1792152001.5), {"level": "error", "msg": "disk full", "code": -7}]`
+    /// with the option `{"chunk": "c1"}`.
+    const MESSAGE: &[u8] = &[
+        0x94, 0xa7, b'a', b'p', b'p', b'.', b'w', b'e', b'b', 0xd7, 0x00, 0x6a, 0xd2, 0x11, 0xc1,
+        0x1d, 0xcd, 0x65, 0x00, 0x83, 0xa5, b'l', b'e', b'v', b'e', b'l', 0xa5, b'e', b'r', b'r',
+        b'o', b'r', 0xa3, b'm', b's', b'g', 0xa9, b'd', b'i', b's', b'k', b' ', b'f', b'u', b'l',
+        b'l', 0xa4, b'c', b'o', b'd', b'e', 0xf9, 0x81, 0xa5, b'c', b'h', b'u', b'n', b'k', 0xa2,
+        b'c', b'1',
+    ];
+
+    const LINE: &str = concat!(
+        r#"{"timestamp":"2026-10-16T12:00:01.500000Z","tag":"app.web","#,
+        r#""level":"error","msg":"disk full","code":-7}"#
+    );
+
+    #[test]
+    fn messages_decode_in_every_mode() {
+        let mut rest = MESSAGE;
+        let message = read_value(&mut rest).expect("decode failed");
+        assert!(rest.is_empty());
+        let event = Forwarded::parse(message).expect("not a forward message");
+        assert_eq!(event.lines(), [LINE]);
+        assert_eq!(event.chunk.as_deref(), Some("c1"));
+
+        // The same entry in Forward mode, with its time in whole seconds.
+        let mut forward = vec![0x92, 0xa3, b'a', b'p', b'p', 0x91, 0x92, 0xce];
+        forward.extend_from_slice(&1_792_152_001_u32.to_be_bytes());
+        forward.extend_from_slice(&[0x81, 0xa1, b'n', 0xd1, 0xff, 0x38]);
+        let message = read_value(&mut &forward[..]).expect("decode failed");
+        let event = Forwarded::parse(message).expect("not a forward message");
+        assert_eq!(
+            event.lines(),
+            [r#"{"timestamp":"2026-10-16T12:00:01.000000Z","tag":"app","n":-200}"#]
+        );
+        assert_eq!(event.chunk, None);
+    }
+
+    #[tokio::test]
+    async fn chunked_messages_are_acknowledged() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (tx, mut rx) = mpsc::channel(16);
+        let served = tokio::spawn(serve(server, tx));
+
+        let (mut reader, mut writer) = tokio::io::split(client);
+        // Split across writes, like a message arriving in pieces.
+        writer
+            .write_all(&MESSAGE[..30])
+            .await
+            .expect("write failed");
+        writer
+            .write_all(&MESSAGE[30..])
+            .await
+            .expect("write failed");
+        let mut answer = vec![0; ack("c1").len()];
+        reader.read_exact(&mut answer).await.expect("read failed");
+        assert_eq!(answer, ack("c1"));
+        drop((reader, writer));
+        served.await.expect("join failed").expect("serve failed");
+
+        let Some(Segment::Line(line)) = rx.recv().await else {
+            panic!("no line");
+        };
+        assert_eq!(line, LINE);
+    }
+
+    #[tokio::test]
+    async fn a_message_past_the_limit_fails_the_connection() {
+        let (mut client, server) = tokio::io::duplex(1 << 16);
+        let (tx, _rx) = mpsc::channel(16);
+        let served = tokio::spawn(serve(server, tx));
+
+        // A string header claiming more than a message may take, then its bytes.
+        let length = u32::try_from(MAX_MESSAGE + 1).expect("length fits");
+        let mut header = vec![0xdb];
+        header.extend_from_slice(&length.to_be_bytes());
+        client.write_all(&header).await.expect("write failed");
+        let padding = vec![b' '; 1 << 16];
+        for _ in 0..=MAX_MESSAGE / padding.len() {
+            if client.write_all(&padding).await.is_err() {
+                break;
+            }
+        }
+        drop(client);
+        let error = served
+            .await
+            .expect("join failed")
+            .expect_err("oversized message taken");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    fs::{self, Metadata},
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use encoding_rs::Encoding;
+use tokio::{
+    fs::File,
+    io::{AsyncRead, ReadBuf},
+    sync::mpsc,
+    time::Sleep,
+};
+
+use crate::reader::{Segment, SegmentReader};
+
+/// How often to check a followed file for appended data once its end has been reached.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Size of the chunks read backwards from the end of a file when looking for the last lines.
+const TAIL_CHUNK: usize = 64 * 1024;
+
+/// Identifies a file independently of its path, to notice it being replaced.
+type FileId = Option<(u64, u64)>;
+
+/// Reads a file like `tail -F`: instead of signalling end of file, waits for data to be
+/// appended and keeps reading. When the file is truncated or replaced (e.g. by logrotate), it
+/// is re-opened from the start, signalling end of file once in between.
+pub(crate) struct FollowReader {
+    path: PathBuf,
+    file: File,
+    id: FileId,
+    position: u64,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl FollowReader {
+    /// `file` must be `path` opened and positioned at `position`.
+    pub(crate) fn new(path: PathBuf, file: fs::File, position: u64) -> io::Result<Self> {
+        let id = file_id(&file.metadata()?);
+        Ok(Self {
+            path,
+            file: File::from_std(file),
+            id,
+            position,
+            sleep: None,
+        })
+    }
+
+    /// Re-opens the file if the one at `path` was truncated below what has been read or is a
+    /// different file altogether. A file that was moved away and not recreated yet is kept
+    /// open, as its writer may not have switched yet.
+    fn reopen_if_rotated(&mut self) -> io::Result<bool> {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return Ok(false);
+        };
+        if file_id(&metadata) == self.id && metadata.len() >= self.position {
+            return Ok(false);
+        }
+        let Ok(file) = fs::File::open(&self.path) else {
+            return Ok(false);
+        };
+        self.id = file_id(&file.metadata()?);
+        self.file = File::from_std(file);
+        self.position = 0;
+        Ok(true)
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> FileId {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> FileId {
+    None
+}
+
+impl AsyncRead for FollowReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(sleep) = &mut this.sleep {
+                ready!(sleep.as_mut().poll(cx));
+                this.sleep = None;
+            }
+
+            let filled = buf.filled().len();
+            ready!(Pin::new(&mut this.file).poll_read(cx, buf))?;
+            let read = buf.filled().len() - filled;
+            if read > 0 {
+                this.position += read as u64;
+                return Poll::Ready(Ok(()));
+            }
+            if this.reopen_if_rotated()? {
+                return Poll::Ready(Ok(()));
+            }
+            this.sleep = Some(Box::pin(tokio::time::sleep(POLL_INTERVAL)));
+        }
+    }
+}
+
+/// A segment read from a followed file.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct FollowedSegment {
+    /// Index of the file into [`crate::AppState::sources`].
+    pub(crate) source: usize,
+    /// Byte offset of the segment in the file.
+    pub(crate) offset: u64,
+    pub(crate) segment: Segment,
+}
+
+/// Reads segments from a followed file, forwarding them to the main loop tagged with `source`,
+/// along with a [`Segment::Rotated`] whenever the file gets rotated.
+pub(crate) async fn forward_followed(
+    reader: FollowReader,
+    source: usize,
+    encoding: &'static Encoding,
+    tx: mpsc::Sender<FollowedSegment>,
+) {
+    let position = reader.position;
+    let mut reader = SegmentReader::new(reader, encoding);
+    reader.set_offset(position);
+    loop {
+        let (segment, offset) = match reader.next_segment().await {
+            Ok(Some(segment)) => (segment, reader.segment_offset()),
+            Ok(None) => {
+                // The re-opened file is read from its start.
+                reader.set_offset(0);
+                (Segment::Rotated, 0)
+            }
+            Err(_) => break,
+        };
+        let followed = FollowedSegment {
+            source,
+            offset,
+            segment,
+        };
+        if tx.send(followed).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Returns the offset at which the last `lines` lines of the input start.
+pub(crate) fn tail_offset<R: Read + Seek>(reader: &mut R, lines: usize) -> io::Result<u64> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    if lines == 0 {
+        return Ok(len);
+    }
+
+    let mut newlines = 0;
+    let mut buf = vec![0; TAIL_CHUNK];
+    let mut end = len;
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_CHUNK as u64);
+        let chunk = &mut buf[..(end - start) as usize];
+        reader.seek(SeekFrom::Start(start))?;
+        reader.read_exact(chunk)?;
+        for (i, &byte) in chunk.iter().enumerate().rev() {
+            let position = start + i as u64;
+            // The newline terminating the last line doesn't start another one.
+            if byte == b'\n' && position + 1 != len {
+                newlines += 1;
+                if newlines == lines {
+                    return Ok(position + 1);
+                }
+            }
+        }
+        end = start;
+    }
+
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FollowReader, FollowedSegment, forward_followed, tail_offset};
+    use crate::reader::{Segment, SegmentReader};
+    use encoding_rs::UTF_8;
+    use std::{
+        fs,
+        io::{Cursor, Write},
+        path::Path,
+        time::Duration,
+    };
+    use tokio::{sync::mpsc, time::timeout};
+
+    fn temp_log(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("logr-{name}-{}.log", std::process::id()))
+    }
+
+    fn open_follow_reader(path: &Path) -> FollowReader {
+        let file = fs::File::open(path).expect("open failed");
+        FollowReader::new(path.to_path_buf(), file, 0).expect("metadata failed")
+    }
+
+    async fn next(rx: &mut mpsc::Receiver<FollowedSegment>) -> Option<(Segment, u64)> {
+        let followed = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out")?;
+        assert_eq!(followed.source, 3);
+        Some((followed.segment, followed.offset))
+    }
+
+    #[test]
+    fn tail_offset_finds_start_of_last_lines() {
+        let mut input = Cursor::new(b"a\nb\nc\n".to_vec());
+        assert_eq!(tail_offset(&mut input, 1).expect("tail failed"), 4);
+        assert_eq!(tail_offset(&mut input, 2).expect("tail failed"), 2);
+        assert_eq!(tail_offset(&mut input, 5).expect("tail failed"), 0);
+        assert_eq!(tail_offset(&mut input, 0).expect("tail failed"), 6);
+
+        let mut unterminated = Cursor::new(b"a\nb\nc".to_vec());
+        assert_eq!(tail_offset(&mut unterminated, 1).expect("tail failed"), 4);
+    }
+
+    #[tokio::test]
+    async fn follow_reader_picks_up_appended_lines() {
+        let path = temp_log("follow");
+        fs::write(&path, "first\n").expect("write failed");
+
+        let mut reader = SegmentReader::new(open_follow_reader(&path), UTF_8);
+        let first = timeout(Duration::from_secs(5), reader.next_segment()).await;
+        assert_eq!(
+            first.expect("timed out").expect("read failed"),
+            Some(Segment::Line("first".to_string()))
+        );
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("open failed");
+        file.write_all(b"second\n").expect("write failed");
+        let second = timeout(Duration::from_secs(5), reader.next_segment()).await;
+        assert_eq!(
+            second.expect("timed out").expect("read failed"),
+            Some(Segment::Line("second".to_string()))
+        );
+
+        fs::remove_file(&path).expect("cleanup failed");
+    }
+
+    #[tokio::test]
+    async fn follow_reader_reopens_rotated_and_truncated_files() {
+        let path = temp_log("rotate");
+        let rotated = temp_log("rotate-old");
+        fs::write(&path, "old\nolder\n").expect("write failed");
+
+        let (tx, mut rx) = mpsc::channel(16);
+        tokio::spawn(forward_followed(open_follow_reader(&path), 3, UTF_8, tx));
+        assert_eq!(
+            next(&mut rx).await,
+            Some((Segment::Line("old".to_string()), 0))
+        );
+        assert_eq!(
+            next(&mut rx).await,
+            Some((Segment::Line("older".to_string()), 4))
+        );
+
+        fs::rename(&path, &rotated).expect("rename failed");
+        fs::write(&path, "new\n").expect("write failed");
+        assert_eq!(next(&mut rx).await, Some((Segment::Rotated, 0)));
+        assert_eq!(
+            next(&mut rx).await,
+            Some((Segment::Line("new".to_string()), 0))
+        );
+
+        fs::write(&path, "").expect("truncate failed");
+        assert_eq!(next(&mut rx).await, Some((Segment::Rotated, 0)));
+        fs::write(&path, "again\n").expect("write failed");
+        assert_eq!(
+            next(&mut rx).await,
+            Some((Segment::Line("again".to_string()), 0))
+        );
+
+        fs::remove_file(&path).expect("cleanup failed");
+        fs::remove_file(&rotated).expect("cleanup failed");
+    }
+}
@@ -0,0 +1,30 @@
+//! Entry points for the fuzz targets in `fuzz/`, only built by `cargo fuzz` (which sets
+//! `--cfg fuzzing`) as the rest of the crate is private.
+
+use crate::{
+    AppState, LogLine,
+    buffer::LogBuffer,
+    build_pattern,
+    json::JsonView,
+    push_line,
+    ui::{exit_lines, highlight_line},
+};
+
+/// Runs a line through everything that styles it for display: ANSI parsing, pattern and level
+/// highlighting (both from scratch and from the matches cached when it was pushed) and the
+/// JSON and logfmt views.
+pub fn highlight(line: &str) {
+    let patterns = ["error", r"\d+", "é|ü", r"\s", "^.", "$"]
+        .iter()
+        .map(|pattern| build_pattern(pattern.to_string(), false).expect("fuzz patterns are valid"))
+        .collect();
+    let mut app = AppState::new(patterns, false);
+    let _ = highlight_line(line, &app.patterns);
+
+    let mut lines = LogBuffer::new(None);
+    push_line(&mut app, &mut lines, LogLine::output(line.to_string()));
+    for view in [JsonView::Colored, JsonView::Flat, JsonView::Raw] {
+        app.json_view = view;
+        let _ = exit_lines(&lines, &app, 1, None);
+    }
+}
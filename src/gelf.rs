@@ -0,0 +1,404 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use miniz_oxide::inflate::{decompress_to_vec_with_limit, decompress_to_vec_zlib_with_limit};
+use serde_json::{Map, Value};
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader},
+    net::{TcpListener, UdpSocket},
+    sync::mpsc,
+};
+
+use crate::{
+    level::Level,
+    parser::epoch_timestamp,
+    reader::{CHANNEL_CAPACITY, Segment, accept},
+};
+
+/// The largest message taken, decompressed, against payloads that inflate without end.
+const MAX_MESSAGE: usize = 8 << 20;
+
+/// How long the chunks of a message are waited for, as the GELF spec has it.
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most chunks a message is sent in, as the GELF spec has it.
+const MAX_CHUNKS: usize = 128;
+
+/// The most chunked messages put together at once, the oldest being dropped for a new one.
+const MAX_PENDING: usize = 128;
+
+/// The most bytes held across the chunked messages being put together, the oldest being
+/// dropped to make room, so a sender can't have them grow without end.
+const MAX_BUFFERED: usize = 32 << 20;
+
+/// Listens for GELF messages on `addr`, over both UDP and TCP, taking each as a JSON line of
+/// its timestamp, level, host and message followed by its additional fields.
+pub(crate) async fn listen(addr: SocketAddr) -> io::Result<mpsc::Receiver<Segment>> {
+    let socket = UdpSocket::bind(addr).await?;
+    let listener = TcpListener::bind(addr).await?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::spawn(receive_datagrams(socket, tx.clone()));
+    accept(listener, move |stream| receive_stream(stream, tx.clone()));
+    Ok(rx)
+}
+
+async fn receive_datagrams(socket: UdpSocket, tx: mpsc::Sender<Segment>) {
+    let mut buf = vec![0; 65_536];
+    let mut chunks = Chunks::default();
+    while let Ok(length) = socket.recv(&mut buf).await {
+        let Some(payload) = chunks.add(&buf[..length], Instant::now()) else {
+            continue;
+        };
+        let Some(line) = decompress(&payload).as_deref().and_then(line) else {
+            continue;
+        };
+        if tx.send(Segment::Line(line)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads the messages of a TCP connection, which are uncompressed and end with a null byte,
+/// failing on one longer than [`MAX_MESSAGE`].
+async fn receive_stream<R: AsyncRead + Unpin>(
+    stream: R,
+    tx: mpsc::Sender<Segment>,
+) -> io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut message = Vec::new();
+    loop {
+        message.clear();
+        let limit = MAX_MESSAGE as u64 + 1;
+        if (&mut stream)
+            .take(limit)
+            .read_until(0, &mut message)
+            .await?
+            == 0
+        {
+            return Ok(());
+        }
+        let message = match message.strip_suffix(&[0]) {
+            Some(message) => message,
+            None if message.len() > MAX_MESSAGE => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "message too large",
+                ));
+            }
+            None => &message,
+        };
+        let Some(line) = line(message) else {
+            continue;
+        };
+        if tx.send(Segment::Line(line)).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// The chunks of the UDP messages too large for one datagram, by message id.
+#[derive(Default)]
+struct Chunks {
+    pending: HashMap<[u8; 8], Message>,
+    /// The pending messages by when their first chunk came in, oldest first.
+    order: BTreeSet<(Instant, [u8; 8])>,
+    /// Bytes held across the pending messages.
+    bytes: usize,
+}
+
+/// A chunked message being put together.
+struct Message {
+    /// When its first chunk came in.
+    started: Instant,
+    parts: Vec<Option<Vec<u8>>>,
+    /// Bytes of its chunks come in so far.
+    bytes: usize,
+}
+
+impl Chunks {
+    /// Takes a datagram, returning the whole payload once it has all of it: right away when
+    /// it isn't chunked, on its last chunk otherwise.
+    fn add(&mut self, datagram: &[u8], now: Instant) -> Option<Vec<u8>> {
+        let Some(chunk) = datagram.strip_prefix(&[0x1e, 0x0f]) else {
+            return Some(datagram.to_vec());
+        };
+        let (header, data) = chunk.split_at_checked(10)?;
+        let id: [u8; 8] = header[..8].try_into().ok()?;
+        let (sequence, count) = (usize::from(header[8]), usize::from(header[9]));
+        if count == 0 || count > MAX_CHUNKS || sequence >= count {
+            return None;
+        }
+        while let Some(&(started, oldest)) = self.order.first() {
+            if now.duration_since(started) < CHUNK_TIMEOUT {
+                break;
+            }
+            self.remove(&oldest);
+        }
+        if !self.pending.contains_key(&id) && self.pending.len() >= MAX_PENDING {
+            self.remove_oldest();
+        }
+        while self.bytes + data.len() > MAX_BUFFERED && self.remove_oldest() {}
+        let message = self.pending.entry(id).or_insert_with(|| {
+            self.order.insert((now, id));
+            Message {
+                started: now,
+                parts: vec![None; count],
+                bytes: 0,
+            }
+        });
+        let replaced = message
+            .parts
+            .get_mut(sequence)?
+            .replace(data.to_vec())
+            .map_or(0, |part| part.len());
+        message.bytes = message.bytes - replaced + data.len();
+        self.bytes = self.bytes - replaced + data.len();
+        // A message larger than taken whole is dropped rather than put together.
+        if message.bytes > MAX_MESSAGE {
+            self.remove(&id);
+            return None;
+        }
+        if message.parts.iter().any(Option::is_none) {
+            return None;
+        }
+        let message = self.remove(&id)?;
+        Some(message.parts.into_iter().flatten().flatten().collect())
+    }
+
+    /// Drops the pending message `id`, returning it.
+    fn remove(&mut self, id: &[u8; 8]) -> Option<Message> {
+        let message = self.pending.remove(id)?;
+        self.order.remove(&(message.started, *id));
+        self.bytes -= message.bytes;
+        Some(message)
+    }
+
+    /// Drops the pending message whose first chunk came in first, if there is one.
+    fn remove_oldest(&mut self) -> bool {
+        let Some(&(_, oldest)) = self.order.first() else {
+            return false;
+        };
+        self.remove(&oldest).is_some()
+    }
+}
+
+/// Inflates a gzip or zlib compressed payload, telling them by their magic bytes. Others are
+/// taken as uncompressed.
+pub(crate) fn decompress(payload: &[u8]) -> Option<Vec<u8>> {
+    match payload {
+        [0x1f, 0x8b, ..] => decompress_to_vec_with_limit(gzip_body(payload)?, MAX_MESSAGE).ok(),
+        [0x78, ..] => decompress_to_vec_zlib_with_limit(payload, MAX_MESSAGE).ok(),
+        _ => Some(payload.to_vec()),
+    }
+}
+
+/// The deflate stream of a gzip member, past its header.
+fn gzip_body(payload: &[u8]) -> Option<&[u8]> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+    let flags = *payload.get(3)?;
+    let mut rest = payload.get(10..)?;
+    if flags & FEXTRA != 0 {
+        let length = usize::from(u16::from_le_bytes([*rest.first()?, *rest.get(1)?]));
+        rest = rest.get(2 + length..)?;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = rest.iter().position(|&byte| byte == 0)?;
+            rest = &rest[end + 1..];
+        }
+    }
+    if flags & FHCRC != 0 {
+        rest = rest.get(2..)?;
+    }
+    Some(rest)
+}
+
+/// A GELF message as a JSON line: its timestamp in ISO 8601 and its syslog level by name
+/// first, so they are shown and found like those of other JSON logs, then its host, its
+/// message and its additional fields, without their leading underscore.
+fn line(message: &[u8]) -> Option<String> {
+    let Ok(Value::Object(mut gelf)) = serde_json::from_slice::<Value>(message) else {
+        return None;
+    };
+    let mut fields = Map::new();
+    if let Some(timestamp) = gelf.remove("timestamp").as_ref().and_then(Value::as_f64) {
+        let micros = (timestamp * 1_000_000.0).round() as i64;
+        fields.insert("timestamp".to_string(), epoch_timestamp(micros).into());
+    }
+    let severity = gelf
+        .remove("level")
+        .as_ref()
+        .and_then(Value::as_u64)
+        .unwrap_or(1);
+    let level = Level::from_syslog(u32::try_from(severity).unwrap_or(u32::MAX));
+    fields.insert("level".to_string(), level.name().into());
+    gelf.remove("version");
+    for key in ["host", "short_message", "full_message"] {
+        if let Some(value) = gelf.remove(key) {
+            let key = if key == "short_message" {
+                "message"
+            } else {
+                key
+            };
+            fields.insert(key.to_string(), value);
+        }
+    }
+    for (key, value) in gelf {
+        let key = key.strip_prefix('_').map_or(key.clone(), str::to_string);
+        fields.entry(key).or_insert(value);
+    }
+    // Written out field by field, as a map would sort the keys.
+    let mut ordered: Vec<(String, Value)> =
+        ["timestamp", "level", "host", "message", "full_message"]
+            .into_iter()
+            .filter_map(|key| fields.remove_entry(key))
+            .collect();
+    ordered.extend(fields);
+    let parts: Vec<String> = ordered
+        .into_iter()
+        .map(|(key, value)| format!("{}:{value}", Value::String(key)))
+        .collect();
+    Some(format!("{{{}}}", parts.join(",")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use miniz_oxide::deflate::{compress_to_vec, compress_to_vec_zlib};
+
+    use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+    use super::{
+        Chunks, MAX_BUFFERED, MAX_CHUNKS, MAX_MESSAGE, MAX_PENDING, decompress, line,
+        receive_stream,
+    };
+    use crate::reader::Segment;
+
+    const MESSAGE: &str = concat!(
+        r#"{"version":"1.1","host":"web-1","short_message":"disk full","#,
+        r#""timestamp":1792152001.5,"level":3,"_request_id":"abc","_status":500}"#
+    );
+
+    #[test]
+    fn messages_become_json_lines_with_the_level_by_name() {
+        assert_eq!(
+            line(MESSAGE.as_bytes()).as_deref(),
+            Some(concat!(
+                r#"{"timestamp":"2026-10-16T12:00:01.500000Z","level":"error","host":"web-1","#,
+                r#""message":"disk full","request_id":"abc","status":500}"#
+            ))
+        );
+        assert_eq!(line(b"not json"), None);
+    }
+
+    #[test]
+    fn chunked_and_compressed_payloads_are_put_back_together() {
+        let now = Instant::now();
+        let compressed = compress_to_vec_zlib(MESSAGE.as_bytes(), 6);
+        let (first, second) = compressed.split_at(compressed.len() / 2);
+        let chunk = |sequence: u8, data: &[u8]| {
+            let mut chunk = vec![0x1e, 0x0f, 1, 2, 3, 4, 5, 6, 7, 8, sequence, 2];
+            chunk.extend_from_slice(data);
+            chunk
+        };
+        let mut chunks = Chunks::default();
+        assert_eq!(chunks.add(&chunk(1, second), now), None);
+        let payload = chunks.add(&chunk(0, first), now).expect("not put together");
+        assert_eq!(decompress(&payload).as_deref(), Some(MESSAGE.as_bytes()));
+
+        // Chunks of a message that took too long are dropped.
+        assert_eq!(chunks.add(&chunk(0, first), now), None);
+        let later = now + Duration::from_secs(6);
+        assert_eq!(chunks.add(&chunk(1, second), later), None);
+
+        let mut gzip = vec![0x1f, 0x8b, 8, 0x08, 0, 0, 0, 0, 0, 255];
+        gzip.extend_from_slice(b"gelf.json\0");
+        gzip.extend_from_slice(&compress_to_vec(MESSAGE.as_bytes(), 6));
+        gzip.extend_from_slice(&[0; 8]);
+        assert_eq!(decompress(&gzip).as_deref(), Some(MESSAGE.as_bytes()));
+        assert_eq!(chunks.add(b"{}", now).as_deref(), Some(&b"{}"[..]));
+    }
+
+    #[test]
+    fn the_oldest_pending_message_makes_way_for_a_new_one() {
+        let now = Instant::now();
+        let first_chunk = |id: u64| {
+            let mut chunk = vec![0x1e, 0x0f];
+            chunk.extend_from_slice(&id.to_be_bytes());
+            chunk.extend_from_slice(&[0, 2]);
+            chunk.extend_from_slice(b"{}");
+            chunk
+        };
+        let mut chunks = Chunks::default();
+        for id in 0..=MAX_PENDING as u64 {
+            let arrived = now + Duration::from_micros(id);
+            assert_eq!(chunks.add(&first_chunk(id), arrived), None);
+        }
+        assert_eq!(chunks.pending.len(), MAX_PENDING);
+        assert_eq!(chunks.order.len(), MAX_PENDING);
+        assert!(!chunks.pending.contains_key(&0u64.to_be_bytes()));
+        assert!(
+            chunks
+                .pending
+                .contains_key(&(MAX_PENDING as u64).to_be_bytes())
+        );
+    }
+
+    #[test]
+    fn chunks_past_the_message_or_buffered_limits_are_dropped() {
+        let now = Instant::now();
+        let chunk = |id: u64, sequence: usize, size: usize| {
+            let mut chunk = vec![0x1e, 0x0f];
+            chunk.extend_from_slice(&id.to_be_bytes());
+            chunk.extend_from_slice(&[sequence as u8, MAX_CHUNKS as u8]);
+            chunk.resize(chunk.len() + size, b' ');
+            chunk
+        };
+        let mut chunks = Chunks::default();
+        let size = MAX_MESSAGE / MAX_CHUNKS + 1;
+        for sequence in 0..MAX_CHUNKS {
+            assert_eq!(chunks.add(&chunk(0, sequence, size), now), None);
+        }
+        assert!(chunks.pending.is_empty());
+        assert_eq!(chunks.bytes, 0);
+
+        for id in 0..MAX_PENDING as u64 {
+            for sequence in 0..16 {
+                let arrived = now + Duration::from_micros(id);
+                assert_eq!(chunks.add(&chunk(id, sequence, 60_000), arrived), None);
+                assert!(chunks.bytes <= MAX_BUFFERED);
+            }
+        }
+        let held: usize = chunks.pending.values().map(|message| message.bytes).sum();
+        assert_eq!(chunks.bytes, held);
+        assert!(!chunks.pending.contains_key(&0u64.to_be_bytes()));
+    }
+
+    #[tokio::test]
+    async fn a_stream_message_past_the_limit_fails_the_connection() {
+        let (mut client, server) = tokio::io::duplex(1 << 16);
+        let (tx, mut rx) = mpsc::channel(16);
+        let received = tokio::spawn(receive_stream(server, tx));
+        client
+            .write_all(b"{\"short_message\":\"hi\"}\0")
+            .await
+            .expect("write failed");
+        client
+            .write_all(&vec![b' '; MAX_MESSAGE + 1])
+            .await
+            .expect("write failed");
+        let error = received
+            .await
+            .expect("join failed")
+            .expect_err("oversized message taken");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(matches!(rx.recv().await, Some(Segment::Line(_))));
+    }
+}
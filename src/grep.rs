@@ -0,0 +1,271 @@
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use encoding_rs::Encoding;
+use tokio::{io::AsyncRead, sync::mpsc};
+
+use crate::{
+    AppState, Args, InputEvent, LogrError, ansi,
+    command::CommandRunner,
+    detect_level, drain, fluent, gelf, kmsg, line_excluded, line_matches_patterns,
+    reader::{Segment, SegmentReader},
+    redact::redact,
+    ui::highlight_line,
+};
+
+/// Prints the input like a colored grep instead of starting the TUI, for when stdout isn't a
+/// terminal: the lines matching the patterns, or every line when no pattern selects lines, with
+/// the matches highlighted if colors are on and the secrets masked. Files are read to their end
+/// rather than followed. With `--output`, the lines matching a pattern are copied there too.
+pub(crate) async fn run(
+    args: &Args,
+    app: &mut AppState,
+    encoding: &'static Encoding,
+) -> Result<(), LogrError> {
+    let mut out = io::BufWriter::new(io::stdout());
+    let printed = if !args.command.is_empty() {
+        print_command(args.command.clone(), encoding, app, &mut out).await
+    } else if args.kmsg {
+        print_segments(kmsg::spawn()?, app, &mut out).await
+    } else if let Some(addr) = args.drain {
+        print_segments(drain::listen(addr).await?, app, &mut out).await
+    } else if let Some(addr) = args.gelf {
+        print_segments(gelf::listen(addr).await?, app, &mut out).await
+    } else if let Some(addr) = args.fluent {
+        print_segments(fluent::listen(addr).await?, app, &mut out).await
+    } else if !args.files.is_empty() || !args.sources.is_empty() {
+        let paths: Vec<PathBuf> = args
+            .files
+            .iter()
+            .chain(args.sources.iter().map(|spec| &spec.path))
+            .cloned()
+            .collect();
+        print_files(&paths, encoding, app, &mut out).await
+    } else {
+        print_input(tokio::io::stdin(), encoding, app, &mut out).await
+    };
+    match printed.and_then(|()| out.flush()) {
+        // The reader went away, like `head` does once it has enough lines.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+async fn print_files(
+    paths: &[PathBuf],
+    encoding: &'static Encoding,
+    app: &mut AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for path in paths {
+        let file = tokio::fs::File::open(path).await?;
+        print_input(file, encoding, app, out).await?;
+    }
+    Ok(())
+}
+
+async fn print_input<R: AsyncRead + Unpin>(
+    input: R,
+    encoding: &'static Encoding,
+    app: &mut AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut reader = SegmentReader::new(input, encoding);
+    let mut progress = None;
+    while let Some(segment) = reader.next_segment().await? {
+        print_segment(segment, &mut progress, app, out)?;
+    }
+    print_segment(Segment::Commit, &mut progress, app, out)
+}
+
+async fn print_segments(
+    mut segments: mpsc::Receiver<Segment>,
+    app: &mut AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut progress = None;
+    while let Some(segment) = segments.recv().await {
+        print_segment(segment, &mut progress, app, out)?;
+    }
+    Ok(())
+}
+
+async fn print_command(
+    command: Vec<String>,
+    encoding: &'static Encoding,
+    app: &mut AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut runner = CommandRunner::spawn(command, encoding)?;
+    let mut progress = None;
+    while let Some(InputEvent::Segment(segment)) = runner.next_event().await {
+        print_segment(segment, &mut progress, app, out)?;
+    }
+    print_segment(Segment::Commit, &mut progress, app, out)
+}
+
+/// Prints the line a segment completes. Of a line redrawn with `\r`, only its last update is
+/// printed, once it is known to be the last one.
+fn print_segment(
+    segment: Segment,
+    progress: &mut Option<String>,
+    app: &mut AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let text = match segment {
+        Segment::Line(text) => {
+            *progress = None;
+            text
+        }
+        Segment::Progress(text) => {
+            *progress = Some(text);
+            return Ok(());
+        }
+        Segment::Commit | Segment::Rotated => match progress.take() {
+            Some(text) => text,
+            None => return Ok(()),
+        },
+    };
+    let Some(line) = grep_line(&text, app) else {
+        return Ok(());
+    };
+    writeln!(out, "{line}")?;
+    if let Some(output) = &mut app.output
+        && line_matches_patterns(&text, &app.patterns)
+    {
+        writeln!(output, "{}", redact(&app.redactions, Cow::Borrowed(&text)))?;
+    }
+    Ok(())
+}
+
+/// The line as printed, or `None` if it is left out: when there are patterns selecting lines
+/// and it matches none of them, when it matches an exclude pattern, or when it states a lower
+/// level than `--level`.
+fn grep_line(text: &str, app: &AppState) -> Option<String> {
+    let selecting = app
+        .patterns
+        .iter()
+        .any(|pattern| pattern.active() && !pattern.exclude);
+    if selecting && !line_matches_patterns(text, &app.patterns) {
+        return None;
+    }
+    if line_excluded(text, &app.patterns) {
+        return None;
+    }
+    if let (Some(min_level), Some(token)) = (app.min_level, detect_level(text, app.priorities))
+        && token.level < min_level
+    {
+        return None;
+    }
+    let text = redact(&app.redactions, Cow::Borrowed(text));
+    if app.colors {
+        Some(ansi::line_to_ansi(&highlight_line(&text, &app.patterns)))
+    } else {
+        Some(text.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grep_line, print_segment};
+    use crate::{AppState, build_patterns, level::Level, reader::Segment, redact::Redaction};
+    use std::fs;
+
+    #[test]
+    fn grep_keeps_matching_lines_or_all_without_patterns() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.colors = false;
+        assert_eq!(grep_line("all good", &app).as_deref(), Some("all good"));
+        app.min_level = Some(Level::Warn);
+        assert_eq!(grep_line("INFO all good", &app), None);
+
+        app.patterns =
+            build_patterns(&["error".to_string()], true).expect("building patterns failed");
+        assert_eq!(grep_line("WARN all good", &app), None);
+        assert_eq!(
+            grep_line("ERROR disk full", &app).as_deref(),
+            Some("ERROR disk full")
+        );
+        app.colors = true;
+        let colored = grep_line("ERROR disk full", &app).expect("grepping line failed");
+        assert!(colored.contains('\x1b'));
+        assert!(colored.contains("disk full"));
+    }
+
+    #[test]
+    fn only_the_last_progress_update_is_printed() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.colors = false;
+        let mut progress = None;
+        let mut out = Vec::new();
+        for segment in [
+            Segment::Progress("10%".to_string()),
+            Segment::Progress("100%".to_string()),
+            Segment::Commit,
+            Segment::Line("done".to_string()),
+        ] {
+            print_segment(segment, &mut progress, &mut app, &mut out)
+                .expect("printing segment failed");
+        }
+        assert_eq!(
+            String::from_utf8(out).expect("decoding output failed"),
+            "100%\ndone\n"
+        );
+    }
+
+    #[test]
+    fn secrets_are_masked_in_the_printed_lines() {
+        let mut app = AppState::new(
+            build_patterns(&["ERROR".to_string()], false).expect("building patterns failed"),
+            false,
+        );
+        app.colors = false;
+        app.redactions = vec![Redaction::parse(r"password=\S+").expect("parse failed")];
+        let mut progress = None;
+        let mut out = Vec::new();
+        for text in ["password=hunter2 ERROR", "password=hunter2 ok"] {
+            print_segment(
+                Segment::Line(text.to_string()),
+                &mut progress,
+                &mut app,
+                &mut out,
+            )
+            .expect("printing segment failed");
+        }
+        assert_eq!(
+            String::from_utf8(out).expect("decoding output failed"),
+            "*** ERROR\n"
+        );
+    }
+
+    #[test]
+    fn lines_matching_a_pattern_are_copied_to_the_output() {
+        let mut app = AppState::new(
+            build_patterns(&["ERROR".to_string()], false).expect("building patterns failed"),
+            false,
+        );
+        app.colors = false;
+        let path = std::env::temp_dir().join(format!("logr-grep-{}.log", std::process::id()));
+        let file = fs::File::create(&path).expect("creating output failed");
+        app.output = Some(Box::new(file));
+        let mut progress = None;
+        let mut out = Vec::new();
+        for text in ["ERROR disk full", "all good"] {
+            print_segment(
+                Segment::Line(text.to_string()),
+                &mut progress,
+                &mut app,
+                &mut out,
+            )
+            .expect("printing segment failed");
+        }
+        app.output = None;
+
+        let written = fs::read_to_string(&path).expect("reading output failed");
+        fs::remove_file(&path).expect("removing output failed");
+        assert_eq!(written, "ERROR disk full\n");
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+/// What the screen shows once idle.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum IdleScreen {
+    /// The view, dimmed.
+    Dim,
+    /// How long no line came, in large digits, above the line and match counts.
+    #[default]
+    Summary,
+}
+
+/// Blanks the screen after a while without new lines or keys pressed, as asked with `--idle`.
+#[derive(Debug)]
+pub(crate) struct Idle {
+    after: Duration,
+    pub(crate) screen: IdleScreen,
+    /// When the last line came.
+    pub(crate) last_line: Instant,
+    last_activity: Instant,
+    /// Whether the screen is blanked.
+    pub(crate) blanked: bool,
+}
+
+impl Idle {
+    pub(crate) fn new(after: Duration, screen: IdleScreen, now: Instant) -> Self {
+        Idle {
+            after,
+            screen,
+            last_line: now,
+            last_activity: now,
+            blanked: false,
+        }
+    }
+
+    /// Takes a line coming in, bringing the view back.
+    pub(crate) fn line(&mut self, now: Instant) {
+        self.last_line = now;
+        self.wake(now);
+    }
+
+    /// Takes a key press, bringing the view back. Returns whether the screen was blanked, as
+    /// the key only wakes it up then.
+    pub(crate) fn wake(&mut self, now: Instant) -> bool {
+        self.last_activity = now;
+        std::mem::take(&mut self.blanked)
+    }
+
+    /// Blanks the screen once it was idle long enough, returning whether the screen has to
+    /// be redrawn: as it is blanked, and every check after while the summary counts up.
+    pub(crate) fn check(&mut self, now: Instant) -> bool {
+        if !self.blanked && now.duration_since(self.last_activity) >= self.after {
+            self.blanked = true;
+            return true;
+        }
+        self.blanked && self.screen == IdleScreen::Summary
+    }
+}
+
+/// A time like `7:05` or `1:02:03`, in hours, minutes and seconds.
+pub(crate) fn clock(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// Rows of the digits and the colon, three columns wide and five rows high.
+const GLYPHS: [(char, [&str; 5]); 11] = [
+    ('0', ["███", "█ █", "█ █", "█ █", "███"]),
+    ('1', ["  █", "  █", "  █", "  █", "  █"]),
+    ('2', ["███", "  █", "███", "█  ", "███"]),
+    ('3', ["███", "  █", "███", "  █", "███"]),
+    ('4', ["█ █", "█ █", "███", "  █", "  █"]),
+    ('5', ["███", "█  ", "███", "  █", "███"]),
+    ('6', ["███", "█  ", "███", "█ █", "███"]),
+    ('7', ["███", "  █", "  █", "  █", "  █"]),
+    ('8', ["███", "█ █", "███", "█ █", "███"]),
+    ('9', ["███", "█ █", "███", "  █", "███"]),
+    (':', ["   ", " █ ", "   ", " █ ", "   "]),
+];
+
+/// Text of digits and colons in large glyphs, as five rows, readable from across a room.
+pub(crate) fn large(text: &str) -> Vec<String> {
+    let glyphs: Vec<&[&str; 5]> = text
+        .chars()
+        .filter_map(|c| GLYPHS.iter().find(|(glyph, _)| *glyph == c))
+        .map(|(_, rows)| rows)
+        .collect();
+    (0..5)
+        .map(|row| {
+            glyphs
+                .iter()
+                .map(|rows| rows[row])
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Idle, IdleScreen, clock, large};
+
+    #[test]
+    fn screen_blanks_after_a_while_without_activity_until_the_next() {
+        let now = Instant::now();
+        let minute = Duration::from_secs(60);
+        let mut idle = Idle::new(minute * 5, IdleScreen::Dim, now);
+
+        assert!(!idle.check(now + minute * 4));
+        idle.line(now + minute * 4);
+        assert!(!idle.check(now + minute * 8));
+        assert!(idle.check(now + minute * 9));
+        assert!(idle.blanked);
+        assert!(!idle.check(now + minute * 10));
+
+        assert!(idle.wake(now + minute * 11));
+        assert!(!idle.blanked);
+        assert!(!idle.wake(now + minute * 12));
+        assert!(!idle.check(now + minute * 16));
+    }
+
+    #[test]
+    fn elapsed_time_is_shown_in_large_digits() {
+        assert_eq!(clock(Duration::from_secs(425)), "7:05");
+        assert_eq!(clock(Duration::from_secs(3723)), "1:02:03");
+        assert_eq!(
+            large("1:0"),
+            [
+                "  █     ███",
+                "  █  █  █ █",
+                "  █     █ █",
+                "  █  █  █ █",
+                "  █     ███"
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::ops::Range;
+
+use serde_json::{Map, Value};
+
+/// How lines that are JSON objects are shown. Patterns, search and filtering keep working on
+/// the raw lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum JsonView {
+    /// The line as it is, with keys, strings, numbers and literals colored.
+    #[default]
+    Colored,
+    /// Nested fields flattened into `key.path=value` pairs.
+    Flat,
+    /// The line as it is, uncolored.
+    Raw,
+}
+
+impl JsonView {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            JsonView::Colored => JsonView::Flat,
+            JsonView::Flat => JsonView::Raw,
+            JsonView::Raw => JsonView::Colored,
+        }
+    }
+}
+
+/// What a piece of a JSON line is, for coloring it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token {
+    Key,
+    String,
+    Number,
+    /// `true`, `false` or `null`.
+    Literal,
+    /// Braces, brackets, colons and commas.
+    Punctuation,
+}
+
+impl Token {
+    pub(crate) fn of(value: &Value) -> Self {
+        match value {
+            Value::String(_) => Token::String,
+            Value::Number(_) => Token::Number,
+            Value::Bool(_) | Value::Null => Token::Literal,
+            Value::Array(_) | Value::Object(_) => Token::Punctuation,
+        }
+    }
+}
+
+fn parse_object(line: &str) -> Option<Map<String, Value>> {
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(line).ok()
+}
+
+/// Splits a line into its JSON tokens (skipping whitespace), or returns `None` if it isn't a
+/// JSON object.
+pub(crate) fn tokenize(line: &str) -> Option<Vec<(Range<usize>, Token)>> {
+    parse_object(line)?;
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let token = match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    // Escaped characters are ASCII, so this never splits a UTF-8 sequence.
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+                if line[i..].trim_start().starts_with(':') {
+                    Token::Key
+                } else {
+                    Token::String
+                }
+            }
+            b'-' | b'0'..=b'9' => {
+                while i < bytes.len()
+                    && matches!(bytes[i], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                {
+                    i += 1;
+                }
+                Token::Number
+            }
+            b't' | b'f' | b'n' => {
+                while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+                Token::Literal
+            }
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => {
+                i += 1;
+                Token::Punctuation
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        tokens.push((start..i, token));
+    }
+    Some(tokens)
+}
+
+/// The fields of a JSON object line, in key order, with nested objects and arrays flattened
+/// into dotted key paths, e.g. `{"req":{"ids":[7]}}` into `req.ids.0` = `7`. `None` if it
+/// isn't a JSON object.
+pub(crate) fn flatten(line: &str) -> Option<Vec<(String, Value)>> {
+    let mut fields = Vec::new();
+    flatten_into(
+        &mut fields,
+        String::new(),
+        Value::Object(parse_object(line)?),
+    );
+    Some(fields)
+}
+
+fn flatten_into(fields: &mut Vec<(String, Value)>, path: String, value: Value) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                flatten_into(fields, join(&key), value);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (index, item) in items.into_iter().enumerate() {
+                flatten_into(fields, join(&index.to_string()), item);
+            }
+        }
+        value => fields.push((path, value)),
+    }
+}
+
+/// A flattened value as shown after its `key=`: strings without whitespace bare, anything else
+/// as JSON.
+pub(crate) fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) if !text.is_empty() && !text.contains(char::is_whitespace) => {
+            text.clone()
+        }
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Token, flatten, tokenize, value_text};
+    use serde_json::json;
+
+    #[test]
+    fn tokenize_tells_keys_from_string_values() {
+        let line = r#"{"msg": "a \"b\" ü", "n": -1.5e3, "ok": true, "tags": [null]}"#;
+        let tokens: Vec<(&str, Token)> = tokenize(line)
+            .expect("line is JSON")
+            .into_iter()
+            .filter(|(_, token)| *token != Token::Punctuation)
+            .map(|(range, token)| (&line[range], token))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (r#""msg""#, Token::Key),
+                (r#""a \"b\" ü""#, Token::String),
+                (r#""n""#, Token::Key),
+                ("-1.5e3", Token::Number),
+                (r#""ok""#, Token::Key),
+                ("true", Token::Literal),
+                (r#""tags""#, Token::Key),
+                ("null", Token::Literal),
+            ]
+        );
+        assert!(tokenize("[1, 2]").is_none());
+        assert!(tokenize("{not json").is_none());
+    }
+
+    #[test]
+    fn flatten_joins_nested_keys_with_dots() {
+        let fields = flatten(r#"{"level":"info","req":{"path":"/a b","ids":[7]},"empty":{}}"#)
+            .expect("line is JSON");
+        let pairs: Vec<(&str, String)> = fields
+            .iter()
+            .map(|(key, value)| (key.as_str(), value_text(value)))
+            .collect();
+
+        assert_eq!(
+            pairs,
+            vec![
+                ("empty", "{}".to_string()),
+                ("level", "info".to_string()),
+                ("req.ids.0", "7".to_string()),
+                ("req.path", r#""/a b""#.to_string()),
+            ]
+        );
+        assert_eq!(value_text(&json!("")), r#""""#);
+    }
+}
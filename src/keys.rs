@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::event::{Binding, KEYMAP};
+
+/// A key as the config file names it: a character like `q` or `G`, a named key like `Esc`,
+/// `PageDown` or `F1`, either of them after `Ctrl-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Key {
+    code: KeyCode,
+    control: bool,
+}
+
+impl Key {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let (name, control) = match text.strip_prefix("Ctrl-") {
+            Some(name) if !name.is_empty() => (name, true),
+            _ => (text, false),
+        };
+        let mut chars = name.chars();
+        let code = match (chars.next()?, chars.next()) {
+            (c, None) => KeyCode::Char(c),
+            _ => match name.to_ascii_lowercase().as_str() {
+                "esc" => KeyCode::Esc,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                lower => KeyCode::F(lower.strip_prefix('f')?.parse().ok()?),
+            },
+        };
+        Some(Key { code, control })
+    }
+
+    /// Whether the key was pressed. Shift isn't compared, as it comes along with the uppercase
+    /// characters.
+    fn takes(self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        self.code == code && self.control == modifiers.contains(KeyModifiers::CONTROL)
+    }
+}
+
+/// The keys the config file binds to commands of the main view, in place of their default
+/// ones, e.g.
+///
+/// ```toml
+/// [keys]
+/// quit = ["Esc"]
+/// page-down = ["Ctrl-f", "PageDown"]
+/// ```
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct KeyBindings {
+    /// Keys with the index of the binding they are for in [`KEYMAP`], tried before the keymap.
+    keys: Vec<(Key, usize)>,
+    /// How the help overlay lists the keys of a rebound binding, by its name.
+    listed: BTreeMap<&'static str, String>,
+}
+
+impl KeyBindings {
+    pub(crate) fn new(keys: &BTreeMap<String, Vec<String>>) -> Result<Self, String> {
+        let mut bindings = KeyBindings::default();
+        for (name, texts) in keys {
+            let index = KEYMAP
+                .iter()
+                .position(|binding| binding.name == name)
+                .ok_or_else(|| format!("no command named {name} in [keys]"))?;
+            for text in texts {
+                let key =
+                    Key::parse(text).ok_or_else(|| format!("unknown key {text} in [keys]"))?;
+                bindings.keys.push((key, index));
+            }
+            bindings.listed.insert(KEYMAP[index].name, texts.join(", "));
+        }
+        Ok(bindings)
+    }
+
+    /// The binding a key is bound to here, if any.
+    pub(crate) fn binding(
+        &self,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> Option<&'static Binding> {
+        self.keys
+            .iter()
+            .find(|(key, _)| key.takes(code, modifiers))
+            .map(|&(_, index)| &KEYMAP[index])
+    }
+
+    /// Whether the binding's default keys were replaced.
+    pub(crate) fn rebinds(&self, binding: &Binding) -> bool {
+        self.listed.contains_key(binding.name)
+    }
+
+    /// The keys of a binding as the help overlay lists them.
+    pub(crate) fn listed(&self, binding: &Binding) -> &str {
+        self.listed
+            .get(binding.name)
+            .map_or(binding.keys, String::as_str)
+    }
+
+    /// The first key of the binding named `name`, to name it in the texts shown.
+    pub(crate) fn key(&self, name: &str) -> &str {
+        KEYMAP
+            .iter()
+            .find(|binding| binding.name == name)
+            .and_then(|binding| self.listed(binding).split(", ").next())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::{Key, KeyBindings};
+
+    #[test]
+    fn keys_are_parsed_and_bound_by_command_name() {
+        assert_eq!(
+            Key::parse("Ctrl-f"),
+            Some(Key {
+                code: KeyCode::Char('f'),
+                control: true,
+            })
+        );
+        assert_eq!(
+            Key::parse("pagedown").map(|key| key.code),
+            Some(KeyCode::PageDown)
+        );
+        assert_eq!(Key::parse("F5").map(|key| key.code), Some(KeyCode::F(5)));
+        assert_eq!(
+            Key::parse("-").map(|key| key.code),
+            Some(KeyCode::Char('-'))
+        );
+        assert_eq!(Key::parse("Ctrl-"), None);
+        assert_eq!(Key::parse("Escape"), None);
+
+        let keys = BTreeMap::from([(
+            "page-down".to_string(),
+            vec!["Ctrl-f".to_string(), "PageDown".to_string()],
+        )]);
+        let bindings = KeyBindings::new(&keys).expect("bindings failed");
+        let binding = bindings
+            .binding(KeyCode::Char('f'), KeyModifiers::CONTROL)
+            .expect("not bound");
+        assert_eq!(binding.name, "page-down");
+        assert!(bindings.rebinds(binding));
+        assert_eq!(bindings.listed(binding), "Ctrl-f, PageDown");
+        assert_eq!(bindings.key("page-down"), "Ctrl-f");
+        assert_eq!(bindings.key("reveal"), "X");
+        assert!(
+            bindings
+                .binding(KeyCode::Char('f'), KeyModifiers::empty())
+                .is_none()
+        );
+
+        let keys = BTreeMap::from([("scroll".to_string(), vec!["j".to_string()])]);
+        assert!(KeyBindings::new(&keys).is_err());
+        let keys = BTreeMap::from([("quit".to_string(), vec!["Escape".to_string()])]);
+        assert!(KeyBindings::new(&keys).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+    process::{Command, Stdio},
+    sync::LazyLock,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+use tokio::sync::mpsc;
+
+use crate::{
+    level::{Level, LevelToken},
+    parser::epoch_timestamp,
+    reader::{CHANNEL_CAPACITY, Segment},
+};
+
+/// A record of `/dev/kmsg`: its syslog priority (with the facility), sequence number,
+/// microseconds since boot, flags and message, e.g.
+/// `3,1234,5678901,-;usb 1-1: device not accepting address 2, error -71`. Records may go on
+/// with lines of ` KEY=value` pairs, which are left out.
+static RECORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d+),\d+,(\d+),[^;]*;(.*)$").expect("kmsg record regex is valid")
+});
+
+/// A line of `dmesg --raw`, e.g. `<3>[    5.678901] usb 1-1: device not accepting address 2`.
+static RAW: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^<(\d+)>\[\s*(\d+)\.(\d{6})\] ?(.*)$").expect("dmesg regex is valid")
+});
+
+/// A line as [`Record::format`] writes it, capturing its facility and priority.
+static FORMATTED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^(?:\S+|\[\s*\d+\.\d{6}\]) ",
+        r"([a-z0-9-]+\.(emerg|alert|crit|err|warning|notice|info|debug)):"
+    ))
+    .expect("kmsg line regex is valid")
+});
+
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+const PRIORITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// A message of the kernel log.
+#[derive(Debug, PartialEq, Eq)]
+struct Record {
+    /// The syslog priority, with the facility in all but its lowest three bits.
+    priority: u32,
+    since_boot: u64,
+    text: String,
+}
+
+impl Record {
+    /// Reads a record of `/dev/kmsg`, or a line of `dmesg --raw`.
+    fn parse(line: &str) -> Option<Self> {
+        if let Some(captures) = RECORD.captures(line) {
+            return Some(Record {
+                priority: captures[1].parse().ok()?,
+                since_boot: captures[2].parse().ok()?,
+                text: captures[3].to_string(),
+            });
+        }
+        let captures = RAW.captures(line)?;
+        let seconds: u64 = captures[2].parse().ok()?;
+        let micros: u64 = captures[3].parse().ok()?;
+        Some(Record {
+            priority: captures[1].parse().ok()?,
+            since_boot: seconds * 1_000_000 + micros,
+            text: captures[4].to_string(),
+        })
+    }
+
+    /// The record as a line: its time, as of the wall clock when the boot time is known and
+    /// since boot otherwise, then its facility and priority like `kern.err`, then its message.
+    fn format(&self, boot: Option<i64>) -> String {
+        let time = match boot {
+            Some(boot) => epoch_timestamp(boot + self.since_boot as i64),
+            None => format!(
+                "[{:>5}.{:06}]",
+                self.since_boot / 1_000_000,
+                self.since_boot % 1_000_000
+            ),
+        };
+        let facility = match FACILITIES.get((self.priority >> 3) as usize) {
+            Some(name) => name.to_string(),
+            None => format!("facility{}", self.priority >> 3),
+        };
+        let priority = PRIORITIES[(self.priority & 7) as usize];
+        format!("{time} {facility}.{priority}: {}", self.text)
+    }
+}
+
+/// The priority of a kernel log line as its level, spanning the facility and priority.
+pub(crate) fn level(line: &str) -> Option<LevelToken> {
+    let captures = FORMATTED.captures(line)?;
+    let token = captures.get(1)?;
+    let severity = PRIORITIES.iter().position(|name| *name == &captures[2])?;
+    Some(LevelToken {
+        level: Level::from_syslog(severity as u32),
+        start: token.start(),
+        end: token.end(),
+    })
+}
+
+/// When the machine booted, in microseconds since the Unix epoch, from its uptime. The kernel
+/// log's clock stops while suspended, so times after a suspend are off like `dmesg -T`'s.
+fn boot_time() -> Option<i64> {
+    let uptime = fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    Some(now.as_micros() as i64 - (seconds * 1_000_000.0) as i64)
+}
+
+/// Reads the kernel log from its start and follows it, from `/dev/kmsg`, or from
+/// `dmesg --follow` when that can't be opened, e.g. with `kernel.dmesg_restrict` set. The
+/// lines are read on a thread of their own, as neither source can be read without blocking.
+pub(crate) fn spawn() -> io::Result<mpsc::Receiver<Segment>> {
+    let (reader, child): (Box<dyn BufRead + Send>, _) = match fs::File::open("/dev/kmsg") {
+        // Records are read whole, so the buffer has to hold the longest one.
+        Ok(file) => (Box::new(BufReader::with_capacity(1 << 16, file)), None),
+        Err(_) => {
+            let mut child = Command::new("dmesg")
+                .args(["--follow", "--raw"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()?;
+            let stdout = child.stdout.take().expect("stdout is piped");
+            (Box::new(BufReader::new(stdout)), Some(child))
+        }
+    };
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let boot = boot_time();
+    thread::spawn(move || {
+        forward(reader, boot, &tx);
+        if let Some(mut child) = child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    });
+    Ok(rx)
+}
+
+fn forward(mut reader: Box<dyn BufRead + Send>, boot: Option<i64>, tx: &mpsc::Sender<Segment>) {
+    let mut bytes = Vec::new();
+    loop {
+        bytes.clear();
+        match reader.read_until(b'\n', &mut bytes) {
+            Ok(0) => return,
+            Ok(_) => {}
+            // Records were overwritten before they were read, the next read goes on after them.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => continue,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        }
+        let line = String::from_utf8_lossy(&bytes);
+        let Some(record) = Record::parse(line.trim_end_matches('\n')) else {
+            continue;
+        };
+        if tx
+            .blocking_send(Segment::Line(record.format(boot)))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Record, level};
+    use crate::level::Level;
+
+    #[test]
+    fn records_show_wall_clock_time_facility_and_priority() {
+        let record =
+            Record::parse("3,1234,5678901,-;usb 1-1: device not accepting address 2, error -71")
+                .expect("no record");
+        assert_eq!(
+            record,
+            Record {
+                priority: 3,
+                since_boot: 5_678_901,
+                text: "usb 1-1: device not accepting address 2, error -71".to_string(),
+            }
+        );
+        let boot = 1_792_152_000_000_000;
+        let line = record.format(Some(boot));
+        assert_eq!(
+            line,
+            concat!(
+                "2026-10-16T12:00:05.678901Z kern.err: ",
+                "usb 1-1: device not accepting address 2, error -71"
+            )
+        );
+        let token = level(&line).expect("no level");
+        assert_eq!(token.level, Level::Error);
+        assert_eq!(&line[token.start..token.end], "kern.err");
+
+        let record = Record::parse("<30>[   12.000042] systemd[1]: Started Journal Service.")
+            .expect("no record");
+        let line = record.format(None);
+        assert_eq!(
+            line,
+            "[   12.000042] daemon.info: systemd[1]: Started Journal Service."
+        );
+        assert_eq!(level(&line).map(|token| token.level), Some(Level::Info));
+        assert_eq!(Record::parse(" SUBSYSTEM=usb"), None);
+    }
+}
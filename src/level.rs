@@ -0,0 +1,103 @@
+use std::sync::LazyLock;
+
+use clap::ValueEnum;
+use regex::Regex;
+
+/// Severity of a log line, ordered from least to most severe.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Fatal => "fatal",
+        }
+    }
+
+    /// The level of a syslog severity, from 0 (emergency) to 7 (debug).
+    pub(crate) fn from_syslog(severity: u32) -> Self {
+        match severity {
+            0..=2 => Level::Fatal,
+            3 => Level::Error,
+            4 => Level::Warn,
+            5 | 6 => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+
+    /// The minimum level shown after `level`, cycling from showing everything through ever
+    /// fewer lines and back.
+    pub(crate) fn next_minimum(level: Option<Level>) -> Option<Level> {
+        match level {
+            None | Some(Level::Trace) => Some(Level::Debug),
+            Some(Level::Debug) => Some(Level::Info),
+            Some(Level::Info) => Some(Level::Warn),
+            Some(Level::Warn) => Some(Level::Error),
+            Some(Level::Error) => Some(Level::Fatal),
+            Some(Level::Fatal) => None,
+        }
+    }
+}
+
+/// Where a line states its level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LevelToken {
+    pub(crate) level: Level,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+static LEVEL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(trace|debug|info|warn(?:ing)?|error|fatal)\b")
+        .expect("level regex is valid")
+});
+
+/// Finds the first level token in a line, e.g. `INFO`, `[warn]` or `level=error`.
+pub(crate) fn detect(line: &str) -> Option<LevelToken> {
+    let found = LEVEL.find(line)?;
+    let level = match found.as_str().to_ascii_lowercase().as_str() {
+        "trace" => Level::Trace,
+        "debug" => Level::Debug,
+        "info" => Level::Info,
+        "warn" | "warning" => Level::Warn,
+        "error" => Level::Error,
+        _ => Level::Fatal,
+    };
+    Some(LevelToken {
+        level,
+        start: found.start(),
+        end: found.end(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Level, detect};
+
+    #[test]
+    fn detect_finds_the_first_level_token_in_any_case() {
+        let line = "2026-10-06 12:00:01 [Warning] retrying after error";
+        let token = detect(line).expect("level found");
+        assert_eq!(token.level, Level::Warn);
+        assert_eq!(&line[token.start..token.end], "Warning");
+
+        assert_eq!(
+            detect("level=FATAL msg=boom").map(|token| token.level),
+            Some(Level::Fatal)
+        );
+        assert!(detect("informational errors").is_none());
+        assert!(Level::Error > Level::Warn);
+    }
+}
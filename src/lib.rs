@@ -1,4 +1,5 @@
-use clap::{ArgAction, Parser};
+use clap::{ArgAction, ArgGroup, Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
 use crossterm::{
     event::{
         DisableMouseCapture, EnableMouseCapture
@@ -9,48 +10,931 @@ use crossterm::{
         disable_raw_mode, enable_raw_mode,
     },
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
+use encoding_rs::Encoding;
+use ratatui::{
+    Terminal, TerminalOptions, Viewport as TerminalViewport,
+    backend::CrosstermBackend,
+    style::{Color, Modifier, Style},
+};
 use regex::{Regex, RegexBuilder};
 use std::{
-    io::{self, Stdout},
-    time::Duration,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, IsTerminal, Seek, SeekFrom, Stdout, Write},
+    net::SocketAddr,
+    num::{NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
 };
 use thiserror::Error;
 use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    time::timeout,
+    sync::mpsc,
+    time::{Interval, MissedTickBehavior, interval},
 };
 
-use crate::{event::handle_event, ui::ui};
+use crate::{
+    action::{Action, History, apply},
+    audit::Audit,
+    buffer::LogBuffer,
+    bus::{AppEvent, Consumer, EventBus},
+    command::{CommandRunner, RunExit, RunSummary},
+    config::{Config, Profile},
+    enrich::{Enrich, Enricher, Resolved},
+    event::{TerminalEvents, handle_key},
+    filter::FilterExpr,
+    follow::{FollowReader, FollowedSegment, forward_followed, tail_offset},
+    idle::{Idle, IdleScreen},
+    json::JsonView,
+    keys::KeyBindings,
+    level::{Level, LevelToken},
+    messages::{Lang, Messages},
+    parser::{DateHour, Format, date_hour, epoch_date_hour},
+    preset::{Outcome, Outcomes, Tool},
+    reader::{CHANNEL_CAPACITY, ENCODING_LABELS, Segment, forward_segments, resolve_encoding},
+    redact::{Redaction, redact},
+    rewrite::Rewrite,
+    share::{Change, Share},
+    sound::{BELL_GAP, Chime, Sound, parse_alert},
+    source::{Source, SourceSpec, parse_source_spec},
+    status::{DEFAULT_STATUS, FAILURES_STATUS, RateMeter, StatusTemplate, parse_status_template},
+    tmux::TmuxArgs,
+    ui::{exit_lines, line_format, ui},
+    visible::VisibleLines,
+    watch::FileWatcher,
+};
 
+mod action;
+mod ansi;
+mod audit;
+mod bench;
+mod buffer;
+mod bus;
+mod clipboard;
+mod command;
+mod config;
+mod drain;
+mod enrich;
 mod event;
+mod export;
+mod filter;
+mod fluent;
+mod follow;
+#[cfg(fuzzing)]
+pub mod fuzz;
+mod gelf;
+mod grep;
+mod idle;
+mod json;
+mod keys;
+mod kmsg;
+mod level;
+mod logcat;
+mod logfmt;
+mod messages;
+mod parser;
+mod pattern_file;
+mod pattern_spec;
+mod pattern_test;
+mod preset;
+mod prompt;
+mod reader;
+mod redact;
+mod rewrite;
+mod share;
+mod sound;
+mod source;
+mod status;
+mod tmux;
 mod ui;
+mod viewer;
+mod visible;
+mod watch;
+pub use pattern_spec::{PatternMode, PatternScope};
+pub use viewer::{LogSender, LogView, LogViewState};
 
-const TICK_RATE: Duration = Duration::from_millis(20);
-const DRAIN_TIMEOUT: Duration = Duration::from_millis(0);
+/// Shortest time between two frames, so bursts of input are drawn once.
+const FRAME_INTERVAL: Duration = Duration::from_millis(20);
+/// Upper bound on input events ingested between two frames, so a flood of input can't keep
+/// the UI from redrawing and handling keys.
+const MAX_DRAIN_EVENTS: usize = 100_000;
+/// How often snoozed patterns are checked for waking up and their countdown redrawn.
+const SNOOZE_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the screen is checked for going idle, and the idle summary redrawn.
+const IDLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Terminal width below which the layout is compacted, about a quarter-screen tmux pane.
+const DEFAULT_NARROW_WIDTH: u16 = 80;
+/// Height of the view drawn inline with `--no-alt-screen`, in lines.
+const DEFAULT_INLINE_HEIGHT: u16 = 20;
+/// Buffer size past which the large-buffer prompt is shown, in lines and MiB.
+const DEFAULT_WARN_LINES: usize = 1_000_000;
+const DEFAULT_WARN_MEMORY: usize = 1024;
+/// Lines starting a new entry when grouping entries: those beginning with a timestamp,
+/// optionally in brackets.
+const DEFAULT_ENTRY_START: &str =
+    r"^\[?(?:\d{4}-\d{2}-\d{2}|\d{2}:\d{2}:\d{2}|[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2})";
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[non_exhaustive]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+#[command(group(ArgGroup::new("followed").multiple(true).conflicts_with("command")))]
 pub struct Args {
-    #[arg(short, long, num_args = 0.., value_delimiter = ',')]
+    #[command(subcommand)]
+    subcommand: Option<Commands>,
+
+    /// Highlight lines matching these patterns, after those of `LOGR_PATTERNS`
+    #[arg(short, long, num_args = 1, value_delimiter = ',', action = ArgAction::Append)]
+    patterns: Vec<String>,
+
+    /// Load patterns from a file, one per line: a regex, `REGEX:STYLE` or `NAME:STYLE:REGEX`,
+    /// with `#` comments. Reloaded with `R`
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    patterns_file: Option<PathBuf>,
+
+    /// Hide lines matching any of these patterns, like grep's invert match
+    #[arg(
+        short = 'x',
+        long,
+        visible_short_alias = 'v',
+        visible_alias = "invert-match",
+        num_args = 1,
+        value_delimiter = ',',
+        action = ArgAction::Append
+    )]
+    exclude: Vec<String>,
+
+    /// Sound an alert on lines matching the pattern, added if not given already: a number of
+    /// bells, or a system sound by name or file, e.g. `ERROR=2` or `deployed=complete`
+    #[arg(long, value_name = "PATTERN=SOUND", value_parser = parse_alert)]
+    alert: Vec<(String, Sound)>,
+
+    /// Rewrite lines as shown, not as kept, copied or exported, with a sed-like rule, e.g.
+    /// `s/(\d{13})/<ts:$1>/` showing epoch milliseconds as times (repeatable)
+    #[arg(long, value_name = "RULE", value_parser = Rewrite::parse)]
+    rewrite: Vec<Rewrite>,
+
+    /// Mask secrets in lines as shown, copied, exported and written to `--output`: `tokens`,
+    /// `emails` or `cards` for the built-in rules, or a regex whose matches, or capture groups
+    /// if it has any, are masked, e.g. `session=(\w+)` (repeatable)
+    #[arg(long, value_name = "RULE", value_parser = Redaction::parse)]
+    redact: Vec<Redaction>,
+
+    #[arg(short, long, action = ArgAction::SetTrue)]
+    ignore_case: bool,
+
+    /// Match the patterns given here as text rather than as regexes, like `1.2.3.4` or `[ERROR]`
+    #[arg(short = 'F', long)]
+    fixed_strings: bool,
+
+    /// Match the patterns given here as whole words only, so `err` doesn't match `transferred`
+    #[arg(short = 'w', long)]
+    word_regexp: bool,
+
+    /// Start with the patterns of this profile from the config file
+    #[arg(long, value_name = "NAME", add = ArgValueCandidates::new(profile_candidates))]
+    profile: Option<String>,
+
+    /// Config file with profiles and key bindings [default: $XDG_CONFIG_HOME/logr/config.toml]
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    config: Option<PathBuf>,
+
+    /// Highlight the output of a build tool or test runner, telling failures and passes apart
+    /// [default: guessed from the command]
+    #[arg(long, value_name = "TOOL")]
+    preset: Option<Tool>,
+
+    /// Same as `--preset cargo`
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "preset")]
+    cargo: bool,
+
+    /// Don't guess a preset from the command
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["preset", "cargo"])]
+    no_preset: bool,
+
+    /// Hide lines stating a lower level than this one
+    #[arg(long, value_enum)]
+    level: Option<Level>,
+
+    /// When to use colors: always, never, or on a terminal unless NO_COLOR is set
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Language of the text on screen [default: from LC_ALL, LC_MESSAGES or LANG]
+    #[arg(long, value_enum)]
+    lang: Option<Lang>,
+
+    /// Don't color the level (INFO, WARN, ERROR, ...) of the lines
+    #[arg(long, action = ArgAction::SetTrue)]
+    no_level_colors: bool,
+
+    /// Drop the previous run's output when re-running the command
+    #[arg(long, action = ArgAction::SetTrue)]
+    clear_on_rerun: bool,
+
+    /// Keep at most this many lines, discarding the oldest ones
+    #[arg(long, value_name = "N")]
+    max_lines: Option<NonZeroUsize>,
+
+    /// Offer ways to limit memory use once the buffer holds this many lines (0 never does)
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_WARN_LINES)]
+    warn_lines: usize,
+
+    /// Offer ways to limit memory use once the lines take about this many MiB (0 never does)
+    #[arg(long, value_name = "MIB", default_value_t = DEFAULT_WARN_MEMORY)]
+    warn_memory: usize,
+
+    /// Encoding of the input, e.g. latin1 or shift-jis (defaults to UTF-8)
+    #[arg(long, add = ArgValueCandidates::new(encoding_candidates))]
+    encoding: Option<String>,
+
+    /// With files, start from their last N lines instead of their beginning
+    #[arg(long, value_name = "N", requires = "followed")]
+    tail: Option<usize>,
+
+    /// Follow a file with options, e.g. `app.log:format=json` (formats: plain, json, access)
+    #[arg(
+        long = "source",
+        value_name = "PATH:format=FORMAT",
+        value_hint = ValueHint::FilePath,
+        value_parser = parse_source_spec,
+        group = "followed"
+    )]
+    sources: Vec<SourceSpec>,
+
+    /// Insert a separator line whenever the timestamps of the lines enter a new day or hour
+    #[arg(long, value_enum, default_value_t = Separators::Day)]
+    separators: Separators,
+
+    /// Show the number of each line in a gutter
+    #[arg(long, action = ArgAction::SetTrue)]
+    line_numbers: bool,
+
+    /// Show epoch seconds and milliseconds in lines as readable times in UTC
+    #[arg(long, action = ArgAction::SetTrue)]
+    human_epochs: bool,
+
+    /// Fold the lines continuing an entry, like stack traces, under the line starting it
+    #[arg(long, action = ArgAction::SetTrue)]
+    group_entries: bool,
+
+    /// Lines matching this start a new entry, the others continue the one before
+    #[arg(long, value_name = "REGEX", default_value = DEFAULT_ENTRY_START)]
+    entry_start: String,
+
+    /// Which edge of the view the newest lines are pinned to while following
+    #[arg(long, value_enum, default_value_t = FollowAt::Bottom)]
+    follow_at: FollowAt,
+
+    /// Status bar content, with fields {follow}, {source}, {lines}, {position}, {rate},
+    /// {matches} and {failures}
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        default_value = DEFAULT_STATUS,
+        value_parser = parse_status_template
+    )]
+    status: StatusTemplate,
+
+    /// Below this terminal width, hide the source labels and shorten the status bar and hints
+    #[arg(long, value_name = "COLUMNS", default_value_t = DEFAULT_NARROW_WIDTH)]
+    narrow_width: u16,
+
+    /// Draw the view inline below the prompt instead of taking over the whole terminal, keeping
+    /// the shell's scrollback around it
+    #[arg(long, action = ArgAction::SetTrue)]
+    no_alt_screen: bool,
+
+    /// Height of the inline view, in lines
+    #[arg(
+        long,
+        value_name = "LINES",
+        default_value_t = DEFAULT_INLINE_HEIGHT,
+        requires = "no_alt_screen"
+    )]
+    height: u16,
+
+    /// On quitting, print the lines on screen, or the last N lines, to the terminal so they
+    /// stay in its scrollback
+    #[arg(long, value_name = "N")]
+    print_on_exit: Option<Option<usize>>,
+
+    /// After this many minutes without new lines or keys pressed, dim the view or show how long
+    /// no line came, until either comes
+    #[arg(long, value_name = "MINUTES")]
+    idle: Option<NonZeroU64>,
+
+    /// What the screen shows once idle
+    #[arg(long, value_enum, default_value_t = IdleScreen::Summary, requires = "idle")]
+    idle_screen: IdleScreen,
+
+    /// Share the patterns added and removed with the other instances started with the same
+    /// group name, e.g. in adjacent tmux panes
+    #[arg(long, value_name = "GROUP")]
+    share: Option<String>,
+
+    /// Look up the reverse DNS names of the IP addresses in matching lines, shown in the detail
+    /// popup or inline after each address too
+    #[arg(long, value_enum, value_name = "WHERE")]
+    enrich: Option<Enrich>,
+
+    /// CSV file of address ranges and their countries (`first,last,country` rows, like DB-IP's
+    /// free country database) to show the countries of addresses too, in the detail popup
+    /// unless `--enrich inline` is given
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    geoip: Option<PathBuf>,
+
+    /// Append every line matching an enabled pattern to this file as it arrives
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+
+    /// Refuse to write lines out of the view, with `--output`, `:write`, `:csv` or copying, and
+    /// to share patterns with `--share`, for watching sensitive streams safely
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["output", "share"])]
+    read_only: bool,
+
+    /// Append every interactive action, like patterns added, filters toggled and exports
+    /// written, to this file with the time it was taken, for incident records
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    audit: Option<PathBuf>,
+
+    /// Re-run the command whenever files matching the glob change
+    #[arg(long, value_name = "GLOB", requires = "command")]
+    watch: Vec<String>,
+
+    /// Read the log of an Android device with `adb logcat`, of the only one connected or of
+    /// the one with this serial
+    #[arg(long, value_name = "SERIAL", conflicts_with_all = ["command", "followed"])]
+    adb: Option<Option<String>>,
+
+    /// With --adb, only read the lines of this tag (repeatable)
+    #[arg(long, value_name = "TAG", requires = "adb")]
+    tag: Vec<String>,
+
+    /// With --adb, only read the lines of this process
+    #[arg(long, value_name = "PID", requires = "adb")]
+    pid: Option<u32>,
+
+    /// Read the kernel log from /dev/kmsg, or from `dmesg --follow` if that can't be opened,
+    /// with times of day and priorities like `kern.err`
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["command", "followed", "adb"])]
+    kmsg: bool,
+
+    /// Listen on this address, e.g. 0.0.0.0:8514, for a Heroku or Cloud Foundry log drain.
+    /// Only plain HTTP is served: as drains post over HTTPS, a proxy or tunnel in front of it
+    /// must terminate TLS
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = ["command", "followed", "adb", "kmsg"]
+    )]
+    drain: Option<SocketAddr>,
+
+    /// Listen on this address, e.g. 0.0.0.0:12201, for GELF messages over UDP and TCP, shown
+    /// as JSON lines
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = ["command", "followed", "adb", "kmsg", "drain"]
+    )]
+    gelf: Option<SocketAddr>,
+
+    /// Listen on this address, e.g. 0.0.0.0:24224, for the Fluentd forward protocol, as sent by
+    /// the forward output of Fluentd or Fluent Bit, showing records as JSON lines
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = ["command", "followed", "adb", "kmsg", "drain", "gelf"]
+    )]
+    fluent: Option<SocketAddr>,
+
+    /// Log files to read and follow as they grow, like `tail -f`, instead of stdin
+    #[arg(value_hint = ValueHint::FilePath, group = "followed")]
+    files: Vec<PathBuf>,
+
+    /// Command to run and read output from instead of stdin
+    #[arg(last = true)]
+    command: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Print which lines of a file match the patterns, without starting the TUI
+    Test(PatternFileArgs),
+    /// Measure ingestion, matching and highlighting throughput on a file
+    Bench(PatternFileArgs),
+    /// Open a tmux window with a pane following each file, the panes sharing their patterns
+    Tmux(TmuxArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct PatternFileArgs {
+    /// Patterns to match, like `--patterns` of the viewer
+    #[arg(short, long, num_args = 1, value_delimiter = ',', action = ArgAction::Append)]
     patterns: Vec<String>,
 
     #[arg(short, long, action = ArgAction::SetTrue)]
     ignore_case: bool,
+
+    /// Log file to run the patterns against
+    #[arg(value_hint = ValueHint::FilePath)]
+    file: PathBuf,
+}
+
+fn encoding_candidates() -> Vec<CompletionCandidate> {
+    ENCODING_LABELS
+        .iter()
+        .map(|label| CompletionCandidate::new(*label))
+        .collect()
+}
+
+/// The profiles of the config file given with `--config` on the command line being completed,
+/// or of the default one.
+fn profile_candidates() -> Vec<CompletionCandidate> {
+    let args: Vec<String> = std::env::args().collect();
+    let path = args
+        .iter()
+        .enumerate()
+        .find_map(|(i, arg)| match arg.strip_prefix("--config") {
+            Some("") => args.get(i + 1).map(PathBuf::from),
+            Some(rest) => rest.strip_prefix('=').map(PathBuf::from),
+            None => None,
+        });
+    Config::load(path.as_deref())
+        .map(|config| profile_candidates_of(&config))
+        .unwrap_or_default()
+}
+
+/// A candidate for each profile, helped with its patterns.
+fn profile_candidates_of(config: &Config) -> Vec<CompletionCandidate> {
+    config
+        .profiles
+        .iter()
+        .map(|(name, profile)| {
+            let patterns: Vec<String> = profile.patterns.iter().map(ToString::to_string).collect();
+            CompletionCandidate::new(name).help(Some(patterns.join(", ").into()))
+        })
+        .collect()
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum LogrError {
     #[error(transparent)]
     IoError(#[from] io::Error),
     #[error(transparent)]
     RegexError(#[from] regex::Error),
+    #[error(transparent)]
+    WatchError(#[from] notify::Error),
+    #[error(transparent)]
+    GlobError(#[from] globset::Error),
+    #[error("unsupported encoding: {0}")]
+    EncodingError(String),
+    #[error("invalid config file {0}")]
+    ConfigError(String),
+    #[error("invalid patterns file {0}")]
+    PatternsFileError(String),
+    #[error("invalid pattern style {0}")]
+    StyleError(String),
+    #[error("no profile named {0} in the config file")]
+    ProfileError(String),
+    #[error("invalid filter expression: {0}")]
+    FilterError(String),
+}
+
+/// Which edge of the view the newest lines are pinned to while following.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum FollowAt {
+    /// History above, the newest line at the bottom.
+    #[default]
+    Bottom,
+    /// The newest line at the top, history growing downward.
+    Top,
+}
+
+impl FollowAt {
+    pub(crate) fn flipped(self) -> Self {
+        match self {
+            FollowAt::Bottom => FollowAt::Top,
+            FollowAt::Top => FollowAt::Bottom,
+        }
+    }
+}
+
+/// When to use colors, on screen and in what is printed to the terminal.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether colors are used. With `auto`, only when writing to a terminal and `NO_COLOR` is
+    /// not set to anything.
+    fn enabled(self, terminal: bool, no_color: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal && !no_color,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Whether `NO_COLOR` asks for no colors, see <https://no-color.org>.
+fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// The patterns of `LOGR_PATTERNS`, comma-separated like those of `--patterns`, followed by the
+/// given ones, for aliases and wrappers to add default patterns. Those given as well are left
+/// to the command line.
+fn with_env_patterns(env: Option<&str>, given: &[String]) -> Vec<String> {
+    env.into_iter()
+        .flat_map(|value| value.split(','))
+        .filter(|pattern| !pattern.is_empty() && !given.iter().any(|given| given == pattern))
+        .map(str::to_string)
+        .chain(given.iter().cloned())
+        .collect()
+}
+
+/// When to mark the timestamps of the lines crossing into a new period.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Separators {
+    Off,
+    Day,
+    Hour,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineKind {
+    Output,
+    RunSeparator,
+    RunSummary {
+        success: bool,
+    },
+    /// Notice inserted by logr itself, e.g. when a followed file was rotated.
+    Marker,
+    /// Inserted where the timestamps of the lines enter a new day or hour.
+    DateSeparator,
+}
+
+pub(crate) struct LogLine {
+    pub(crate) text: String,
+    pub(crate) kind: LineKind,
+    /// Set while the line is a progress update that later input may still overwrite.
+    pub(crate) live: bool,
+    /// Index into [`AppState::sources`] of the file the line was read from.
+    pub(crate) source: Option<usize>,
+    /// Byte offset of the line in the file it was read from.
+    pub(crate) offset: Option<u64>,
+    /// When logr received the line.
+    pub(crate) received: SystemTime,
+    /// Matches of the patterns in `text`, kept up to date as the patterns change. Not computed
+    /// for lines other than output, nor for lines that didn't go through [`push_line`].
+    pub(crate) matches: Option<Vec<PatternMatch>>,
+    /// The level the line states, detected like its matches.
+    pub(crate) level: Option<LevelToken>,
+    /// Position among the output lines received, from 1, unchanged by filtering and eviction.
+    pub(crate) number: Option<usize>,
+    /// Set on output lines continuing the entry of the line before them, e.g. the lines of a
+    /// stack trace, to how many lines back the line starting the entry is.
+    pub(crate) continues: Option<usize>,
+    /// On lines starting an entry, the number of lines continuing it.
+    pub(crate) continuations: usize,
+    /// Whether the lines continuing the entry are shown while grouping entries. Kept the same
+    /// on all lines of the entry.
+    pub(crate) expanded: bool,
+    /// Whether the line reports a passed or failed test, as told by the preset in use.
+    pub(crate) outcome: Option<Outcome>,
+    /// Times the line came again right after itself, collapsed into it while collapsing
+    /// repeated lines.
+    pub(crate) repeats: usize,
+    /// Identity of the line in the buffer, given when it is pushed and increasing in buffer
+    /// order, unlike its index, which eviction changes.
+    pub(crate) id: u64,
+}
+
+/// Where one of the patterns matches the text of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PatternMatch {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// Index into [`AppState::patterns`].
+    pub(crate) pattern: usize,
+    pub(crate) exclude: bool,
+}
+
+impl LogLine {
+    /// A line of `kind` received now, with nothing about it known yet.
+    fn new(text: String, kind: LineKind) -> Self {
+        Self {
+            text,
+            kind,
+            live: false,
+            source: None,
+            offset: None,
+            received: SystemTime::now(),
+            matches: None,
+            level: None,
+            number: None,
+            continues: None,
+            continuations: 0,
+            expanded: false,
+            outcome: None,
+            repeats: 0,
+            id: 0,
+        }
+    }
+
+    pub(crate) fn output(text: String) -> Self {
+        Self::new(text, LineKind::Output)
+    }
+
+    pub(crate) fn progress(text: String) -> Self {
+        Self {
+            live: true,
+            ..Self::new(text, LineKind::Output)
+        }
+    }
+
+    pub(crate) fn run_separator(text: String) -> Self {
+        Self::new(text, LineKind::RunSeparator)
+    }
+
+    pub(crate) fn marker(text: &str) -> Self {
+        Self::new(text.to_string(), LineKind::Marker)
+    }
+
+    pub(crate) fn date_separator(text: String) -> Self {
+        Self::new(text, LineKind::DateSeparator)
+    }
+
+    pub(crate) fn run_summary(summary: &RunSummary) -> Self {
+        let kind = LineKind::RunSummary {
+            success: summary.success,
+        };
+        Self::new(summary.text.clone(), kind)
+    }
+
+    pub(crate) fn with_source(mut self, source: Option<usize>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Makes the line start what is left of an entry after the line starting it was evicted.
+    pub(crate) fn promote(&mut self, evicted: &LogLine) {
+        self.continues = None;
+        self.continuations = evicted.continuations - 1;
+    }
+
+    /// Whether the line looks like raw binary data rather than text.
+    pub(crate) fn is_binary(&self) -> bool {
+        self.text.chars().any(|c| {
+            c == char::REPLACEMENT_CHARACTER || (c.is_control() && c != '\t' && c != '\x1b')
+        })
+    }
+}
+
+pub(crate) enum InputEvent {
+    Segment(Segment),
+    SourceSegment(FollowedSegment),
+    RunExited(RunExit),
+}
+
+enum Input {
+    /// Segments of stdin, of the kernel log, or of a listener for a log drain, GELF or Fluentd.
+    Stream(mpsc::Receiver<Segment>),
+    /// Segments of all followed files, tagged with the index of the file.
+    Files(mpsc::Receiver<FollowedSegment>),
+    Command(Box<CommandRunner>),
+}
+
+impl Input {
+    fn stdin(encoding: &'static Encoding) -> Self {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        tokio::spawn(forward_segments(tokio::io::stdin(), encoding, tx));
+        Input::Stream(rx)
+    }
+
+    /// Follows `paths` across rotations, starting from their last `tail` lines if given.
+    fn files(
+        paths: &[PathBuf],
+        tail: Option<usize>,
+        encoding: &'static Encoding,
+    ) -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        for (source, path) in paths.iter().enumerate() {
+            let mut file = fs::File::open(path)?;
+            if let Some(tail) = tail {
+                let offset = tail_offset(&mut file, tail)?;
+                file.seek(SeekFrom::Start(offset))?;
+            }
+            let position = file.stream_position()?;
+            let reader = FollowReader::new(path.clone(), file, position)?;
+            tokio::spawn(forward_followed(reader, source, encoding, tx.clone()));
+        }
+        Ok(Input::Files(rx))
+    }
+
+    /// Waits for the next input event. Never resolves once stdin is exhausted.
+    async fn next_event(&mut self) -> Option<InputEvent> {
+        match self {
+            Input::Stream(segments) => match segments.recv().await {
+                Some(segment) => Some(InputEvent::Segment(segment)),
+                None => std::future::pending().await,
+            },
+            Input::Files(segments) => match segments.recv().await {
+                Some(followed) => Some(InputEvent::SourceSegment(followed)),
+                None => std::future::pending().await,
+            },
+            Input::Command(runner) => runner.next_event().await,
+        }
+    }
+
+    /// Returns the next already received input event without waiting.
+    fn try_next_event(&mut self) -> Option<InputEvent> {
+        match self {
+            Input::Stream(segments) => segments.try_recv().ok().map(InputEvent::Segment),
+            Input::Files(segments) => segments.try_recv().ok().map(InputEvent::SourceSegment),
+            Input::Command(runner) => runner.try_next_event(),
+        }
+    }
 }
 
+/// A pattern highlighting or hiding the lines it matches, with how its matches are styled. Built
+/// with its methods or read through serde, from a table like those of the config file.
 pub struct PatternSpec {
     pattern: String,
     case_sensitive: bool,
+    /// Lines matching the pattern are hidden instead of highlighted.
+    exclude: bool,
+    /// Disabled patterns are kept in the list (and keep their color) but match nothing.
+    enabled: bool,
+    /// Snoozed patterns match nothing either, until they wake up on their own.
+    snooze: Option<Snooze>,
+    /// Sounded when a line matches, unless a pattern hides it.
+    sound: Option<Sound>,
+    /// The color of the matches, or `None` for the palette's color of the pattern's place.
+    color: Option<Color>,
+    /// The background and modifiers of the matches, over their color.
+    style: Style,
+    /// A match colors the whole line rather than only the matched text.
+    whole_line: bool,
+    /// The pattern is matched as text rather than as a regex, its regex built from it escaped.
+    literal: bool,
+    /// The pattern only matches whole words, its regex put between word boundaries.
+    word: bool,
+    /// Shown, and referred to in filter expressions, besides the text. Given in a patterns file.
+    name: Option<String>,
+    /// Loaded from `--patterns-file`, and replaced when it is reloaded.
+    from_file: bool,
     regex: Regex,
+    /// The regex has capture groups, and only what they capture is highlighted, like the name
+    /// in `user=(\w+)`.
+    groups: bool,
+    /// How long matching lines against the regex took so far.
+    cost: MatchCost,
+}
+
+impl PatternSpec {
+    /// Whether the pattern matches lines, being neither disabled nor snoozed.
+    fn active(&self) -> bool {
+        self.enabled && self.snooze.is_none()
+    }
+
+    /// The non-empty spans of a text to highlight: the matches or, with capture groups, what
+    /// the groups capture, or the whole match when none of them captures anything.
+    fn highlights(&self, text: &str) -> Vec<(usize, usize)> {
+        if !self.groups {
+            return self
+                .regex
+                .find_iter(text)
+                .filter(|found| !found.is_empty())
+                .map(|found| (found.start(), found.end()))
+                .collect();
+        }
+        let mut spans = Vec::new();
+        for captures in self.regex.captures_iter(text) {
+            let groups = captures.iter().skip(1).flatten();
+            let before = spans.len();
+            spans.extend(
+                groups
+                    .filter(|group| !group.is_empty())
+                    .map(|group| (group.start(), group.end())),
+            );
+            if spans.len() == before
+                && let Some(found) = captures.get(0).filter(|found| !found.is_empty())
+            {
+                spans.push((found.start(), found.end()));
+            }
+        }
+        spans
+    }
+}
+
+/// Average time a line taking a pattern longer than this to match flags it as slow in the
+/// patterns dialog.
+const SLOW_PATTERN: Duration = Duration::from_micros(50);
+
+/// A pattern taking longer than this to match a single line gets disabled, so it can't keep
+/// stalling the session.
+const PATTERN_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How large a pattern's compiled regex may get, well below the regex crate's default so a
+/// pattern like `\w{1000}` is refused when added rather than slowing down every line.
+const PATTERN_SIZE_LIMIT: usize = 1 << 20;
+
+/// The time a pattern spent matching lines, recorded through a shared reference as lines are
+/// matched against all the patterns at once.
+#[derive(Default)]
+struct MatchCost {
+    total: Cell<Duration>,
+    lines: Cell<u32>,
+    /// The longest a line took since the pattern was last checked for running away.
+    slowest: Cell<Duration>,
+}
+
+impl MatchCost {
+    fn record(&self, elapsed: Duration) {
+        self.total.set(self.total.get().saturating_add(elapsed));
+        self.lines.set(self.lines.get().saturating_add(1));
+        self.slowest.set(self.slowest.get().max(elapsed));
+    }
+
+    /// How long the slowest line took, if it took longer than [`PATTERN_TIMEOUT`]. Starts over,
+    /// so a pattern enabled again gets another chance.
+    fn take_runaway(&self) -> Option<Duration> {
+        Some(self.slowest.take()).filter(|&slowest| slowest > PATTERN_TIMEOUT)
+    }
+
+    /// The average time a line took, once it is slow enough to flag.
+    fn slow(&self) -> Option<Duration> {
+        let lines = self.lines.get();
+        (lines > 0)
+            .then(|| self.total.get() / lines)
+            .filter(|&per_line| per_line > SLOW_PATTERN)
+    }
+}
+
+/// How long a snoozed pattern sleeps for, in minutes, cycled through in the patterns dialog.
+const SNOOZE_MINUTES: [u64; 3] = [5, 15, 60];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Snooze {
+    pub(crate) minutes: u64,
+    pub(crate) until: Instant,
+}
+
+impl Snooze {
+    /// The snooze after `snooze`, each one longer than the last until the pattern is woken up.
+    pub(crate) fn next(snooze: Option<Snooze>, now: Instant) -> Option<Snooze> {
+        let minutes = match snooze {
+            None => SNOOZE_MINUTES[0],
+            Some(snooze) => *SNOOZE_MINUTES
+                .iter()
+                .find(|&&minutes| minutes > snooze.minutes)?,
+        };
+        Some(Snooze {
+            minutes,
+            until: now + Duration::from_secs(minutes * 60),
+        })
+    }
+
+    /// Time left, for counting down.
+    pub(crate) fn remaining(&self, now: Instant) -> Duration {
+        self.until.saturating_duration_since(now)
+    }
+}
+
+/// A part of the screen with its own settings, like whether long lines wrap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Viewport {
+    Main,
+    Detail,
+    Help,
+}
+
+/// The view frozen while lines keep coming in behind it.
+pub(crate) struct Pause {
+    /// Index into the buffer one past the last line shown, kept on the same line as old lines
+    /// are evicted.
+    end: usize,
+    /// Lines received since pausing.
+    received: usize,
+}
+
+/// Lines selected to be copied, by their numbers: from the line the selection started on to
+/// the one the cursor is on, in either order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Selection {
+    anchor: usize,
+    cursor: usize,
+}
+
+impl Selection {
+    fn contains(self, number: usize) -> bool {
+        (self.anchor.min(self.cursor)..=self.anchor.max(self.cursor)).contains(&number)
+    }
 }
 
 struct AppState {
@@ -58,12 +942,139 @@ struct AppState {
     selected: usize,
     dialog_open: bool,
     input: String,
+    /// Bytes of the input after the text cursor, counted from the end so that text put in the
+    /// input line leaves the cursor after it.
+    after_cursor: usize,
+    /// The pattern whose text is in the input line, replaced in place when it is entered.
+    editing: Option<usize>,
     pattern_error: Option<String>,
     ignore_case: bool,
+    /// Index among the visible lines of the line the view starts at, derived from `anchor`
+    /// before each frame and key.
     scroll: usize,
+    /// Id of the line the view starts at while not following, keeping the view on it as lines
+    /// are evicted before it or come into and out of view.
+    anchor: Option<u64>,
+    /// The lines shown, kept as lines come and go to count them and place `anchor` among them.
+    visible: RefCell<VisibleLines>,
     follow: bool,
+    pause: Option<Pause>,
     filter_only: bool,
+    /// The expression filtering shows the lines it holds for, instead of those matching any
+    /// pattern.
+    filter_expr: Option<FilterExpr>,
     wrap: bool,
+    /// Rows of the line the view starts at scrolled past, when scrolling wrapped lines by rows.
+    row_offset: usize,
+    /// Width the lines of the main view were last wrapped to, which scrolling them by rows counts
+    /// their rows at.
+    wrap_width: Cell<u16>,
+    line_numbers: bool,
+    /// Epoch times in lines are shown as readable ones.
+    human_epochs: bool,
+    /// Output lines numbered so far.
+    numbered: usize,
+    run_summary: Option<RunSummary>,
+    /// Id of the line the detail popup shows, which stays on it as lines are evicted before it.
+    detail_line: Option<u64>,
+    detail_hex: bool,
+    detail_wrap: bool,
+    help_wrap: bool,
+    /// Off with `--color never` or `NO_COLOR`, leaving only bold, reversed and the like.
+    colors: bool,
+    level_colors: bool,
+    /// Lines stating a lower level are hidden. Lines without a level are always shown, as they
+    /// are often continuations of the line before.
+    min_level: Option<Level>,
+    follow_at: FollowAt,
+    /// Lines continuing an entry are hidden unless it is expanded.
+    group_entries: bool,
+    entry_start: Regex,
+    json_view: JsonView,
+    history: History,
+    sources: Vec<Source>,
+    sources_open: bool,
+    source_selected: usize,
+    /// Keys of logfmt fields left out when showing logfmt lines.
+    hidden_fields: BTreeSet<String>,
+    fields_open: bool,
+    /// The keys listed in the fields dialog, collected when it was opened.
+    field_keys: Vec<String>,
+    field_selected: usize,
+    search_open: bool,
+    search_input: String,
+    search_error: Option<String>,
+    search: Option<Regex>,
+    /// Id of the line holding the current search match.
+    search_match: Option<u64>,
+    prompt_open: bool,
+    prompt_input: String,
+    prompt_error: Option<String>,
+    /// Result of the last prompt command, shown until the next key press.
+    status: Option<String>,
+    separators: Separators,
+    /// Date and hour of the latest line with a timestamp.
+    last_date_hour: Option<DateHour>,
+    /// The sources (`None` for lines without one) some line of which carried a timestamp. Lines
+    /// of the others are placed in time by when they were received instead.
+    timestamped: BTreeSet<Option<usize>>,
+    status_template: StatusTemplate,
+    narrow_width: u16,
+    rate: RateMeter,
+    warn_lines: usize,
+    warn_bytes: usize,
+    /// Set once the large-buffer prompt was shown, so it is shown at most once.
+    large_buffer_warned: bool,
+    large_buffer_open: bool,
+    /// Set from the large-buffer prompt, collapsing an output line repeating the one before it
+    /// into that one.
+    dedup: bool,
+    /// Set from the large-buffer prompt, where the lines evicted from the buffer are written
+    /// rather than dropped.
+    spill: Option<Box<dyn Write>>,
+    help_open: bool,
+    selection: Option<Selection>,
+    /// The text of the UI, in the chosen language.
+    messages: &'static Messages,
+    /// Where lines matching a pattern are copied to as they arrive, with `--output`.
+    output: Option<Box<dyn Write>>,
+    /// The alert sounding for a line matching a pattern with a sound.
+    chime: Chime,
+    /// Set with `--idle`, blanking the screen after a while without activity.
+    idle: Option<Idle>,
+    /// Rules rewriting lines as shown, from `--rewrite` and the profile picked with `--profile`.
+    rewrites: Vec<Rewrite>,
+    /// Set with `--read-only`, refusing to write the lines anywhere.
+    read_only: bool,
+    /// Set with `--audit`, recording the interactive actions.
+    audit: Option<Audit>,
+    /// Rules masking secrets in lines, from `--redact` and the config file.
+    redactions: Vec<Redaction>,
+    /// Whether the secrets are shown on screen after all, confirmed in `reveal_open`. Copies,
+    /// exports and `--output` stay masked.
+    revealed: bool,
+    reveal_open: bool,
+    /// Set with `--share`, telling the other instances of the group about pattern changes.
+    share: Option<Share>,
+    /// Set with `--enrich` or `--geoip`, knowing the names and countries of addresses in lines.
+    enricher: Option<Enricher>,
+    /// Reads the level of a line of a log stating priorities of its own: logcat's with `--adb`,
+    /// the kernel's with `--kmsg`.
+    priorities: Option<fn(&str) -> Option<LevelToken>>,
+    /// Tells the lines reporting passed and failed tests, with a preset like `--cargo`.
+    outcomes: Option<Outcomes>,
+    /// The pattern profiles of the config file, by name.
+    profiles: BTreeMap<String, Profile>,
+    /// Given with `--patterns-file`, to reload its patterns from.
+    patterns_file: Option<PathBuf>,
+    profiles_open: bool,
+    profile_selected: usize,
+    failures_open: bool,
+    /// The failures listed in the failures panel by line number, collected when it was opened.
+    failures: Vec<(usize, String)>,
+    failure_selected: usize,
+    /// The keys of the main view bound in the config file.
+    keys: KeyBindings,
 }
 
 impl AppState {
@@ -74,122 +1085,1743 @@ impl AppState {
             selected: 0,
             dialog_open: false,
             input: String::new(),
+            after_cursor: 0,
+            editing: None,
             pattern_error: None,
             ignore_case,
             scroll: 0,
+            anchor: None,
+            visible: RefCell::default(),
             follow: true,
+            pause: None,
             filter_only: false,
+            filter_expr: None,
             wrap: false,
+            row_offset: 0,
+            wrap_width: Cell::new(0),
+            line_numbers: false,
+            human_epochs: false,
+            numbered: 0,
+            run_summary: None,
+            detail_line: None,
+            detail_hex: false,
+            detail_wrap: true,
+            help_wrap: true,
+            colors: true,
+            level_colors: true,
+            min_level: None,
+            follow_at: FollowAt::default(),
+            group_entries: false,
+            entry_start: Regex::new(DEFAULT_ENTRY_START).expect("entry start regex is valid"),
+            json_view: JsonView::default(),
+            history: History::default(),
+            sources: Vec::new(),
+            sources_open: false,
+            source_selected: 0,
+            hidden_fields: BTreeSet::new(),
+            fields_open: false,
+            field_keys: Vec::new(),
+            field_selected: 0,
+            search_open: false,
+            search_input: String::new(),
+            search_error: None,
+            search: None,
+            search_match: None,
+            prompt_open: false,
+            prompt_input: String::new(),
+            prompt_error: None,
+            status: None,
+            separators: Separators::Off,
+            last_date_hour: None,
+            timestamped: BTreeSet::new(),
+            status_template: StatusTemplate::default(),
+            narrow_width: DEFAULT_NARROW_WIDTH,
+            warn_lines: DEFAULT_WARN_LINES,
+            warn_bytes: DEFAULT_WARN_MEMORY << 20,
+            large_buffer_warned: false,
+            large_buffer_open: false,
+            dedup: false,
+            spill: None,
+            help_open: false,
+            selection: None,
+            messages: Lang::En.messages(),
+            output: None,
+            chime: Chime::default(),
+            idle: None,
+            rewrites: Vec::new(),
+            read_only: false,
+            audit: None,
+            redactions: Vec::new(),
+            revealed: false,
+            reveal_open: false,
+            share: None,
+            enricher: None,
+            priorities: None,
+            outcomes: None,
+            profiles: BTreeMap::new(),
+            patterns_file: None,
+            profiles_open: false,
+            profile_selected: 0,
+            failures_open: false,
+            failures: Vec::new(),
+            failure_selected: 0,
+            keys: KeyBindings::default(),
+            rate: RateMeter::new(Instant::now()),
+        }
+    }
+
+    pub(crate) fn wrap_mut(&mut self, viewport: Viewport) -> &mut bool {
+        match viewport {
+            Viewport::Main => &mut self.wrap,
+            Viewport::Detail => &mut self.detail_wrap,
+            Viewport::Help => &mut self.help_wrap,
         }
     }
 }
 
-pub async fn run(args: Args) -> Result<(), LogrError> {
-    let mut patterns = Vec::new();
-    for pattern in &args.patterns {
-        patterns.push(build_pattern(pattern.clone(), !args.ignore_case)?);
+pub async fn run(mut args: Args) -> Result<(), LogrError> {
+    match &args.subcommand {
+        Some(Commands::Test(file_args)) => return pattern_test::run(file_args),
+        Some(Commands::Bench(file_args)) => return bench::run(file_args).await,
+        Some(Commands::Tmux(tmux_args)) => return tmux::run(tmux_args),
+        None => {}
+    }
+
+    let env_patterns = std::env::var("LOGR_PATTERNS").ok();
+    args.patterns = with_env_patterns(env_patterns.as_deref(), &args.patterns);
+    if let Some(serial) = &args.adb {
+        args.command = logcat::command(serial.as_deref(), &args.tag, args.pid);
+    }
+    let config = Config::load(args.config.as_deref())?;
+    let mut patterns = match &args.profile {
+        Some(name) => config.profile(name)?.build(args.ignore_case)?,
+        None => Vec::new(),
+    };
+    if let Some(path) = &args.patterns_file {
+        patterns.extend(pattern_file::load(path, args.ignore_case)?);
+    }
+    let given = patterns.len();
+    patterns.extend(build_filters(
+        &args.patterns,
+        &args.exclude,
+        args.ignore_case,
+        args.fixed_strings,
+    )?);
+    add_alerts(
+        &mut patterns,
+        &args.alert,
+        args.ignore_case,
+        args.fixed_strings,
+    )?;
+    if args.word_regexp {
+        for pattern in &mut patterns[given..] {
+            set_word(pattern, true)?;
+        }
+    }
+    let tool = args
+        .preset
+        .or(args.cargo.then_some(Tool::Cargo))
+        .or_else(|| Tool::detect(&args.command).filter(|_| !args.no_preset));
+    if let Some(tool) = tool {
+        patterns.extend(build_patterns(&tool.preset().patterns(), false)?);
     }
     let mut app = AppState::new(patterns, args.ignore_case);
+    if let Some(name) = &args.profile {
+        app.rewrites = config.profile(name)?.rewrites.clone();
+    }
+    app.rewrites.extend(args.rewrite.iter().cloned());
+    app.read_only = args.read_only;
+    app.redactions = config.redactions;
+    app.redactions.extend(args.redact.iter().cloned());
+    app.profiles = config.profiles;
+    app.patterns_file = args.patterns_file.clone();
+    app.keys = config.bindings;
+    app.separators = args.separators;
+    app.follow_at = args.follow_at;
+    app.colors = args
+        .color
+        .enabled(io::stdout().is_terminal(), no_color_env());
+    app.messages = args.lang.unwrap_or_else(Lang::from_env).messages();
+    app.level_colors = !args.no_level_colors;
+    app.min_level = args.level;
+    app.priorities = if args.adb.is_some() {
+        Some(logcat::level)
+    } else if args.kmsg {
+        Some(kmsg::level)
+    } else {
+        None
+    };
+    app.line_numbers = args.line_numbers;
+    app.human_epochs = args.human_epochs;
+    app.group_entries = args.group_entries;
+    app.entry_start = Regex::new(&args.entry_start)?;
+    app.status_template = args.status.clone();
+    if let Some(tool) = tool {
+        app.outcomes = Some(tool.preset().outcomes());
+        if args.status == StatusTemplate::default() {
+            app.status_template =
+                parse_status_template(FAILURES_STATUS).expect("failures status template is valid");
+        }
+    }
+    app.narrow_width = args.narrow_width;
+    app.idle = args.idle.map(|minutes| {
+        let after = Duration::from_secs(minutes.get().saturating_mul(60));
+        Idle::new(after, args.idle_screen, Instant::now())
+    });
+    app.warn_lines = args.warn_lines;
+    app.warn_bytes = args.warn_memory.saturating_mul(1 << 20);
+    if let Some(path) = &args.output {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        app.output = Some(Box::new(io::LineWriter::new(file)));
+    }
+    if let Some(path) = &args.audit {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        app.audit = Some(Audit::new(Box::new(file)));
+    }
+    let encoding = resolve_encoding(args.encoding.as_deref())
+        .ok_or_else(|| LogrError::EncodingError(args.encoding.clone().unwrap_or_default()))?;
+    if !io::stdout().is_terminal() {
+        return grep::run(&args, &mut app, encoding).await;
+    }
 
-    let mut terminal = term_init()?;
-    let stdin = BufReader::new(tokio::io::stdin());
-    let mut lines_stream = stdin.lines();
-    let mut lines = Vec::new();
+    let mut lines = LogBuffer::new(args.max_lines);
+    let input = if !args.command.is_empty() {
+        let runner = CommandRunner::spawn(args.command.clone(), encoding)?;
+        push_line(&mut app, &mut lines, runner.separator());
+        Input::Command(Box::new(runner))
+    } else if !args.files.is_empty() || !args.sources.is_empty() {
+        let specs: Vec<SourceSpec> = args
+            .files
+            .iter()
+            .map(|path| SourceSpec {
+                path: path.clone(),
+                format: Format::Plain,
+            })
+            .chain(args.sources.iter().cloned())
+            .collect();
+        app.sources = specs
+            .iter()
+            .map(|spec| Source::new(spec.path.display().to_string(), spec.format))
+            .collect();
+        let paths: Vec<PathBuf> = specs.into_iter().map(|spec| spec.path).collect();
+        Input::files(&paths, args.tail, encoding)?
+    } else if args.kmsg {
+        Input::Stream(kmsg::spawn()?)
+    } else if let Some(addr) = args.drain {
+        Input::Stream(drain::listen(addr).await?)
+    } else if let Some(addr) = args.gelf {
+        Input::Stream(gelf::listen(addr).await?)
+    } else if let Some(addr) = args.fluent {
+        Input::Stream(fluent::listen(addr).await?)
+    } else {
+        Input::stdin(encoding)
+    };
+    let mut reader = Reader {
+        input,
+        watcher: if args.watch.is_empty() {
+            None
+        } else {
+            Some(FileWatcher::new(&args.watch)?)
+        },
+        clear_on_rerun: args.clear_on_rerun,
+    };
 
-    loop {
-        let total_lines = filtered_line_count(&lines, &app.patterns, app.filter_only);
-        let view_height = terminal.size()?.height.saturating_sub(2) as usize;
-        let event_result = handle_event(&mut app, total_lines, view_height)?;
-        if event_result.exit {
-            break;
-        }
+    let mut sharing = Sharing { changes: None };
+    if let Some(group) = &args.share {
+        let (share, changes) = Share::join(group)?;
+        app.share = Some(share);
+        sharing.changes = Some(changes);
+    }
 
-        let mut should_draw = event_result.redraw || app.dialog_open;
-        if let Ok(Ok(Some(line))) = timeout(TICK_RATE, lines_stream.next_line()).await {
-            lines.push(line);
-            while let Ok(Ok(Some(line))) = timeout(DRAIN_TIMEOUT, lines_stream.next_line()).await {
-                lines.push(line);
+    let mut enrichment = Enrichment { lookups: None };
+    let enrich = args.enrich.or(args.geoip.as_ref().map(|_| Enrich::Detail));
+    if let Some(mode) = enrich {
+        let (enricher, lookups) = Enricher::new(mode, args.geoip.as_deref())?;
+        app.enricher = Some(enricher);
+        enrichment.lookups = Some(lookups);
+    }
+
+    let inline = args.no_alt_screen.then_some(args.height);
+    let mut screen = Screen::new(term_init(inline)?, inline);
+    let command_line: Vec<String> = std::env::args().collect();
+    audit::record(&mut app, &format!("Started {}", command_line.join(" ")));
+    let mut terminal_events = TerminalEvents::new();
+    let mut bus = EventBus::default();
+    let mut timers = Timers::new();
+
+    loop {
+        // Sleeps until something happens, only waking up for frames while the screen is out of
+        // date.
+        tokio::select! {
+            () = terminal_events.publish_next(&mut bus) => {}
+            Some(event) = reader.input.next_event() => {
+                bus.publish(AppEvent::Input(event));
+                let pending = std::iter::from_fn(|| reader.input.try_next_event());
+                for event in pending.take(MAX_DRAIN_EVENTS) {
+                    bus.publish(AppEvent::Input(event));
+                }
+            }
+            () = files_changed(&mut reader.watcher) => bus.publish(AppEvent::FilesChanged),
+            Some(change) = shared_change(&mut sharing.changes) => {
+                bus.publish(AppEvent::Shared(change));
+            }
+            Some(lookup) = resolved_address(&mut enrichment.lookups) => {
+                bus.publish(AppEvent::Resolved(lookup));
+            }
+            _ = screen.frames.tick(), if screen.dirty => bus.publish(AppEvent::Tick),
+            _ = timers.snoozes.tick(), if app.patterns.iter().any(|pattern| pattern.snooze.is_some()) => {
+                bus.publish(AppEvent::SnoozeTick);
+            }
+            _ = timers.bells.tick(), if app.chime.pending() => bus.publish(AppEvent::BellTick),
+            _ = timers.idle_checks.tick(), if app.idle.is_some() => {
+                bus.publish(AppEvent::IdleTick);
             }
-            should_draw = true;
         }
 
-        if should_draw {
-            terminal.draw(|f| ui(f, &lines, &app))?;
+        let consumers: &mut [&mut dyn Consumer] = &mut [
+            &mut reader,
+            &mut sharing,
+            &mut enrichment,
+            &mut timers,
+            &mut screen,
+        ];
+        if bus.dispatch(consumers, &mut app, &mut lines)?.is_break() {
+            break;
         }
     }
 
-    term_cleanup(terminal)?;
+    audit::record(&mut app, "Quit");
+    // What is left in the scrollback outlives the session, so it is masked either way.
+    app.revealed = false;
+    settle_scroll(&mut app, &lines);
+    let kept = match args.print_on_exit {
+        Some(count) => {
+            let view_height = screen_height(&screen.terminal, inline)?.saturating_sub(2) as usize;
+            exit_lines(&lines, &app, view_height, count)
+        }
+        None => Vec::new(),
+    };
+    term_cleanup(screen.terminal, inline.is_some())?;
+    let mut stdout = io::stdout().lock();
+    for line in &kept {
+        if app.colors {
+            writeln!(stdout, "{}", ansi::line_to_ansi(line))?;
+        } else {
+            writeln!(stdout, "{line}")?;
+        }
+    }
 
     Ok(())
 }
 
-type LogrTerminal = Terminal<CrosstermBackend<Stdout>>;
+/// The input source, consuming what it reads and the re-runs of the command asked for.
+struct Reader {
+    input: Input,
+    watcher: Option<FileWatcher>,
+    clear_on_rerun: bool,
+}
 
-fn term_init() -> Result<LogrTerminal, io::Error> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(
-        stdout,
-        EnterAlternateScreen,
-        TermClear(ClearType::All),
-        EnableMouseCapture
-    )?;
-    let backend = CrosstermBackend::new(stdout);
-    Terminal::new(backend)
+impl Consumer for Reader {
+    fn consume(
+        &mut self,
+        event: AppEvent,
+        app: &mut AppState,
+        lines: &mut LogBuffer,
+        bus: &mut EventBus,
+    ) -> Result<Option<AppEvent>, LogrError> {
+        match event {
+            AppEvent::Input(event) => {
+                if self.watcher.is_some() {
+                    alert(&event)?;
+                }
+                if let Some(idle) = &mut app.idle {
+                    idle.line(Instant::now());
+                }
+                ingest(app, lines, event);
+            }
+            AppEvent::RerunRequested | AppEvent::FilesChanged => {
+                let Input::Command(runner) = &mut self.input else {
+                    return Ok(None);
+                };
+                rerun(app, lines, runner, self.clear_on_rerun)?;
+            }
+            event => return Ok(Some(event)),
+        }
+        bus.publish(AppEvent::Changed);
+        Ok(None)
+    }
 }
 
-fn term_cleanup(mut terminal: LogrTerminal) -> Result<(), io::Error> {
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()
+/// The membership in a `--share` group, consuming the changes of the other members.
+struct Sharing {
+    changes: Option<mpsc::Receiver<Change>>,
 }
 
-fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
-    RegexBuilder::new(pattern)
-        .case_insensitive(!case_sensitive)
-        .build()
+impl Consumer for Sharing {
+    fn consume(
+        &mut self,
+        event: AppEvent,
+        app: &mut AppState,
+        lines: &mut LogBuffer,
+        bus: &mut EventBus,
+    ) -> Result<Option<AppEvent>, LogrError> {
+        let AppEvent::Shared(change) = event else {
+            return Ok(Some(event));
+        };
+        take_shared(app, lines, change);
+        bus.publish(AppEvent::Changed);
+        Ok(None)
+    }
 }
 
-fn build_pattern(pattern: String, case_sensitive: bool) -> Result<PatternSpec, LogrError> {
-    let regex = build_regex(&pattern, case_sensitive)?;
-    Ok(PatternSpec {
-        pattern,
-        case_sensitive,
-        regex,
-    })
+/// The address lookups of `--enrich`, consuming their results.
+struct Enrichment {
+    lookups: Option<mpsc::Receiver<Resolved>>,
 }
 
-fn max_start(total_lines: usize, view_height: usize) -> usize {
-    if view_height == 0 {
-        0
-    } else {
-        total_lines.saturating_sub(view_height)
+impl Consumer for Enrichment {
+    fn consume(
+        &mut self,
+        event: AppEvent,
+        app: &mut AppState,
+        _lines: &mut LogBuffer,
+        bus: &mut EventBus,
+    ) -> Result<Option<AppEvent>, LogrError> {
+        let AppEvent::Resolved(lookup) = event else {
+            return Ok(Some(event));
+        };
+        if let Some(enricher) = &mut app.enricher {
+            enricher.resolved(lookup);
+        }
+        bus.publish(AppEvent::Changed);
+        Ok(None)
     }
 }
 
-pub(crate) fn line_matches_patterns(line: &str, patterns: &[PatternSpec]) -> bool {
-    patterns.iter().any(|pattern| pattern.regex.is_match(line))
+/// The timers waking snoozed patterns, blanking an idle screen and sounding alerts.
+struct Timers {
+    snoozes: Interval,
+    bells: Interval,
+    idle_checks: Interval,
 }
 
-fn filtered_line_count(lines: &[String], patterns: &[PatternSpec], filter_only: bool) -> usize {
-    if !filter_only {
-        return lines.len();
+impl Timers {
+    fn new() -> Self {
+        let mut bells = interval(BELL_GAP);
+        bells.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            snoozes: interval(SNOOZE_INTERVAL),
+            bells,
+            idle_checks: interval(IDLE_INTERVAL),
+        }
     }
-    lines
-        .iter()
-        .filter(|line| line_matches_patterns(line, patterns))
-        .count()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{build_pattern, build_regex, line_matches_patterns, max_start};
+impl Consumer for Timers {
+    fn consume(
+        &mut self,
+        event: AppEvent,
+        app: &mut AppState,
+        lines: &mut LogBuffer,
+        bus: &mut EventBus,
+    ) -> Result<Option<AppEvent>, LogrError> {
+        let changed = match event {
+            // The countdown is only shown in the patterns dialog.
+            AppEvent::SnoozeTick => wake_snoozed(app, lines, Instant::now()) || app.dialog_open,
+            AppEvent::IdleTick => app
+                .idle
+                .as_mut()
+                .is_some_and(|idle| idle.check(Instant::now())),
+            AppEvent::BellTick => {
+                app.chime.play(&mut io::stdout())?;
+                false
+            }
+            event => return Ok(Some(event)),
+        };
+        if changed {
+            bus.publish(AppEvent::Changed);
+        }
+        Ok(None)
+    }
+}
 
-    #[test]
+/// The terminal, consuming the keys pressed and drawing a frame once something changed.
+struct Screen {
+    terminal: LogrTerminal,
+    inline: Option<u16>,
+    frames: Interval,
+    /// Whether something changed since the last frame was drawn.
+    dirty: bool,
+}
+
+impl Screen {
+    fn new(terminal: LogrTerminal, inline: Option<u16>) -> Self {
+        let mut frames = interval(FRAME_INTERVAL);
+        frames.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            terminal,
+            inline,
+            frames,
+            dirty: true,
+        }
+    }
+}
+
+impl Consumer for Screen {
+    fn consume(
+        &mut self,
+        event: AppEvent,
+        app: &mut AppState,
+        lines: &mut LogBuffer,
+        bus: &mut EventBus,
+    ) -> Result<Option<AppEvent>, LogrError> {
+        match event {
+            AppEvent::Key(key) => {
+                self.dirty = true;
+                // The key waking up a blanked screen does nothing else.
+                if let Some(idle) = &mut app.idle
+                    && idle.wake(Instant::now())
+                {
+                    return Ok(None);
+                }
+                settle_scroll(app, lines);
+                let total_lines = filtered_line_count(lines, app);
+                let view_height = screen_height(&self.terminal, self.inline)?.saturating_sub(2);
+                let result = handle_key(app, lines, total_lines, view_height as usize, key)?;
+                if result.exit {
+                    bus.publish(AppEvent::Quit);
+                }
+                if result.rerun {
+                    bus.publish(AppEvent::RerunRequested);
+                }
+            }
+            AppEvent::Resize | AppEvent::Changed => self.dirty = true,
+            AppEvent::Tick => {
+                settle_scroll(app, lines);
+                self.terminal.draw(|f| ui(f, lines, app))?;
+                self.dirty = false;
+            }
+            event => return Ok(Some(event)),
+        }
+        Ok(None)
+    }
+}
+
+fn ingest(app: &mut AppState, lines: &mut LogBuffer, event: InputEvent) {
+    match event {
+        InputEvent::Segment(segment) => match push_segment(lines, None, segment) {
+            Some(line) => push_output(app, lines, line),
+            None => update_last_matches(app, lines),
+        },
+        InputEvent::SourceSegment(followed) => {
+            match push_segment(lines, Some(followed.source), followed.segment) {
+                Some(mut line) => {
+                    line.offset = Some(followed.offset);
+                    push_output(app, lines, line);
+                }
+                None => update_last_matches(app, lines),
+            }
+        }
+        InputEvent::RunExited(exit) => {
+            let summary = exit.summary(lines, &app.patterns);
+            push_line(app, lines, LogLine::run_summary(&summary));
+            app.run_summary = Some(summary);
+        }
+    }
+}
+
+/// Updates the matches and level of the last line after a progress update overwrote its text.
+fn update_last_matches(app: &AppState, lines: &mut LogBuffer) {
+    if let Some(line) = lines.last_mut() {
+        update_matches(line, &app.patterns);
+        update_level(line, app.priorities);
+        update_outcome(line, app.outcomes.as_ref());
+    }
+    lines.reindex_last();
+}
+
+/// Pushes a line of input, preceded by a separator when its timestamp enters a new period.
+fn push_output(app: &mut AppState, lines: &mut LogBuffer, line: LogLine) {
+    app.rate.record(Instant::now());
+    if let Some(pause) = &mut app.pause {
+        pause.received += 1;
+    }
+    if line.kind == LineKind::Output
+        && !app.timestamped.contains(&line.source)
+        && line_format(&line, app).timestamp(&line.text).is_some()
+    {
+        app.timestamped.insert(line.source);
+    }
+    if let Some(separator) = period_separator(app, &line) {
+        push_line(app, lines, separator);
+    }
+    push_line(app, lines, line);
+    write_matched(app, lines);
+    sound_matched(app, lines);
+    if disable_runaway_patterns(app) {
+        refresh_matches(app, lines);
+    }
+    check_buffer_size(app, lines);
+}
+
+/// Appends the line just pushed to the `--output` file if it matches an enabled pattern and
+/// none excluding lines. Writing stops at the first error, telling why in the status bar.
+fn write_matched(app: &mut AppState, lines: &LogBuffer) {
+    let Some(output) = &mut app.output else {
+        return;
+    };
+    let Some(line) = lines.len().checked_sub(1).and_then(|last| lines.get(last)) else {
+        return;
+    };
+    let Some(matches) = &line.matches else {
+        return;
+    };
+    if !matches.iter().any(|found| !found.exclude) || matches.iter().any(|found| found.exclude) {
+        return;
+    }
+    if let Err(err) = writeln!(
+        output,
+        "{}",
+        redact(&app.redactions, line.text.as_str().into())
+    ) {
+        app.output = None;
+        app.status = Some(format!("Stopped writing matched lines: {err}"));
+    }
+}
+
+/// Sounds the alert of the first pattern with one that the line just pushed matches, unless a
+/// pattern hides the line.
+fn sound_matched(app: &mut AppState, lines: &LogBuffer) {
+    let Some(line) = lines.len().checked_sub(1).and_then(|last| lines.get(last)) else {
+        return;
+    };
+    let Some(matches) = &line.matches else {
+        return;
+    };
+    if matches.iter().any(|found| found.exclude) {
+        return;
+    }
+    let sound = matches
+        .iter()
+        .find_map(|found| app.patterns.get(found.pattern)?.sound.as_ref());
+    if let Some(sound) = sound {
+        app.chime.queue(sound, Instant::now());
+    }
+}
+
+/// Disables the patterns that took longer than [`PATTERN_TIMEOUT`] to match a line, telling why
+/// in the patterns dialog, and returns whether there were any.
+fn disable_runaway_patterns(app: &mut AppState) -> bool {
+    let mut disabled = false;
+    for pattern in &mut app.patterns {
+        if let Some(took) = pattern.cost.take_runaway()
+            && pattern.enabled
+        {
+            pattern.enabled = false;
+            disabled = true;
+            app.pattern_error = Some(format!(
+                "Disabled {}: matching a line took {}ms",
+                pattern.pattern,
+                took.as_millis()
+            ));
+        }
+    }
+    disabled
+}
+
+/// Opens the large-buffer prompt the first time an unbounded buffer grows past either
+/// threshold, before it grows large enough to get logr killed.
+fn check_buffer_size(app: &mut AppState, lines: &LogBuffer) {
+    if app.large_buffer_warned || lines.is_bounded() {
+        return;
+    }
+    let too_many = app.warn_lines > 0 && lines.len() >= app.warn_lines;
+    let too_big = app.warn_bytes > 0 && lines.estimated_bytes() >= app.warn_bytes;
+    if too_many || too_big {
+        app.large_buffer_warned = true;
+        app.large_buffer_open = true;
+    }
+}
+
+fn period_separator(app: &mut AppState, line: &LogLine) -> Option<LogLine> {
+    if app.separators == Separators::Off || line.kind != LineKind::Output {
+        return None;
+    }
+    let current = match line_format(line, app).timestamp(&line.text) {
+        Some(timestamp) => date_hour(&timestamp)?,
+        None if !app.timestamped.contains(&line.source) => received_date_hour(line.received),
+        None => return None,
+    };
+    let date = current.date.clone()?;
+    let previous = app.last_date_hour.replace(current.clone())?;
+    let changed = match app.separators {
+        Separators::Off => false,
+        Separators::Day => previous.date != current.date,
+        Separators::Hour => previous != current,
+    };
+    if !changed {
+        return None;
+    }
+    let text = match (app.separators, current.hour) {
+        (Separators::Hour, Some(hour)) => format!("──── {date} {hour:02}:00 ────"),
+        _ => format!("──── {date} ────"),
+    };
+    Some(LogLine::date_separator(text))
+}
+
+fn received_date_hour(received: SystemTime) -> DateHour {
+    let seconds = received
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    epoch_date_hour(i64::try_from(seconds).unwrap_or(i64::MAX))
+}
+
+/// Resolves when the watched files changed, never when not watching any.
+async fn files_changed(watcher: &mut Option<FileWatcher>) {
+    match watcher {
+        Some(watcher) => watcher.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves with the next pattern change of another instance, never when not sharing patterns.
+async fn shared_change(shared: &mut Option<mpsc::Receiver<Change>>) -> Option<Change> {
+    match shared {
+        Some(changes) => changes.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves with the next reverse lookup finished, never without `--enrich`.
+async fn resolved_address(resolved: &mut Option<mpsc::Receiver<Resolved>>) -> Option<Resolved> {
+    match resolved {
+        Some(lookups) => lookups.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Makes the pattern change of another instance, as an action so it can be undone. Changes
+/// already made here, like the echo of one sent from here, are ignored.
+fn take_shared(app: &mut AppState, lines: &mut LogBuffer, change: Change) {
+    let action = match change {
+        Change::Add {
+            pattern,
+            case_sensitive,
+        } if !app.patterns.iter().any(|spec| spec.pattern == pattern) => Action::AddPattern {
+            pattern,
+            case_sensitive,
+        },
+        Change::Remove { pattern } => {
+            match app.patterns.iter().position(|spec| spec.pattern == pattern) {
+                Some(index) => Action::RemovePattern { index },
+                None => return,
+            }
+        }
+        Change::Add { .. } => return,
+    };
+    // A pattern that doesn't build here is left out, like one typed in.
+    let _ = apply(app, lines, action);
+}
+
+/// Rings the bell when a run fails, so failures get noticed while watching files.
+fn alert(event: &InputEvent) -> io::Result<()> {
+    match event {
+        InputEvent::RunExited(exit) if !exit.success() => ring_bell(),
+        _ => Ok(()),
+    }
+}
+
+/// Restarts the command, separating the new run's output from the previous one.
+fn rerun(
+    app: &mut AppState,
+    lines: &mut LogBuffer,
+    runner: &mut CommandRunner,
+    clear: bool,
+) -> io::Result<()> {
+    runner.rerun()?;
+    app.run_summary = None;
+    if clear {
+        lines.clear();
+        if let Some(pause) = &mut app.pause {
+            pause.end = 0;
+        }
+        app.detail_line = None;
+        app.search_match = None;
+        app.selection = None;
+        app.scroll = 0;
+        app.follow = true;
+    }
+    push_line(app, lines, runner.separator());
+    Ok(())
+}
+
+/// Appends a line, keeping the view on the same lines when the buffer evicts old ones.
+fn push_line(app: &mut AppState, lines: &mut LogBuffer, mut line: LogLine) {
+    if line.kind == LineKind::Output {
+        app.numbered += 1;
+        line.number = Some(app.numbered);
+    }
+    if app.dedup
+        && app.pause.is_none()
+        && let Some(last) = lines.last_mut()
+        && repeats(last, &line)
+    {
+        last.repeats += 1;
+        return;
+    }
+    update_matches(&mut line, &app.patterns);
+    update_level(&mut line, app.priorities);
+    update_outcome(&mut line, app.outcomes.as_ref());
+    if let Some(enricher) = &mut app.enricher
+        && line
+            .matches
+            .as_ref()
+            .is_some_and(|matches| matches.iter().any(|found| !found.exclude))
+    {
+        enricher.request(&line.text);
+    }
+    group_entry(app, lines, &mut line);
+    let Some(evicted) = lines.push(line) else {
+        return;
+    };
+    spill(app, &evicted);
+    // What refers to lines by id stays on them, until they are evicted themselves.
+    app.detail_line = app.detail_line.filter(|&id| lines.position(id).is_some());
+    app.search_match = app.search_match.filter(|&id| lines.position(id).is_some());
+    if let Some(pause) = &mut app.pause {
+        pause.end = pause.end.saturating_sub(1);
+    }
+}
+
+/// Whether `line` repeats `last`, the line before it, to be collapsed into it.
+fn repeats(last: &LogLine, line: &LogLine) -> bool {
+    line.kind == LineKind::Output
+        && last.kind == LineKind::Output
+        && !last.live
+        && last.source == line.source
+        && last.text == line.text
+}
+
+/// Appends an output line evicted from the buffer to the spill file, as many times as it came
+/// and masked like `--output`. Writing stops at the first error, telling why in the status bar.
+fn spill(app: &mut AppState, line: &LogLine) {
+    let Some(out) = &mut app.spill else {
+        return;
+    };
+    if line.kind != LineKind::Output {
+        return;
+    }
+    let text = redact(&app.redactions, line.text.as_str().into());
+    let written = (0..=line.repeats).try_for_each(|_| writeln!(out, "{text}"));
+    if let Err(err) = written {
+        app.spill = None;
+        app.status = Some((app.messages.spill_stopped)(&err.to_string()));
+    }
+}
+
+/// Where the lines evicted from the buffer are spilled to, in the temporary directory.
+pub(crate) fn spill_path() -> PathBuf {
+    std::env::temp_dir().join(format!("logr-spill-{}.log", std::process::id()))
+}
+
+/// Creates the file the lines evicted from the buffer are spilled to, only open to the user.
+/// It is made anew, so a file or link already there isn't written through.
+pub(crate) fn create_spill(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+/// Makes an output line not matching the entry start regex continue the entry of the output
+/// line before it, if that came from the same source.
+fn group_entry(app: &AppState, lines: &mut LogBuffer, line: &mut LogLine) {
+    if line.kind != LineKind::Output || app.entry_start.is_match(&line.text) {
+        return;
+    }
+    let Some(last) = lines.len().checked_sub(1) else {
+        return;
+    };
+    if lines[last].kind != LineKind::Output || lines[last].source != line.source {
+        return;
+    }
+    let first = entry_first_line(lines, last);
+    line.continues = Some(lines.len() - first);
+    if let Some(first) = lines.get_mut(first) {
+        first.continuations += 1;
+        line.expanded = first.expanded;
+    }
+}
+
+/// Index of the line starting the entry the line at `index` is part of.
+pub(crate) fn entry_first_line(lines: &LogBuffer, index: usize) -> usize {
+    // Lines continuing an entry whose first line was evicted point before the buffer, to
+    // where the line promoted in its place now is.
+    index.saturating_sub(lines[index].continues.unwrap_or(0))
+}
+
+/// Finds the (non-empty) matches of the active patterns in `text`.
+fn find_matches(text: &str, patterns: &[PatternSpec]) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+    for (index, pattern) in patterns.iter().enumerate() {
+        if !pattern.active() {
+            continue;
+        }
+        let started = Instant::now();
+        matches.extend(
+            pattern
+                .highlights(text)
+                .into_iter()
+                .map(|(start, end)| PatternMatch {
+                    start,
+                    end,
+                    pattern: index,
+                    exclude: pattern.exclude,
+                }),
+        );
+        pattern.cost.record(started.elapsed());
+    }
+    matches
+}
+
+fn update_matches(line: &mut LogLine, patterns: &[PatternSpec]) {
+    if line.kind == LineKind::Output {
+        line.matches = Some(find_matches(&line.text, patterns));
+    }
+}
+
+fn update_outcome(line: &mut LogLine, outcomes: Option<&Outcomes>) {
+    if line.kind == LineKind::Output {
+        line.outcome = outcomes.and_then(|outcomes| outcomes.outcome(&line.text));
+    }
+}
+
+fn update_level(line: &mut LogLine, priorities: Option<fn(&str) -> Option<LevelToken>>) {
+    if line.kind == LineKind::Output {
+        line.level = detect_level(&line.text, priorities);
+    }
+}
+
+/// The level a line states, taking the priority of a line of a log with priorities as its own.
+fn detect_level(
+    text: &str,
+    priorities: Option<fn(&str) -> Option<LevelToken>>,
+) -> Option<LevelToken> {
+    priorities
+        .and_then(|priority| priority(text))
+        .or_else(|| level::detect(text))
+}
+
+/// Recomputes the matches of every line, after the patterns changed.
+pub(crate) fn refresh_matches(app: &AppState, lines: &mut LogBuffer) {
+    for line in lines.iter_mut() {
+        update_matches(line, &app.patterns);
+    }
+    lines.reindex();
+}
+
+/// Wakes up the patterns whose snooze is over, returning whether there were any.
+fn wake_snoozed(app: &mut AppState, lines: &mut LogBuffer, now: Instant) -> bool {
+    let mut woken = false;
+    for pattern in &mut app.patterns {
+        if pattern.snooze.is_some_and(|snooze| snooze.until <= now) {
+            pattern.snooze = None;
+            woken = true;
+        }
+    }
+    if woken {
+        refresh_matches(app, lines);
+    }
+    woken
+}
+
+/// Derives the scroll offset from the line the view is anchored at, or the first one after it
+/// still buffered, so the view stays on it however many lines were evicted or hidden since.
+fn settle_scroll(app: &mut AppState, lines: &LogBuffer) {
+    if let Some(anchor) = app.anchor.filter(|_| !app.follow) {
+        let start = lines.index_from(anchor);
+        if lines.get(start).is_none_or(|line| line.id != anchor) {
+            app.row_offset = 0;
+        }
+        let end = app.pause.as_ref().map_or(lines.len(), |pause| pause.end);
+        let scroll = app.visible.borrow_mut().before(lines, app, start.min(end));
+        app.scroll = scroll;
+    }
+}
+
+/// Anchors the view at the line it starts at, after a key may have scrolled it. Starting at
+/// another line, none of its rows are scrolled past.
+pub(crate) fn anchor_scroll(app: &mut AppState, lines: &LogBuffer) {
+    let anchor = if app.follow {
+        None
+    } else {
+        visible_line_index(lines, app, app.scroll).map(|index| lines[index].id)
+    };
+    if anchor != app.anchor {
+        app.row_offset = 0;
+    }
+    app.anchor = anchor;
+}
+
+/// Applies a segment to the last line while it is a live progress line from the same source,
+/// otherwise returns the line to append.
+fn push_segment(lines: &mut LogBuffer, source: Option<usize>, segment: Segment) -> Option<LogLine> {
+    let live = lines
+        .last_mut()
+        .filter(|line| line.live && line.source == source);
+    let line = match segment {
+        Segment::Line(text) => match live {
+            Some(line) => {
+                line.text = text;
+                line.live = false;
+                None
+            }
+            None => Some(LogLine::output(text)),
+        },
+        Segment::Progress(text) => match live {
+            Some(line) => {
+                line.text = text;
+                None
+            }
+            None => Some(LogLine::progress(text)),
+        },
+        Segment::Commit => {
+            if let Some(line) = live {
+                line.live = false;
+            }
+            None
+        }
+        Segment::Rotated => {
+            if let Some(line) = live {
+                line.live = false;
+            }
+            Some(LogLine::marker("--- file rotated ---"))
+        }
+    };
+    line.map(|line| line.with_source(source))
+}
+
+type LogrTerminal = Terminal<CrosstermBackend<Stdout>>;
+
+/// Sets up the terminal, drawing on the alternate screen unless an inline height is given.
+fn term_init(inline: Option<u16>) -> Result<LogrTerminal, io::Error> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    let Some(height) = inline else {
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            TermClear(ClearType::All),
+            EnableMouseCapture
+        )?;
+        let backend = CrosstermBackend::new(stdout);
+        return Terminal::new(backend);
+    };
+    // The mouse is left to the terminal, so its scrollback can still be scrolled.
+    let backend = CrosstermBackend::new(stdout);
+    Terminal::with_options(
+        backend,
+        TerminalOptions {
+            viewport: TerminalViewport::Inline(height),
+        },
+    )
+}
+
+fn term_cleanup(mut terminal: LogrTerminal, inline: bool) -> Result<(), io::Error> {
+    disable_raw_mode()?;
+    if inline {
+        terminal.clear()?;
+    } else {
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+    }
+    terminal.show_cursor()
+}
+
+/// Rows the view is drawn in: the whole terminal, or as much of the inline height as fits.
+fn screen_height(terminal: &LogrTerminal, inline: Option<u16>) -> io::Result<u16> {
+    let rows = terminal.size()?.height;
+    Ok(inline.map_or(rows, |height| height.min(rows)))
+}
+
+fn ring_bell() -> Result<(), io::Error> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x07")?;
+    stdout.flush()
+}
+
+fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .size_limit(PATTERN_SIZE_LIMIT)
+        .build()
+}
+
+fn build_pattern(pattern: String, case_sensitive: bool) -> Result<PatternSpec, LogrError> {
+    let regex = build_regex(&pattern, case_sensitive)?;
+    Ok(PatternSpec {
+        pattern,
+        case_sensitive,
+        exclude: false,
+        enabled: true,
+        snooze: None,
+        sound: None,
+        color: None,
+        style: Style::default(),
+        whole_line: false,
+        literal: false,
+        word: false,
+        name: None,
+        from_file: false,
+        groups: regex.captures_len() > 1,
+        regex,
+        cost: MatchCost::default(),
+    })
+}
+
+/// Builds a pattern matching its text as it is, like `1.2.3.4` or `[ERROR]`.
+fn build_literal(pattern: String, case_sensitive: bool) -> Result<PatternSpec, LogrError> {
+    let mut spec = build_pattern(regex::escape(&pattern), case_sensitive)?;
+    spec.pattern = pattern;
+    spec.literal = true;
+    Ok(spec)
+}
+
+fn build_spec(
+    pattern: String,
+    case_sensitive: bool,
+    literal: bool,
+) -> Result<PatternSpec, LogrError> {
+    if literal {
+        build_literal(pattern, case_sensitive)
+    } else {
+        build_pattern(pattern, case_sensitive)
+    }
+}
+
+/// The regex of a pattern's text, escaped first for a literal one and put between word
+/// boundaries for a whole-word one.
+fn pattern_regex(
+    pattern: &str,
+    case_sensitive: bool,
+    literal: bool,
+    word: bool,
+) -> Result<Regex, regex::Error> {
+    let pattern = if literal {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    if word {
+        build_regex(&format!(r"\b(?:{pattern})\b"), case_sensitive)
+    } else {
+        build_regex(&pattern, case_sensitive)
+    }
+}
+
+/// Makes a pattern match only whole words, or anywhere again.
+fn set_word(pattern: &mut PatternSpec, word: bool) -> Result<(), LogrError> {
+    pattern.regex = pattern_regex(
+        &pattern.pattern,
+        pattern.case_sensitive,
+        pattern.literal,
+        word,
+    )?;
+    pattern.word = word;
+    pattern.cost = MatchCost::default();
+    Ok(())
+}
+
+/// Builds patterns given like `ERROR` or, with the style of their matches, `ERROR:red`.
+fn build_patterns(patterns: &[String], ignore_case: bool) -> Result<Vec<PatternSpec>, LogrError> {
+    build_texts(patterns, ignore_case, false)
+}
+
+/// Builds patterns like [`build_patterns`], as literal ones with `literal`.
+fn build_texts(
+    patterns: &[String],
+    ignore_case: bool,
+    literal: bool,
+) -> Result<Vec<PatternSpec>, LogrError> {
+    patterns
+        .iter()
+        .map(|text| build_text(text, ignore_case, literal))
+        .collect()
+}
+
+/// Builds a pattern given like `ERROR` or `ERROR:red`, see [`build_patterns`].
+fn build_text(text: &str, ignore_case: bool, literal: bool) -> Result<PatternSpec, LogrError> {
+    let (text, color, style, whole_line) = split_style(text);
+    let mut pattern = build_spec(text.to_string(), !ignore_case, literal)?;
+    pattern.color = color;
+    pattern.style = style;
+    pattern.whole_line = whole_line;
+    Ok(pattern)
+}
+
+/// Splits the style off a pattern like `ERROR:red`, `timeout:#ff8800` or
+/// `FATAL:white+on-red+bold`: a color, a background color after `on-`, and `bold`,
+/// `underline` or `reverse`, joined with `+`, and `line` for the whole line of a match to take
+/// the style. A pattern whose last `:` isn't followed by a style, like `\d{2}:\d{2}`, has none.
+fn split_style(text: &str) -> (&str, Option<Color>, Style, bool) {
+    let unstyled = (text, None, Style::default(), false);
+    let Some((pattern, spec)) = text.rsplit_once(':') else {
+        return unstyled;
+    };
+    if pattern.is_empty() {
+        return unstyled;
+    }
+    match parse_style(spec) {
+        Some((color, style, whole_line)) => (pattern, color, style, whole_line),
+        None => unstyled,
+    }
+}
+
+/// Parses a style like `red` or `white+on-red+bold+line` into the color, the background and
+/// modifiers, and whether it takes the whole line. `None` when it isn't one.
+fn parse_style(spec: &str) -> Option<(Option<Color>, Style, bool)> {
+    let mut color = None;
+    let mut style = Style::default();
+    let mut whole_line = false;
+    for item in spec.split('+') {
+        match item {
+            "line" => whole_line = true,
+            "bold" => style = style.add_modifier(Modifier::BOLD),
+            "underline" => style = style.add_modifier(Modifier::UNDERLINED),
+            "reverse" => style = style.add_modifier(Modifier::REVERSED),
+            _ => match item.strip_prefix("on-") {
+                Some(name) => style = style.bg(parse_color(name)?),
+                None => color = Some(parse_color(item)?),
+            },
+        }
+    }
+    Some((color, style, whole_line))
+}
+
+/// A color by name or hex code. Numbers would be taken as indexes into the terminal's palette,
+/// but are more likely part of the pattern, like in `port:80`.
+fn parse_color(name: &str) -> Option<Color> {
+    if name.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    name.parse().ok()
+}
+
+/// Builds the patterns highlighting lines followed by the ones hiding them.
+fn build_filters(
+    patterns: &[String],
+    exclude: &[String],
+    ignore_case: bool,
+    literal: bool,
+) -> Result<Vec<PatternSpec>, LogrError> {
+    let mut filters = build_texts(patterns, ignore_case, literal)?;
+    for mut pattern in build_texts(exclude, ignore_case, literal)? {
+        pattern.exclude = true;
+        filters.push(pattern);
+    }
+    Ok(filters)
+}
+
+/// Gives the patterns their alert sounds, adding those not among them yet.
+fn add_alerts(
+    patterns: &mut Vec<PatternSpec>,
+    alerts: &[(String, Sound)],
+    ignore_case: bool,
+    literal: bool,
+) -> Result<(), LogrError> {
+    for (text, sound) in alerts {
+        let (text, color, style, whole_line) = split_style(text);
+        let existing = patterns
+            .iter_mut()
+            .find(|pattern| !pattern.exclude && pattern.pattern == text);
+        match existing {
+            Some(pattern) => {
+                pattern.sound = Some(sound.clone());
+                pattern.color = color.or(pattern.color);
+                pattern.style = pattern.style.patch(style);
+                pattern.whole_line |= whole_line;
+            }
+            None => {
+                let mut pattern = build_spec(text.to_string(), !ignore_case, literal)?;
+                pattern.sound = Some(sound.clone());
+                pattern.color = color;
+                pattern.style = style;
+                pattern.whole_line = whole_line;
+                patterns.push(pattern);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn max_start(total_lines: usize, view_height: usize) -> usize {
+    if view_height == 0 {
+        0
+    } else {
+        total_lines.saturating_sub(view_height)
+    }
+}
+
+/// Whether the view scrolls wrapped lines by the rows they take on screen. Following at the top,
+/// it scrolls by lines.
+pub(crate) fn scrolls_rows(app: &AppState) -> bool {
+    app.wrap && app.follow_at == FollowAt::Bottom && app.wrap_width.get() > 0
+}
+
+/// [`max_start`] for wrapped lines taking `rows(position)` rows each: the visible line the
+/// newest view starts at, and the rows of it scrolled past.
+fn max_row_start(
+    total_lines: usize,
+    view_height: usize,
+    rows: impl Fn(usize) -> usize,
+) -> (usize, usize) {
+    if view_height == 0 {
+        return (0, 0);
+    }
+    let mut left = view_height;
+    for position in (0..total_lines).rev() {
+        let taken = rows(position);
+        if taken >= left {
+            return (position, taken - left);
+        }
+        left -= taken;
+    }
+    (0, 0)
+}
+
+/// Moves a view starting at a visible line, with some of its rows scrolled past, by `delta`
+/// rows: down when positive, up when negative. Moving down, it can end past the newest view.
+fn scroll_rows(
+    (mut position, mut offset): (usize, usize),
+    delta: isize,
+    total_lines: usize,
+    rows: impl Fn(usize) -> usize,
+) -> (usize, usize) {
+    let mut left = delta.unsigned_abs();
+    if delta < 0 {
+        while left > offset {
+            if position == 0 {
+                return (0, 0);
+            }
+            left -= offset + 1;
+            position -= 1;
+            offset = rows(position) - 1;
+        }
+        (position, offset - left)
+    } else {
+        while position < total_lines {
+            let taken = rows(position);
+            if offset + left < taken {
+                return (position, offset + left);
+            }
+            left -= taken - offset;
+            position += 1;
+            offset = 0;
+        }
+        (position, 0)
+    }
+}
+
+/// Index among the visible lines of the one at the top of the screen, for a view starting (in
+/// buffer order) at `start`. Following at the top, the newest line of the view is on top.
+fn top_line(start: usize, total_lines: usize, view_height: usize, follow_at: FollowAt) -> usize {
+    match follow_at {
+        FollowAt::Bottom => start,
+        FollowAt::Top => (start + view_height).min(total_lines).saturating_sub(1),
+    }
+}
+
+/// The start of a view with the visible line at `position` at the top of the screen, the
+/// inverse of [`top_line`].
+fn start_for_top(position: usize, view_height: usize, follow_at: FollowAt) -> usize {
+    match follow_at {
+        FollowAt::Bottom => position,
+        FollowAt::Top => (position + 1).saturating_sub(view_height),
+    }
+}
+
+/// Whether any of the active, non-exclude patterns matches the line.
+pub(crate) fn line_matches_patterns(line: &str, patterns: &[PatternSpec]) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| pattern.active() && !pattern.exclude && pattern.regex.is_match(line))
+}
+
+fn line_excluded(line: &str, patterns: &[PatternSpec]) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| pattern.active() && pattern.exclude && pattern.regex.is_match(line))
+}
+
+pub(crate) fn line_visible(line: &LogLine, app: &AppState) -> bool {
+    let hidden = line
+        .source
+        .and_then(|source| app.sources.get(source))
+        .is_some_and(|source| source.hidden);
+    if hidden {
+        return false;
+    }
+    if line.kind != LineKind::Output {
+        return true;
+    }
+    if app.group_entries && line.continues.is_some() && !line.expanded {
+        return false;
+    }
+    if let (Some(min_level), Some(token)) = (app.min_level, line.level)
+        && token.level < min_level
+    {
+        return false;
+    }
+    let excluded = match &line.matches {
+        Some(matches) => matches.iter().any(|found| found.exclude),
+        None => line_excluded(&line.text, &app.patterns),
+    };
+    if excluded || !app.filter_only {
+        return !excluded;
+    }
+    match (&app.filter_expr, &line.matches) {
+        (Some(expression), _) => {
+            expression.holds(&app.patterns, |index| pattern_matched(line, app, index))
+        }
+        (None, Some(matches)) => matches.iter().any(|found| !found.exclude),
+        (None, None) => line_matches_patterns(&line.text, &app.patterns),
+    }
+}
+
+/// Whether the pattern with this index matches a line, as found at ingestion when it was.
+fn pattern_matched(line: &LogLine, app: &AppState, index: usize) -> bool {
+    match &line.matches {
+        Some(matches) => matches.iter().any(|found| found.pattern == index),
+        None => app
+            .patterns
+            .get(index)
+            .is_some_and(|pattern| pattern.active() && pattern.regex.is_match(&line.text)),
+    }
+}
+
+/// The lines the view is drawn from: the whole buffer, or while paused, the lines up to where
+/// it was paused.
+pub(crate) fn snapshot<'a>(
+    lines: &'a LogBuffer,
+    app: &'a AppState,
+) -> impl Iterator<Item = &'a LogLine> {
+    let end = app.pause.as_ref().map_or(lines.len(), |pause| pause.end);
+    lines.iter().take(end)
+}
+
+pub(crate) fn visible_lines<'a>(
+    lines: &'a LogBuffer,
+    app: &'a AppState,
+) -> impl Iterator<Item = &'a LogLine> {
+    visible_indices(lines, app).map(move |index| &lines[index])
+}
+
+/// Indices into `lines` of the lines in the (possibly filtered) view. Filtering by any pattern,
+/// only the lines indexed as filterable are looked at, rather than the whole buffer.
+pub(crate) fn visible_indices<'a>(
+    lines: &'a LogBuffer,
+    app: &'a AppState,
+) -> impl Iterator<Item = usize> + 'a {
+    let end = app.pause.as_ref().map_or(lines.len(), |pause| pause.end);
+    let candidates: Box<dyn Iterator<Item = usize> + 'a> =
+        if app.filter_only && app.filter_expr.is_none() {
+            Box::new(lines.filterable().take_while(move |&index| index < end))
+        } else {
+            Box::new(0..end.min(lines.len()))
+        };
+    candidates.filter(move |&index| line_visible(&lines[index], app))
+}
+
+/// Maps a position in the (possibly filtered) view to an index into `lines`.
+pub(crate) fn visible_line_index(
+    lines: &LogBuffer,
+    app: &AppState,
+    position: usize,
+) -> Option<usize> {
+    visible_indices(lines, app).nth(position)
+}
+
+fn filtered_line_count(lines: &LogBuffer, app: &AppState) -> usize {
+    let end = app.pause.as_ref().map_or(lines.len(), |pause| pause.end);
+    if !app.filter_only
+        && app.min_level.is_none()
+        && !app.group_entries
+        && app.sources.iter().all(|source| !source.hidden)
+        && app
+            .patterns
+            .iter()
+            .all(|pattern| !pattern.active() || !pattern.exclude)
+    {
+        return end;
+    }
+    app.visible.borrow_mut().len(lines, app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AppState, Args, Color, ColorChoice, Commands, Format, InputEvent, LineKind, LogBuffer,
+        LogLine, MatchCost, Modifier, Segment, Separators, Snooze, Sound, Source, Style,
+        add_alerts, anchor_scroll, build_filters, build_pattern, build_patterns, build_regex,
+        create_spill, entry_first_line, filtered_line_count, ingest, line_matches_patterns,
+        max_row_start, max_start, profile_candidates_of, push_line, push_output, push_segment,
+        scroll_rows, set_word, settle_scroll, take_shared, visible_line_index, visible_lines,
+        wake_snoozed, with_env_patterns,
+    };
+    use crate::{
+        config::{Config, Profile, ProfilePattern},
+        level::Level,
+        share::Change,
+        status::Field,
+    };
+    use clap::{CommandFactory, Parser};
+    use std::{
+        fs, io,
+        num::NonZeroUsize,
+        path::PathBuf,
+        time::{Duration, Instant, SystemTime},
+    };
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn profiles_are_completed_with_their_patterns() {
+        let mut config = Config::default();
+        let nginx = Profile {
+            patterns: vec![
+                ProfilePattern::Text("upstream timed out".to_string()),
+                ProfilePattern::Text("crit".to_string()),
+            ],
+            ..Profile::default()
+        };
+        config.profiles.insert("nginx".to_string(), nginx);
+        config
+            .profiles
+            .insert("java".to_string(), Profile::default());
+
+        let candidates: Vec<(String, String)> = profile_candidates_of(&config)
+            .iter()
+            .map(|candidate| {
+                let help = candidate.get_help().map(ToString::to_string);
+                (
+                    candidate.get_value().to_string_lossy().into_owned(),
+                    help.unwrap_or_default(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            candidates,
+            [
+                ("java".to_string(), String::new()),
+                ("nginx".to_string(), "upstream timed out, crit".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn file_argument_accepts_tail_but_not_command() {
+        let args = Args::try_parse_from(["logr", "--tail", "10", "app.log"]).expect("parse failed");
+        assert_eq!(args.files, vec![PathBuf::from("app.log")]);
+        assert_eq!(args.tail, Some(10));
+
+        assert!(Args::try_parse_from(["logr", "app.log", "--", "make"]).is_err());
+        assert!(Args::try_parse_from(["logr", "--tail", "10"]).is_err());
+
+        let args = Args::try_parse_from(["logr", "api.log", "worker.log"]).expect("parse failed");
+        assert_eq!(args.files.len(), 2);
+
+        let args = Args::try_parse_from(["logr", "-p", "error", "-x", "ping,pong", "app.log"])
+            .expect("parse failed");
+        assert_eq!(args.patterns, vec!["error"]);
+        assert_eq!(args.exclude, vec!["ping", "pong"]);
+        assert_eq!(args.files, vec![PathBuf::from("app.log")]);
+
+        let args = Args::try_parse_from(["logr", "--tail", "5", "--source", "a.log:format=json"])
+            .expect("parse failed");
+        assert_eq!(args.sources[0].format, Format::Json);
+        assert!(Args::try_parse_from(["logr", "--source", "a.log", "--", "make"]).is_err());
+    }
+
+    #[test]
+    fn height_is_only_accepted_inline() {
+        let args = Args::try_parse_from(["logr", "--no-alt-screen"]).expect("parse failed");
+        assert!(args.no_alt_screen);
+        assert_eq!(args.height, 20);
+
+        let args = Args::try_parse_from(["logr", "--no-alt-screen", "--height", "8"])
+            .expect("parse failed");
+        assert_eq!(args.height, 8);
+        assert!(Args::try_parse_from(["logr", "--height", "8"]).is_err());
+    }
+
+    #[test]
+    fn adb_takes_an_optional_serial_and_its_own_filters() {
+        let args = Args::try_parse_from(["logr", "--adb"]).expect("parse failed");
+        assert_eq!(args.adb, Some(None));
+        let args = Args::try_parse_from(["logr", "--adb", "emulator-5554", "--tag", "MyApp"])
+            .expect("parse failed");
+        assert_eq!(args.adb, Some(Some("emulator-5554".to_string())));
+        assert_eq!(args.tag, ["MyApp"]);
+
+        assert!(Args::try_parse_from(["logr", "--tag", "MyApp"]).is_err());
+        assert!(Args::try_parse_from(["logr", "--adb", "--", "make"]).is_err());
+        assert!(Args::try_parse_from(["logr", "--kmsg", "--adb"]).is_err());
+        assert!(Args::try_parse_from(["logr", "--kmsg", "app.log"]).is_err());
+        let args = Args::try_parse_from(["logr", "--drain", "0.0.0.0:8514"]).expect("parse failed");
+        assert_eq!(args.drain, Some(([0, 0, 0, 0], 8514).into()));
+        assert!(Args::try_parse_from(["logr", "--drain", "0.0.0.0:8514", "--kmsg"]).is_err());
+        assert!(Args::try_parse_from(["logr", "--gelf", "0.0.0.0:12201", "--kmsg"]).is_err());
+        assert!(Args::try_parse_from(["logr", "--fluent", "0.0.0.0:24224", "--", "make"]).is_err());
+    }
+
+    #[test]
+    fn colors_follow_the_choice_the_terminal_and_no_color() {
+        assert!(ColorChoice::Auto.enabled(true, false));
+        assert!(!ColorChoice::Auto.enabled(false, false));
+        assert!(!ColorChoice::Auto.enabled(true, true));
+        assert!(ColorChoice::Always.enabled(false, true));
+        assert!(!ColorChoice::Never.enabled(true, false));
+
+        let args = Args::try_parse_from(["logr", "--color", "never"]).expect("parse failed");
+        assert_eq!(args.color, ColorChoice::Never);
+    }
+
+    #[test]
+    fn read_only_refuses_the_output_file_and_sharing() {
+        let args = Args::try_parse_from(["logr", "--read-only", "app.log"]).expect("parse failed");
+        assert!(args.read_only);
+        for flag in ["--output", "--share"] {
+            let parsed = Args::try_parse_from(["logr", "--read-only", flag, "x", "app.log"]);
+            assert!(parsed.is_err(), "{flag} accepted");
+        }
+    }
+
+    #[test]
+    fn env_patterns_come_before_the_given_ones() {
+        let given = vec!["GET".to_string(), "ERROR:red".to_string()];
+        assert_eq!(
+            with_env_patterns(Some("WARN:yellow,,GET"), &given),
+            ["WARN:yellow", "GET", "ERROR:red"]
+        );
+        assert_eq!(with_env_patterns(None, &given), given);
+        assert!(with_env_patterns(Some(""), &[]).is_empty());
+    }
+
+    #[test]
+    fn exclude_accepts_comma_separated_patterns() {
+        let args = Args::try_parse_from(["logr", "-p", "GET", "--exclude", "health,metrics"])
+            .expect("parse failed");
+        assert_eq!(args.patterns, vec!["GET"]);
+        assert_eq!(args.exclude, vec!["health", "metrics"]);
+
+        let args = Args::try_parse_from(["logr", "-v", "GET /healthz", "--invert-match", "ping"])
+            .expect("parse failed");
+        assert_eq!(args.exclude, vec!["GET /healthz", "ping"]);
+    }
+
+    #[test]
+    fn status_template_is_parsed_and_checked_on_the_command_line() {
+        let args = Args::try_parse_from(["logr"]).expect("parse failed");
+        assert!(args.status.contains(Field::Position));
+
+        let args =
+            Args::try_parse_from(["logr", "--status", "{follow} {rate}"]).expect("parse failed");
+        assert!(args.status.contains(Field::Rate));
+        assert!(!args.status.contains(Field::Position));
+        assert!(Args::try_parse_from(["logr", "--status", "{speed}"]).is_err());
+    }
+
+    #[test]
+    fn large_buffer_prompt_opens_once_past_the_line_threshold() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.warn_lines = 2;
+        let mut lines = LogBuffer::new(None);
+        let push = |app: &mut AppState, lines: &mut LogBuffer| {
+            let segment = Segment::Line("line".to_string());
+            ingest(app, lines, InputEvent::Segment(segment));
+        };
+
+        push(&mut app, &mut lines);
+        assert!(!app.large_buffer_open);
+        push(&mut app, &mut lines);
+        assert!(app.large_buffer_open);
+
+        app.large_buffer_open = false;
+        push(&mut app, &mut lines);
+        assert!(!app.large_buffer_open);
+
+        let mut app = AppState::new(Vec::new(), false);
+        app.warn_lines = 2;
+        let mut lines = LogBuffer::new(NonZeroUsize::new(10));
+        push(&mut app, &mut lines);
+        push(&mut app, &mut lines);
+        assert!(!app.large_buffer_open);
+    }
+
+    #[test]
+    fn repeated_lines_collapse_and_evicted_ones_spill_to_a_file() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.dedup = true;
+        let path = std::env::temp_dir().join(format!("logr-spill-test-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let file = create_spill(&path).expect("creating spill failed");
+        app.spill = Some(Box::new(io::LineWriter::new(file)));
+        let mut lines = LogBuffer::new(NonZeroUsize::new(2));
+        for text in ["retry", "retry", "retry", "done", "retry", "exit"] {
+            push_output(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        app.spill = None;
+
+        let kept: Vec<(&str, usize)> = lines
+            .iter()
+            .map(|line| (line.text.as_str(), line.repeats))
+            .collect();
+        assert_eq!(kept, [("retry", 0), ("exit", 0)]);
+        assert_eq!(lines[1].number, Some(6));
+        let written = fs::read_to_string(&path).expect("reading spill failed");
+        assert!(
+            create_spill(&path).is_err(),
+            "an existing file is written through"
+        );
+        fs::remove_file(&path).expect("removing spill failed");
+        assert_eq!(written, "retry\nretry\nretry\ndone\n");
+    }
+
+    #[test]
+    fn min_level_hides_lower_levels_but_keeps_lines_without_one() {
+        let args = Args::try_parse_from(["logr", "--level", "warn"]).expect("parse failed");
+        assert_eq!(args.level, Some(Level::Warn));
+
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::new(None);
+        for text in ["INFO started", "WARN slow", "  at frame 1", "ERROR failed"] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        app.min_level = args.level;
+
+        let shown: Vec<&str> = visible_lines(&lines, &app)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(shown, vec!["WARN slow", "  at frame 1", "ERROR failed"]);
+        assert_eq!(filtered_line_count(&lines, &app), 3);
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
     fn build_regex_respects_case_sensitivity() {
         let sensitive = build_regex("foo", true).expect("regex build failed");
         let insensitive = build_regex("foo", false).expect("regex build failed");
@@ -208,6 +2840,145 @@ mod tests {
         assert!(!pattern.regex.is_match("BAR"));
     }
 
+    #[test]
+    fn patterns_may_be_given_with_the_color_of_their_matches() {
+        let texts = [
+            "ERROR:red",
+            "timeout:#ff8800",
+            r"\d{2}:\d{2}",
+            "port:80",
+            "level:info",
+        ];
+        let texts: Vec<String> = texts.iter().map(|text| text.to_string()).collect();
+        let patterns = build_patterns(&texts, false).expect("build failed");
+        let built: Vec<(&str, Option<Color>)> = patterns
+            .iter()
+            .map(|pattern| (pattern.pattern.as_str(), pattern.color))
+            .collect();
+        assert_eq!(
+            built,
+            [
+                ("ERROR", Some(Color::Red)),
+                ("timeout", Some(Color::Rgb(0xff, 0x88, 0x00))),
+                (r"\d{2}:\d{2}", None),
+                ("port:80", None),
+                ("level:info", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn patterns_may_be_given_with_a_background_and_modifiers() {
+        let texts = [
+            "FATAL:white+on-red+bold",
+            "panic:reverse+underline",
+            "x:red+",
+            "y:on-",
+        ];
+        let texts: Vec<String> = texts.iter().map(|text| text.to_string()).collect();
+        let patterns = build_patterns(&texts, false).expect("build failed");
+        let built: Vec<(&str, Option<Color>, Style)> = patterns
+            .iter()
+            .map(|pattern| (pattern.pattern.as_str(), pattern.color, pattern.style))
+            .collect();
+        let plain = Style::default();
+        assert_eq!(
+            built,
+            [
+                (
+                    "FATAL",
+                    Some(Color::White),
+                    plain.bg(Color::Red).add_modifier(Modifier::BOLD)
+                ),
+                (
+                    "panic",
+                    None,
+                    plain.add_modifier(Modifier::REVERSED | Modifier::UNDERLINED)
+                ),
+                ("x:red+", None, plain),
+                ("y:on-", None, plain),
+            ]
+        );
+    }
+
+    #[test]
+    fn shared_pattern_changes_are_made_once() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::new(None);
+        let add = Change::Add {
+            pattern: "ERROR".to_string(),
+            case_sensitive: false,
+        };
+        take_shared(&mut app, &mut lines, add.clone());
+        take_shared(&mut app, &mut lines, add);
+        assert_eq!(app.patterns.len(), 1);
+        assert!(!app.patterns[0].case_sensitive);
+
+        let remove = Change::Remove {
+            pattern: "ERROR".to_string(),
+        };
+        take_shared(&mut app, &mut lines, remove.clone());
+        take_shared(&mut app, &mut lines, remove);
+        assert!(app.patterns.is_empty());
+    }
+
+    #[test]
+    fn fixed_strings_are_matched_as_text_and_shown_as_given() {
+        let args = Args::try_parse_from(["logr", "-F", "-p", "1.2.3.4,[ERROR]:red", "-x", "a+b"])
+            .expect("parse failed");
+        let patterns = build_filters(&args.patterns, &args.exclude, false, args.fixed_strings)
+            .expect("building filters failed");
+        let shown: Vec<&str> = patterns
+            .iter()
+            .map(|pattern| pattern.pattern.as_str())
+            .collect();
+        assert_eq!(shown, ["1.2.3.4", "[ERROR]", "a+b"]);
+        assert!(patterns.iter().all(|pattern| pattern.literal));
+        assert_eq!(patterns[1].color, Some(Color::Red));
+
+        assert!(line_matches_patterns("from 1.2.3.4", &patterns[..1]));
+        assert!(!line_matches_patterns("from 1x2x3x4", &patterns[..1]));
+        assert!(line_matches_patterns("[ERROR] disk full", &patterns[1..2]));
+        assert!(!line_matches_patterns("E disk full", &patterns[1..2]));
+    }
+
+    #[test]
+    fn word_regexp_matches_the_patterns_as_whole_words() {
+        let args = Args::try_parse_from(["logr", "-w", "-p", "err,a|b"]).expect("parse failed");
+        let mut patterns = build_patterns(&args.patterns, false).expect("building patterns failed");
+        for pattern in &mut patterns {
+            set_word(pattern, args.word_regexp).expect("set_word failed");
+        }
+        assert!(patterns.iter().all(|pattern| pattern.word));
+        assert_eq!(patterns[0].pattern, "err");
+        assert!(line_matches_patterns("err: disk full", &patterns[..1]));
+        assert!(!line_matches_patterns(
+            "transferred 3 files",
+            &patterns[..1]
+        ));
+        assert!(line_matches_patterns("x b y", &patterns[1..]));
+        assert!(!line_matches_patterns("ab", &patterns[1..]));
+
+        set_word(&mut patterns[0], false).expect("set_word failed");
+        assert!(line_matches_patterns("transferred 3 files", &patterns[..1]));
+    }
+
+    #[test]
+    fn wrapped_lines_scroll_by_rows() {
+        let rows = |position: usize| [1, 3, 2, 1][position];
+        assert_eq!(max_row_start(4, 4, rows), (1, 2));
+        assert_eq!(max_row_start(4, 10, rows), (0, 0));
+        assert_eq!(max_row_start(4, 0, rows), (0, 0));
+
+        assert_eq!(scroll_rows((1, 2), -1, 4, rows), (1, 1));
+        assert_eq!(scroll_rows((1, 0), -1, 4, rows), (0, 0));
+        assert_eq!(scroll_rows((2, 0), -2, 4, rows), (1, 1));
+        assert_eq!(scroll_rows((1, 0), -5, 4, rows), (0, 0));
+        assert_eq!(scroll_rows((0, 0), 2, 4, rows), (1, 1));
+        assert_eq!(scroll_rows((1, 1), 2, 4, rows), (2, 0));
+        assert_eq!(scroll_rows((2, 1), 9, 4, rows), (4, 0));
+    }
+
     #[test]
     fn max_start_handles_empty_and_small_windows() {
         assert_eq!(max_start(0, 10), 0);
@@ -224,4 +2995,474 @@ mod tests {
         assert!(line_matches_patterns("error happened", &patterns));
         assert!(!line_matches_patterns("all good", &patterns));
     }
+
+    #[test]
+    fn visible_lines_keeps_run_separators_in_filter_mode() {
+        let patterns =
+            vec![build_pattern("error".to_string(), true).expect("pattern build failed")];
+        let mut app = AppState::new(patterns, false);
+        let lines = LogBuffer::from(vec![
+            LogLine::run_separator("--- run 1 ---".to_string()),
+            LogLine::output("all good".to_string()),
+            LogLine::output("error happened".to_string()),
+        ]);
+
+        app.filter_only = true;
+        let visible: Vec<&str> = visible_lines(&lines, &app)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(visible, vec!["--- run 1 ---", "error happened"]);
+        app.filter_only = false;
+        assert_eq!(visible_lines(&lines, &app).count(), 3);
+    }
+
+    #[test]
+    fn push_segment_collapses_progress_updates() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::from(vec![LogLine::output("start".to_string())]);
+        for segment in [
+            Segment::Progress("10%".to_string()),
+            Segment::Progress("50%".to_string()),
+        ] {
+            if let Some(line) = push_segment(&mut lines, None, segment) {
+                push_line(&mut app, &mut lines, line);
+            }
+        }
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].live);
+
+        for segment in [
+            Segment::Line("done".to_string()),
+            Segment::Line("next".to_string()),
+        ] {
+            if let Some(line) = push_segment(&mut lines, None, segment) {
+                push_line(&mut app, &mut lines, line);
+            }
+        }
+
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, vec!["start", "done", "next"]);
+        assert!(lines.iter().all(|line| !line.live));
+    }
+
+    #[test]
+    fn push_segment_keeps_progress_of_interleaved_sources_apart() {
+        let mut lines = LogBuffer::default();
+        for (source, segment) in [
+            (Some(0), Segment::Progress("a 10%".to_string())),
+            (Some(1), Segment::Line("b started".to_string())),
+            (Some(0), Segment::Progress("a 50%".to_string())),
+            (Some(0), Segment::Line("a done".to_string())),
+        ] {
+            if let Some(line) = push_segment(&mut lines, source, segment) {
+                lines.push(line);
+            }
+        }
+
+        let texts: Vec<(Option<usize>, &str)> = lines
+            .iter()
+            .map(|line| (line.source, line.text.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                (Some(0), "a 10%"),
+                (Some(1), "b started"),
+                (Some(0), "a done"),
+            ]
+        );
+    }
+
+    #[test]
+    fn visible_lines_skips_hidden_sources() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.sources = ["api.log", "db.log"]
+            .into_iter()
+            .map(|name| Source::new(name.to_string(), Format::Plain))
+            .collect();
+        app.sources[1].hidden = true;
+        let lines = LogBuffer::from(vec![
+            LogLine::output("request".to_string()).with_source(Some(0)),
+            LogLine::output("query".to_string()).with_source(Some(1)),
+        ]);
+
+        let visible: Vec<&str> = visible_lines(&lines, &app)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(visible, vec!["request"]);
+        assert_eq!(filtered_line_count(&lines, &app), 1);
+    }
+
+    #[test]
+    fn exclude_patterns_hide_lines_and_compose_with_filter() {
+        let mut exclude = build_pattern("health".to_string(), true).expect("pattern build failed");
+        exclude.exclude = true;
+        let patterns = vec![
+            build_pattern("GET".to_string(), true).expect("pattern build failed"),
+            exclude,
+        ];
+        let mut app = AppState::new(patterns, false);
+        let lines = LogBuffer::from(vec![
+            LogLine::output("GET /health".to_string()),
+            LogLine::output("GET /users".to_string()),
+            LogLine::output("POST /users".to_string()),
+            LogLine::run_separator("$ make".to_string()),
+        ]);
+
+        let visible = |app: &AppState| -> Vec<String> {
+            visible_lines(&lines, app)
+                .map(|line| line.text.clone())
+                .collect()
+        };
+        assert_eq!(visible(&app), ["GET /users", "POST /users", "$ make"]);
+        assert_eq!(filtered_line_count(&lines, &app), 3);
+
+        app.filter_only = true;
+        assert_eq!(visible(&app), ["GET /users", "$ make"]);
+        assert_eq!(filtered_line_count(&lines, &app), 2);
+
+        app.patterns[1].enabled = false;
+        assert_eq!(visible(&app), ["GET /health", "GET /users", "$ make"]);
+        app.patterns[0].enabled = false;
+        assert_eq!(visible(&app), ["$ make"]);
+    }
+
+    #[test]
+    fn ingest_separates_days_and_hours() {
+        let ingest_lines = |separators, texts: &[&str]| {
+            let mut app = AppState::new(Vec::new(), false);
+            app.separators = separators;
+            let mut lines = LogBuffer::default();
+            for text in texts {
+                let segment = Segment::Line(text.to_string());
+                ingest(&mut app, &mut lines, InputEvent::Segment(segment));
+            }
+            lines
+                .iter()
+                .map(|line| line.text.clone())
+                .collect::<Vec<_>>()
+        };
+        let texts = [
+            "2026-10-15 23:59:58 a",
+            "no timestamp",
+            "2026-10-16 00:00:01 b",
+            "2026-10-16 01:00:00 c",
+        ];
+
+        assert_eq!(
+            ingest_lines(Separators::Day, &texts),
+            vec![
+                texts[0],
+                texts[1],
+                "──── 2026-10-16 ────",
+                texts[2],
+                texts[3]
+            ]
+        );
+        assert_eq!(
+            ingest_lines(Separators::Hour, &texts)[4..],
+            ["──── 2026-10-16 01:00 ────", texts[3]]
+        );
+        assert_eq!(ingest_lines(Separators::Off, &texts), texts);
+    }
+
+    #[test]
+    fn patterns_averaging_slow_matches_are_flagged() {
+        let cost = MatchCost::default();
+        assert_eq!(cost.slow(), None);
+
+        cost.record(Duration::from_micros(10));
+        assert_eq!(cost.slow(), None);
+        cost.record(Duration::from_micros(290));
+        assert_eq!(cost.slow(), Some(Duration::from_micros(150)));
+    }
+
+    #[test]
+    fn patterns_too_slow_for_a_line_are_disabled() {
+        let mut app = AppState::new(
+            vec![
+                build_pattern("slow".to_string(), true).expect("pattern build failed"),
+                build_pattern("fast".to_string(), true).expect("pattern build failed"),
+            ],
+            false,
+        );
+        let mut lines = LogBuffer::default();
+        push_output(&mut app, &mut lines, LogLine::output("slow".to_string()));
+        assert!(
+            lines[0]
+                .matches
+                .as_ref()
+                .is_some_and(|matches| !matches.is_empty())
+        );
+
+        app.patterns[0].cost.record(Duration::from_secs(1));
+        push_output(&mut app, &mut lines, LogLine::output("fast".to_string()));
+        assert!(!app.patterns[0].enabled);
+        assert!(app.patterns[1].enabled);
+        assert_eq!(
+            app.pattern_error.as_deref(),
+            Some("Disabled slow: matching a line took 1000ms")
+        );
+        assert_eq!(lines[0].matches.as_deref(), Some(&[][..]));
+        assert!(build_regex(r"\w{1000}", true).is_err());
+    }
+
+    #[test]
+    fn output_gets_the_lines_matching_a_pattern() {
+        let mut excluded =
+            build_pattern("debug".to_string(), false).expect("building pattern failed");
+        excluded.exclude = true;
+        let patterns = vec![
+            build_pattern("error".to_string(), false).expect("building pattern failed"),
+            excluded,
+        ];
+        let mut app = AppState::new(patterns, false);
+        let path = std::env::temp_dir().join(format!("logr-output-{}.log", std::process::id()));
+        let file = fs::File::create(&path).expect("creating output failed");
+        app.output = Some(Box::new(io::LineWriter::new(file)));
+        let mut lines = LogBuffer::default();
+        for text in [
+            "error: disk full",
+            "all good",
+            "debug error retry",
+            "another error",
+        ] {
+            push_output(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        app.output = None;
+
+        let written = fs::read_to_string(&path).expect("reading output failed");
+        fs::remove_file(&path).expect("removing output failed");
+        assert_eq!(written, "error: disk full\nanother error\n");
+    }
+
+    #[test]
+    fn alerts_sound_for_the_lines_matching_their_pattern() {
+        let args = Args::try_parse_from([
+            "logr",
+            "-p",
+            "ERROR",
+            "-x",
+            "retry",
+            "--alert",
+            "ERROR=2",
+            "--alert",
+            "deploy finished=complete",
+        ])
+        .expect("parse failed");
+        let mut patterns = build_filters(&args.patterns, &args.exclude, false, false)
+            .expect("building filters failed");
+        add_alerts(&mut patterns, &args.alert, false, false).expect("adding alerts failed");
+        assert_eq!(patterns.len(), 3);
+        assert_eq!(patterns[0].sound, Some(Sound::Bells(2)));
+        assert_eq!(patterns[1].sound, None);
+        assert_eq!(patterns[2].pattern, "deploy finished");
+        assert!(Args::try_parse_from(["logr", "--alert", "ERROR"]).is_err());
+
+        let mut app = AppState::new(patterns, false);
+        let mut lines = LogBuffer::default();
+        for text in ["all good", "ERROR, will retry"] {
+            push_output(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        assert!(!app.chime.pending());
+        push_output(
+            &mut app,
+            &mut lines,
+            LogLine::output("ERROR: disk full".to_string()),
+        );
+        let mut rung = Vec::new();
+        while app.chime.pending() {
+            app.chime.play(&mut rung).expect("ringing failed");
+        }
+        assert_eq!(rung, b"\x07\x07");
+    }
+
+    #[test]
+    fn lines_without_timestamps_are_separated_by_when_they_were_received() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.separators = Separators::Day;
+        let mut lines = LogBuffer::default();
+        let day = Duration::from_secs(86_400);
+        for (text, received) in [("a", day * 20_000), ("b", day * 20_000 + day)] {
+            let mut line = LogLine::output(text.to_string());
+            line.received = SystemTime::UNIX_EPOCH + received;
+            push_output(&mut app, &mut lines, line);
+        }
+        push_output(
+            &mut app,
+            &mut lines,
+            LogLine::output("2026-01-01 c".to_string()),
+        );
+        push_output(&mut app, &mut lines, LogLine::output("d".to_string()));
+
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "a",
+                "──── 2024-10-05 ────",
+                "b",
+                "──── 2026-01-01 ────",
+                "2026-01-01 c",
+                "d"
+            ]
+        );
+    }
+
+    #[test]
+    fn push_segment_marks_file_rotation() {
+        let mut lines = LogBuffer::from(vec![LogLine::progress("50%".to_string())]);
+
+        let marker = push_segment(&mut lines, None, Segment::Rotated).expect("no marker line");
+
+        assert_eq!(marker.kind, LineKind::Marker);
+        assert_eq!(marker.text, "--- file rotated ---");
+        assert!(!lines[0].live);
+    }
+
+    #[test]
+    fn visible_line_index_skips_filtered_lines() {
+        let patterns =
+            vec![build_pattern("error".to_string(), true).expect("pattern build failed")];
+        let mut app = AppState::new(patterns, false);
+        let lines = LogBuffer::from(vec![
+            LogLine::output("all good".to_string()),
+            LogLine::output("error one".to_string()),
+            LogLine::output("still good".to_string()),
+            LogLine::output("error two".to_string()),
+        ]);
+
+        app.filter_only = true;
+        assert_eq!(visible_line_index(&lines, &app, 1), Some(3));
+        assert_eq!(visible_line_index(&lines, &app, 2), None);
+        app.filter_only = false;
+        assert_eq!(visible_line_index(&lines, &app, 1), Some(1));
+    }
+
+    #[test]
+    fn binary_detection_ignores_tabs_and_ansi() {
+        assert!(!LogLine::output("\x1b[31mred\x1b[0m\tok".to_string()).is_binary());
+        assert!(LogLine::output("raw\x00\x01buffer".to_string()).is_binary());
+        assert!(LogLine::output("bad \u{FFFD} byte".to_string()).is_binary());
+    }
+
+    #[test]
+    fn push_line_keeps_view_steady_when_evicting() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::new(NonZeroUsize::new(3));
+        for i in 0..3 {
+            push_line(&mut app, &mut lines, LogLine::output(i.to_string()));
+        }
+        app.follow = false;
+        app.scroll = 2;
+        anchor_scroll(&mut app, &lines);
+        app.detail_line = Some(2);
+        app.search_match = Some(0);
+
+        push_line(&mut app, &mut lines, LogLine::output("3".to_string()));
+        settle_scroll(&mut app, &lines);
+
+        assert_eq!(app.scroll, 1);
+        assert_eq!(app.detail_line, Some(2));
+        assert_eq!(lines.position(2), Some(1));
+        assert_eq!(app.search_match, None);
+        assert_eq!(lines[app.scroll].text, "2");
+        assert_eq!(lines.evicted(), 1);
+    }
+
+    #[test]
+    fn pattern_file_commands_take_the_file_after_patterns() {
+        for command in ["test", "bench"] {
+            let args = Args::try_parse_from(["logr", command, "-p", "error,warn", "app.log"])
+                .expect("parse failed");
+            let Some(Commands::Test(file_args) | Commands::Bench(file_args)) = args.subcommand
+            else {
+                panic!("no {command} subcommand");
+            };
+            assert_eq!(file_args.patterns, vec!["error", "warn"]);
+            assert_eq!(file_args.file, PathBuf::from("app.log"));
+        }
+    }
+
+    #[test]
+    fn grouping_folds_lines_continuing_an_entry() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.group_entries = true;
+        let mut lines = LogBuffer::new(NonZeroUsize::new(4));
+        for text in [
+            "2026-10-16 12:00:00 ERROR request failed",
+            "java.lang.IllegalStateException: closed",
+            "    at Pool.get(Pool.java:42)",
+            "[12:00:01] INFO retrying",
+        ] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+
+        assert_eq!(lines[0].continuations, 2);
+        assert_eq!(lines[2].continues, Some(2));
+        assert_eq!(visible_lines(&lines, &app).count(), 2);
+        assert_eq!(filtered_line_count(&lines, &app), 2);
+        app.group_entries = false;
+        assert_eq!(filtered_line_count(&lines, &app), 4);
+        app.group_entries = true;
+
+        // Evicting the line starting the entry leaves the next one starting what is left of it.
+        push_line(
+            &mut app,
+            &mut lines,
+            LogLine::output("    at Main.run".to_string()),
+        );
+        let texts: Vec<&str> = visible_lines(&lines, &app)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "java.lang.IllegalStateException: closed",
+                "[12:00:01] INFO retrying"
+            ]
+        );
+        assert_eq!(lines[0].continuations, 1);
+        assert_eq!(lines[3].continues, Some(1));
+        assert_eq!(entry_first_line(&lines, 1), 0);
+        assert_eq!(entry_first_line(&lines, 3), 2);
+    }
+
+    #[test]
+    fn snoozed_patterns_match_nothing_until_they_wake_up() {
+        let patterns =
+            vec![build_pattern("error".to_string(), true).expect("pattern build failed")];
+        let mut app = AppState::new(patterns, false);
+        let mut lines = LogBuffer::new(None);
+        let now = Instant::now();
+
+        let snooze = Snooze::next(None, now);
+        assert_eq!(snooze.map(|snooze| snooze.minutes), Some(5));
+        app.patterns[0].snooze = snooze;
+        push_line(
+            &mut app,
+            &mut lines,
+            LogLine::output("error one".to_string()),
+        );
+        assert_eq!(lines[0].matches.as_deref(), Some(&[][..]));
+
+        assert!(!wake_snoozed(
+            &mut app,
+            &mut lines,
+            now + Duration::from_secs(299)
+        ));
+        assert!(wake_snoozed(
+            &mut app,
+            &mut lines,
+            now + Duration::from_secs(300)
+        ));
+        assert_eq!(lines[0].matches.as_ref().map(Vec::len), Some(1));
+
+        let longest = Snooze::next(Snooze::next(snooze, now), now);
+        assert_eq!(
+            longest.map(|snooze| snooze.remaining(now)),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(Snooze::next(longest, now), None);
+    }
 }
@@ -0,0 +1,80 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::level::{Level, LevelToken};
+
+/// A line of `adb logcat -v threadtime` output, capturing its priority and tag, e.g.
+/// `10-16 12:00:01.123  1234  1240 E ActivityManager: ANR in com.example`.
+static THREADTIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d{3}\s+\d+\s+\d+ ([VDIWEFA] [^:]*?)\s*: ")
+        .expect("logcat regex is valid")
+});
+
+/// The command reading the log of the device with `serial` (or the only one connected), only
+/// the lines of `tags` and of the process `pid` if given.
+pub(crate) fn command(serial: Option<&str>, tags: &[String], pid: Option<u32>) -> Vec<String> {
+    let mut command = vec!["adb".to_string()];
+    if let Some(serial) = serial {
+        command.extend(["-s".to_string(), serial.to_string()]);
+    }
+    command.extend(["logcat", "-v", "threadtime"].map(str::to_string));
+    if let Some(pid) = pid {
+        command.push(format!("--pid={pid}"));
+    }
+    if !tags.is_empty() {
+        command.extend(tags.iter().map(|tag| format!("{tag}:V")));
+        command.push("*:S".to_string());
+    }
+    command
+}
+
+/// The priority of a logcat line as its level, spanning the priority letter and the tag so
+/// both take the level's color.
+pub(crate) fn level(line: &str) -> Option<LevelToken> {
+    let token = THREADTIME.captures(line)?.get(1)?;
+    let level = match &token.as_str()[..1] {
+        "V" => Level::Trace,
+        "D" => Level::Debug,
+        "I" => Level::Info,
+        "W" => Level::Warn,
+        "E" => Level::Error,
+        _ => Level::Fatal,
+    };
+    Some(LevelToken {
+        level,
+        start: token.start(),
+        end: token.end(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command, level};
+    use crate::level::Level;
+
+    #[test]
+    fn priority_and_tag_make_the_level_token() {
+        let line = "10-16 12:00:01.123  1234  1240 E ActivityManager: ANR in com.example";
+        let token = level(line).expect("no level");
+        assert_eq!(token.level, Level::Error);
+        assert_eq!(&line[token.start..token.end], "E ActivityManager");
+
+        let line = "10-16 12:00:01.200   987   987 V Choreographer   : Skipped 31 frames";
+        assert_eq!(level(line).map(|token| token.level), Some(Level::Trace));
+        assert_eq!(level("2026-10-16 12:00:01 ERROR not logcat"), None);
+    }
+
+    #[test]
+    fn tags_and_pid_become_logcat_filters() {
+        let tags = ["ActivityManager".to_string(), "MyApp".to_string()];
+        assert_eq!(
+            command(Some("emulator-5554"), &tags, Some(1234)).join(" "),
+            "adb -s emulator-5554 logcat -v threadtime --pid=1234 ActivityManager:V MyApp:V *:S"
+        );
+        assert_eq!(
+            command(None, &[], None).join(" "),
+            "adb logcat -v threadtime"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::ops::Range;
+
+/// A `key=value` pair of a logfmt line, as byte ranges into it. Quoted values keep their
+/// quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pair {
+    pub(crate) key: Range<usize>,
+    pub(crate) value: Range<usize>,
+}
+
+/// Splits a logfmt line like `level=error msg="no route" svc=auth` into its pairs, or returns
+/// `None` if it isn't one: every word has to be a pair, and there have to be at least two so
+/// plain lines with the odd `key=value` in them don't count.
+pub(crate) fn parse(line: &str) -> Option<Vec<Pair>> {
+    let bytes = line.as_bytes();
+    let mut pairs = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == bytes.len() {
+            break;
+        }
+        let key_start = i;
+        while i < bytes.len() && is_key_byte(bytes[i]) {
+            i += 1;
+        }
+        if i == key_start || bytes.get(i) != Some(&b'=') {
+            return None;
+        }
+        let key = key_start..i;
+        i += 1;
+        let value_start = i;
+        if bytes.get(i) == Some(&b'"') {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b'"' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            if i >= bytes.len() {
+                return None;
+            }
+            i += 1;
+        } else {
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'"' {
+                i += 1;
+            }
+        }
+        if i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+            return None;
+        }
+        pairs.push(Pair {
+            key,
+            value: value_start..i,
+        });
+    }
+    (pairs.len() >= 2).then_some(pairs)
+}
+
+fn is_key_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b'/' | b'@')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn parse_splits_pairs_with_quoted_and_empty_values() {
+        let line = r#"level=error msg="no \"route\" found" svc=auth retry="#;
+        let pairs: Vec<(&str, &str)> = parse(line)
+            .expect("line is logfmt")
+            .into_iter()
+            .map(|pair| (&line[pair.key], &line[pair.value]))
+            .collect();
+
+        assert_eq!(
+            pairs,
+            vec![
+                ("level", "error"),
+                ("msg", r#""no \"route\" found""#),
+                ("svc", "auth"),
+                ("retry", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_lines_that_are_not_all_pairs() {
+        assert!(parse("GET /index.html took=3ms").is_none());
+        assert!(parse("level=info").is_none());
+        assert!(parse(r#"level=info msg="unterminated"#).is_none());
+        assert!(parse("level=info msg=a\"b").is_none());
+        assert!(parse("").is_none());
+    }
+}
@@ -1,7 +1,9 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    CompleteEnv::with_factory(logr::Args::command).complete();
     let args = logr::Args::parse();
     Ok(logr::run(args).await?)
 }
@@ -0,0 +1,486 @@
+use clap::ValueEnum;
+
+use crate::event::KEYMAP;
+
+/// The language of the text on screen.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Lang {
+    #[default]
+    En,
+    Pl,
+}
+
+impl Lang {
+    /// The language of the locale environment variables (`LC_ALL`, `LC_MESSAGES`, then
+    /// `LANG`), or English when they name none logr speaks.
+    pub(crate) fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Lang::En, |locale| Self::from_locale(&locale))
+    }
+
+    /// Parses a locale like `pl_PL.UTF-8`, by its language.
+    fn from_locale(locale: &str) -> Self {
+        match locale.split(['_', '.', '@']).next() {
+            Some("pl") => Lang::Pl,
+            _ => Lang::En,
+        }
+    }
+
+    pub(crate) fn messages(self) -> &'static Messages {
+        match self {
+            Lang::En => &EN,
+            Lang::Pl => &PL,
+        }
+    }
+}
+
+/// Keys a hint under the view names, by the names of their bindings in [`KEYMAP`], with what
+/// they do. Keys named together are shown joined with a slash, like `j/k`.
+pub(crate) type Hint = &'static [(&'static [&'static str], &'static str)];
+
+/// The text the UI shows, in one language. Every language has to fill in every message, so
+/// none can be left untranslated.
+pub(crate) struct Messages {
+    pub(crate) hint: Hint,
+    pub(crate) narrow_hint: Hint,
+    pub(crate) help_title: &'static str,
+    /// What each binding of [`KEYMAP`] does, by its name and in its order, for the help overlay.
+    pub(crate) key_help: [(&'static str, &'static str); KEYMAP.len()],
+    pub(crate) patterns_title: &'static str,
+    pub(crate) fields_title: &'static str,
+    pub(crate) no_fields: &'static str,
+    pub(crate) sources_title: &'static str,
+    pub(crate) failures_title: &'static str,
+    pub(crate) no_failures: &'static str,
+    pub(crate) profiles_title: &'static str,
+    pub(crate) no_profiles: &'static str,
+    /// Why the profile couldn't be used.
+    pub(crate) cant_use_profile: fn(&str) -> String,
+    pub(crate) large_buffer_title: &'static str,
+    pub(crate) large_buffer_keep_all: &'static str,
+    pub(crate) reveal_title: &'static str,
+    pub(crate) reveal_warning: &'static str,
+    pub(crate) reveal_confirm: &'static str,
+    pub(crate) no_secrets: &'static str,
+    /// The key masking them again.
+    pub(crate) revealed: fn(&str) -> String,
+    pub(crate) selecting: &'static str,
+    pub(crate) cut_short: &'static str,
+    pub(crate) following: &'static str,
+    pub(crate) not_following: &'static str,
+    /// The line number and the view `x` switches to, hex or not.
+    pub(crate) detail_title: fn(usize, bool) -> String,
+    /// Whether wrap, follow and filter are on and the case is ignored.
+    pub(crate) toggles: fn(bool, bool, bool, bool) -> String,
+    pub(crate) large_buffer_holds: fn(usize, usize) -> String,
+    pub(crate) large_buffer_keep: fn(usize) -> String,
+    pub(crate) large_buffer_spill: fn(usize) -> String,
+    pub(crate) large_buffer_dedup: &'static str,
+    /// The lines kept and the file the older ones go to.
+    pub(crate) spilling: fn(usize, &str) -> String,
+    /// Why the older lines couldn't be written.
+    pub(crate) spill_stopped: fn(&str) -> String,
+    pub(crate) deduplicating: &'static str,
+    /// Times the line came.
+    pub(crate) repeated: fn(usize) -> String,
+    pub(crate) folded: fn(usize) -> String,
+    /// The key grouping entries.
+    pub(crate) not_grouping: fn(&str) -> String,
+    pub(crate) paused: fn(usize) -> String,
+    pub(crate) min_level: fn(&str) -> String,
+    pub(crate) discarded: fn(usize) -> String,
+    /// Minutes and seconds left.
+    pub(crate) snoozed: fn(u64, u64) -> String,
+    /// Microseconds a line.
+    pub(crate) slow: fn(u128) -> String,
+    pub(crate) line_count: fn(&str) -> String,
+    pub(crate) match_count: fn(usize) -> String,
+    pub(crate) failure_count: fn(usize) -> String,
+    pub(crate) new_while_paused: fn(usize) -> String,
+    pub(crate) copied: fn(usize) -> String,
+    pub(crate) not_copying: &'static str,
+    /// The path not written to.
+    pub(crate) not_writing: fn(&str) -> String,
+    pub(crate) keeping: fn(usize) -> String,
+    pub(crate) no_patterns_file: &'static str,
+    pub(crate) reloaded_patterns: &'static str,
+    /// Why the patterns file couldn't be reloaded.
+    pub(crate) cant_reload_patterns: fn(&str) -> String,
+    /// How long no line came, like `7:05`.
+    pub(crate) idle_for: fn(&str) -> String,
+    /// Why the audit log couldn't be written.
+    pub(crate) audit_stopped: fn(&str) -> String,
+}
+
+const EN: Messages = Messages {
+    hint: &[
+        (&["help"], "help"),
+        (&["patterns"], "patterns"),
+        (&["wrap"], "wrap"),
+        (&["filter"], "filter"),
+        (&["down", "up"], "scroll down/up"),
+        (&["page-down", "page-up"], "page down/up"),
+        (&["quit"], "quit"),
+    ],
+    narrow_hint: &[
+        (&["help"], "help"),
+        (&["patterns"], "pat"),
+        (&["wrap"], "wrap"),
+        (&["filter"], "filter"),
+        (&["quit"], "quit"),
+    ],
+    help_title: "Help (w: wrap, any other key: close)",
+    key_help: [
+        ("quit", "Quit"),
+        ("help", "Show this help"),
+        ("undo", "Undo the last change to the patterns or the view"),
+        ("redo", "Redo"),
+        ("rerun", "Rerun the command"),
+        ("previous-run", "Jump to the previous run"),
+        ("next-run", "Jump to the next run"),
+        (
+            "previous-failure",
+            "Jump to the previous failure, with a preset like --preset cargo",
+        ),
+        ("next-failure", "Jump to the next failure"),
+        ("failures", "List the failures"),
+        (
+            "profiles",
+            "Switch to a profile of patterns from the config file",
+        ),
+        (
+            "reload-patterns",
+            "Reload the patterns of the patterns file",
+        ),
+        ("patterns", "Edit the patterns"),
+        ("wrap", "Wrap long lines"),
+        ("line-numbers", "Show line numbers"),
+        ("epochs", "Show epoch times as readable ones"),
+        ("reveal", "Reveal or mask the secrets masked with --redact"),
+        ("sources", "Show or hide sources"),
+        ("fields", "Show or hide JSON fields"),
+        ("search", "Search"),
+        ("next-match", "Jump to the next search match"),
+        ("previous-match", "Jump to the previous search match"),
+        (
+            "prompt",
+            "Run a command: count, csv, write, filter, a line number or a percentage",
+        ),
+        (
+            "write",
+            "Write the shown lines to a file: write [all] [plain] FILE",
+        ),
+        ("detail", "Show the top line in full"),
+        (
+            "select",
+            "Select lines with j/k, copying them to the clipboard with y",
+        ),
+        ("filter", "Show only lines matching the patterns"),
+        (
+            "filter-expression",
+            "Filter by an expression over the patterns: filter (error | warn) & !health",
+        ),
+        ("pause", "Pause or resume the view"),
+        ("group-entries", "Group multi-line entries"),
+        ("fold-entry", "Fold or unfold the top entry"),
+        ("order", "Show the newest lines at the top or bottom"),
+        ("min-level", "Cycle the minimum level shown"),
+        ("json-view", "Cycle how JSON lines are shown"),
+        ("clear", "Clear the lines"),
+        ("up", "Scroll up"),
+        ("down", "Scroll down"),
+        ("page-up", "Scroll up half a page"),
+        ("page-down", "Scroll down half a page"),
+        ("top", "Go to the top"),
+        ("bottom", "Go to the bottom"),
+    ],
+    patterns_title: "Patterns (Enter: add or edit, Del: delete, Space: on/off, Ctrl-s: snooze, Ctrl-t: case, Ctrl-f: literal, c: color, l: line, w: word, Tab: negate, Esc: close)",
+    fields_title: "Fields (Space: show/hide, Esc: close)",
+    no_fields: "No logfmt fields",
+    sources_title: "Sources (Space: show/hide, Left/Right: format, Esc: close)",
+    failures_title: "Failures (Enter: go to, Esc: close)",
+    no_failures: "No failures",
+    profiles_title: "Profiles (Enter: use, Esc: close)",
+    no_profiles: "No profiles in the config file",
+    cant_use_profile: |err| format!("Can't use the profile: {err}"),
+    large_buffer_title: "Large buffer",
+    large_buffer_keep_all: "Esc: keep every line",
+    reveal_title: "Reveal secrets",
+    reveal_warning: "The masked secrets will be shown on screen until masked again. Copies, exports and --output stay masked.",
+    reveal_confirm: "y: reveal, any other key: keep them masked",
+    no_secrets: "No secrets to reveal (--redact)",
+    revealed: |key| format!("Secrets revealed, {key} masks them again"),
+    selecting: "[selecting: j/k extend, y copy, Esc cancel]",
+    cut_short: "[highlighting cut short]",
+    following: "follow",
+    not_following: "paused",
+    detail_title: |line, hex| {
+        let other = if hex { "text" } else { "hex" };
+        format!("Line {line} (x: {other}, w: wrap, Esc: close)")
+    },
+    toggles: |wrap, follow, filter, ignore_case| {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let case = if ignore_case {
+            "insensitive"
+        } else {
+            "sensitive"
+        };
+        format!(
+            "wrap: {} | follow: {} | filter: {} | case: {case}",
+            on_off(wrap),
+            on_off(follow),
+            on_off(filter)
+        )
+    },
+    large_buffer_holds: |lines, mib| format!("The buffer holds {lines} lines, about {mib} MiB."),
+    large_buffer_keep: |lines| {
+        format!("b: keep only the last {lines} lines from now on, like --max-lines")
+    },
+    large_buffer_spill: |lines| {
+        format!("s: keep the last {lines} lines, writing older ones to a file instead")
+    },
+    large_buffer_dedup: "d: collapse a line repeating the one before it into that one",
+    spilling: |lines, path| format!("Keeping the last {lines} lines, older ones in {path}"),
+    spill_stopped: |err| format!("Stopped writing the older lines: {err}"),
+    deduplicating: "Collapsing repeated lines",
+    repeated: |times| format!(" [x{times}]"),
+    folded: |lines| format!(" [+{lines} lines]"),
+    not_grouping: |key| format!("Not grouping entries ({key})"),
+    paused: |received| format!("[paused, {received} new]"),
+    min_level: |level| format!("[level >= {level}]"),
+    discarded: |lines| format!("[{lines} lines discarded]"),
+    snoozed: |minutes, seconds| format!(" (snoozed {minutes}:{seconds:02})"),
+    slow: |micros| format!(" (slow: {micros}µs a line)"),
+    line_count: |count| format!("{count} lines"),
+    match_count: |count| format!("{count} matches"),
+    failure_count: |count| format!("{count} failures"),
+    new_while_paused: |lines| format!("{lines} new lines while paused"),
+    copied: |lines| format!("Copied {lines} lines"),
+    not_copying: "Not copying: read-only (--read-only)",
+    not_writing: |path| format!("Not writing {path}: read-only (--read-only)"),
+    keeping: |lines| format!("Keeping the last {lines} lines"),
+    no_patterns_file: "No patterns file (--patterns-file)",
+    reloaded_patterns: "Reloaded the patterns file",
+    cant_reload_patterns: |err| format!("Can't reload the patterns file: {err}"),
+    idle_for: |elapsed| format!("No new lines for {elapsed}"),
+    audit_stopped: |err| format!("Stopped writing the audit log: {err}"),
+};
+
+const PL: Messages = Messages {
+    hint: &[
+        (&["help"], "pomoc"),
+        (&["patterns"], "wzorce"),
+        (&["wrap"], "zawijanie"),
+        (&["filter"], "filtr"),
+        (&["down", "up"], "w dół/w górę"),
+        (&["page-down", "page-up"], "strona w dół/w górę"),
+        (&["quit"], "wyjście"),
+    ],
+    narrow_hint: &[
+        (&["help"], "pomoc"),
+        (&["patterns"], "wzorce"),
+        (&["wrap"], "zawijanie"),
+        (&["filter"], "filtr"),
+        (&["quit"], "wyjście"),
+    ],
+    help_title: "Pomoc (w: zawijanie, dowolny inny klawisz: zamknij)",
+    key_help: [
+        ("quit", "Wyjdź"),
+        ("help", "Pokaż tę pomoc"),
+        ("undo", "Cofnij ostatnią zmianę wzorców lub widoku"),
+        ("redo", "Ponów"),
+        ("rerun", "Uruchom polecenie ponownie"),
+        ("previous-run", "Przejdź do poprzedniego uruchomienia"),
+        ("next-run", "Przejdź do następnego uruchomienia"),
+        (
+            "previous-failure",
+            "Przejdź do poprzedniego błędu, z presetem jak --preset cargo",
+        ),
+        ("next-failure", "Przejdź do następnego błędu"),
+        ("failures", "Wypisz błędy"),
+        (
+            "profiles",
+            "Przełącz na profil wzorców z pliku konfiguracyjnego",
+        ),
+        ("reload-patterns", "Wczytaj ponownie wzorce z pliku wzorców"),
+        ("patterns", "Edytuj wzorce"),
+        ("wrap", "Zawijaj długie wiersze"),
+        ("line-numbers", "Pokaż numery wierszy"),
+        ("epochs", "Pokaż czasy epoch w czytelnej postaci"),
+        (
+            "reveal",
+            "Odsłoń lub zamaskuj sekrety zamaskowane przez --redact",
+        ),
+        ("sources", "Pokaż lub ukryj źródła"),
+        ("fields", "Pokaż lub ukryj pola JSON"),
+        ("search", "Szukaj"),
+        ("next-match", "Przejdź do następnego wyniku wyszukiwania"),
+        (
+            "previous-match",
+            "Przejdź do poprzedniego wyniku wyszukiwania",
+        ),
+        (
+            "prompt",
+            "Uruchom polecenie: count, csv, write, filter, numer wiersza lub procent",
+        ),
+        (
+            "write",
+            "Zapisz pokazane wiersze do pliku: write [all] [plain] PLIK",
+        ),
+        ("detail", "Pokaż górny wiersz w całości"),
+        (
+            "select",
+            "Zaznacz wiersze przez j/k, kopiując je do schowka przez y",
+        ),
+        ("filter", "Pokazuj tylko wiersze pasujące do wzorców"),
+        (
+            "filter-expression",
+            "Filtruj wyrażeniem nad wzorcami: filter (error | warn) & !health",
+        ),
+        ("pause", "Wstrzymaj lub wznów widok"),
+        ("group-entries", "Grupuj wielowierszowe wpisy"),
+        ("fold-entry", "Zwiń lub rozwiń górny wpis"),
+        ("order", "Pokazuj najnowsze wiersze na górze lub na dole"),
+        ("min-level", "Zmień minimalny pokazywany poziom"),
+        ("json-view", "Zmień sposób pokazywania wierszy JSON"),
+        ("clear", "Wyczyść wiersze"),
+        ("up", "Przewiń w górę"),
+        ("down", "Przewiń w dół"),
+        ("page-up", "Przewiń w górę o pół strony"),
+        ("page-down", "Przewiń w dół o pół strony"),
+        ("top", "Przejdź na górę"),
+        ("bottom", "Przejdź na dół"),
+    ],
+    patterns_title: "Wzorce (Enter: dodaj lub edytuj, Del: usuń, Spacja: wł./wył., Ctrl-s: uśpij, Ctrl-t: wielkość liter, Ctrl-f: dosłownie, c: kolor, l: wiersz, w: słowo, Tab: zaneguj, Esc: zamknij)",
+    fields_title: "Pola (Spacja: pokaż/ukryj, Esc: zamknij)",
+    no_fields: "Brak pól logfmt",
+    sources_title: "Źródła (Spacja: pokaż/ukryj, Lewo/Prawo: format, Esc: zamknij)",
+    failures_title: "Błędy (Enter: przejdź, Esc: zamknij)",
+    no_failures: "Brak błędów",
+    profiles_title: "Profile (Enter: użyj, Esc: zamknij)",
+    no_profiles: "Brak profili w pliku konfiguracyjnym",
+    cant_use_profile: |err| format!("Nie można użyć profilu: {err}"),
+    large_buffer_title: "Duży bufor",
+    large_buffer_keep_all: "Esc: zachowaj wszystkie wiersze",
+    reveal_title: "Odsłoń sekrety",
+    reveal_warning: "Zamaskowane sekrety będą widoczne na ekranie, dopóki nie zostaną ponownie zamaskowane. Kopie, eksporty i --output pozostają zamaskowane.",
+    reveal_confirm: "y: odsłoń, dowolny inny klawisz: pozostaw zamaskowane",
+    no_secrets: "Brak sekretów do odsłonięcia (--redact)",
+    revealed: |key| format!("Sekrety odsłonięte, {key} maskuje je ponownie"),
+    selecting: "[zaznaczanie: j/k rozszerz, y kopiuj, Esc anuluj]",
+    cut_short: "[podświetlanie przerwane]",
+    following: "śledzenie",
+    not_following: "wstrzymano",
+    detail_title: |line, hex| {
+        let other = if hex { "tekst" } else { "hex" };
+        format!("Wiersz {line} (x: {other}, w: zawijanie, Esc: zamknij)")
+    },
+    toggles: |wrap, follow, filter, ignore_case| {
+        let on_off = |on: bool| if on { "wł." } else { "wył." };
+        let case = if ignore_case {
+            "ignorowana"
+        } else {
+            "rozróżniana"
+        };
+        format!(
+            "zawijanie: {} | śledzenie: {} | filtr: {} | wielkość liter: {case}",
+            on_off(wrap),
+            on_off(follow),
+            on_off(filter)
+        )
+    },
+    large_buffer_holds: |lines, mib| {
+        format!(
+            "Bufor zawiera {lines} {}, około {mib} MiB.",
+            polish_lines(lines)
+        )
+    },
+    large_buffer_keep: |lines| {
+        format!(
+            "b: od teraz zachowuj tylko ostatnie {lines} {}, jak --max-lines",
+            polish_lines(lines)
+        )
+    },
+    large_buffer_spill: |lines| {
+        format!(
+            "s: zachowuj ostatnie {lines} {}, zapisując starsze do pliku",
+            polish_lines(lines)
+        )
+    },
+    large_buffer_dedup: "d: zwijaj wiersz powtarzający poprzedni w ten poprzedni",
+    spilling: |lines, path| format!("Zachowywane ostatnie wiersze: {lines}, starsze w {path}"),
+    spill_stopped: |err| format!("Przerwano zapis starszych wierszy: {err}"),
+    deduplicating: "Zwijanie powtórzonych wierszy",
+    repeated: |times| format!(" [x{times}]"),
+    folded: |lines| format!(" [+{lines} {}]", polish_lines(lines)),
+    not_grouping: |key| format!("Wpisy nie są grupowane ({key})"),
+    paused: |received| format!("[wstrzymano, nowe: {received}]"),
+    min_level: |level| format!("[poziom >= {level}]"),
+    discarded: |lines| format!("[odrzucone wiersze: {lines}]"),
+    snoozed: |minutes, seconds| format!(" (uśpiony {minutes}:{seconds:02})"),
+    slow: |micros| format!(" (wolny: {micros}µs na wiersz)"),
+    line_count: |count| format!("wiersze: {count}"),
+    match_count: |count| format!("dopasowania: {count}"),
+    failure_count: |count| format!("błędy: {count}"),
+    new_while_paused: |lines| format!("Nowe wiersze podczas wstrzymania: {lines}"),
+    copied: |lines| format!("Skopiowano {lines} {}", polish_lines(lines)),
+    not_copying: "Nie kopiuję: tylko do odczytu (--read-only)",
+    not_writing: |path| format!("Nie zapisuję {path}: tylko do odczytu (--read-only)"),
+    keeping: |lines| format!("Zachowywane ostatnie wiersze: {lines}"),
+    no_patterns_file: "Brak pliku wzorców (--patterns-file)",
+    reloaded_patterns: "Wczytano ponownie plik wzorców",
+    cant_reload_patterns: |err| format!("Nie można ponownie wczytać pliku wzorców: {err}"),
+    idle_for: |elapsed| format!("Brak nowych wierszy od {elapsed}"),
+    audit_stopped: |err| format!("Przerwano zapis dziennika audytu: {err}"),
+};
+
+/// "Lines" in Polish, which takes one of three forms depending on the count.
+fn polish_lines(count: usize) -> &'static str {
+    match (count % 10, count % 100) {
+        _ if count == 1 => "wiersz",
+        (2..=4, tens) if !(12..=14).contains(&tens) => "wiersze",
+        _ => "wierszy",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lang, polish_lines};
+    use crate::event::KEYMAP;
+
+    #[test]
+    fn locales_pick_the_language_by_its_code() {
+        assert_eq!(Lang::from_locale("pl_PL.UTF-8"), Lang::Pl);
+        assert_eq!(Lang::from_locale("pl"), Lang::Pl);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+        assert_eq!((Lang::Pl.messages().folded)(3), " [+3 wiersze]");
+    }
+
+    #[test]
+    fn key_help_follows_the_keymap() {
+        let names: Vec<&str> = KEYMAP.iter().map(|binding| binding.name).collect();
+        for lang in [Lang::En, Lang::Pl] {
+            let messages = lang.messages();
+            let described: Vec<&str> = messages.key_help.iter().map(|&(name, _)| name).collect();
+            assert_eq!(described, names, "{lang:?}");
+            for (keys, _) in messages.hint.iter().chain(messages.narrow_hint) {
+                assert!(keys.iter().all(|key| names.contains(key)), "{lang:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn polish_lines_follow_the_plural_forms() {
+        let forms: Vec<&str> = [1, 2, 5, 12, 22, 25, 104].map(polish_lines).to_vec();
+        assert_eq!(
+            forms,
+            [
+                "wiersz", "wiersze", "wierszy", "wierszy", "wiersze", "wierszy", "wiersze"
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::json::value_text;
+
+/// How the lines of a source are parsed for display. Patterns, search and the detail popup
+/// keep working on the raw lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Format {
+    /// Lines are shown as they are.
+    #[default]
+    Plain,
+    /// One JSON object per line, shown as timestamp, level and message followed by the
+    /// remaining fields.
+    Json,
+    /// The nginx/Apache combined access log format.
+    Access,
+}
+
+impl Format {
+    pub(crate) const ALL: [Format; 3] = [Format::Plain, Format::Json, Format::Access];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Format::Plain => "plain",
+            Format::Json => "json",
+            Format::Access => "access",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    /// The format after this one, cycling back to the first.
+    pub(crate) fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&format| format == self)
+            .unwrap_or_default();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Renders a raw line for display, or returns `None` to show it as it is (also when it
+    /// isn't in this format).
+    pub(crate) fn render(self, line: &str) -> Option<String> {
+        match self {
+            Format::Plain => None,
+            Format::Json => render_json(line),
+            Format::Access => render_access(line),
+        }
+    }
+
+    /// The timestamp the line carries, as written in it, if it is in this format. Plain lines
+    /// are searched for the first thing looking like a date or time.
+    pub(crate) fn timestamp(self, line: &str) -> Option<String> {
+        match self {
+            Format::Plain => PLAIN_TIMESTAMP
+                .find(line)
+                .map(|timestamp| timestamp.as_str().to_string()),
+            Format::Json => {
+                let Ok(Value::Object(mut fields)) = serde_json::from_str(line) else {
+                    return None;
+                };
+                take_first(&mut fields, TIMESTAMP_KEYS).map(|value| match value {
+                    Value::String(text) => text,
+                    value => value.to_string(),
+                })
+            }
+            Format::Access => Some(ACCESS_LINE.captures(line)?[2].to_string()),
+        }
+    }
+}
+
+const TIMESTAMP_KEYS: &[&str] = &["timestamp", "time", "ts", "@timestamp"];
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity"];
+const MESSAGE_KEYS: &[&str] = &["message", "msg"];
+
+fn render_json(line: &str) -> Option<String> {
+    let Ok(Value::Object(mut fields)) = serde_json::from_str(line) else {
+        return None;
+    };
+    let mut parts = Vec::new();
+    for keys in [TIMESTAMP_KEYS, LEVEL_KEYS, MESSAGE_KEYS] {
+        if let Some(value) = take_first(&mut fields, keys) {
+            parts.push(match value {
+                Value::String(text) => text,
+                value => value.to_string(),
+            });
+        }
+    }
+    for (key, value) in &fields {
+        parts.push(format!("{key}={}", value_text(value)));
+    }
+    Some(parts.join(" "))
+}
+
+fn take_first(fields: &mut Map<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter().find_map(|key| fields.remove(*key))
+}
+
+/// The date and hour a timestamp falls on, for telling when logs cross into a new day or hour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DateHour {
+    /// `YYYY-MM-DD`, or e.g. `Oct 6` for timestamps without a year. Unknown for bare times.
+    pub(crate) date: Option<String>,
+    pub(crate) hour: Option<u32>,
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+static ISO_DATE_HOUR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{4}-\d{2}-\d{2})(?:[T ](\d{2}):)?").expect("ISO date regex is valid")
+});
+static SYSLOG_DATE_HOUR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^([A-Z][a-z]{2}) +(\d{1,2}) (\d{2}):").expect("syslog date regex is valid")
+});
+static ACCESS_DATE_HOUR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{2})/([A-Z][a-z]{2})/(\d{4}):(\d{2}):").expect("access date regex is valid")
+});
+static TIME_HOUR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{2}):\d{2}").expect("time regex is valid"));
+static EPOCH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{9,10}|\d{12,13})(?:\.\d+)?$").expect("epoch regex is valid")
+});
+
+/// Works out the date and hour of a timestamp as returned by [`Format::timestamp`]. Epoch
+/// timestamps (in seconds or milliseconds) are taken as UTC.
+pub(crate) fn date_hour(timestamp: &str) -> Option<DateHour> {
+    let number = |text: &str| text.parse::<u32>().ok();
+    if let Some(captures) = ISO_DATE_HOUR.captures(timestamp) {
+        return Some(DateHour {
+            date: Some(captures[1].to_string()),
+            hour: captures.get(2).and_then(|hour| number(hour.as_str())),
+        });
+    }
+    if let Some(captures) = SYSLOG_DATE_HOUR.captures(timestamp) {
+        return Some(DateHour {
+            date: Some(format!("{} {}", &captures[1], &captures[2])),
+            hour: number(&captures[3]),
+        });
+    }
+    if let Some(captures) = ACCESS_DATE_HOUR.captures(timestamp) {
+        let month = MONTHS.iter().position(|month| *month == &captures[2])? + 1;
+        return Some(DateHour {
+            date: Some(format!("{}-{month:02}-{}", &captures[3], &captures[1])),
+            hour: number(&captures[4]),
+        });
+    }
+    if let Some(captures) = EPOCH.captures(timestamp) {
+        let mut seconds: i64 = captures[1].parse().ok()?;
+        if captures[1].len() > 10 {
+            seconds /= 1000;
+        }
+        return Some(epoch_date_hour(seconds));
+    }
+    TIME_HOUR.captures(timestamp).map(|captures| DateHour {
+        date: None,
+        hour: number(&captures[1]),
+    })
+}
+
+/// The date and hour, in UTC, of a time in seconds since the Unix epoch.
+pub(crate) fn epoch_date_hour(seconds: i64) -> DateHour {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    DateHour {
+        date: Some(format!("{year:04}-{month:02}-{day:02}")),
+        hour: u32::try_from(seconds.rem_euclid(86_400) / 3600).ok(),
+    }
+}
+
+/// An ISO 8601 timestamp, in UTC to the microsecond, of a time in microseconds since the Unix
+/// epoch.
+pub(crate) fn epoch_timestamp(micros: i64) -> String {
+    let seconds = micros.div_euclid(1_000_000);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second_of_day = seconds.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60,
+        micros.rem_euclid(1_000_000)
+    )
+}
+
+/// Converts days since the Unix epoch to a (proleptic Gregorian) year, month and day.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// ISO 8601 dates and times, syslog dates and bare times of day.
+static PLAIN_TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\b\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?)?\b",
+        r"|\b[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}\b",
+        r"|\b\d{2}:\d{2}:\d{2}(?:[.,]\d+)?\b",
+    ))
+    .expect("timestamp regex is valid")
+});
+
+static ACCESS_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(\S+) \S+ \S+ \[([^\]]+)\] "([^"]*)" (\d{3}) (\d+|-)(?: "([^"]*)" "([^"]*)")?"#)
+        .expect("access log regex is valid")
+});
+
+fn render_access(line: &str) -> Option<String> {
+    let captures = ACCESS_LINE.captures(line)?;
+    let mut rendered = format!(
+        "{} {} {} {}B {}",
+        &captures[2], &captures[4], &captures[3], &captures[5], &captures[1]
+    );
+    if let Some(agent) = captures.get(7) {
+        rendered.push_str(&format!(" ua=\"{}\"", agent.as_str()));
+    }
+    Some(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DateHour, Format, date_hour};
+
+    #[test]
+    fn json_lines_show_timestamp_level_and_message_first() {
+        let line =
+            r#"{"msg":"request done","level":"info","ts":"12:00:01","status":200,"path":"/a b"}"#;
+
+        assert_eq!(
+            Format::Json.render(line).as_deref(),
+            Some(r#"12:00:01 info request done path="/a b" status=200"#)
+        );
+        assert_eq!(Format::Json.render("not json"), None);
+        assert_eq!(Format::Plain.render(line), None);
+    }
+
+    #[test]
+    fn access_lines_show_time_status_and_request() {
+        let line = r#"10.0.0.1 - - [10/Oct/2026:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326 "-" "curl/8.5.0""#;
+
+        assert_eq!(
+            Format::Access.render(line).as_deref(),
+            Some(
+                r#"10/Oct/2026:13:55:36 +0000 200 GET /index.html HTTP/1.1 2326B 10.0.0.1 ua="curl/8.5.0""#
+            )
+        );
+        assert_eq!(Format::Access.render("plain text"), None);
+        assert_eq!(
+            Format::Access.timestamp(line).as_deref(),
+            Some("10/Oct/2026:13:55:36 +0000")
+        );
+    }
+
+    #[test]
+    fn json_timestamps_are_taken_from_the_first_timestamp_key() {
+        let line = r#"{"time":1760000000,"msg":"started"}"#;
+
+        assert_eq!(Format::Json.timestamp(line).as_deref(), Some("1760000000"));
+        assert_eq!(Format::Json.timestamp(r#"{"msg":"started"}"#), None);
+        assert_eq!(Format::Plain.timestamp(line), None);
+    }
+
+    #[test]
+    fn plain_timestamps_are_found_anywhere_in_the_line() {
+        let timestamp = |line| Format::Plain.timestamp(line);
+
+        assert_eq!(
+            timestamp("[2026-10-16T12:00:01.123Z] INFO started").as_deref(),
+            Some("2026-10-16T12:00:01.123Z")
+        );
+        assert_eq!(
+            timestamp("Oct  6 09:15:02 host sshd[42]: accepted").as_deref(),
+            Some("Oct  6 09:15:02")
+        );
+        assert_eq!(
+            timestamp("worker 3 done at 23:59:59").as_deref(),
+            Some("23:59:59")
+        );
+        assert_eq!(timestamp("no time here, 12345"), None);
+    }
+
+    #[test]
+    fn formats_cycle_and_round_trip_names() {
+        for format in Format::ALL {
+            assert_eq!(Format::from_name(format.name()), Some(format));
+        }
+        assert_eq!(Format::Plain.next(), Format::Json);
+        assert_eq!(Format::Access.next(), Format::Plain);
+    }
+
+    #[test]
+    fn date_hour_understands_common_timestamps() {
+        let date_hour =
+            |timestamp| date_hour(timestamp).map(|DateHour { date, hour }| (date, hour));
+        let expected = |date: &str, hour| Some((Some(date.to_string()), Some(hour)));
+
+        assert_eq!(
+            date_hour("2026-10-16T23:59:59Z"),
+            expected("2026-10-16", 23)
+        );
+        assert_eq!(date_hour("Oct  6 09:15:02"), expected("Oct 6", 9));
+        assert_eq!(
+            date_hour("10/Oct/2026:13:55:36 +0000"),
+            expected("2026-10-10", 13)
+        );
+        assert_eq!(date_hour("1760000000"), expected("2025-10-09", 8));
+        assert_eq!(date_hour("1760000000123"), expected("2025-10-09", 8));
+        assert_eq!(date_hour("07:30:00"), Some((None, Some(7))));
+        assert_eq!(date_hour("soon"), None);
+    }
+}
@@ -0,0 +1,131 @@
+use std::{fs, path::Path};
+
+use ratatui::style::{Color, Style};
+
+use crate::{LogrError, PatternSpec, build_pattern, parse_style, split_style};
+
+/// A line of a patterns file, split into its parts.
+#[derive(Debug, PartialEq)]
+struct Entry<'a> {
+    name: Option<&'a str>,
+    regex: &'a str,
+    color: Option<Color>,
+    style: Style,
+    whole_line: bool,
+}
+
+/// Loads the patterns of a file, for teams to share curated sets of them. Each line is a
+/// pattern like those given with `--patterns`, a regex with an optional style after its last
+/// `:`, or `NAME:STYLE:REGEX` naming it, the name or style left empty if not wanted. Blank
+/// lines and lines starting with `#` are skipped.
+pub(crate) fn load(path: &Path, ignore_case: bool) -> Result<Vec<PatternSpec>, LogrError> {
+    let text = fs::read_to_string(path)?;
+    let mut patterns = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = parse_line(line);
+        let mut pattern = build_pattern(entry.regex.to_string(), !ignore_case).map_err(|err| {
+            LogrError::PatternsFileError(format!("{} line {}: {err}", path.display(), number + 1))
+        })?;
+        pattern.name = entry.name.map(str::to_string);
+        pattern.color = entry.color;
+        pattern.style = entry.style;
+        pattern.whole_line = entry.whole_line;
+        pattern.from_file = true;
+        patterns.push(pattern);
+    }
+    Ok(patterns)
+}
+
+/// Takes a line for `NAME:STYLE:REGEX` when it starts with a name of word characters and
+/// dashes and a valid or empty style, and for a pattern as given with `--patterns` otherwise,
+/// so a regex like `\d{2}:\d{2}:\d{2}` is taken as it is.
+fn parse_line(line: &str) -> Entry<'_> {
+    let mut parts = line.splitn(3, ':');
+    if let (Some(name), Some(spec), Some(regex)) = (parts.next(), parts.next(), parts.next())
+        && !regex.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        && let Some((color, style, whole_line)) = match spec {
+            "" => Some((None, Style::default(), false)),
+            spec => parse_style(spec),
+        }
+    {
+        return Entry {
+            name: (!name.is_empty()).then_some(name),
+            regex,
+            color,
+            style,
+            whole_line,
+        };
+    }
+    let (regex, color, style, whole_line) = split_style(line);
+    Entry {
+        name: None,
+        regex,
+        color,
+        style,
+        whole_line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ratatui::style::{Color, Modifier, Style};
+
+    use super::{Entry, load, parse_line};
+    use crate::LogrError;
+
+    #[test]
+    fn lines_are_named_patterns_or_patterns_as_given() {
+        assert_eq!(
+            parse_line("disk-full:red+bold:No space left"),
+            Entry {
+                name: Some("disk-full"),
+                regex: "No space left",
+                color: Some(Color::Red),
+                style: Style::default().add_modifier(Modifier::BOLD),
+                whole_line: false,
+            }
+        );
+        let entry = parse_line("oom::Out of memory: .*");
+        assert_eq!(
+            (entry.name, entry.regex),
+            (Some("oom"), "Out of memory: .*")
+        );
+        let entry = parse_line(":line:panicked");
+        assert_eq!(
+            (entry.name, entry.regex, entry.whole_line),
+            (None, "panicked", true)
+        );
+        let entry = parse_line("timeout:#ff8800");
+        assert_eq!((entry.name, entry.regex), (None, "timeout"));
+        assert_eq!(parse_line(r"\d{2}:\d{2}:\d{2}").regex, r"\d{2}:\d{2}:\d{2}");
+    }
+
+    #[test]
+    fn files_skip_comments_and_report_the_line_of_an_invalid_regex() {
+        let path = std::env::temp_dir().join(format!("logr-patterns-{}.txt", std::process::id()));
+        fs::write(&path, "# errors\nerr:red:ERROR\n\n  timed out  \n").expect("write failed");
+        let patterns = load(&path, false).expect("load failed");
+        let loaded: Vec<(Option<&str>, &str)> = patterns
+            .iter()
+            .map(|pattern| (pattern.name.as_deref(), pattern.pattern.as_str()))
+            .collect();
+        assert_eq!(loaded, [(Some("err"), "ERROR"), (None, "timed out")]);
+        assert!(patterns.iter().all(|pattern| pattern.from_file));
+
+        fs::write(&path, "ok\nbroken::(\n").expect("write failed");
+        let Err(LogrError::PatternsFileError(message)) = load(&path, false) else {
+            panic!("invalid regex loaded");
+        };
+        assert!(message.contains("line 2"), "{message}");
+        fs::remove_file(&path).expect("remove failed");
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+use crate::{
+    LogrError, MatchCost, PatternSpec, build_spec, parse_style, pattern_regex, set_word,
+    sound::Sound,
+};
+
+/// What the lines a pattern matches do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternMode {
+    /// Shown, with the matches highlighted.
+    #[default]
+    Highlight,
+    /// Hidden.
+    Exclude,
+}
+
+/// What the style of a pattern's matches colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternScope {
+    /// The matched text, or what its capture groups capture.
+    #[default]
+    Match,
+    /// The whole line.
+    Line,
+}
+
+/// A pattern as written in the config file, e.g.
+///
+/// ```toml
+/// pattern = "disk full"
+/// name = "disk"
+/// literal = true
+/// style = "white+on-red+bold"
+/// scope = "line"
+/// ```
+///
+/// The data of a [`PatternSpec`], which compiles its regex from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PatternDef {
+    pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default)]
+    mode: PatternMode,
+    /// Left out, as `--ignore-case` has it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    case_sensitive: Option<bool>,
+    #[serde(default)]
+    literal: bool,
+    #[serde(default)]
+    word: bool,
+    #[serde(default = "enabled")]
+    enabled: bool,
+    #[serde(default)]
+    scope: PatternScope,
+    /// Like the style given after a pattern with `--patterns`, e.g. `white+on-red+bold`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    style: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sound: Option<Sound>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Shown like the patterns dialog shows patterns, excluding ones after a `!`.
+impl fmt::Display for PatternDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            PatternMode::Highlight => f.write_str(&self.pattern),
+            PatternMode::Exclude => write!(f, "!{}", self.pattern),
+        }
+    }
+}
+
+impl PatternDef {
+    /// Compiles the pattern, case-insensitive with `ignore_case` unless it says otherwise.
+    pub(crate) fn build(self, ignore_case: bool) -> Result<PatternSpec, LogrError> {
+        let (color, style, _) = match &self.style {
+            Some(spec) => parse_style(spec).ok_or_else(|| LogrError::StyleError(spec.clone()))?,
+            None => (None, Style::default(), false),
+        };
+        let case_sensitive = self.case_sensitive.unwrap_or(!ignore_case);
+        let mut spec = build_spec(self.pattern, case_sensitive, self.literal)?;
+        if self.word {
+            set_word(&mut spec, true)?;
+        }
+        spec.name = self.name;
+        spec.exclude = self.mode == PatternMode::Exclude;
+        spec.enabled = self.enabled;
+        spec.whole_line = self.scope == PatternScope::Line;
+        spec.color = color;
+        spec.style = style;
+        spec.sound = self.sound;
+        Ok(spec)
+    }
+}
+
+impl PatternSpec {
+    /// A regex pattern, matched case-sensitively, highlighting its matches in the color of its
+    /// place among the patterns.
+    pub fn new(pattern: impl Into<String>) -> Result<Self, LogrError> {
+        build_spec(pattern.into(), true, false)
+    }
+
+    /// A pattern matching `text` as it is rather than as a regex.
+    pub fn literal(text: impl Into<String>) -> Result<Self, LogrError> {
+        build_spec(text.into(), true, true)
+    }
+
+    /// Matches case-insensitively, rebuilding the regex and resetting the cost of matching it.
+    pub fn ignore_case(mut self) -> Result<Self, LogrError> {
+        self.regex = pattern_regex(&self.pattern, false, self.literal, self.word)?;
+        self.case_sensitive = false;
+        self.cost = MatchCost::default();
+        Ok(self)
+    }
+
+    /// Matches whole words only.
+    pub fn whole_words(mut self) -> Result<Self, LogrError> {
+        set_word(&mut self, true)?;
+        Ok(self)
+    }
+
+    /// Whether the pattern highlights the lines it matches or hides them.
+    #[must_use]
+    pub fn with_mode(mut self, mode: PatternMode) -> Self {
+        self.exclude = mode == PatternMode::Exclude;
+        self
+    }
+
+    /// Whether a match colors only the matched text or the whole line.
+    #[must_use]
+    pub fn with_scope(mut self, scope: PatternScope) -> Self {
+        self.whole_line = scope == PatternScope::Line;
+        self
+    }
+
+    /// The color of the matches, instead of the palette's color of the pattern's place.
+    #[must_use]
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// The background and modifiers of the matches, over their color.
+    #[must_use]
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Shown, and referred to in filter expressions, besides the text.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Disabled patterns keep their place and color but match nothing.
+    #[must_use]
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// The text of the pattern, a regex unless it is literal.
+    pub fn text(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether the pattern highlights or hides the lines it matches.
+    pub fn mode(&self) -> PatternMode {
+        if self.exclude {
+            PatternMode::Exclude
+        } else {
+            PatternMode::Highlight
+        }
+    }
+
+    /// Whether a match colors the matched text or the whole line.
+    pub fn scope(&self) -> PatternScope {
+        if self.whole_line {
+            PatternScope::Line
+        } else {
+            PatternScope::Match
+        }
+    }
+
+    pub(crate) fn definition(&self) -> PatternDef {
+        PatternDef {
+            pattern: self.pattern.clone(),
+            name: self.name.clone(),
+            mode: self.mode(),
+            case_sensitive: Some(self.case_sensitive),
+            literal: self.literal,
+            word: self.word,
+            enabled: self.enabled,
+            scope: self.scope(),
+            style: style_spec(self.color, self.style),
+            sound: self.sound.clone(),
+        }
+    }
+}
+
+impl Serialize for PatternSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.definition().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PatternSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PatternDef::deserialize(deserializer)?
+            .build(false)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// A style as written after a pattern, the inverse of [`parse_style`] but for `line`.
+fn style_spec(color: Option<Color>, style: Style) -> Option<String> {
+    let mut items: Vec<String> = color.map(color_name).into_iter().collect();
+    items.extend(style.bg.map(|bg| format!("on-{}", color_name(bg))));
+    for (modifier, name) in [
+        (Modifier::BOLD, "bold"),
+        (Modifier::UNDERLINED, "underline"),
+        (Modifier::REVERSED, "reverse"),
+    ] {
+        if style.add_modifier.contains(modifier) {
+            items.push(name.to_string());
+        }
+    }
+    (!items.is_empty()).then(|| items.join("+"))
+}
+
+fn color_name(color: Color) -> String {
+    color.to_string().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Color, Modifier, Style};
+
+    use super::{PatternScope, PatternSpec};
+
+    #[test]
+    fn patterns_round_trip_through_their_definitions() {
+        let spec = PatternSpec::literal("a.b")
+            .and_then(PatternSpec::ignore_case)
+            .expect("build failed")
+            .with_name("dots")
+            .with_color(Color::LightRed)
+            .with_style(
+                Style::default()
+                    .bg(Color::Rgb(0, 0, 128))
+                    .add_modifier(Modifier::BOLD),
+            )
+            .with_scope(PatternScope::Line);
+        let text = toml::to_string(&spec).expect("serialize failed");
+        assert!(
+            text.contains(r##"style = "lightred+on-#000080+bold""##),
+            "{text}"
+        );
+
+        let read: PatternSpec = toml::from_str(&text).expect("deserialize failed");
+        assert_eq!(read.definition(), spec.definition());
+        assert!(read.regex.is_match("A.B") && !read.regex.is_match("axb"));
+        assert!(read.whole_line);
+
+        assert!(toml::from_str::<PatternSpec>(r#"pattern = "(""#).is_err());
+        assert!(toml::from_str::<PatternSpec>("pattern = \"x\"\nstyle = \"plaid\"").is_err());
+        assert!(toml::from_str::<PatternSpec>("pattern = \"x\"\ncolour = \"red\"").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use regex::{Captures, Regex};
+
+use crate::{LogrError, PatternFileArgs, PatternSpec, build_patterns};
+
+#[derive(Default, Clone)]
+struct PatternStats {
+    lines: usize,
+    matches: usize,
+    elapsed: Duration,
+}
+
+pub(crate) fn run(args: &PatternFileArgs) -> Result<(), LogrError> {
+    let patterns = build_patterns(&args.patterns, args.ignore_case)?;
+    let text = String::from_utf8_lossy(&fs::read(&args.file)?).into_owned();
+    test_patterns(&text, &patterns, &mut io::stdout().lock())?;
+    Ok(())
+}
+
+/// Prints every matching line with its matches and capture groups, followed by per-pattern
+/// line counts, match counts and time spent matching.
+fn test_patterns(text: &str, patterns: &[PatternSpec], out: &mut impl Write) -> io::Result<()> {
+    let mut stats = vec![PatternStats::default(); patterns.len()];
+    for (number, line) in text.lines().enumerate() {
+        let mut details = Vec::new();
+        for (pattern, stats) in patterns.iter().zip(stats.iter_mut()) {
+            let started = Instant::now();
+            let captures: Vec<Captures> = pattern.regex.captures_iter(line).collect();
+            stats.elapsed += started.elapsed();
+            if captures.is_empty() {
+                continue;
+            }
+            stats.lines += 1;
+            stats.matches += captures.len();
+            for captures in &captures {
+                details.push(format!(
+                    "  [{}] {}",
+                    pattern.pattern,
+                    describe_captures(&pattern.regex, captures)
+                ));
+            }
+        }
+
+        if !details.is_empty() {
+            writeln!(out, "{}: {line}", number + 1)?;
+            for detail in details {
+                writeln!(out, "{detail}")?;
+            }
+        }
+    }
+
+    writeln!(out)?;
+    for (pattern, stats) in patterns.iter().zip(&stats) {
+        writeln!(
+            out,
+            "{}: {} lines, {} matches, {:.2?}",
+            pattern.pattern, stats.lines, stats.matches, stats.elapsed
+        )?;
+    }
+    Ok(())
+}
+
+fn describe_captures(regex: &Regex, captures: &Captures) -> String {
+    let mut description = format!("{:?}", &captures[0]);
+    for (index, name) in regex.capture_names().enumerate().skip(1) {
+        if let Some(group) = captures.get(index) {
+            let label = name.map_or_else(|| format!("${index}"), str::to_string);
+            description.push_str(&format!(" {label}={:?}", group.as_str()));
+        }
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_patterns;
+    use crate::build_patterns;
+
+    #[test]
+    fn prints_matching_lines_with_capture_groups() {
+        let patterns = build_patterns(
+            &[
+                r"user=(?<user>\w+)".to_string(),
+                r"took (\d+)ms".to_string(),
+            ],
+            false,
+        )
+        .expect("pattern build failed");
+        let text = "login user=alice\nnothing here\nquery took 12ms user=bob\n";
+
+        let mut out = Vec::new();
+        test_patterns(text, &patterns, &mut out).expect("write failed");
+        let out = String::from_utf8(out).expect("output is not UTF-8");
+        let mut lines = out.lines();
+
+        assert_eq!(lines.next(), Some("1: login user=alice"));
+        assert_eq!(
+            lines.next(),
+            Some(r#"  [user=(?<user>\w+)] "user=alice" user="alice""#)
+        );
+        assert_eq!(lines.next(), Some("3: query took 12ms user=bob"));
+        assert_eq!(
+            lines.next(),
+            Some(r#"  [user=(?<user>\w+)] "user=bob" user="bob""#)
+        );
+        assert_eq!(
+            lines.next(),
+            Some(r#"  [took (\d+)ms] "took 12ms" $1="12""#)
+        );
+        assert_eq!(lines.next(), Some(""));
+        assert!(
+            lines
+                .next()
+                .is_some_and(|line| line.starts_with(r"user=(?<user>\w+): 2 lines, 2 matches"))
+        );
+        assert!(
+            lines
+                .next()
+                .is_some_and(|line| line.starts_with(r"took (\d+)ms: 1 lines, 1 matches"))
+        );
+    }
+}
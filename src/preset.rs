@@ -0,0 +1,209 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use regex::Regex;
+
+/// A tool whose output logr has a preset for.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Tool {
+    /// `cargo build` and `cargo test`.
+    Cargo,
+    Pytest,
+    Jest,
+    /// `go test`.
+    Go,
+}
+
+impl Tool {
+    /// Recognizes the tool a command runs, like `cargo test` or `python -m pytest`.
+    pub(crate) fn detect(command: &[String]) -> Option<Self> {
+        let program = Path::new(command.first()?).file_name()?.to_str()?;
+        let args: Vec<&str> = command[1..].iter().map(String::as_str).collect();
+        match (program, args.as_slice()) {
+            ("cargo", ["build" | "check" | "clippy" | "test" | "nextest", ..]) => Some(Tool::Cargo),
+            ("pytest" | "py.test", _) => Some(Tool::Pytest),
+            ("python" | "python3", ["-m", "pytest", ..]) => Some(Tool::Pytest),
+            ("jest", _) => Some(Tool::Jest),
+            ("npx", ["jest", ..]) => Some(Tool::Jest),
+            ("go", ["test", ..]) => Some(Tool::Go),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn preset(self) -> &'static Preset {
+        match self {
+            Tool::Cargo => &CARGO,
+            Tool::Pytest => &PYTEST,
+            Tool::Jest => &JEST,
+            Tool::Go => &GO,
+        }
+    }
+}
+
+/// Patterns for the output of a tool, along with which of its lines report a passed or failed
+/// test (or build).
+pub(crate) struct Preset {
+    pub(crate) patterns: &'static [&'static str],
+    /// Lines reporting a failure, counted in the status bar, listed by `!` and jumped between
+    /// with `{`/`}`.
+    pub(crate) failed: &'static str,
+    pub(crate) passed: &'static str,
+}
+
+/// Compiler errors and warnings, and failed tests.
+const CARGO: Preset = Preset {
+    patterns: &[
+        r"^error(\[E\d{4}\])?:",
+        r"^warning:",
+        r"^test .+ \.\.\. FAILED$",
+    ],
+    failed: r"^(error(\[E\d{4}\])?:|test .+ \.\.\. FAILED$)",
+    passed: r"^test .+ \.\.\. ok$",
+};
+
+/// Failures are told by the short test summary, which pytest prints with or without `-v`.
+const PYTEST: Preset = Preset {
+    patterns: &[r"\bFAILED\b", r"\bERROR\b", r"^E .*", r"\bPASSED\b"],
+    failed: r"^(FAILED|ERROR) \S+",
+    passed: r"\sPASSED\b",
+};
+
+const JEST: Preset = Preset {
+    patterns: &[r"^\s*FAIL\b", r"^\s*PASS\b", r"[✕×] .*", r"● .*"],
+    failed: r"^\s*[✕×] ",
+    passed: r"^\s*[✓√] ",
+};
+
+const GO: Preset = Preset {
+    patterns: &[
+        r"--- FAIL: \S+",
+        r"--- PASS: \S+",
+        r"^FAIL\b",
+        r"^ok\b",
+        r"^panic:",
+    ],
+    failed: r"^\s*--- FAIL: ",
+    passed: r"^\s*--- PASS: ",
+};
+
+impl Preset {
+    pub(crate) fn patterns(&self) -> Vec<String> {
+        self.patterns
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect()
+    }
+
+    pub(crate) fn outcomes(&self) -> Outcomes {
+        Outcomes {
+            failed: Regex::new(self.failed).expect("preset failure pattern is valid"),
+            passed: Regex::new(self.passed).expect("preset pass pattern is valid"),
+        }
+    }
+}
+
+/// Whether a line reports a passed or a failed test (or build).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Passed,
+    Failed,
+}
+
+/// The compiled failure and pass patterns of a preset.
+pub(crate) struct Outcomes {
+    failed: Regex,
+    passed: Regex,
+}
+
+impl Outcomes {
+    pub(crate) fn outcome(&self, text: &str) -> Option<Outcome> {
+        if self.failed.is_match(text) {
+            Some(Outcome::Failed)
+        } else if self.passed.is_match(text) {
+            Some(Outcome::Passed)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outcome, Tool};
+    use crate::{build_patterns, line_matches_patterns};
+
+    #[test]
+    fn cargo_preset_matches_errors_warnings_and_failed_tests() {
+        let preset = Tool::Cargo.preset();
+        let patterns = build_patterns(&preset.patterns(), false).expect("building patterns failed");
+        let outcomes = preset.outcomes();
+        for (line, matched, outcome) in [
+            (
+                "error[E0308]: mismatched types",
+                true,
+                Some(Outcome::Failed),
+            ),
+            (
+                "error: could not compile `logr`",
+                true,
+                Some(Outcome::Failed),
+            ),
+            ("warning: unused variable: `x`", true, None),
+            (
+                "test ui::tests::renders ... FAILED",
+                true,
+                Some(Outcome::Failed),
+            ),
+            (
+                "test ui::tests::renders ... ok",
+                false,
+                Some(Outcome::Passed),
+            ),
+            ("   Compiling logr v0.1.0", false, None),
+        ] {
+            assert_eq!(line_matches_patterns(line, &patterns), matched, "{line}");
+            assert_eq!(outcomes.outcome(line), outcome, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_runner_presets_tell_failures_from_passes() {
+        for (tool, failed, passed) in [
+            (
+                Tool::Pytest,
+                "FAILED tests/test_api.py::test_login - AssertionError",
+                "tests/test_api.py::test_logout PASSED                [ 50%]",
+            ),
+            (
+                Tool::Jest,
+                "    ✕ adds numbers (5 ms)",
+                "    ✓ subtracts numbers (1 ms)",
+            ),
+            (
+                Tool::Go,
+                "--- FAIL: TestParse (0.00s)",
+                "    --- PASS: TestParse/empty (0.00s)",
+            ),
+        ] {
+            let outcomes = tool.preset().outcomes();
+            assert_eq!(outcomes.outcome(failed), Some(Outcome::Failed), "{failed}");
+            assert_eq!(outcomes.outcome(passed), Some(Outcome::Passed), "{passed}");
+            assert!(build_patterns(&tool.preset().patterns(), false).is_ok());
+        }
+    }
+
+    #[test]
+    fn tools_are_detected_from_the_command() {
+        let detect = |command: &str| {
+            let command: Vec<String> = command.split(' ').map(str::to_string).collect();
+            Tool::detect(&command)
+        };
+        assert_eq!(detect("cargo test --workspace"), Some(Tool::Cargo));
+        assert_eq!(detect("/usr/bin/python3 -m pytest -x"), Some(Tool::Pytest));
+        assert_eq!(detect("npx jest --watch"), Some(Tool::Jest));
+        assert_eq!(detect("go test ./..."), Some(Tool::Go));
+        assert_eq!(detect("cargo run"), None);
+        assert_eq!(detect("go build"), None);
+        assert_eq!(detect("tail -f app.log"), None);
+    }
+}
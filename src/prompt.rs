@@ -0,0 +1,349 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use regex::Regex;
+
+use crate::{
+    AppState, LineKind, LogLine,
+    buffer::LogBuffer,
+    build_regex,
+    export::{Columns, write_csv},
+    line_visible,
+    redact::redact,
+    ui::plain_text,
+    visible_lines,
+};
+
+/// A command entered at the `:` prompt.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum PromptCommand {
+    /// `count REGEX`: counts the buffered lines matching the regex.
+    Count(String),
+    /// `csv FILE REGEX` or `csv FILE json:FIELD,...`: exports the capture groups of the regex,
+    /// or the fields of JSON lines, to a CSV file with a row per buffered line they are in.
+    Csv { path: String, columns: CsvColumns },
+    /// `write [all] [plain] FILE`: writes the shown lines, or with `all` every buffered one, to
+    /// a file as they came in, or with `plain` without their escape sequences.
+    Write {
+        path: String,
+        all: bool,
+        plain: bool,
+    },
+}
+
+/// The columns of a `csv` command, before the regex is built.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum CsvColumns {
+    Captures(String),
+    Fields(Vec<String>),
+}
+
+/// Where a `:1234` or `:50%` command moves the view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GoTo {
+    /// The line with this number, as shown in the gutter.
+    Line(usize),
+    /// This far through the visible lines, in percent.
+    Percent(usize),
+}
+
+/// Parses a go-to command (without the leading `:`), or returns `None` if the input isn't one.
+/// Unlike the other commands it moves the view, so it's run by the prompt itself.
+pub(crate) fn parse_go_to(input: &str) -> Option<GoTo> {
+    let input = input.trim();
+    match input.strip_suffix('%') {
+        Some(percent) => percent
+            .parse::<usize>()
+            .ok()
+            .map(|percent| GoTo::Percent(percent.min(100))),
+        None => input.parse().ok().map(GoTo::Line),
+    }
+}
+
+/// Parses a `filter EXPRESSION` command, returning the expression, or `None` within for a bare
+/// `filter` going back to filtering by any pattern. Like going to a line, it changes the view,
+/// so it's run by the prompt itself.
+pub(crate) fn parse_filter(input: &str) -> Option<Option<String>> {
+    let input = input.trim();
+    let expression = input.strip_prefix("filter")?;
+    if expression.is_empty() {
+        return Some(None);
+    }
+    expression
+        .starts_with(char::is_whitespace)
+        .then(|| Some(expression.trim_start().to_string()))
+}
+
+/// Parses a command entered at the prompt (without the leading `:`).
+pub(crate) fn parse_command(input: &str) -> Result<PromptCommand, String> {
+    let input = input.trim_start();
+    let (name, argument) = input.split_once(' ').unwrap_or((input, ""));
+    match name {
+        "count" if !argument.is_empty() => Ok(PromptCommand::Count(argument.to_string())),
+        "count" => Err("Usage: count REGEX".to_string()),
+        "csv" => match argument.split_once(' ') {
+            Some((path, spec)) if !spec.is_empty() => {
+                let columns = match spec.strip_prefix("json:") {
+                    Some(fields) => CsvColumns::Fields(
+                        fields
+                            .split(',')
+                            .map(|field| field.trim().to_string())
+                            .collect(),
+                    ),
+                    None => CsvColumns::Captures(spec.to_string()),
+                };
+                Ok(PromptCommand::Csv {
+                    path: path.to_string(),
+                    columns,
+                })
+            }
+            _ => Err("Usage: csv FILE REGEX, or csv FILE json:FIELD,...".to_string()),
+        },
+        "write" => {
+            let mut argument = argument.trim_start();
+            let (mut all, mut plain) = (false, false);
+            loop {
+                let (flag, rest) = match argument.split_once(' ') {
+                    Some(("all", rest)) => (&mut all, rest),
+                    Some(("plain", rest)) => (&mut plain, rest),
+                    _ => break,
+                };
+                *flag = true;
+                argument = rest.trim_start();
+            }
+            // A flag on its own is missing its file, which `./plain` names instead.
+            if matches!(argument, "" | "all" | "plain") {
+                return Err("Usage: write [all] [plain] FILE".to_string());
+            }
+            Ok(PromptCommand::Write {
+                path: argument.to_string(),
+                all,
+                plain,
+            })
+        }
+        _ => Err(format!("Unknown command: {name}")),
+    }
+}
+
+/// Runs a command, returning the message to show in the status bar. Commands leave the
+/// patterns and the view as they are.
+pub(crate) fn run_command(
+    app: &AppState,
+    lines: &LogBuffer,
+    command: PromptCommand,
+) -> Result<String, String> {
+    match command {
+        PromptCommand::Count(pattern) => {
+            let regex = build_regex(&pattern, !app.ignore_case)
+                .map_err(|err| format!("Invalid pattern: {err}"))?;
+            Ok(count_matches(app, lines, &regex))
+        }
+        PromptCommand::Csv { path, .. } | PromptCommand::Write { path, .. } if app.read_only => {
+            Err((app.messages.not_writing)(&path))
+        }
+        PromptCommand::Csv { path, columns } => {
+            let columns = match columns {
+                CsvColumns::Captures(pattern) => Columns::Captures(
+                    build_regex(&pattern, !app.ignore_case)
+                        .map_err(|err| format!("Invalid pattern: {err}"))?,
+                ),
+                CsvColumns::Fields(fields) => Columns::Fields(fields),
+            };
+            let rows = File::create(&path)
+                .and_then(|file| {
+                    let mut out = BufWriter::new(file);
+                    let rows = write_csv(&mut out, &columns, &app.redactions, lines.iter())?;
+                    out.flush()?;
+                    Ok(rows)
+                })
+                .map_err(|err| format!("Can't write {path}: {err}"))?;
+            Ok(format!("Wrote {rows} rows to {path}"))
+        }
+        PromptCommand::Write { path, all, plain } => {
+            let written = File::create(&path)
+                .and_then(|file| {
+                    let mut out = BufWriter::new(file);
+                    let written = write_lines(&mut out, app, lines, all, plain)?;
+                    out.flush()?;
+                    Ok(written)
+                })
+                .map_err(|err| format!("Can't write {path}: {err}"))?;
+            Ok(format!("Wrote {written} lines to {path}"))
+        }
+    }
+}
+
+/// Writes the output lines shown in the view, or all of them, returning how many.
+fn write_lines(
+    out: &mut impl Write,
+    app: &AppState,
+    lines: &LogBuffer,
+    all: bool,
+    plain: bool,
+) -> io::Result<usize> {
+    let selected: Box<dyn Iterator<Item = &LogLine>> = if all {
+        Box::new(lines.iter())
+    } else {
+        Box::new(visible_lines(lines, app))
+    };
+    let mut written = 0;
+    for line in selected.filter(|line| line.kind == LineKind::Output) {
+        let text = if plain {
+            Cow::Owned(plain_text(&line.text))
+        } else {
+            Cow::Borrowed(line.text.as_str())
+        };
+        writeln!(out, "{}", redact(&app.redactions, text))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+fn count_matches(app: &AppState, lines: &LogBuffer, regex: &Regex) -> String {
+    let mut matching = 0;
+    let mut visible = 0;
+    for line in lines
+        .iter()
+        .filter(|line| line.kind == LineKind::Output && regex.is_match(&line.text))
+    {
+        matching += 1;
+        if line_visible(line, app) {
+            visible += 1;
+        }
+    }
+    let mut message = format!(
+        "{matching} of {} lines match /{}/",
+        lines.len(),
+        regex.as_str()
+    );
+    if app.filter_only {
+        message.push_str(&format!(", {visible} visible"));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CsvColumns, GoTo, PromptCommand, parse_command, parse_go_to, run_command, write_lines,
+    };
+    use crate::{AppState, LogLine, buffer::LogBuffer, build_pattern};
+
+    #[test]
+    fn parse_go_to_takes_line_numbers_and_percentages() {
+        assert_eq!(parse_go_to("1234"), Some(GoTo::Line(1234)));
+        assert_eq!(parse_go_to(" 50% "), Some(GoTo::Percent(50)));
+        assert_eq!(parse_go_to("250%"), Some(GoTo::Percent(100)));
+        assert_eq!(parse_go_to("count 1"), None);
+        assert_eq!(parse_go_to("-1"), None);
+        assert_eq!(parse_go_to("%"), None);
+    }
+
+    #[test]
+    fn parse_command_takes_the_rest_of_the_line_as_argument() {
+        assert_eq!(
+            parse_command("count took \\d+ms"),
+            Ok(PromptCommand::Count("took \\d+ms".to_string()))
+        );
+        assert!(parse_command("count").is_err());
+        assert_eq!(
+            parse_command("csv out.csv took (\\d+)ms"),
+            Ok(PromptCommand::Csv {
+                path: "out.csv".to_string(),
+                columns: CsvColumns::Captures("took (\\d+)ms".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_command("csv out.csv json:level, req.path"),
+            Ok(PromptCommand::Csv {
+                path: "out.csv".to_string(),
+                columns: CsvColumns::Fields(vec!["level".to_string(), "req.path".to_string()]),
+            })
+        );
+        assert!(parse_command("csv out.csv").is_err());
+        assert_eq!(
+            parse_command("frobnicate x"),
+            Err("Unknown command: frobnicate".to_string())
+        );
+    }
+
+    #[test]
+    fn count_reports_matching_and_visible_lines() {
+        let patterns = vec![build_pattern("GET".to_string(), true).expect("pattern build failed")];
+        let mut app = AppState::new(patterns, false);
+        let lines = LogBuffer::from(vec![
+            LogLine::output("GET /a 200".to_string()),
+            LogLine::output("POST /a 500".to_string()),
+            LogLine::output("GET /b 500".to_string()),
+        ]);
+        let count = |app: &AppState| {
+            run_command(app, &lines, PromptCommand::Count("500".to_string())).expect("count failed")
+        };
+
+        assert_eq!(count(&app), "2 of 3 lines match /500/");
+        app.filter_only = true;
+        assert_eq!(count(&app), "2 of 3 lines match /500/, 1 visible");
+        assert!(
+            run_command(&app, &lines, PromptCommand::Count("(".to_string()))
+                .is_err_and(|err| err.starts_with("Invalid pattern"))
+        );
+    }
+
+    #[test]
+    fn write_takes_flags_before_the_file_name() {
+        assert_eq!(
+            parse_command("write all plain my log.txt"),
+            Ok(PromptCommand::Write {
+                path: "my log.txt".to_string(),
+                all: true,
+                plain: true,
+            })
+        );
+        assert_eq!(
+            parse_command("write out.log"),
+            Ok(PromptCommand::Write {
+                path: "out.log".to_string(),
+                all: false,
+                plain: false,
+            })
+        );
+        assert!(parse_command("write plain").is_err());
+    }
+
+    #[test]
+    fn write_lines_writes_shown_or_all_lines_raw_or_plain() {
+        let patterns = vec![build_pattern("GET".to_string(), true).expect("pattern build failed")];
+        let mut app = AppState::new(patterns, false);
+        app.filter_only = true;
+        let lines = LogBuffer::from(vec![
+            LogLine::run_separator("--- run 1 ---".to_string()),
+            LogLine::output("\x1b[32mGET\x1b[0m /a".to_string()),
+            LogLine::output("POST /a".to_string()),
+        ]);
+        let write = |all, plain| {
+            let mut out = Vec::new();
+            let written = write_lines(&mut out, &app, &lines, all, plain).expect("write failed");
+            (written, String::from_utf8(out).expect("output is UTF-8"))
+        };
+
+        assert_eq!(
+            write(false, false),
+            (1, "\x1b[32mGET\x1b[0m /a\n".to_string())
+        );
+        assert_eq!(write(true, true), (2, "GET /a\nPOST /a\n".to_string()));
+
+        app.read_only = true;
+        let command = PromptCommand::Write {
+            path: "out.log".to_string(),
+            all: true,
+            plain: false,
+        };
+        assert_eq!(
+            run_command(&app, &lines, command),
+            Err("Not writing out.log: read-only (--read-only)".to_string())
+        );
+    }
+}
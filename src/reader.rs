@@ -0,0 +1,373 @@
+use std::{io, sync::Arc};
+
+use encoding_rs::{Encoding, UTF_8};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{Semaphore, mpsc},
+};
+
+/// Segments buffered between a reader task and the main loop. When full, the reader task stops
+/// reading, applying backpressure to the producer instead of growing without bound.
+pub(crate) const CHANNEL_CAPACITY: usize = 64 * 1024;
+
+/// The most connections a listener serves at once. Further ones wait in the backlog of the
+/// socket until one closes, so a sender can't have a task and its buffers made per connection
+/// without end.
+pub(crate) const MAX_CONNECTIONS: usize = 64;
+
+/// Accepts the connections of `listener` on a task of its own, serving each with `serve` on
+/// another, at most [`MAX_CONNECTIONS`] at once.
+pub(crate) fn accept<F, T>(listener: TcpListener, serve: F)
+where
+    F: Fn(TcpStream) -> T + Send + 'static,
+    T: Future + Send + 'static,
+{
+    let permits = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    tokio::spawn(async move {
+        while let Ok(permit) = Arc::clone(&permits).acquire_owned().await
+            && let Ok((stream, _)) = listener.accept().await
+        {
+            let served = serve(stream);
+            tokio::spawn(async move {
+                served.await;
+                drop(permit);
+            });
+        }
+    });
+}
+
+/// A piece of input terminated by either a newline or a carriage return.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    /// Text terminated by a newline.
+    Line(String),
+    /// Text terminated by a carriage return, to be overwritten by whatever follows it.
+    Progress(String),
+    /// A newline directly following a carriage return, finalizing the last progress update.
+    Commit,
+    /// The followed file was truncated or replaced, the segments after this one come from its
+    /// new contents.
+    Rotated,
+}
+
+/// Splits input into segments, so that tools redrawing a line with `\r` (progress bars,
+/// spinners) update a single line instead of producing one line per redraw.
+pub(crate) struct SegmentReader<R> {
+    reader: BufReader<R>,
+    encoding: &'static Encoding,
+    pending: Vec<u8>,
+    after_cr: bool,
+    /// Set after flushing the pending text at end of input, so the end is still reported.
+    ended: bool,
+    /// Offset of the next byte to be read.
+    offset: u64,
+    /// Offset of the first byte of the pending segment.
+    start: u64,
+    /// Offset of the first byte of the last returned segment.
+    segment_offset: u64,
+}
+
+impl<R: AsyncRead + Unpin> SegmentReader<R> {
+    /// `encoding` must be ASCII compatible, as segments are split on raw `\n` and `\r` bytes.
+    pub(crate) fn new(reader: R, encoding: &'static Encoding) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            encoding,
+            pending: Vec::new(),
+            after_cr: false,
+            ended: false,
+            offset: 0,
+            start: 0,
+            segment_offset: 0,
+        }
+    }
+
+    /// Sets the offset of the next byte to be read, for input that doesn't start at the
+    /// beginning of a file or restarted with a new one.
+    pub(crate) fn set_offset(&mut self, offset: u64) {
+        self.offset = offset;
+        self.start = offset;
+    }
+
+    /// Byte offset in the input at which the last returned segment starts.
+    pub(crate) fn segment_offset(&self) -> u64 {
+        self.segment_offset
+    }
+
+    /// Returns the next segment, or `None` at end of input. Cancel safe: partially read
+    /// segments are kept until the next call.
+    ///
+    /// Readers that keep producing data after signalling end of input (like a followed file
+    /// being rotated) can be read further after `None`.
+    pub(crate) async fn next_segment(&mut self) -> io::Result<Option<Segment>> {
+        if self.ended {
+            self.ended = false;
+            return Ok(None);
+        }
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                self.after_cr = false;
+                if self.pending.is_empty() {
+                    return Ok(None);
+                }
+                let text = take_text(&mut self.pending, self.encoding);
+                self.ended = text.is_some();
+                self.segment_offset = self.start;
+                self.start = self.offset;
+                return Ok(text.map(Segment::Line));
+            }
+
+            let mut consumed = 0;
+            let mut segment = None;
+            for &byte in available {
+                consumed += 1;
+                self.offset += 1;
+                match byte {
+                    b'\n' => {
+                        segment = if self.after_cr && self.pending.is_empty() {
+                            Some(Segment::Commit)
+                        } else {
+                            take_text(&mut self.pending, self.encoding).map(Segment::Line)
+                        };
+                        self.after_cr = false;
+                        self.segment_offset = self.start;
+                        self.start = self.offset;
+                        break;
+                    }
+                    b'\r' => {
+                        self.after_cr = true;
+                        self.segment_offset = self.start;
+                        self.start = self.offset;
+                        if !self.pending.is_empty() {
+                            segment =
+                                take_text(&mut self.pending, self.encoding).map(Segment::Progress);
+                            break;
+                        }
+                    }
+                    _ => {
+                        if self.pending.is_empty() {
+                            self.start = self.offset - 1;
+                        }
+                        self.after_cr = false;
+                        self.pending.push(byte);
+                    }
+                }
+            }
+            self.reader.consume(consumed);
+
+            if segment.is_some() {
+                return Ok(segment);
+            }
+        }
+    }
+}
+
+/// Reads segments until end of input (or until the receiving side is gone), forwarding them to
+/// the main loop. Meant to run as a dedicated task so the main loop never waits on reads.
+pub(crate) async fn forward_segments<R>(
+    reader: R,
+    encoding: &'static Encoding,
+    tx: mpsc::Sender<Segment>,
+) where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let mut reader = SegmentReader::new(reader, encoding);
+    while let Ok(Some(segment)) = reader.next_segment().await {
+        if tx.send(segment).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Takes the pending bytes as text, transcoding them from `encoding`. Bytes that aren't valid
+/// UTF-8 are kept as replacement characters, so binary junk shows up rather than leaving a gap.
+fn take_text(pending: &mut Vec<u8>, encoding: &'static Encoding) -> Option<String> {
+    let bytes = std::mem::take(pending);
+    if encoding == UTF_8 {
+        return Some(
+            String::from_utf8(bytes)
+                .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()),
+        );
+    }
+    Some(encoding.decode_without_bom_handling(&bytes).0.into_owned())
+}
+
+/// Commonly used encoding labels, offered by shell completion for `--encoding`.
+pub(crate) const ENCODING_LABELS: &[&str] = &[
+    "utf-8",
+    "latin1",
+    "windows-1250",
+    "windows-1251",
+    "windows-1252",
+    "iso-8859-2",
+    "iso-8859-15",
+    "koi8-r",
+    "shift-jis",
+    "euc-jp",
+    "euc-kr",
+    "gbk",
+    "gb18030",
+    "big5",
+];
+
+/// Looks up an encoding by its WHATWG label (e.g. `latin1`, `shift-jis`), defaulting to UTF-8.
+pub(crate) fn resolve_encoding(label: Option<&str>) -> Option<&'static Encoding> {
+    match label {
+        None => Some(UTF_8),
+        Some(label) => {
+            Encoding::for_label(label.as_bytes()).filter(|encoding| encoding.is_ascii_compatible())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ENCODING_LABELS, MAX_CONNECTIONS, Segment, SegmentReader, accept, resolve_encoding,
+    };
+    use encoding_rs::{Encoding, SHIFT_JIS, UTF_8, WINDOWS_1252};
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+    use tokio::{
+        net::{TcpListener, TcpStream},
+        sync::Notify,
+        time::sleep,
+    };
+
+    async fn segments(input: &[u8]) -> Vec<Segment> {
+        decoded_segments(input, UTF_8).await
+    }
+
+    async fn decoded_segments(input: &[u8], encoding: &'static Encoding) -> Vec<Segment> {
+        let mut reader = SegmentReader::new(input, encoding);
+        let mut segments = Vec::new();
+        while let Some(segment) = reader.next_segment().await.expect("read failed") {
+            segments.push(segment);
+        }
+        segments
+    }
+
+    #[tokio::test]
+    async fn splits_lines_and_progress_updates() {
+        let segments = segments(b"start\n10%\r50%\r100%\r\nlast").await;
+
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Line("start".to_string()),
+                Segment::Progress("10%".to_string()),
+                Segment::Progress("50%".to_string()),
+                Segment::Progress("100%".to_string()),
+                Segment::Commit,
+                Segment::Line("last".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn treats_text_after_carriage_return_as_line() {
+        let segments = segments(b"\rfoo\nspinner\rdone\n").await;
+
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Line("foo".to_string()),
+                Segment::Progress("spinner".to_string()),
+                Segment::Line("done".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn transcodes_legacy_encodings() {
+        let segments = decoded_segments(b"caf\xe9\n", WINDOWS_1252).await;
+        assert_eq!(segments, vec![Segment::Line("café".to_string())]);
+
+        let segments = decoded_segments(b"\x93\xfa\x96\x7b\n", SHIFT_JIS).await;
+        assert_eq!(segments, vec![Segment::Line("日本".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn keeps_invalid_utf8_lines_with_replacement_characters() {
+        let segments = decoded_segments(b"ok\nbad \xff\xfe byte\nnext\n", UTF_8).await;
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Line("ok".to_string()),
+                Segment::Line("bad \u{FFFD}\u{FFFD} byte".to_string()),
+                Segment::Line("next".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tracks_segment_offsets() {
+        let mut reader = SegmentReader::new(&b"ab\n\rspin\rdone\nlast"[..], UTF_8);
+        reader.set_offset(100);
+        let mut offsets = Vec::new();
+        while let Some(segment) = reader.next_segment().await.expect("read failed") {
+            offsets.push((segment, reader.segment_offset()));
+        }
+
+        assert_eq!(
+            offsets,
+            vec![
+                (Segment::Line("ab".to_string()), 100),
+                (Segment::Progress("spin".to_string()), 104),
+                (Segment::Line("done".to_string()), 109),
+                (Segment::Line("last".to_string()), 114),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_encoding_accepts_labels_and_rejects_utf16() {
+        assert_eq!(resolve_encoding(None), Some(UTF_8));
+        assert_eq!(resolve_encoding(Some("latin1")), Some(WINDOWS_1252));
+        assert_eq!(resolve_encoding(Some("shift-jis")), Some(SHIFT_JIS));
+        assert_eq!(resolve_encoding(Some("utf-16le")), None);
+        assert_eq!(resolve_encoding(Some("no-such-encoding")), None);
+    }
+
+    #[test]
+    fn completion_labels_are_supported() {
+        for label in ENCODING_LABELS {
+            assert!(resolve_encoding(Some(label)).is_some(), "{label}");
+        }
+    }
+
+    #[tokio::test]
+    async fn at_most_so_many_connections_are_served_at_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind failed");
+        let addr = listener.local_addr().expect("address failed");
+        let served = Arc::new(AtomicUsize::new(0));
+        let release = Arc::new(Notify::new());
+        accept(listener, {
+            let (served, release) = (Arc::clone(&served), Arc::clone(&release));
+            move |_stream| {
+                let (served, release) = (Arc::clone(&served), Arc::clone(&release));
+                async move {
+                    served.fetch_add(1, Ordering::SeqCst);
+                    release.notified().await;
+                }
+            }
+        });
+        let mut clients = Vec::new();
+        for _ in 0..=MAX_CONNECTIONS {
+            clients.push(TcpStream::connect(addr).await.expect("connect failed"));
+        }
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(served.load(Ordering::SeqCst), MAX_CONNECTIONS);
+
+        release.notify_one();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(served.load(Ordering::SeqCst), MAX_CONNECTIONS + 1);
+    }
+}
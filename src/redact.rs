@@ -0,0 +1,197 @@
+use std::{borrow::Cow, fmt};
+
+use regex::Regex;
+use serde::Deserialize;
+
+/// What a masked secret is shown and exported as.
+const MASK: &str = "***";
+
+/// Values of credentials given as `key=value` or `"key": "value"`, bearer and basic
+/// authorization, and tokens of well-known shapes: GitHub, AWS access keys, Slack and JWTs.
+const TOKENS: &str = concat!(
+    r"(?i)(?:\b(?:bearer|basic)\s+",
+    r"|\b(?:api[_-]?key|(?:access|auth|refresh)[_-]?token|token|secret|password|passwd|pwd)",
+    r#"["']?\s*[:=]\s*["']?)([^\s"',;&]+)"#,
+    r"|\b(gh[pousr]_[A-Za-z0-9]{36,}|AKIA[0-9A-Z]{16}|xox[abpr]-[A-Za-z0-9-]{10,}",
+    r"|eyJ[\w-]+\.[\w-]+\.[\w-]+)",
+);
+
+const EMAILS: &str = r"\b[\w.%+-]+@[\w-]+(?:\.[\w-]+)*\.[A-Za-z]{2,}\b";
+
+/// 13 to 19 digits, optionally grouped with spaces or dashes, starting like the card numbers
+/// of the major networks do, so epoch milliseconds aren't taken for them.
+const CARDS: &str = r"\b[2-6]\d(?:[ -]?\d){11,17}\b";
+
+/// A rule masking the secrets in lines: the built-in `tokens`, `emails` or `cards`, or a regex
+/// whose matches, or capture groups if it has any, are masked.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct Redaction {
+    rule: String,
+    regex: Regex,
+    /// Whether matches are masked only if their digits pass the Luhn check, for card numbers.
+    luhn: bool,
+}
+
+impl Redaction {
+    pub(crate) fn parse(rule: &str) -> Result<Self, String> {
+        let (pattern, luhn) = match rule {
+            "tokens" => (TOKENS, false),
+            "emails" => (EMAILS, false),
+            "cards" => (CARDS, true),
+            pattern => (pattern, false),
+        };
+        let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+        Ok(Redaction {
+            rule: rule.to_string(),
+            regex,
+            luhn,
+        })
+    }
+
+    /// The byte ranges of the text the rule masks.
+    fn spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        for captures in self.regex.captures_iter(text) {
+            let found = captures.get(0).expect("group 0 always matches");
+            if self.luhn && !luhn_valid(found.as_str()) {
+                continue;
+            }
+            let groups: Vec<(usize, usize)> = captures
+                .iter()
+                .skip(1)
+                .flatten()
+                .map(|group| (group.start(), group.end()))
+                .collect();
+            if groups.is_empty() {
+                spans.push((found.start(), found.end()));
+            } else {
+                spans.extend(groups);
+            }
+        }
+        spans
+    }
+}
+
+impl TryFrom<String> for Redaction {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, String> {
+        Redaction::parse(&rule)
+    }
+}
+
+impl fmt::Debug for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.rule)
+    }
+}
+
+impl PartialEq for Redaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.rule == other.rule
+    }
+}
+
+impl Eq for Redaction {}
+
+/// The text with what the rules match masked. Borrowed when nothing is.
+pub(crate) fn redact<'a>(redactions: &[Redaction], text: Cow<'a, str>) -> Cow<'a, str> {
+    let mut spans: Vec<(usize, usize)> = redactions
+        .iter()
+        .flat_map(|redaction| redaction.spans(&text))
+        .filter(|(start, end)| start < end)
+        .collect();
+    if spans.is_empty() {
+        return text;
+    }
+    spans.sort_unstable();
+    let mut masked = String::with_capacity(text.len());
+    let mut at = 0;
+    for (start, end) in spans {
+        if end <= at {
+            continue;
+        }
+        if start >= at {
+            masked.push_str(&text[at..start]);
+            masked.push_str(MASK);
+        }
+        at = end;
+    }
+    masked.push_str(&text[at..]);
+    Cow::Owned(masked)
+}
+
+/// Whether the digits of a number pass the Luhn check card numbers are made to.
+fn luhn_valid(number: &str) -> bool {
+    let sum: u32 = number
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| match (index % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{Redaction, redact};
+
+    fn masked(rules: &[&str], text: &str) -> String {
+        let redactions: Vec<Redaction> = rules
+            .iter()
+            .map(|rule| Redaction::parse(rule).expect("parse failed"))
+            .collect();
+        redact(&redactions, Cow::Borrowed(text)).into_owned()
+    }
+
+    #[test]
+    fn built_in_rules_mask_tokens_emails_and_card_numbers() {
+        let rules = ["tokens", "emails", "cards"];
+        assert_eq!(
+            masked(
+                &rules,
+                "GET /?api_key=s3cr3t&page=2 Authorization: Bearer abc.def"
+            ),
+            "GET /?api_key=***&page=2 Authorization: Bearer ***"
+        );
+        assert_eq!(
+            masked(
+                &rules,
+                r#"{"user":"ann@example.com","password": "hunter2"}"#
+            ),
+            r#"{"user":"***","password": "***"}"#
+        );
+        assert_eq!(
+            masked(&rules, "paid with 4111 1111 1111 1111 at 1700000000000"),
+            "paid with *** at 1700000000000"
+        );
+        assert_eq!(
+            masked(&rules, "order 4111111111111112 shipped"),
+            "order 4111111111111112 shipped"
+        );
+    }
+
+    #[test]
+    fn regex_rules_mask_their_capture_groups_or_whole_matches() {
+        assert_eq!(
+            masked(
+                &[r"session=(\w+)", r"\bid-\d+"],
+                "session=f00 user id-42 session=b4r"
+            ),
+            "session=*** user *** session=***"
+        );
+        assert!(matches!(
+            redact(&[], Cow::Borrowed("nothing to hide")),
+            Cow::Borrowed(_)
+        ));
+        assert!(Redaction::parse("(").is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use std::{borrow::Cow, fmt, sync::LazyLock};
+
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use crate::parser::epoch_timestamp;
+
+/// Numbers marked for rendering in a replacement, like `<ts:$1>` or `<bytes:$1>`.
+static MARKED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<(ts|bytes|ns|us|ms):(\d+)>").expect("marked number regex is valid")
+});
+
+/// The binary units of byte counts, by their size in bytes.
+const BYTE_UNITS: [(&str, f64); 5] = [
+    ("B", 1.0),
+    ("KiB", 1024.0),
+    ("MiB", 1_048_576.0),
+    ("GiB", 1_073_741_824.0),
+    ("TiB", 1_099_511_627_776.0),
+];
+
+/// The units of durations, by their length in nanoseconds.
+const DURATION_UNITS: [(&str, f64); 6] = [
+    ("ns", 1.0),
+    ("µs", 1e3),
+    ("ms", 1e6),
+    ("s", 1e9),
+    ("min", 60e9),
+    ("h", 3600e9),
+];
+
+/// Numbers of 10 or 13 digits, taken for epoch seconds or milliseconds when in range.
+static EPOCH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:\d{13}|\d{10})\b").expect("epoch regex is valid"));
+
+/// Epoch seconds before this, in 2001, are taken for other numbers rather than times, like a
+/// zero-padded `0000000000`.
+const EARLIEST_EPOCH: i64 = 978_307_200;
+
+/// Epoch seconds past this, in 2100, are taken for other numbers rather than times.
+const LATEST_EPOCH: i64 = 4_102_444_800;
+
+/// A rule rewriting lines as shown, like `s/(\d{13})/<ts:$1>/`: the matches of a regex are
+/// replaced as with sed, the first of each line or, with the `g` flag, all of them. The
+/// replacement refers to capture groups with `$1` or `${name}`, and `<ts:...>` in it renders
+/// an epoch time in seconds, milliseconds, microseconds or nanoseconds as an ISO 8601 one.
+/// Likewise `<bytes:...>` renders a byte count like `1.2 MiB`, and `<ns:...>`, `<us:...>` and
+/// `<ms:...>` a duration in that unit like `340 ms`.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct Rewrite {
+    rule: String,
+    regex: Regex,
+    replacement: String,
+    global: bool,
+}
+
+impl Rewrite {
+    pub(crate) fn parse(rule: &str) -> Result<Self, String> {
+        let invalid = || format!("expected s/REGEX/REPLACEMENT/, got {rule}");
+        let rest = rule.strip_prefix('s').ok_or_else(invalid)?;
+        let delimiter = rest
+            .chars()
+            .next()
+            .filter(|c| !c.is_alphanumeric() && *c != '\\');
+        let delimiter = delimiter.ok_or_else(invalid)?;
+        let parts = split_unescaped(&rest[delimiter.len_utf8()..], delimiter);
+        let [pattern, replacement, flags] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        let global = match flags.as_str() {
+            "" => false,
+            "g" => true,
+            _ => return Err(format!("unknown flags {flags} in {rule}")),
+        };
+        let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+        Ok(Rewrite {
+            rule: rule.to_string(),
+            regex,
+            replacement: replacement.clone(),
+            global,
+        })
+    }
+
+    fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let limit = if self.global { 0 } else { 1 };
+        self.regex.replacen(text, limit, |captures: &Captures| {
+            let mut replaced = String::new();
+            captures.expand(&self.replacement, &mut replaced);
+            render_marks(&replaced).into_owned()
+        })
+    }
+}
+
+impl TryFrom<String> for Rewrite {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, String> {
+        Rewrite::parse(&rule)
+    }
+}
+
+impl fmt::Debug for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.rule)
+    }
+}
+
+impl PartialEq for Rewrite {
+    fn eq(&self, other: &Self) -> bool {
+        self.rule == other.rule
+    }
+}
+
+impl Eq for Rewrite {}
+
+/// The text as shown after the rewrites and, with `epochs`, with the epoch times in it made
+/// readable. Borrowed when nothing applies.
+pub(crate) fn rewrite<'a>(rewrites: &[Rewrite], epochs: bool, text: &'a str) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    for rewrite in rewrites {
+        text = replace(text, |text| rewrite.apply(text));
+    }
+    if epochs {
+        text = replace(text, humanize_epochs);
+    }
+    text
+}
+
+/// The text a step changes it into, kept borrowed as long as no step changes it.
+fn replace<'a>(text: Cow<'a, str>, step: impl Fn(&str) -> Cow<'_, str>) -> Cow<'a, str> {
+    let changed = match step(&text) {
+        Cow::Owned(changed) => Some(changed),
+        Cow::Borrowed(_) => None,
+    };
+    changed.map_or(text, Cow::Owned)
+}
+
+/// Renders the epoch seconds and milliseconds between 2001 and 2100 in a text as ISO 8601 times
+/// in UTC, to the second or the millisecond.
+fn humanize_epochs(text: &str) -> Cow<'_, str> {
+    EPOCH.replace_all(text, |captures: &Captures| {
+        let digits = &captures[0];
+        let value: i64 = digits.parse().unwrap_or(i64::MAX);
+        let (micros, precision) = match digits.len() {
+            10 => (value.saturating_mul(1_000_000), 19),
+            _ => (value.saturating_mul(1_000), 23),
+        };
+        if !(EARLIEST_EPOCH * 1_000_000..=LATEST_EPOCH * 1_000_000).contains(&micros) {
+            return digits.to_string();
+        }
+        format!("{}Z", &epoch_timestamp(micros)[..precision])
+    })
+}
+
+/// Splits at the delimiters not escaped with a backslash, unescaping them.
+fn split_unescaped(text: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().expect("there is always a part");
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => parts.push(String::new()),
+            c => part.push(c),
+        }
+    }
+    parts
+}
+
+/// Renders the numbers marked in a replacement. Epoch times tell their unit by their number
+/// of digits, and marks of other lengths are left as they are.
+fn render_marks(text: &str) -> Cow<'_, str> {
+    MARKED.replace_all(text, |captures: &Captures| {
+        let digits = &captures[2];
+        let rendered = match &captures[1] {
+            "ts" => epoch_time(digits),
+            "bytes" => digits
+                .parse::<f64>()
+                .ok()
+                .map(|bytes| in_units(bytes, &BYTE_UNITS)),
+            unit => digits.parse::<f64>().ok().map(|value| {
+                let nanos = match unit {
+                    "us" => value * 1e3,
+                    "ms" => value * 1e6,
+                    _ => value,
+                };
+                in_units(nanos, &DURATION_UNITS)
+            }),
+        };
+        rendered.unwrap_or_else(|| captures[0].to_string())
+    })
+}
+
+fn epoch_time(digits: &str) -> Option<String> {
+    let value: i64 = digits.parse().ok()?;
+    let micros = match digits.len() {
+        10 => value.checked_mul(1_000_000)?,
+        13 => value.checked_mul(1_000)?,
+        16 => value,
+        19 => value / 1_000,
+        _ => return None,
+    };
+    Some(epoch_timestamp(micros))
+}
+
+/// A quantity in the largest of the units it makes at least one of, with a decimal below ten.
+fn in_units(value: f64, units: &[(&str, f64)]) -> String {
+    let (name, size) = units
+        .iter()
+        .rev()
+        .find(|(_, size)| value >= *size)
+        .unwrap_or(&units[0]);
+    let scaled = value / size;
+    if scaled < 10.0 && *size > 1.0 {
+        format!("{scaled:.1} {name}")
+    } else {
+        format!("{scaled:.0} {name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rewrite, rewrite};
+
+    #[test]
+    fn epoch_times_in_range_are_made_readable() {
+        assert_eq!(
+            rewrite(
+                &[],
+                true,
+                "ts=1700000000 at=1700000000123 id=9999999999 v1700000000"
+            ),
+            "ts=2023-11-14T22:13:20Z at=2023-11-14T22:13:20.123Z id=9999999999 v1700000000"
+        );
+        assert_eq!(
+            rewrite(&[], true, "id=0000000000 n=0000000000042"),
+            "id=0000000000 n=0000000000042"
+        );
+        let rules = [Rewrite::parse("s/at=/sent=/").expect("parse failed")];
+        assert_eq!(
+            rewrite(&rules, true, "at=1700000000"),
+            "sent=2023-11-14T22:13:20Z"
+        );
+        assert_eq!(rewrite(&rules, false, "at=1700000000"), "sent=1700000000");
+    }
+
+    #[test]
+    fn rules_rewrite_matches_and_render_marked_epoch_times() {
+        let rules = [
+            Rewrite::parse(r"s/(\d{13})/<ts:$1>/").expect("parse failed"),
+            Rewrite::parse(r"s|user=(\w+)|user=<${1}>|g").expect("parse failed"),
+        ];
+
+        assert_eq!(
+            rewrite(&rules, false, "at 1700000000123 user=ann user=bob"),
+            "at 2023-11-14T22:13:20.123000Z user=<ann> user=<bob>"
+        );
+        assert!(matches!(
+            rewrite(&rules, false, "nothing to see"),
+            std::borrow::Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            rewrite(
+                &[Rewrite::parse(r"s/a\/b/<ts:12>/").expect("parse failed")],
+                false,
+                "a/b"
+            ),
+            "<ts:12>"
+        );
+
+        let sizes = Rewrite::parse(r"s/size=(\d+) took=(\d+)/size=<bytes:$1> took=<ns:$2>/");
+        let sizes = [sizes.expect("parse failed")];
+        assert_eq!(
+            rewrite(&sizes, false, "size=1258291 took=340000000"),
+            "size=1.2 MiB took=340 ms"
+        );
+        assert_eq!(
+            rewrite(&sizes, false, "size=512 took=90"),
+            "size=512 B took=90 ns"
+        );
+        let durations =
+            [Rewrite::parse(r"s/(\d+)us (\d+)ms/<us:$1> <ms:$2>/").expect("parse failed")];
+        assert_eq!(
+            rewrite(&durations, false, "1500us 150000ms"),
+            "1.5 ms 2.5 min"
+        );
+
+        assert!(Rewrite::parse("s/a/b").is_err());
+        assert!(Rewrite::parse("s/a/b/x").is_err());
+        assert!(Rewrite::parse("s/(/b/").is_err());
+        assert!(Rewrite::parse("y/a/b/").is_err());
+    }
+}
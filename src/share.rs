@@ -0,0 +1,249 @@
+use std::{collections::BTreeSet, io};
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::UnixDatagram,
+    },
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::PatternSpec;
+
+/// Largest change sent between instances, far above any pattern typed in.
+#[cfg(unix)]
+const MAX_CHANGE: usize = 64 << 10;
+
+/// A pattern added or removed in one of the instances sharing patterns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Change {
+    Add {
+        pattern: String,
+        case_sensitive: bool,
+    },
+    Remove {
+        pattern: String,
+    },
+}
+
+/// The text of the patterns, by which they are told apart between instances.
+pub(crate) fn texts(patterns: &[PatternSpec]) -> BTreeSet<String> {
+    patterns
+        .iter()
+        .map(|pattern| pattern.pattern.clone())
+        .collect()
+}
+
+/// The patterns added and removed since they were `before`.
+pub(crate) fn changes(before: &BTreeSet<String>, after: &[PatternSpec]) -> Vec<Change> {
+    let now = texts(after);
+    let removed = before.difference(&now).map(|pattern| Change::Remove {
+        pattern: pattern.clone(),
+    });
+    let added = after
+        .iter()
+        .filter(|pattern| !before.contains(&pattern.pattern))
+        .map(|pattern| Change::Add {
+            pattern: pattern.pattern.clone(),
+            case_sensitive: pattern.case_sensitive,
+        });
+    removed.chain(added).collect()
+}
+
+/// The membership of this instance in a group of instances sharing their patterns with
+/// `--share`, e.g. in adjacent tmux panes. Each member binds a Unix datagram socket in the
+/// group's directory and sends its changes to the sockets of the others.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) struct Share {
+    #[cfg(unix)]
+    sender: UnixDatagram,
+    #[cfg(unix)]
+    dir: PathBuf,
+    #[cfg(unix)]
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Share {
+    /// Joins the group, returning the changes the other members send.
+    pub(crate) fn join(group: &str) -> io::Result<(Self, mpsc::Receiver<Change>)> {
+        if group.is_empty() || group.contains(['/', '\0']) || group.starts_with('.') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid group name {group}"),
+            ));
+        }
+        let dir = groups_dir()?.join(group);
+        // Only the user's own instances may change their patterns.
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)?;
+        let path = dir.join(format!("{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let receiver = UnixDatagram::bind(&path)?;
+        receiver.set_nonblocking(true)?;
+        let receiver = tokio::net::UnixDatagram::from_std(receiver)?;
+        let sender = UnixDatagram::unbound()?;
+        // A member too busy to take a change misses it, rather than stalling this one.
+        sender.set_nonblocking(true)?;
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            let mut buf = vec![0; MAX_CHANGE];
+            while let Ok(length) = receiver.recv(&mut buf).await {
+                let Ok(change) = serde_json::from_slice(&buf[..length]) else {
+                    continue;
+                };
+                if tx.send(change).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok((Share { sender, dir, path }, rx))
+    }
+
+    /// Sends a change to the other members, removing the sockets left behind by those gone
+    /// without leaving.
+    pub(crate) fn broadcast(&self, change: &Change) {
+        let Ok(message) = serde_json::to_vec(change) else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path == self.path || path.extension().is_none_or(|extension| extension != "sock") {
+                continue;
+            }
+            if let Err(err) = self.sender.send_to(&message, &path)
+                && err.kind() == io::ErrorKind::ConnectionRefused
+            {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Share {
+    pub(crate) fn join(_group: &str) -> io::Result<(Self, mpsc::Receiver<Change>)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "sharing patterns needs Unix domain sockets",
+        ))
+    }
+
+    pub(crate) fn broadcast(&self, _change: &Change) {}
+}
+
+#[cfg(unix)]
+impl Drop for Share {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// `logr/share` in `$XDG_RUNTIME_DIR`, or in a directory of the user's own under the temporary
+/// one when that isn't set, failing if another user could have made or taken that directory.
+#[cfg(unix)]
+fn groups_dir() -> io::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir).join("logr").join("share"));
+    }
+    let user = std::env::var("USER").unwrap_or_default();
+    let dir = std::env::temp_dir().join(format!("logr-{user}"));
+    if let Err(err) = fs::DirBuilder::new().mode(0o700).create(&dir)
+        && err.kind() != io::ErrorKind::AlreadyExists
+    {
+        return Err(err);
+    }
+    check_private(&dir)?;
+    Ok(dir.join("share"))
+}
+
+/// Checks that `dir` is a directory, not a symlink, owned by the user and only open to them.
+#[cfg(unix)]
+fn check_private(dir: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(dir)?;
+    // SAFETY: geteuid has no preconditions and can't fail.
+    let uid = unsafe { libc::geteuid() };
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o777 != 0o700 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} isn't a private directory of the user, refusing to share through it",
+                dir.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, changes, check_private, texts};
+    use crate::build_pattern;
+
+    #[test]
+    fn pattern_changes_are_told_by_their_text() {
+        let pattern = |text: &str| build_pattern(text.to_string(), true).expect("build failed");
+        let before = texts(&[pattern("ERROR"), pattern("WARN")]);
+        let mut after = vec![pattern("ERROR"), pattern("timeout")];
+        after[1].case_sensitive = false;
+
+        assert_eq!(
+            changes(&before, &after),
+            [
+                Change::Remove {
+                    pattern: "WARN".to_string(),
+                },
+                Change::Add {
+                    pattern: "timeout".to_string(),
+                    case_sensitive: false,
+                },
+            ]
+        );
+        assert!(changes(&texts(&after), &after).is_empty());
+
+        let change = &changes(&before, &after)[1];
+        let message = serde_json::to_string(change).expect("serialize failed");
+        assert_eq!(
+            message,
+            r#"{"add":{"pattern":"timeout","case_sensitive":false}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Change>(&message).ok().as_ref(),
+            Some(change)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_private_directories_are_shared_through() {
+        use std::{
+            fs,
+            os::unix::fs::{PermissionsExt, symlink},
+        };
+
+        let dir = std::env::temp_dir().join(format!("logr-private-{}", std::process::id()));
+        let link = dir.with_extension("link");
+        fs::create_dir(&dir).expect("create failed");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).expect("chmod failed");
+        symlink(&dir, &link).expect("symlink failed");
+
+        assert!(check_private(&dir).is_ok());
+        assert!(check_private(&link).is_err());
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).expect("chmod failed");
+        assert!(check_private(&dir).is_err());
+
+        fs::remove_file(&link).expect("remove failed");
+        fs::remove_dir(&dir).expect("remove failed");
+    }
+}
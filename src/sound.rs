@@ -0,0 +1,188 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Time between the bells of one alert, long enough for terminals not to merge them into one.
+pub(crate) const BELL_GAP: Duration = Duration::from_millis(250);
+
+/// Quiet time after an alert, during which the alerts of other lines are dropped, so a burst of
+/// matching lines sounds once.
+const ALERT_GAP: Duration = Duration::from_secs(1);
+
+/// How a line matching a pattern is announced: with a number of terminal bells, or with a
+/// system sound by name, like `complete` from the freedesktop sound theme or `Glass` on macOS,
+/// or by the path of a sound file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) enum Sound {
+    Bells(u8),
+    Named(String),
+}
+
+impl Sound {
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        match text.parse::<u8>() {
+            Ok(count @ 1..=9) => Ok(Sound::Bells(count)),
+            Ok(_) => Err(format!("bell count {text} isn't between 1 and 9")),
+            Err(_) if text.trim().is_empty() => Err("empty sound name".to_string()),
+            Err(_) => Ok(Sound::Named(text.to_string())),
+        }
+    }
+
+    /// How the patterns dialog shows the sound next to its pattern.
+    pub(crate) fn label(&self) -> String {
+        match self {
+            Sound::Bells(count) => format!(" ♪{count}"),
+            Sound::Named(name) => format!(" ♪ {name}"),
+        }
+    }
+}
+
+impl From<Sound> for String {
+    fn from(sound: Sound) -> Self {
+        match sound {
+            Sound::Bells(count) => count.to_string(),
+            Sound::Named(name) => name,
+        }
+    }
+}
+
+impl TryFrom<String> for Sound {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        Sound::parse(&text)
+    }
+}
+
+/// Parses `--alert PATTERN=SOUND`, splitting at the last `=` as patterns may contain one.
+pub(crate) fn parse_alert(spec: &str) -> Result<(String, Sound), String> {
+    let (pattern, sound) = spec
+        .rsplit_once('=')
+        .filter(|(pattern, _)| !pattern.is_empty())
+        .ok_or_else(|| format!("expected PATTERN=SOUND, got {spec}"))?;
+    Ok((pattern.to_string(), Sound::parse(sound)?))
+}
+
+/// The alert being sounded, its bells rung one at a time as the main loop gets to them.
+#[derive(Debug, Default)]
+pub(crate) struct Chime {
+    bells: u8,
+    named: Option<String>,
+    quiet_until: Option<Instant>,
+}
+
+impl Chime {
+    /// Starts sounding an alert, unless the last one is still sounding or was just sounded.
+    pub(crate) fn queue(&mut self, sound: &Sound, now: Instant) {
+        if self.quiet_until.is_some_and(|until| now < until) {
+            return;
+        }
+        let bells = match sound {
+            Sound::Bells(count) => *count,
+            Sound::Named(name) => {
+                self.named = Some(name.clone());
+                0
+            }
+        };
+        self.bells = bells;
+        self.quiet_until = Some(now + BELL_GAP * u32::from(bells) + ALERT_GAP);
+    }
+
+    /// Whether anything is left to sound.
+    pub(crate) fn pending(&self) -> bool {
+        self.bells > 0 || self.named.is_some()
+    }
+
+    /// Plays the named sound, or rings the next bell to `out`. A sound that can't be played,
+    /// for lack of a player, rings a bell instead.
+    pub(crate) fn play(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if let Some(name) = self.named.take() {
+            if play_named(&name).is_ok() {
+                return Ok(());
+            }
+        } else if self.bells > 0 {
+            self.bells -= 1;
+        } else {
+            return Ok(());
+        }
+        out.write_all(b"\x07")?;
+        out.flush()
+    }
+}
+
+/// Plays a sound with the player at hand: `afplay` on macOS, `paplay` for sound files and
+/// `canberra-gtk-play` for sound theme names elsewhere.
+fn play_named(name: &str) -> io::Result<()> {
+    let file = name.contains('/');
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("afplay");
+        if file {
+            command.arg(name);
+        } else {
+            command.arg(Path::new("/System/Library/Sounds").join(format!("{name}.aiff")));
+        }
+        command
+    } else if file {
+        let mut command = Command::new("paplay");
+        command.arg(name);
+        command
+    } else {
+        let mut command = Command::new("canberra-gtk-play");
+        command.args(["--id", name]);
+        command
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Chime, Sound, parse_alert};
+
+    #[test]
+    fn alerts_ring_their_bells_one_at_a_time_and_drop_those_in_between() {
+        assert_eq!(
+            parse_alert("deploy=finished=complete"),
+            Ok((
+                "deploy=finished".to_string(),
+                Sound::Named("complete".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_alert("ERROR=3"),
+            Ok(("ERROR".to_string(), Sound::Bells(3)))
+        );
+        assert!(parse_alert("ERROR").is_err());
+        assert!(parse_alert("=3").is_err());
+        assert!(parse_alert("ERROR=0").is_err());
+
+        let now = Instant::now();
+        let mut chime = Chime::default();
+        let mut out = Vec::new();
+        chime.queue(&Sound::Bells(2), now);
+        chime.queue(&Sound::Bells(3), now + Duration::from_millis(300));
+        while chime.pending() {
+            chime.play(&mut out).expect("play failed");
+        }
+        assert_eq!(out, b"\x07\x07");
+
+        chime.queue(&Sound::Bells(1), now + Duration::from_secs(2));
+        assert!(chime.pending());
+    }
+}
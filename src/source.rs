@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use crate::parser::Format;
+
+/// A followed input file, labelling its lines when several files are followed at once.
+pub(crate) struct Source {
+    pub(crate) name: String,
+    pub(crate) hidden: bool,
+    pub(crate) format: Format,
+}
+
+impl Source {
+    pub(crate) fn new(name: String, format: Format) -> Self {
+        Self {
+            name,
+            hidden: false,
+            format,
+        }
+    }
+}
+
+/// A file to follow given with `--source`, e.g. `app.log:format=json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceSpec {
+    pub(crate) path: PathBuf,
+    pub(crate) format: Format,
+}
+
+/// Parses `PATH[:OPTION=VALUE,...]`. Options are only taken from after the last `:`, and only
+/// when they look like options, so paths containing `:` keep working.
+pub(crate) fn parse_source_spec(spec: &str) -> Result<SourceSpec, String> {
+    let (path, options) = match spec.rsplit_once(':') {
+        Some((path, options)) if options.contains('=') => (path, options),
+        _ => (spec, ""),
+    };
+    let mut source = SourceSpec {
+        path: PathBuf::from(path),
+        format: Format::Plain,
+    };
+    for option in options.split(',').filter(|option| !option.is_empty()) {
+        match option.split_once('=') {
+            Some(("format", name)) => {
+                source.format = Format::from_name(name).ok_or_else(|| {
+                    let names: Vec<&str> = Format::ALL.iter().map(|format| format.name()).collect();
+                    format!(
+                        "unknown format `{name}`, expected one of: {}",
+                        names.join(", ")
+                    )
+                })?;
+            }
+            _ => {
+                return Err(format!(
+                    "unknown source option `{option}`, expected format=FORMAT"
+                ));
+            }
+        }
+    }
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_source_spec;
+    use crate::parser::Format;
+    use std::path::Path;
+
+    #[test]
+    fn parses_path_and_format() {
+        let spec = parse_source_spec("logs/a.log:format=access").expect("parse failed");
+        assert_eq!(spec.path, Path::new("logs/a.log"));
+        assert_eq!(spec.format, Format::Access);
+
+        let spec = parse_source_spec("C:\\logs\\a.log").expect("parse failed");
+        assert_eq!(spec.path, Path::new("C:\\logs\\a.log"));
+        assert_eq!(spec.format, Format::Plain);
+
+        assert!(parse_source_spec("a.log:format=xml").is_err());
+        assert!(parse_source_spec("a.log:colour=red").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use std::time::{Duration, Instant};
+
+/// Template of the status bar shown by default: the position in the buffer while scrolled.
+pub(crate) const DEFAULT_STATUS: &str = "{position}";
+/// Template of the status bar shown by default with a preset telling failures apart.
+pub(crate) const FAILURES_STATUS: &str = "{failures} {position}";
+
+/// A value the status bar template can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Field {
+    /// Whether the view follows new lines.
+    Follow,
+    /// Name of the source of the line at the top of the view.
+    Source,
+    /// Number of lines shown, and buffered when filtered.
+    Lines,
+    /// Position of the top of the view, while scrolled.
+    Position,
+    /// Lines received per second.
+    Rate,
+    /// Number of lines matching the patterns.
+    Matches,
+    /// Number of lines reporting a failure, with a preset telling them apart.
+    Failures,
+}
+
+impl Field {
+    const ALL: [Field; 7] = [
+        Field::Follow,
+        Field::Source,
+        Field::Lines,
+        Field::Position,
+        Field::Rate,
+        Field::Matches,
+        Field::Failures,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Follow => "follow",
+            Field::Source => "source",
+            Field::Lines => "lines",
+            Field::Position => "position",
+            Field::Rate => "rate",
+            Field::Matches => "matches",
+            Field::Failures => "failures",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+/// The status bar content, e.g. `{follow} {lines} {rate}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StatusTemplate {
+    pieces: Vec<Piece>,
+}
+
+impl Default for StatusTemplate {
+    fn default() -> Self {
+        parse_status_template(DEFAULT_STATUS).expect("default status template is valid")
+    }
+}
+
+impl StatusTemplate {
+    #[cfg(test)]
+    pub(crate) fn contains(&self, field: Field) -> bool {
+        self.pieces.contains(&Piece::Field(field))
+    }
+
+    /// Fills in the fields, trimming the whitespace around fields that turned out empty.
+    pub(crate) fn render(&self, mut value: impl FnMut(Field) -> String) -> String {
+        let mut rendered = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => rendered.push_str(text),
+                Piece::Field(field) => rendered.push_str(&value(*field)),
+            }
+        }
+        rendered.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Parses a template with fields in braces, like `{follow} {lines}`. `{{` and `}}` stand for
+/// literal braces.
+pub(crate) fn parse_status_template(template: &str) -> Result<StatusTemplate, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("unclosed `{{` in `{template}`"))?;
+                let name = &rest[..end];
+                let field = Field::ALL
+                    .into_iter()
+                    .find(|field| field.name() == name)
+                    .ok_or_else(|| {
+                        let names: Vec<&str> =
+                            Field::ALL.iter().map(|field| field.name()).collect();
+                        format!(
+                            "unknown field `{{{name}}}`, expected one of: {}",
+                            names.join(", ")
+                        )
+                    })?;
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Field(field));
+                chars = rest[end + 1..].chars();
+            }
+            '}' => return Err(format!("unmatched `}}` in `{template}`")),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(StatusTemplate { pieces })
+}
+
+/// How long lines are counted for before the rate is updated.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Measures the rate at which lines are received.
+pub(crate) struct RateMeter {
+    window_start: Instant,
+    count: u32,
+    rate: f64,
+}
+
+impl RateMeter {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            count: 0,
+            rate: 0.0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            self.rate = f64::from(self.count) / elapsed.as_secs_f64();
+            self.window_start = now;
+            self.count = 0;
+        }
+        self.count += 1;
+    }
+
+    /// Lines per second over the last complete window, decaying while no lines come in.
+    pub(crate) fn rate(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            f64::from(self.count) / elapsed.as_secs_f64()
+        } else {
+            self.rate
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Field, RateMeter, parse_status_template};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn templates_render_fields_and_collapse_empty_ones() {
+        let template =
+            parse_status_template("{follow} {source} | {lines} {{raw}}").expect("parse failed");
+        let rendered = template.render(|field| match field {
+            Field::Follow => "follow".to_string(),
+            Field::Lines => "10 lines".to_string(),
+            _ => String::new(),
+        });
+
+        assert_eq!(rendered, "follow | 10 lines {raw}");
+        assert!(template.contains(Field::Source));
+        assert!(!template.contains(Field::Rate));
+    }
+
+    #[test]
+    fn templates_reject_unknown_and_unbalanced_fields() {
+        assert!(
+            parse_status_template("{speed}")
+                .is_err_and(|err| err.contains("unknown field `{speed}`"))
+        );
+        assert!(parse_status_template("{lines").is_err());
+        assert!(parse_status_template("lines}").is_err());
+    }
+
+    #[test]
+    fn rate_meter_counts_lines_per_window() {
+        let start = Instant::now();
+        let mut meter = RateMeter::new(start);
+        for i in 0..50 {
+            meter.record(start + Duration::from_millis(i * 10));
+        }
+        meter.record(start + Duration::from_secs(1));
+        assert_eq!(meter.rate(start + Duration::from_millis(1500)), 50.0);
+        assert_eq!(meter.rate(start + Duration::from_secs(3)), 0.5);
+    }
+}
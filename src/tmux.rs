@@ -0,0 +1,186 @@
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use clap::ValueEnum;
+
+use crate::LogrError;
+
+/// How the panes of a log wall are laid out, named after tmux's layouts.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Layout {
+    /// Up to four panes, two by two.
+    Quad,
+    /// Side by side.
+    Columns,
+    /// One above the other.
+    Rows,
+    /// As many rows and columns as it takes.
+    #[default]
+    Tiled,
+}
+
+impl Layout {
+    fn name(self) -> &'static str {
+        match self {
+            Layout::Quad | Layout::Tiled => "tiled",
+            Layout::Columns => "even-horizontal",
+            Layout::Rows => "even-vertical",
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct TmuxArgs {
+    /// How the panes are laid out
+    #[arg(long, value_enum, default_value_t = Layout::Tiled)]
+    layout: Layout,
+
+    /// The group the instances share their patterns in
+    #[arg(long, value_name = "GROUP", default_value = "wall")]
+    group: String,
+
+    /// Patterns highlighted in every pane, like `--patterns` of the viewer
+    #[arg(short, long, num_args = 1, value_delimiter = ',', action = clap::ArgAction::Append)]
+    patterns: Vec<String>,
+
+    /// Log files to follow, one per pane
+    #[arg(required = true, value_hint = clap::ValueHint::FilePath)]
+    files: Vec<PathBuf>,
+}
+
+/// Opens a tmux window, or a new session when not running inside tmux, with a logr pane
+/// following each file. The instances share their patterns with `--share`, so a pattern added
+/// in one pane is added in all of them.
+pub(crate) fn run(args: &TmuxArgs) -> Result<(), LogrError> {
+    if args.layout == Layout::Quad && args.files.len() > 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "the quad layout has room for 4 files, got {}",
+                args.files.len()
+            ),
+        )
+        .into());
+    }
+    let exe = env::current_exe()?;
+    let dir = env::current_dir()?;
+    let inside = env::var_os("TMUX").is_some_and(|tmux| !tmux.is_empty());
+    let status = Command::new("tmux")
+        .args(tmux_args(args, &exe, &dir, inside))
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("tmux failed: {status}")).into());
+    }
+    Ok(())
+}
+
+/// The tmux commands opening the panes, chained with `;` into a single invocation so each one
+/// acts on the window the first one opened. The panes start in `dir`, where the file paths
+/// were given, rather than wherever tmux would start them.
+fn tmux_args(args: &TmuxArgs, exe: &Path, dir: &Path, inside: bool) -> Vec<String> {
+    let mut tmux: Vec<String> = Vec::new();
+    for (i, file) in args.files.iter().enumerate() {
+        if i == 0 && inside {
+            tmux.extend([
+                "new-window".to_string(),
+                "-n".to_string(),
+                args.group.clone(),
+            ]);
+        } else if i == 0 {
+            // Detached, so the panes are in place by the time it is attached.
+            tmux.extend(["new-session", "-d", "-s"].map(String::from));
+            tmux.push(format!("logr-{}", args.group));
+        } else {
+            tmux.extend(["split-window", "-d"].map(String::from));
+        }
+        tmux.extend(["-c".to_string(), dir.display().to_string()]);
+        tmux.push(pane_command(args, exe, file));
+        // Laid out after every split, so there is room for the next one.
+        tmux.extend([";", "select-layout", "tiled", ";"].map(String::from));
+    }
+    tmux.extend(["select-layout", args.layout.name()].map(String::from));
+    if !inside {
+        tmux.extend([";", "attach-session"].map(String::from));
+    }
+    tmux
+}
+
+/// The shell command of a pane, running logr on the file in the group. The patterns are given
+/// after the file and joined to their option, so none of them is taken for the file or an option.
+fn pane_command(args: &TmuxArgs, exe: &Path, file: &Path) -> String {
+    let mut command = vec![
+        exe.display().to_string(),
+        "--share".to_string(),
+        args.group.clone(),
+        file.display().to_string(),
+    ];
+    if !args.patterns.is_empty() {
+        command.push(format!("--patterns={}", args.patterns.join(",")));
+    }
+    command
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quotes an argument for the shell tmux runs pane commands with, unless it needs none.
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=,:@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::Parser;
+
+    use super::{TmuxArgs, pane_command, shell_quote, tmux_args};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        tmux: TmuxArgs,
+    }
+
+    #[test]
+    fn panes_follow_a_file_each_in_a_shared_group() {
+        let cli = Cli::try_parse_from([
+            "tmux", "--layout", "quad", "-p", "error", "a.log", "b c.log",
+        ])
+        .expect("parse failed");
+        let exe = Path::new("/usr/bin/logr");
+        let dir = Path::new("/var/log/app");
+
+        let args = tmux_args(&cli.tmux, exe, dir, true);
+        assert_eq!(
+            args.join(" "),
+            "new-window -n wall -c /var/log/app /usr/bin/logr --share wall a.log \
+             --patterns=error ; select-layout tiled ; split-window -d -c /var/log/app \
+             /usr/bin/logr --share wall 'b c.log' --patterns=error ; select-layout tiled ; \
+             select-layout tiled"
+        );
+        let args = tmux_args(&cli.tmux, exe, dir, false);
+        assert_eq!(
+            args[..6],
+            ["new-session", "-d", "-s", "logr-wall", "-c", "/var/log/app"]
+        );
+        assert_eq!(args[args.len() - 2..], [";", "attach-session"]);
+
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+
+        let command = pane_command(&cli.tmux, exe, Path::new("a.log"));
+        let pane = crate::Args::try_parse_from(command.split(' ')).expect("pane parse failed");
+        assert_eq!(pane.files, [Path::new("a.log")]);
+        assert_eq!(pane.patterns, ["error"]);
+    }
+}
@@ -1,12 +1,35 @@
-use crate::{line_matches_patterns, max_start, AppState, PatternSpec};
+use crate::{
+    buffer::LogBuffer,
+    event::{input_cursor, KEYMAP},
+    idle::{clock, large, Idle, IdleScreen},
+    json::{self, JsonView, Token},
+    level::{Level, LevelToken},
+    logfmt, max_row_start, max_start,
+    parser::Format,
+    preset::Outcome,
+    redact::redact,
+    rewrite::rewrite,
+    scrolls_rows,
+    status::Field,
+    top_line, AppState, FollowAt, LineKind, LogLine, PatternMatch, PatternSpec,
+};
 use ansi_to_tui::IntoText as _;
 use ratatui::{
+    buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    ops::Range,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use unicode_segmentation::UnicodeSegmentation as _;
+use unicode_width::UnicodeWidthStr as _;
 
 const PATTERN_COLORS: [Color; 10] = [
     Color::Red,
@@ -21,51 +44,332 @@ const PATTERN_COLORS: [Color; 10] = [
     Color::LightBlue,
 ];
 
-fn pattern_color(index: usize) -> Color {
-    PATTERN_COLORS[index % PATTERN_COLORS.len()]
+const SOURCE_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::Red,
+];
+
+/// How long highlighting the rows of a frame may take before the rest are shown plain.
+const RENDER_BUDGET: Duration = Duration::from_millis(50);
+
+/// How many of the newest lines are previewed while the view is paused.
+const PREVIEW_LINES: usize = 3;
+
+/// How many lines up from the top of the view to look for a timestamp for the sticky header.
+const STICKY_LOOKBACK: usize = 1000;
+
+/// The color of a pattern's matches: its own, or the palette's color of its place.
+pub(crate) fn pattern_color(patterns: &[PatternSpec], index: usize) -> Color {
+    patterns
+        .get(index)
+        .and_then(|pattern| pattern.color)
+        .unwrap_or(PATTERN_COLORS[index % PATTERN_COLORS.len()])
 }
 
-pub(crate) fn ui(f: &mut Frame, lines: &[String], app: &AppState) {
+/// The style of a pattern's matches: its color, with its background and modifiers.
+fn pattern_style(patterns: &[PatternSpec], index: usize) -> Style {
+    let style = Style::default().fg(pattern_color(patterns, index));
+    match patterns.get(index) {
+        Some(pattern) => style.patch(pattern.style),
+        None => style,
+    }
+}
+
+/// The palette color after `color`, wrapping around, as the patterns dialog cycles through
+/// them. Colors not in the palette go on with its first.
+pub(crate) fn next_pattern_color(color: Color) -> Color {
+    let next = PATTERN_COLORS
+        .iter()
+        .position(|palette| *palette == color)
+        .map_or(0, |index| index + 1);
+    PATTERN_COLORS[next % PATTERN_COLORS.len()]
+}
+
+fn source_color(index: usize) -> Color {
+    SOURCE_COLORS[index % SOURCE_COLORS.len()]
+}
+
+pub(crate) fn ui(f: &mut Frame, lines: &LogBuffer, app: &AppState) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(0)
         .constraints([Constraint::Percentage(100)])
         .split(f.area());
 
-    let filtered_lines: Vec<&String> = if app.filter_only {
-        lines
-            .iter()
-            .filter(|line| line_matches_patterns(line, &app.patterns))
-            .collect()
-    } else {
-        lines.iter().collect()
-    };
+    let narrow = f.area().width < app.narrow_width;
+    // Only the lines on screen are looked up among those shown, which are kept as lines come
+    // and go rather than filtered for every frame.
+    let mut visible = app.visible.borrow_mut();
+    let total_lines = visible.len(lines, app);
 
     let content_height = chunks[0].height.saturating_sub(2) as usize;
-    let total_lines = filtered_lines.len();
-    let max_start = max_start(total_lines, content_height);
-    let start = if app.follow {
-        max_start
+    app.wrap_width.set(chunks[0].width.saturating_sub(2));
+    let (start, end, row_offset, percent) = if scrolls_rows(app) {
+        visible.count_rows(lines, app, app.wrap_width.get());
+        let visible = &*visible;
+        let rows = |position: usize| visible.rows(position);
+        let newest = max_row_start(total_lines, content_height, rows);
+        let (start, row_offset) = if app.follow {
+            newest
+        } else {
+            (app.scroll, app.row_offset).min(newest)
+        };
+        let row_offset = if start < total_lines {
+            row_offset.min(rows(start) - 1)
+        } else {
+            0
+        };
+        let mut end = start;
+        let mut filled = 0;
+        while end < total_lines && filled < row_offset + content_height {
+            filled += rows(end);
+            end += 1;
+        }
+        // Through the rows rather than the lines, as long lines take more of the scrollback.
+        let percent = ((start, row_offset) < newest).then(|| {
+            let above = visible.rows_before(start) + row_offset;
+            (above + 1) * 100 / visible.rows_before(total_lines)
+        });
+        (start, end, row_offset, percent)
     } else {
-        app.scroll.min(max_start)
+        let max_start = max_start(total_lines, content_height);
+        let start = if app.follow {
+            max_start
+        } else {
+            app.scroll.min(max_start)
+        };
+        let end = (start + content_height).min(total_lines);
+        let percent = (start < max_start).then(|| (start + 1) * 100 / total_lines);
+        (start, end, 0, percent)
     };
-    let rows = filtered_lines[start..]
-        .iter()
-        .map(|line| highlight_line(line, &app.patterns));
+    // Lines scrolled past above the top of the screen.
+    let hidden_above = match app.follow_at {
+        FollowAt::Bottom => start,
+        FollowAt::Top => total_lines - end,
+    };
+    let window = visible.lines(lines, app, start..end);
+    let first = window.first().copied();
+    let shown: Vec<&LogLine> = match app.follow_at {
+        FollowAt::Bottom => window,
+        FollowAt::Top => window.into_iter().rev().collect(),
+    };
+    let top = top_line(start, total_lines, content_height, app.follow_at);
+    let recent = visible.lines(
+        lines,
+        app,
+        (top + 1).saturating_sub(STICKY_LOOKBACK)..top + 1,
+    );
+    let preview = visible.lines(
+        lines,
+        app,
+        incoming_preview(total_lines, start, content_height, app.follow),
+    );
+    drop(visible);
+    // Lines are only labelled with their source when there is more than one to tell apart, and
+    // the room to spare.
+    let label_width = if app.sources.len() > 1 && !narrow {
+        app.sources.iter().map(|source| source.name.width()).max()
+    } else {
+        None
+    };
+    let number_width = app
+        .line_numbers
+        .then(|| shown.iter().filter_map(|line| line.number).max())
+        .flatten()
+        .map(|number| number.to_string().len());
+    let current_match = app
+        .search_match
+        .and_then(|id| lines.position(id))
+        .and_then(|index| lines.get(index));
+    let started = Instant::now();
+    let mut cut_short = false;
+    let mut rows = Vec::with_capacity(shown.len());
+    for line in &shown {
+        // Once highlighting took the frame's budget, the remaining rows are shown plain rather
+        // than letting a pathological pattern freeze the UI.
+        cut_short = cut_short || started.elapsed() > RENDER_BUDGET;
+        let mut rendered = if cut_short {
+            unhighlighted_row(line)
+        } else {
+            render_row(line, app)
+        };
+        if !cut_short
+            && let Some(search) = &app.search
+            && line.kind == LineKind::Output
+        {
+            let current = current_match.is_some_and(|current| std::ptr::eq(current, *line));
+            rendered = emphasize_matches(rendered, search, search_style(current));
+        }
+        if app.group_entries && line.continuations > 0 && !line.expanded {
+            rendered.spans.push(Span::styled(
+                (app.messages.folded)(line.continuations),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if line.repeats > 0 {
+            rendered.spans.push(Span::styled(
+                (app.messages.repeated)(line.repeats + 1),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if let (Some(width), Some(source)) = (label_width, line.source) {
+            rendered = with_source_label(rendered, app, source, width);
+        }
+        if let Some(selection) = app.selection
+            && line.number.is_some_and(|number| selection.contains(number))
+        {
+            rendered = rendered.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+        rows.push(match number_width {
+            Some(width) => with_line_number(rendered, line.number, width),
+            None => rendered,
+        });
+    }
 
-    let mut table = Paragraph::new(rows.collect::<Vec<_>>())
-        .block(Block::default())
-        .block(Block::new().borders(Borders::all()));
+    let mut block = Block::new().borders(Borders::all());
+    if let Some(summary) = &app.run_summary {
+        block = block.title(Span::styled(
+            summary.text.clone(),
+            run_summary_style(summary.success),
+        ));
+    }
+    if hidden_above > 0
+        && let Some(timestamp) = sticky_timestamp(&recent, app)
+    {
+        block = block.title(
+            Line::from(Span::styled(
+                format!(" {timestamp} "),
+                Style::default().fg(Color::Cyan),
+            ))
+            .centered(),
+        );
+    }
+    if let Some(pause) = &app.pause {
+        block = block.title(
+            Line::from(Span::styled(
+                (app.messages.paused)(pause.received),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .right_aligned(),
+        );
+    }
+    if let Some(min_level) = app.min_level {
+        block = block.title(
+            Line::from(Span::styled(
+                (app.messages.min_level)(min_level.name()),
+                level_style(min_level),
+            ))
+            .right_aligned(),
+        );
+    }
+    if lines.evicted() > 0 {
+        block = block.title(
+            Line::from(Span::styled(
+                (app.messages.discarded)(lines.evicted()),
+                Style::default().fg(Color::DarkGray),
+            ))
+            .right_aligned(),
+        );
+    }
+    if app.selection.is_some() {
+        block = block.title(
+            Line::from(Span::styled(
+                app.messages.selecting,
+                Style::default().fg(Color::Cyan),
+            ))
+            .right_aligned(),
+        );
+    }
+    if cut_short {
+        block = block.title(
+            Line::from(Span::styled(
+                app.messages.cut_short,
+                Style::default().fg(Color::Yellow),
+            ))
+            .right_aligned(),
+        );
+    }
+    let mut table = Paragraph::new(rows).block(Block::default()).block(block);
 
     if app.wrap {
-        table = table.wrap(Wrap { trim: false });
+        table = table
+            .wrap(Wrap { trim: false })
+            .scroll((u16::try_from(row_offset).unwrap_or(u16::MAX), 0));
     }
 
     f.render_widget(table, chunks[0]);
 
-    if chunks[0].height > 0 {
-        let hint = "p: patterns | w: wrap | f: filter | j/k: scroll down/up | ctrl-d/ctrl-u: page down/up | q: quit";
-        let hint_width = hint.len() as u16;
+    if !preview.is_empty() {
+        let height = preview.len() as u16;
+        // The strip sits on the side the new lines come in from.
+        let y = match app.follow_at {
+            FollowAt::Bottom => chunks[0].y + chunks[0].height.saturating_sub(1 + height),
+            FollowAt::Top => chunks[0].y + 1,
+        };
+        let area = Rect {
+            x: chunks[0].x + 1,
+            y,
+            width: chunks[0].width.saturating_sub(2),
+            height,
+        };
+        let dimmed = Style::default().add_modifier(Modifier::DIM);
+        let mut rows: Vec<Line> = preview
+            .iter()
+            .map(|line| render_line(line, app).patch_style(dimmed))
+            .collect();
+        if app.follow_at == FollowAt::Top {
+            rows.reverse();
+        }
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(rows), area);
+    }
+
+    let input_bar = if app.search_open {
+        Some(('/', &app.search_input, &app.search_error))
+    } else if app.prompt_open {
+        Some((':', &app.prompt_input, &app.prompt_error))
+    } else {
+        None
+    };
+    if let Some((prefix, input, error)) = input_bar
+        && chunks[0].height > 0
+    {
+        let mut spans = vec![Span::raw(format!("{prefix}{input}"))];
+        if let Some(err) = error {
+            spans.push(Span::styled(
+                format!("  {err}"),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        let area = Rect {
+            x: chunks[0].x + 1,
+            y: chunks[0].y + chunks[0].height.saturating_sub(1),
+            width: chunks[0].width.saturating_sub(2),
+            height: 1,
+        };
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(Line::from(spans)), area);
+    } else if let Some(status) = &app.status
+        && chunks[0].height > 0
+    {
+        let area = Rect {
+            x: chunks[0].x + 1,
+            y: chunks[0].y + chunks[0].height.saturating_sub(1),
+            width: chunks[0].width.saturating_sub(2),
+            height: 1,
+        };
+        let status_line = Paragraph::new(status.as_str()).style(Style::default().fg(Color::Yellow));
+        f.render_widget(Clear, area);
+        f.render_widget(status_line, area);
+    } else if chunks[0].height > 0 {
+        let hint = hint_text(app, narrow);
+        let hint_width = hint.width() as u16;
         let max_width = chunks[0].width.saturating_sub(2);
         if hint_width <= max_width {
             let area = Rect {
@@ -79,11 +383,9 @@ pub(crate) fn ui(f: &mut Frame, lines: &[String], app: &AppState) {
         }
     }
 
-    if total_lines > 0 && start < max_start {
-        let current_line = start.saturating_add(1);
-        let percent = (current_line * 100) / total_lines;
-        let status = format!("[{current_line}/{total_lines} ({percent}%)]");
-        let width = status.len() as u16;
+    let status = status_text(app, lines, total_lines, first, start, percent, narrow);
+    if !status.is_empty() {
+        let width = status.width() as u16;
         let max_width = chunks[0].width.saturating_sub(2);
         if width <= max_width && chunks[0].height > 0 {
             let x = chunks[0].x + chunks[0].width.saturating_sub(width + 1);
@@ -100,6 +402,7 @@ pub(crate) fn ui(f: &mut Frame, lines: &[String], app: &AppState) {
     }
 
     if app.dialog_open {
+        let now = Instant::now();
         let area = centered_rect(80, 60, f.area());
         f.render_widget(Clear, area);
         let mut dialog_lines = Vec::new();
@@ -107,10 +410,47 @@ pub(crate) fn ui(f: &mut Frame, lines: &[String], app: &AppState) {
         for (i, pattern) in app.patterns.iter().enumerate() {
             let prefix = if app.selected == i { "> " } else { "  " };
             let checkbox = if pattern.case_sensitive { "[x]" } else { "[ ]" };
-            dialog_lines.push(Line::from(Span::styled(
-                format!("{prefix}{checkbox} {}", pattern.pattern),
-                Style::default().fg(pattern_color(i)),
-            )));
+            let exclude = if pattern.exclude { "! " } else { "" };
+            let whole_line = if pattern.whole_line { "≡ " } else { "" };
+            let literal = if pattern.literal { "= " } else { "" };
+            let word = if pattern.word { "\\b " } else { "" };
+            let name = pattern
+                .name
+                .as_ref()
+                .map_or_else(String::new, |name| format!("{name}: "));
+            let mut style = pattern_style(&app.patterns, i);
+            if !pattern.enabled {
+                style = style.add_modifier(Modifier::DIM | Modifier::CROSSED_OUT);
+            } else if pattern.snooze.is_some() {
+                style = style.add_modifier(Modifier::DIM);
+            }
+            let mut spans = vec![Span::styled(
+                format!(
+                    "{prefix}{checkbox} {exclude}{whole_line}{literal}{word}{name}{}",
+                    pattern.pattern
+                ),
+                style,
+            )];
+            if let Some(sound) = &pattern.sound {
+                spans.push(Span::styled(
+                    sound.label(),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            if let Some(snooze) = pattern.snooze {
+                let remaining = snooze.remaining(now).as_secs();
+                spans.push(Span::styled(
+                    (app.messages.snoozed)(remaining / 60, remaining % 60),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            if let Some(per_line) = pattern.cost.slow() {
+                spans.push(Span::styled(
+                    (app.messages.slow)(per_line.as_micros()),
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+            dialog_lines.push(Line::from(spans));
         }
 
         if let Some(err) = &app.pattern_error {
@@ -120,32 +460,503 @@ pub(crate) fn ui(f: &mut Frame, lines: &[String], app: &AppState) {
             )));
         }
 
-        let input_style = if app.selected == app.patterns.len() {
+        let input_style = if let Some(index) = app.editing {
+            Style::default().fg(pattern_color(&app.patterns, index))
+        } else if app.selected == app.patterns.len() {
             Style::default().fg(Color::Cyan)
         } else {
             Style::default().fg(Color::White)
         };
+        // Edited patterns are marked in the input line with `~` rather than `+`.
+        let prefix = format!(
+            "{}{} ",
+            if app.selected == app.patterns.len() {
+                "> "
+            } else {
+                "  "
+            },
+            if app.editing.is_some() { "~" } else { "+" },
+        );
+        let before_cursor = Span::raw(&app.input[..input_cursor(app)]);
+        let cursor_x = usize::from(area.x) + 1 + prefix.len() + before_cursor.width();
+        let cursor_y = usize::from(area.y) + 1 + dialog_lines.len();
         dialog_lines.push(Line::from(Span::styled(
-            format!(
-                "{}+ {}",
-                if app.selected == app.patterns.len() {
-                    "> "
-                } else {
-                    "  "
-                },
-                app.input
-            ),
+            format!("{prefix}{}", app.input),
             input_style,
         )));
 
-        let dialog = Paragraph::new(dialog_lines).block(
-            Block::default()
-                .borders(Borders::all())
-                .title("Patterns (Enter: add, Del: delete, Left/Right: case, Esc: close)"),
-        );
+        let title = app.messages.patterns_title;
+        let dialog = Paragraph::new(dialog_lines)
+            .block(Block::default().borders(Borders::all()).title(title));
 
         f.render_widget(dialog, area);
+        // Past the border, the input line is cut off and so is the cursor.
+        if cursor_x + 1 < usize::from(area.right()) && cursor_y + 1 < usize::from(area.bottom()) {
+            f.set_cursor_position((cursor_x as u16, cursor_y as u16));
+        }
+    }
+
+    if app.sources_open {
+        render_sources(f, app);
+    }
+
+    if app.fields_open {
+        render_fields(f, app);
+    }
+
+    if let Some(index) = app.detail_line.and_then(|id| lines.position(id))
+        && let Some(line) = lines.get(index)
+    {
+        render_detail(f, index, line, app);
+    }
+
+    if app.failures_open {
+        render_failures(f, app);
+    }
+
+    if app.profiles_open {
+        render_profiles(f, app);
+    }
+
+    if app.help_open {
+        render_help(f, app);
+    }
+
+    if app.large_buffer_open {
+        render_large_buffer(f, lines, app);
+    }
+
+    if app.reveal_open {
+        render_reveal(f, app);
+    }
+
+    if let Some(idle) = &app.idle
+        && idle.blanked
+    {
+        render_idle(f, lines, app, idle);
+    }
+
+    if !app.colors {
+        strip_colors(f.buffer_mut());
+    }
+}
+
+/// Dims the view while idle, or covers it with how long no line came, large enough to be read
+/// from across the room, and the counts of the lines.
+fn render_idle(f: &mut Frame, lines: &LogBuffer, app: &AppState, idle: &Idle) {
+    let area = f.area();
+    if idle.screen == IdleScreen::Dim {
+        let dim = Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::DIM);
+        f.buffer_mut().set_style(area, dim);
+        return;
+    }
+    f.render_widget(Clear, area);
+
+    let elapsed = clock(idle.last_line.elapsed());
+    let mut content: Vec<Line> = large(&elapsed)
+        .into_iter()
+        .map(|row| Line::styled(row, Style::default().fg(Color::Yellow)).centered())
+        .collect();
+    content.push(Line::default());
+    content.push(Line::from((app.messages.idle_for)(&elapsed)).centered());
+    let mut counts = vec![
+        (app.messages.line_count)(&lines.len().to_string()),
+        (app.messages.match_count)(matching_line_count(lines)),
+    ];
+    if app.outcomes.is_some() {
+        let failures = lines
+            .iter()
+            .filter(|line| line.outcome == Some(Outcome::Failed))
+            .count();
+        counts.push((app.messages.failure_count)(failures));
+    }
+    content.push(Line::styled(counts.join(" | "), Style::default().fg(Color::DarkGray)).centered());
+
+    let top = area.height.saturating_sub(content.len() as u16) / 2;
+    let area = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    f.render_widget(Paragraph::new(content), area);
+}
+
+/// Takes the colors out of a drawn frame, keeping the modifiers so highlights done with bold
+/// or reversed text still show.
+fn strip_colors(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        cell.set_fg(Color::Reset).set_bg(Color::Reset);
+    }
+}
+
+fn render_help(f: &mut Frame, app: &AppState) {
+    let area = centered_rect(70, 80, f.area());
+    f.render_widget(Clear, area);
+
+    let mut dialog = Paragraph::new(help_lines(app)).block(
+        Block::default()
+            .borders(Borders::all())
+            .title(app.messages.help_title),
+    );
+    if app.help_wrap {
+        dialog = dialog.wrap(Wrap { trim: false });
+    }
+
+    f.render_widget(dialog, area);
+}
+
+/// The current toggles, then every key of the main view as the keymap lists it.
+fn help_lines(app: &AppState) -> Vec<Line<'static>> {
+    let toggles = (app.messages.toggles)(app.wrap, app.follow, app.filter_only, app.ignore_case);
+    let mut content = vec![
+        Line::from(Span::styled(
+            toggles,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    let width = KEYMAP
+        .iter()
+        .map(|binding| app.keys.listed(binding).len())
+        .max()
+        .unwrap_or(0);
+    content.extend(
+        KEYMAP
+            .iter()
+            .zip(app.messages.key_help)
+            .map(|(binding, (_, help))| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$}  ", app.keys.listed(binding)),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(help),
+                ])
+            }),
+    );
+    content
+}
+
+/// The hint under the view, naming the keys as bound.
+fn hint_text(app: &AppState, narrow: bool) -> String {
+    let (hint, gap, separator) = if narrow {
+        (app.messages.narrow_hint, ":", " ")
+    } else {
+        (app.messages.hint, ": ", " | ")
+    };
+    hint.iter()
+        .map(|(names, what)| {
+            let keys: Vec<&str> = names.iter().map(|name| app.keys.key(name)).collect();
+            format!("{}{gap}{what}", keys.join("/"))
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn render_large_buffer(f: &mut Frame, lines: &LogBuffer, app: &AppState) {
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let content = vec![
+        Line::from((app.messages.large_buffer_holds)(
+            lines.len(),
+            lines.estimated_bytes() >> 20,
+        )),
+        Line::from(""),
+        Line::from((app.messages.large_buffer_keep)(lines.len())),
+        Line::from((app.messages.large_buffer_spill)(lines.len())),
+        Line::from(app.messages.large_buffer_dedup),
+        Line::from(app.messages.large_buffer_keep_all),
+    ];
+    let dialog = Paragraph::new(content).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::all())
+            .title(app.messages.large_buffer_title)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+
+    f.render_widget(dialog, area);
+}
+
+fn render_reveal(f: &mut Frame, app: &AppState) {
+    let area = centered_rect(60, 30, f.area());
+    f.render_widget(Clear, area);
+
+    let content = vec![
+        Line::from(app.messages.reveal_warning),
+        Line::from(""),
+        Line::from(app.messages.reveal_confirm),
+    ];
+    let dialog = Paragraph::new(content).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::all())
+            .title(app.messages.reveal_title)
+            .border_style(Style::default().fg(Color::Red)),
+    );
+
+    f.render_widget(dialog, area);
+}
+
+fn render_fields(f: &mut Frame, app: &AppState) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let content: Vec<Line> = app
+        .field_keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let prefix = if app.field_selected == i { "> " } else { "  " };
+            let checkbox = if app.hidden_fields.contains(key) {
+                "[ ]"
+            } else {
+                "[x]"
+            };
+            Line::from(Span::styled(
+                format!("{prefix}{checkbox} {key}"),
+                json_style(Token::Key),
+            ))
+        })
+        .collect();
+    let dialog = Paragraph::new(content).block(
+        Block::default()
+            .borders(Borders::all())
+            .title(app.messages.fields_title),
+    );
+
+    f.render_widget(dialog, area);
+}
+
+fn render_profiles(f: &mut Frame, app: &AppState) {
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let content: Vec<Line> = app
+        .profiles
+        .iter()
+        .enumerate()
+        .map(|(i, (name, profile))| {
+            let prefix = if app.profile_selected == i {
+                "> "
+            } else {
+                "  "
+            };
+            // Excluding patterns are marked the way the patterns dialog marks them.
+            let patterns: Vec<String> = profile
+                .patterns
+                .iter()
+                .map(ToString::to_string)
+                .chain(profile.exclude.iter().map(|pattern| format!("!{pattern}")))
+                .collect();
+            Line::from(vec![
+                Span::styled(
+                    format!("{prefix}{name}"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("  {}", patterns.join(", ")),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+    let dialog = Paragraph::new(content).block(
+        Block::default()
+            .borders(Borders::all())
+            .title(app.messages.profiles_title),
+    );
+
+    f.render_widget(dialog, area);
+}
+
+fn render_failures(f: &mut Frame, app: &AppState) {
+    let area = centered_rect(80, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let width = app
+        .failures
+        .last()
+        .map_or(0, |(number, _)| number.to_string().len());
+    let content: Vec<Line> = app
+        .failures
+        .iter()
+        .enumerate()
+        .map(|(i, (number, text))| {
+            let prefix = if app.failure_selected == i {
+                "> "
+            } else {
+                "  "
+            };
+            Line::from(vec![
+                Span::raw(prefix),
+                Span::styled(
+                    format!("{number:>width$} "),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    masked(Cow::Owned(plain_text(text)), app).into_owned(),
+                    outcome_style(Outcome::Failed),
+                ),
+            ])
+        })
+        .collect();
+    let dialog = Paragraph::new(content).block(
+        Block::default()
+            .borders(Borders::all())
+            .title(app.messages.failures_title),
+    );
+
+    f.render_widget(dialog, area);
+}
+
+fn render_sources(f: &mut Frame, app: &AppState) {
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let content: Vec<Line> = app
+        .sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let prefix = if app.source_selected == i { "> " } else { "  " };
+            let checkbox = if source.hidden { "[ ]" } else { "[x]" };
+            Line::from(Span::styled(
+                format!(
+                    "{prefix}{checkbox} {} ({})",
+                    source.name,
+                    source.format.name()
+                ),
+                Style::default().fg(source_color(i)),
+            ))
+        })
+        .collect();
+    let dialog = Paragraph::new(content).block(
+        Block::default()
+            .borders(Borders::all())
+            .title(app.messages.sources_title),
+    );
+
+    f.render_widget(dialog, area);
+}
+
+fn render_detail(f: &mut Frame, index: usize, line: &LogLine, app: &AppState) {
+    let area = centered_rect(80, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let hex = app.detail_hex;
+    let mut content = provenance(line, app);
+    content.push(Line::default());
+    let text = masked(Cow::Borrowed(&line.text), app);
+    if hex {
+        content.extend(hex_dump(text.as_bytes()).into_iter().map(Line::from));
+    } else {
+        content.push(parse_ansi_line(&text));
     }
+    let title = (app.messages.detail_title)(index + 1, hex);
+    let mut detail =
+        Paragraph::new(content).block(Block::default().borders(Borders::all()).title(title));
+    if app.detail_wrap {
+        detail = detail.wrap(Wrap { trim: false });
+    }
+
+    f.render_widget(detail, area);
+}
+
+/// Where a line came from: its source, position in it, when it was received and what it was
+/// parsed and matched as.
+fn provenance(line: &LogLine, app: &AppState) -> Vec<Line<'static>> {
+    let source = line.source.and_then(|source| app.sources.get(source));
+    let format = line_format(line, app);
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{name:<10}"), Style::default().fg(Color::DarkGray)),
+            Span::raw(value),
+        ])
+    };
+
+    let mut matched = vec![Span::styled(
+        format!("{:<10}", "Patterns"),
+        Style::default().fg(Color::DarkGray),
+    )];
+    for (i, pattern) in app.patterns.iter().enumerate() {
+        if pattern.regex.is_match(&line.text) {
+            let exclude = if pattern.exclude { "!" } else { "" };
+            matched.push(Span::styled(
+                format!("{exclude}{} ", pattern.pattern),
+                Style::default().fg(pattern_color(&app.patterns, i)),
+            ));
+        }
+    }
+    if matched.len() == 1 {
+        matched.push(Span::raw("none"));
+    }
+
+    let mut fields = vec![
+        field(
+            "Source",
+            source.map_or_else(|| "-".to_string(), |source| source.name.clone()),
+        ),
+        field(
+            "Offset",
+            line.offset
+                .map_or_else(|| "-".to_string(), |offset| format!("byte {offset}")),
+        ),
+        field("Received", clock_time(line.received)),
+        field(
+            "Timestamp",
+            format
+                .timestamp(&line.text)
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+        field("Parser", format.name().to_string()),
+    ];
+    if let Some(enricher) = &app.enricher {
+        fields.extend(
+            enricher
+                .summaries(&line.text)
+                .into_iter()
+                .map(|summary| field("Address", summary)),
+        );
+    }
+    fields.push(Line::from(matched));
+    fields
+}
+
+/// Formats the time of day, in UTC, with millisecond precision.
+fn clock_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}.{:03} UTC",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Formats bytes as a classic hex dump: offset, 16 hex bytes and their printable ASCII.
+fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}  {:<47}  |{ascii}|", row * 16, hex.join(" "))
+        })
+        .collect()
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -174,32 +985,578 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn highlight_line(line: &str, patterns: &[PatternSpec]) -> Line<'static> {
-    let base_line = parse_ansi_line(line);
+/// Rows a line of the main view takes wrapped to `width` columns.
+pub(crate) fn visual_rows(line: &LogLine, app: &AppState, width: u16) -> usize {
+    Paragraph::new(render_row(line, app))
+        .wrap(Wrap { trim: false })
+        .line_count(width)
+        .max(1)
+}
+
+/// A line as shown in the main view, in its source's format, before search matches and
+/// source labels are marked on it.
+pub(crate) fn render_row(line: &LogLine, app: &AppState) -> Line<'static> {
+    let text = match line.kind {
+        LineKind::Output => {
+            let text = rewrite(&app.rewrites, app.human_epochs, &line.text);
+            let text = match &app.enricher {
+                Some(enricher) => enricher.annotate(text),
+                None => text,
+            };
+            masked(text, app)
+        }
+        _ => Cow::Borrowed(line.text.as_str()),
+    };
+    match (line.kind, line_format(line, app).render(&text)) {
+        (LineKind::Output, Some(text)) => highlight_line(&text, &app.patterns),
+        // The matches found at ingestion are in the raw text, so rewritten lines are matched
+        // again as shown.
+        (LineKind::Output, None) if matches!(text, Cow::Owned(_)) => {
+            let highlighted = highlight_line(&text, &app.patterns);
+            match line.outcome {
+                Some(outcome) => highlighted.style(outcome_style(outcome)),
+                None => highlighted,
+            }
+        }
+        (LineKind::Output, None) => render_json_line(line, app)
+            .or_else(|| render_logfmt_line(line, app))
+            .unwrap_or_else(|| render_line(line, app)),
+        _ => render_line(line, app),
+    }
+}
+
+/// The text with its secrets masked, unless they were revealed.
+fn masked<'a>(text: Cow<'a, str>, app: &AppState) -> Cow<'a, str> {
+    if app.revealed {
+        text
+    } else {
+        redact(&app.redactions, text)
+    }
+}
+
+/// A row shown as its text, without parsing escape sequences or matching any patterns, for
+/// when the frame ran out of time.
+fn unhighlighted_row(line: &LogLine) -> Line<'static> {
+    let text: String = line
+        .text
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\t')
+        .collect();
+    Line::from(Span::styled(
+        text,
+        Style::default().add_modifier(Modifier::DIM),
+    ))
+}
+
+/// The lines to leave in the terminal's scrollback on exit, oldest first: the ones on screen,
+/// or the newest `count` shown lines.
+pub(crate) fn exit_lines(
+    lines: &LogBuffer,
+    app: &AppState,
+    view_height: usize,
+    count: Option<usize>,
+) -> Vec<Line<'static>> {
+    let mut visible = app.visible.borrow_mut();
+    let total_lines = visible.len(lines, app);
+    let positions = match count {
+        Some(count) => total_lines.saturating_sub(count)..total_lines,
+        None => {
+            let max_start = max_start(total_lines, view_height);
+            let start = if app.follow {
+                max_start
+            } else {
+                app.scroll.min(max_start)
+            };
+            start..(start + view_height).min(total_lines)
+        }
+    };
+    let window = visible.lines(lines, app, positions);
+    drop(visible);
+    window.iter().map(|line| render_row(line, app)).collect()
+}
+
+fn render_line(line: &LogLine, app: &AppState) -> Line<'static> {
+    match line.kind {
+        LineKind::Output => {
+            let highlighted = match &line.matches {
+                Some(matches) if !line.text.contains(|c: char| c.is_control() && c != '\t') => {
+                    let level = line.level.filter(|_| app.level_colors);
+                    highlight_matches(&line.text, level, matches, &app.patterns)
+                }
+                _ => highlight_line(&line.text, &app.patterns),
+            };
+            match line.outcome {
+                Some(outcome) => highlighted.style(outcome_style(outcome)),
+                None => highlighted,
+            }
+        }
+        LineKind::RunSeparator => Line::from(Span::styled(
+            line.text.clone(),
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )),
+        LineKind::RunSummary { success } => {
+            Line::from(Span::styled(line.text.clone(), run_summary_style(success)))
+        }
+        LineKind::Marker => Line::from(Span::styled(
+            line.text.clone(),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        LineKind::DateSeparator => Line::from(Span::styled(
+            line.text.clone(),
+            Style::default().fg(Color::DarkGray),
+        )),
+    }
+}
+
+fn with_source_label(
+    mut line: Line<'static>,
+    app: &AppState,
+    source: usize,
+    width: usize,
+) -> Line<'static> {
+    if let Some(name) = app.sources.get(source).map(|source| &source.name) {
+        // Padded to the columns the name takes, which for CJK are twice its characters.
+        let padding = width.saturating_sub(name.width()) + 1;
+        let label = Span::styled(
+            format!("{name}{:padding$}", ""),
+            Style::default().fg(source_color(source)),
+        );
+        line.spans.insert(0, label);
+    }
+    line
+}
+
+/// Puts the number of the line in a gutter of `width` digits, left blank for lines logr
+/// inserted itself.
+fn with_line_number(mut line: Line<'static>, number: Option<usize>, width: usize) -> Line<'static> {
+    let gutter = match number {
+        Some(number) => format!("{number:>width$} "),
+        None => " ".repeat(width + 1),
+    };
+    line.spans.insert(
+        0,
+        Span::styled(gutter, Style::default().fg(Color::DarkGray)),
+    );
+    line
+}
+
+/// Search matches are shown reversed, except on the current match's line where they stand out
+/// even more.
+fn search_style(current: bool) -> Style {
+    if current {
+        Style::default()
+            .fg(Color::Black)
+            .bg(Color::Yellow)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().add_modifier(Modifier::REVERSED)
+    }
+}
+
+/// Levels get their own colors rather than pattern ones, and are overridden by pattern
+/// highlights.
+fn level_style(level: Level) -> Style {
+    match level {
+        Level::Trace => Style::default().fg(Color::DarkGray),
+        Level::Debug => Style::default().fg(Color::Blue),
+        Level::Info => Style::default().fg(Color::Green),
+        Level::Warn => Style::default().fg(Color::Yellow),
+        Level::Error => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        Level::Fatal => Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+    }
+}
+
+/// Restyles the text between `start` and `end`, on top of its existing style.
+fn restyle(line: Line<'static>, start: usize, end: usize, style: Style) -> Line<'static> {
+    let plain = line_plain_text(&line);
+    let len = plain.len();
+    let (start, end) = Graphemes::new(&plain).widen(start, end);
+    let mut spans = slice_line_spans(&line, 0, start);
+    for mut span in slice_line_spans(&line, start, end) {
+        span.style = span.style.patch(style);
+        spans.push(span);
+    }
+    spans.extend(slice_line_spans(&line, end, len));
+    Line {
+        style: line.style,
+        alignment: line.alignment,
+        spans,
+    }
+}
+
+/// Restyles the parts of `line` matched by `regex`, on top of their existing style.
+fn emphasize_matches(line: Line<'static>, regex: &Regex, style: Style) -> Line<'static> {
+    let plain = line_plain_text(&line);
+    let graphemes = Graphemes::new(&plain);
+    let mut spans = Vec::new();
+    let mut cursor = 0;
+    for mat in regex.find_iter(&plain) {
+        let (start, end) = graphemes.widen(mat.start(), mat.end());
+        if start == end || end <= cursor {
+            continue;
+        }
+        let start = start.max(cursor);
+        spans.extend(slice_line_spans(&line, cursor, start));
+        for mut span in slice_line_spans(&line, start, end) {
+            span.style = span.style.patch(style);
+            spans.push(span);
+        }
+        cursor = end;
+    }
+
+    if cursor == 0 {
+        return line;
+    }
+    spans.extend(slice_line_spans(&line, cursor, plain.len()));
+    Line {
+        style: line.style,
+        alignment: line.alignment,
+        spans,
+    }
+}
+
+/// Fills in the status bar template for the view starting at `start`, in short forms when
+/// `narrow`.
+fn status_text(
+    app: &AppState,
+    lines: &LogBuffer,
+    total_lines: usize,
+    first: Option<&LogLine>,
+    start: usize,
+    percent: Option<usize>,
+    narrow: bool,
+) -> String {
+    app.status_template.render(|field| match field {
+        Field::Follow => if app.follow {
+            app.messages.following
+        } else {
+            app.messages.not_following
+        }
+        .to_string(),
+        Field::Source => first
+            .and_then(|line| line.source)
+            .and_then(|source| app.sources.get(source))
+            .map(|source| source.name.clone())
+            .unwrap_or_default(),
+        Field::Lines => {
+            let count = if total_lines == lines.len() {
+                total_lines.to_string()
+            } else {
+                format!("{total_lines}/{}", lines.len())
+            };
+            if narrow {
+                count
+            } else {
+                (app.messages.line_count)(&count)
+            }
+        }
+        Field::Position => match percent {
+            Some(percent) if narrow => format!("[{percent}%]"),
+            Some(percent) => format!("[{}/{total_lines} ({percent}%)]", start + 1),
+            None => String::new(),
+        },
+        Field::Rate => format!("{:.0}/s", app.rate.rate(Instant::now())),
+        Field::Matches if narrow => format!("{}m", matching_line_count(lines)),
+        Field::Matches => (app.messages.match_count)(matching_line_count(lines)),
+        Field::Failures if app.outcomes.is_none() => String::new(),
+        Field::Failures => {
+            let count = lines
+                .iter()
+                .filter(|line| line.outcome == Some(Outcome::Failed))
+                .count();
+            if narrow {
+                format!("{count}!")
+            } else {
+                (app.messages.failure_count)(count)
+            }
+        }
+    })
+}
+
+/// Number of lines matching any of the (non-exclude) patterns, as of their cached matches.
+fn matching_line_count(lines: &LogBuffer) -> usize {
+    lines
+        .iter()
+        .filter(|line| {
+            line.matches
+                .as_ref()
+                .is_some_and(|matches| matches.iter().any(|found| !found.exclude))
+        })
+        .count()
+}
+
+/// Renders an output line that is a JSON object as set by the JSON view, or returns `None` for
+/// other lines (and for every line in the raw view).
+fn render_json_line(line: &LogLine, app: &AppState) -> Option<Line<'static>> {
+    match app.json_view {
+        JsonView::Raw => None,
+        JsonView::Colored => {
+            let text = &line.text;
+            let mut spans = Vec::new();
+            let mut cursor = 0;
+            for (range, token) in json::tokenize(text)? {
+                if cursor < range.start {
+                    spans.push(Span::raw(text[cursor..range.start].to_string()));
+                }
+                cursor = range.end;
+                spans.push(Span::styled(text[range].to_string(), json_style(token)));
+            }
+            if cursor < text.len() {
+                spans.push(Span::raw(text[cursor..].to_string()));
+            }
+            // The colored line keeps the raw text, so the matches found at ingestion still apply.
+            Some(match &line.matches {
+                Some(matches) => {
+                    highlight_cached(Line::from(spans), text.len(), matches, &app.patterns)
+                }
+                None => highlight_spans(Line::from(spans), &app.patterns),
+            })
+        }
+        JsonView::Flat => {
+            let mut spans = Vec::new();
+            for (key, value) in json::flatten(&line.text)? {
+                if !spans.is_empty() {
+                    spans.push(Span::raw(" "));
+                }
+                spans.push(Span::styled(key, json_style(Token::Key)));
+                spans.push(Span::styled("=", json_style(Token::Punctuation)));
+                spans.push(Span::styled(
+                    json::value_text(&value),
+                    json_style(Token::of(&value)),
+                ));
+            }
+            Some(highlight_spans(Line::from(spans), &app.patterns))
+        }
+    }
+}
+
+/// Renders an output line in logfmt with its keys and values colored like JSON ones, leaving
+/// out the hidden fields, or returns `None` for other lines.
+fn render_logfmt_line(line: &LogLine, app: &AppState) -> Option<Line<'static>> {
+    let text = &line.text;
+    let pairs = logfmt::parse(text)?;
+    let mut spans = Vec::new();
+    let mut cursor = 0;
+    let mut intact = true;
+    for pair in pairs {
+        if app.hidden_fields.contains(&text[pair.key.clone()]) {
+            intact = false;
+            cursor = pair.value.end;
+            continue;
+        }
+        // Whitespace left over from hidden fields before the first shown one is dropped.
+        if !spans.is_empty() || cursor == 0 {
+            spans.push(Span::raw(text[cursor..pair.key.start].to_string()));
+        }
+        let value = &text[pair.value.clone()];
+        spans.push(Span::styled(
+            text[pair.key.clone()].to_string(),
+            json_style(Token::Key),
+        ));
+        spans.push(Span::styled("=", json_style(Token::Punctuation)));
+        spans.push(Span::styled(
+            value.to_string(),
+            json_style(logfmt_token(value)),
+        ));
+        cursor = pair.value.end;
+    }
+    spans.push(Span::raw(text[cursor..].to_string()));
+    // Unless fields were left out, the line keeps the raw text the cached matches were found in.
+    Some(match &line.matches {
+        Some(matches) if intact => {
+            highlight_cached(Line::from(spans), text.len(), matches, &app.patterns)
+        }
+        _ => highlight_spans(Line::from(spans), &app.patterns),
+    })
+}
+
+/// Colors logfmt values by what they look like, as they are all just text.
+fn logfmt_token(value: &str) -> Token {
+    if value.starts_with('"') {
+        Token::String
+    } else if value.parse::<f64>().is_ok() {
+        Token::Number
+    } else if matches!(value, "true" | "false" | "null") {
+        Token::Literal
+    } else {
+        Token::String
+    }
+}
+
+fn json_style(token: Token) -> Style {
+    let color = match token {
+        Token::Key => Color::Cyan,
+        Token::String => Color::Green,
+        Token::Number => Color::Magenta,
+        Token::Literal => Color::Yellow,
+        Token::Punctuation => Color::DarkGray,
+    };
+    Style::default().fg(color)
+}
+
+/// Positions among the lines shown of the newest ones, while the view is paused above them,
+/// to show what's coming in without resuming.
+fn incoming_preview(
+    total_lines: usize,
+    start: usize,
+    view_height: usize,
+    follow: bool,
+) -> Range<usize> {
+    let hidden = total_lines.saturating_sub(start + view_height);
+    if follow || hidden == 0 {
+        return total_lines..total_lines;
+    }
+    total_lines - hidden.min(PREVIEW_LINES)..total_lines
+}
+
+/// The format of a line's source, plain for lines without one.
+pub(crate) fn line_format(line: &LogLine, app: &AppState) -> Format {
+    line.source
+        .and_then(|source| app.sources.get(source))
+        .map_or(Format::Plain, |source| source.format)
+}
+
+/// The latest timestamp of the lines shown up to the one at the top of the view, telling when
+/// the view is even when that line carries none. Without any, lines of sources without timestamps tell
+/// when they were received.
+fn sticky_timestamp(lines: &[&LogLine], app: &AppState) -> Option<String> {
+    let mut recent = lines
+        .iter()
+        .rev()
+        .take(STICKY_LOOKBACK)
+        .filter(|line| line.kind == LineKind::Output);
+    recent
+        .clone()
+        .find_map(|line| line_format(line, app).timestamp(&line.text))
+        .or_else(|| {
+            recent
+                .find(|line| !app.timestamped.contains(&line.source))
+                .map(|line| clock_time(line.received))
+        })
+}
+
+/// Lines reporting a passed or failed test take the colors of run summaries, under the
+/// highlights.
+fn outcome_style(outcome: Outcome) -> Style {
+    run_summary_style(outcome == Outcome::Passed)
+}
+
+fn run_summary_style(success: bool) -> Style {
+    let color = if success { Color::Green } else { Color::Red };
+    Style::default().fg(color).add_modifier(Modifier::BOLD)
+}
+
+pub(crate) fn highlight_line(line: &str, patterns: &[PatternSpec]) -> Line<'static> {
+    highlight_spans(parse_ansi_line(line), patterns)
+}
+
+/// Highlights the pattern matches in an already styled line, on top of its styles.
+fn highlight_spans(base_line: Line<'static>, patterns: &[PatternSpec]) -> Line<'static> {
     let plain = line_plain_text(&base_line);
+    if let Some(index) = patterns.iter().position(|pattern| {
+        pattern.whole_line && !pattern.exclude && pattern.active() && pattern.regex.is_match(&plain)
+    }) {
+        return style_whole_line(base_line, pattern_style(patterns, index));
+    }
 
-    let mut ranges: Vec<(usize, usize, usize, Color)> = Vec::new();
+    let mut ranges: Vec<(usize, usize, usize, Style)> = Vec::new();
     for (index, pattern) in patterns.iter().enumerate() {
-        let color = pattern_color(index);
-        for mat in pattern.regex.find_iter(&plain) {
-            let start = mat.start();
-            let end = mat.end();
-            if start < end {
-                ranges.push((start, end, index, color));
-            }
+        if pattern.exclude || !pattern.active() {
+            continue;
+        }
+        let style = pattern_style(patterns, index);
+        for (start, end) in pattern.highlights(&plain) {
+            ranges.push((start, end, index, style));
         }
     }
 
+    apply_highlights(base_line, plain.len(), ranges)
+}
+
+/// Highlights a line using the matches (and colors its level) as found when it was ingested.
+/// Only valid for lines without escape sequences (or other control characters), as matches are
+/// found in the raw text rather than the displayed one.
+fn highlight_matches(
+    line: &str,
+    level: Option<LevelToken>,
+    matches: &[PatternMatch],
+    patterns: &[PatternSpec],
+) -> Line<'static> {
+    let mut base_line = parse_ansi_line(line);
+    if let Some(level) = level {
+        base_line = restyle(base_line, level.start, level.end, level_style(level.level));
+    }
+    highlight_cached(base_line, line.len(), matches, patterns)
+}
+
+/// Highlights cached matches in a styled line whose text is the raw text they were found in.
+fn highlight_cached(
+    base_line: Line<'static>,
+    text_len: usize,
+    matches: &[PatternMatch],
+    patterns: &[PatternSpec],
+) -> Line<'static> {
+    let whole_line = matches
+        .iter()
+        .filter(|found| !found.exclude)
+        .map(|found| found.pattern)
+        .filter(|&index| {
+            patterns
+                .get(index)
+                .is_some_and(|pattern| pattern.whole_line)
+        })
+        .min();
+    if let Some(index) = whole_line {
+        return style_whole_line(base_line, pattern_style(patterns, index));
+    }
+    let ranges = matches
+        .iter()
+        .filter(|found| !found.exclude)
+        .map(|found| {
+            (
+                found.start,
+                found.end,
+                found.pattern,
+                pattern_style(patterns, found.pattern),
+            )
+        })
+        .collect();
+    apply_highlights(base_line, text_len, ranges)
+}
+
+/// Styles a line matching a whole-line pattern throughout, over its own styles, without
+/// slicing its spans at the matches.
+fn style_whole_line(mut line: Line<'static>, style: Style) -> Line<'static> {
+    for span in &mut line.spans {
+        span.style = span.style.patch(style);
+    }
+    line
+}
+
+fn apply_highlights(
+    base_line: Line<'static>,
+    text_len: usize,
+    mut ranges: Vec<(usize, usize, usize, Style)>,
+) -> Line<'static> {
     if ranges.is_empty() {
         return base_line;
     }
 
+    let graphemes = Graphemes::new(&line_plain_text(&base_line));
+    for range in &mut ranges {
+        (range.0, range.1) = graphemes.widen(range.0, range.1);
+    }
     ranges.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.2.cmp(&b.2)));
     let mut spans = Vec::new();
     let mut cursor = 0;
-    let text_len = plain.len();
 
-    for (mut start, end, _, color) in ranges {
+    for (mut start, end, _, style) in ranges {
         if end <= cursor {
             continue;
         }
@@ -210,7 +1567,7 @@ fn highlight_line(line: &str, patterns: &[PatternSpec]) -> Line<'static> {
             spans.extend(slice_line_spans(&base_line, cursor, start));
         }
         for mut span in slice_line_spans(&base_line, start, end) {
-            span.style = span.style.fg(color);
+            span.style = span.style.patch(style);
             spans.push(span);
         }
         cursor = end;
@@ -234,6 +1591,11 @@ fn parse_ansi_line(line: &str) -> Line<'static> {
     }
 }
 
+/// The text of a line without its escape sequences.
+pub(crate) fn plain_text(text: &str) -> String {
+    line_plain_text(&parse_ansi_line(text))
+}
+
 fn line_plain_text(line: &Line<'_>) -> String {
     let mut out = String::new();
     for span in &line.spans {
@@ -261,8 +1623,10 @@ fn slice_line_spans(line: &Line<'_>, start: usize, end: usize) -> Vec<Span<'stat
             break;
         }
 
-        let slice_start = start.saturating_sub(span_start);
-        let slice_end = (end - span_start).min(len);
+        // Cached matches and levels are found in the raw text, so a line displayed differently
+        // could have them end mid-character.
+        let slice_start = floor_char_boundary(&span.content, start.saturating_sub(span_start));
+        let slice_end = floor_char_boundary(&span.content, (end - span_start).min(len));
         if slice_start < slice_end {
             spans.push(Span::styled(
                 span.content[slice_start..slice_end].to_string(),
@@ -275,3 +1639,620 @@ fn slice_line_spans(line: &Line<'_>, start: usize, end: usize) -> Vec<Span<'stat
 
     spans
 }
+
+/// Where the grapheme clusters of a text start, and where it ends. Highlights are widened to the
+/// clusters they touch, so a match doesn't split an emoji sequence, a flag or a character and its
+/// combining marks into spans rendered apart.
+struct Graphemes(Vec<usize>);
+
+impl Graphemes {
+    fn new(text: &str) -> Self {
+        Graphemes(
+            text.grapheme_indices(true)
+                .map(|(index, _)| index)
+                .chain([text.len()])
+                .collect(),
+        )
+    }
+
+    /// The range from the start of the cluster `start` falls in to the end of the one `end`
+    /// falls in, clamped to the text.
+    fn widen(&self, start: usize, end: usize) -> (usize, usize) {
+        let len = self.0[self.0.len() - 1];
+        let start = self.0[self.0.partition_point(|&index| index <= start) - 1];
+        let end = self
+            .0
+            .get(self.0.partition_point(|&index| index < end))
+            .copied()
+            .unwrap_or(len);
+        (start, end.max(start))
+    }
+}
+
+/// The start of the character `index` falls in.
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clock_time, emphasize_matches, exit_lines, help_lines, hex_dump, highlight_line,
+        highlight_matches, hint_text, incoming_preview, line_plain_text, matching_line_count,
+        parse_ansi_line, provenance, render_json_line, render_line, render_logfmt_line, render_row,
+        search_style, slice_line_spans, status_text, sticky_timestamp, strip_colors,
+        unhighlighted_row, with_line_number,
+    };
+    use crate::{
+        buffer::LogBuffer, build_pattern, build_patterns, json::JsonView, parser::Format,
+        push_line, rewrite::Rewrite, source::Source, status::parse_status_template, AppState,
+        LogLine, PatternMatch,
+    };
+    use proptest::prelude::*;
+    use ratatui::{
+        buffer::Buffer,
+        layout::Rect,
+        style::{Color, Modifier, Style},
+        text::{Line, Span},
+    };
+    use regex::Regex;
+    use std::{
+        num::NonZeroUsize,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    #[test]
+    fn hex_dump_formats_offsets_and_ascii() {
+        let dump = hex_dump(b"Hello, binary\x00\x01\x02 world");
+
+        assert_eq!(dump.len(), 2);
+        assert_eq!(
+            dump[0],
+            "00000000  48 65 6c 6c 6f 2c 20 62 69 6e 61 72 79 00 01 02  |Hello, binary...|"
+        );
+        assert_eq!(
+            dump[1],
+            format!("00000010  {:<47}  | world|", "20 77 6f 72 6c 64")
+        );
+    }
+
+    #[test]
+    fn provenance_lists_source_offset_timestamp_parser_and_patterns() {
+        let patterns = vec![
+            build_pattern("error".to_string(), true).expect("pattern build failed"),
+            build_pattern("warn".to_string(), true).expect("pattern build failed"),
+        ];
+        let mut app = AppState::new(patterns, false);
+        app.sources = vec![Source::new("api.log".to_string(), Format::Json)];
+        let mut line =
+            LogLine::output(r#"{"ts":"12:00:01","msg":"error"}"#.to_string()).with_source(Some(0));
+        line.offset = Some(42);
+        line.received = UNIX_EPOCH + Duration::from_millis(90_061_005);
+
+        let fields: Vec<String> = provenance(&line, &app)
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "Source    api.log",
+                "Offset    byte 42",
+                "Received  01:01:01.005 UTC",
+                "Timestamp 12:00:01",
+                "Parser    json",
+                "Patterns  error ",
+            ]
+        );
+    }
+
+    #[test]
+    fn sticky_timestamp_falls_back_to_lines_above_the_top() {
+        let app = AppState::new(Vec::new(), false);
+        let lines: Vec<LogLine> = [
+            "12:00:00 start",
+            "12:00:05 request",
+            "  at handler.rs:10",
+            "  at main.rs:3",
+        ]
+        .into_iter()
+        .map(|text| LogLine::output(text.to_string()))
+        .collect();
+        let lines: Vec<&LogLine> = lines.iter().collect();
+
+        assert_eq!(sticky_timestamp(&lines, &app).as_deref(), Some("12:00:05"));
+        assert_eq!(
+            sticky_timestamp(&lines[..1], &app).as_deref(),
+            Some("12:00:00")
+        );
+    }
+
+    #[test]
+    fn sticky_timestamp_falls_back_to_when_untimed_lines_were_received() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut line = LogLine::output("no time here".to_string());
+        line.received = UNIX_EPOCH + Duration::from_millis(3_723_004);
+
+        assert_eq!(
+            sticky_timestamp(&[&line], &app).as_deref(),
+            Some("01:02:03.004 UTC")
+        );
+        app.timestamped.insert(None);
+        assert_eq!(sticky_timestamp(&[&line], &app), None);
+    }
+
+    #[test]
+    fn cached_matches_highlight_like_matching_on_the_fly() {
+        let mut exclude = build_pattern("debug".to_string(), true).expect("pattern build failed");
+        exclude.exclude = true;
+        let patterns = vec![
+            build_pattern("err\\w*".to_string(), true).expect("pattern build failed"),
+            build_pattern("or".to_string(), true).expect("pattern build failed"),
+            exclude,
+        ];
+        let mut app = AppState::new(patterns, false);
+        let mut lines = LogBuffer::default();
+        let text = "debug: error in worker\tport 80";
+        push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+
+        let matches = lines[0].matches.as_deref().expect("matches not computed");
+        assert_eq!(matches.len(), 5);
+        assert_eq!(
+            highlight_matches(text, None, matches, &app.patterns),
+            highlight_line(text, &app.patterns)
+        );
+    }
+
+    #[test]
+    fn pattern_styles_are_merged_over_the_ansi_style() {
+        let patterns = build_patterns(&["FATAL:white+on-red+bold".to_string()], false)
+            .expect("pattern build failed");
+        let line = highlight_line("\x1b[4mFATAL\x1b[0m: disk full", &patterns);
+
+        assert_eq!(line.spans[0].content, "FATAL");
+        let style = line.spans[0].style;
+        assert_eq!(style.fg, Some(Color::White));
+        assert_eq!(style.bg, Some(Color::Red));
+        assert!(style
+            .add_modifier
+            .contains(Modifier::BOLD | Modifier::UNDERLINED));
+        assert_eq!(line_plain_text(&line), "FATAL: disk full");
+    }
+
+    #[test]
+    fn whole_line_patterns_style_the_line_throughout() {
+        let texts = ["disk".to_string(), "FATAL:white+on-red+line".to_string()];
+        let patterns = build_patterns(&texts, false).expect("pattern build failed");
+        let mut app = AppState::new(patterns, false);
+        let mut lines = LogBuffer::default();
+        let text = "\x1b[4mFATAL\x1b[0m: disk full";
+        push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+
+        let line = highlight_line(text, &app.patterns);
+        assert_eq!(line.spans.len(), 2);
+        assert!(line
+            .spans
+            .iter()
+            .all(|span| span.style.bg == Some(Color::Red)));
+        assert!(line.spans[0]
+            .style
+            .add_modifier
+            .contains(Modifier::UNDERLINED));
+        assert_eq!(line_plain_text(&line), "FATAL: disk full");
+
+        let plain = "FATAL: disk full";
+        push_line(&mut app, &mut lines, LogLine::output(plain.to_string()));
+        let matches = lines[1].matches.as_deref().expect("matches not computed");
+        assert_eq!(
+            highlight_matches(plain, None, matches, &app.patterns),
+            highlight_line(plain, &app.patterns)
+        );
+    }
+
+    #[test]
+    fn capture_groups_are_highlighted_instead_of_the_whole_match() {
+        let texts = [r"user=(\w+)", r"took (\d+)?ms"].map(String::from);
+        let patterns = build_patterns(&texts, false).expect("pattern build failed");
+        let mut app = AppState::new(patterns, false);
+        let mut lines = LogBuffer::default();
+        let text = "user=ann took ms";
+        push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+
+        let line = highlight_line(text, &app.patterns);
+        let highlighted: Vec<&str> = line
+            .spans
+            .iter()
+            .filter(|span| span.style.fg.is_some())
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(highlighted, ["ann", "took ms"]);
+        let matches = lines[0].matches.as_deref().expect("matches not computed");
+        assert_eq!(highlight_matches(text, None, matches, &app.patterns), line);
+    }
+
+    #[test]
+    fn highlights_keep_emoji_combining_marks_and_cjk_whole() {
+        let texts = ["e", "💻", "日本"].map(String::from);
+        let patterns = build_patterns(&texts, false).expect("pattern build failed");
+        let text = "cafe\u{301} 👩\u{200d}💻 日本語 🇵🇱";
+
+        let line = highlight_line(text, &patterns);
+        let highlighted: Vec<&str> = line
+            .spans
+            .iter()
+            .filter(|span| span.style.fg.is_some())
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(highlighted, ["e\u{301}", "👩\u{200d}💻", "日本"]);
+        assert_eq!(line_plain_text(&line), text);
+        let search = Regex::new("🇵|語").expect("regex build failed");
+        let emphasized = emphasize_matches(line, &search, search_style(true));
+        let marked: Vec<&str> = emphasized
+            .spans
+            .iter()
+            .filter(|span| span.style.bg.is_some())
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(marked, ["語", "🇵🇱"]);
+    }
+
+    #[test]
+    fn rewritten_lines_are_matched_as_shown_and_kept_raw() {
+        let patterns = vec![build_pattern("UTC|Z$".to_string(), true).expect("build failed")];
+        let mut app = AppState::new(patterns, false);
+        app.rewrites = vec![Rewrite::parse(r"s/(\d{10})/<ts:$1>/").expect("parse failed")];
+        let mut lines = LogBuffer::default();
+        let text = "started 1700000000";
+        push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+
+        let row = render_row(&lines[0], &app);
+        assert_eq!(line_plain_text(&row), "started 2023-11-14T22:13:20.000000Z");
+        assert_eq!(
+            row.spans.last().map(|span| span.content.as_ref()),
+            Some("Z")
+        );
+        assert_eq!(lines[0].text, text);
+    }
+
+    #[test]
+    fn clock_time_wraps_around_days() {
+        let time = UNIX_EPOCH + Duration::from_secs(86_400 + 3_600);
+        assert_eq!(clock_time(time), "01:00:00.000 UTC");
+    }
+
+    #[test]
+    fn emphasize_matches_restyles_only_matched_text() {
+        let regex = Regex::new("err").expect("regex build failed");
+        let line = emphasize_matches(Line::from("an err and err"), &regex, search_style(true));
+
+        let spans: Vec<(&str, bool)> = line
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style == search_style(true)))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("an ", false),
+                ("err", true),
+                (" and ", false),
+                ("err", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn matching_line_count_ignores_exclude_patterns() {
+        let mut exclude = build_pattern("health".to_string(), true).expect("pattern build failed");
+        exclude.exclude = true;
+        let mut app = AppState::new(
+            vec![
+                build_pattern("GET".to_string(), true).expect("pattern build failed"),
+                exclude,
+            ],
+            false,
+        );
+        let mut lines = LogBuffer::new(None);
+        for text in ["GET /a", "GET /health", "POST /health", "POST /b"] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+
+        assert_eq!(matching_line_count(&lines), 2);
+    }
+
+    #[test]
+    fn status_text_is_shortened_when_narrow() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.status_template =
+            parse_status_template("{follow} {lines} {position}").expect("template parse failed");
+        app.follow = false;
+        let lines = LogBuffer::from(
+            (0..10)
+                .map(|i| LogLine::output(format!("line {i}")))
+                .collect::<Vec<_>>(),
+        );
+        let first = lines.get(4);
+
+        assert_eq!(
+            status_text(&app, &lines, 10, first, 4, Some(50), false),
+            "paused 10 lines [5/10 (50%)]"
+        );
+        assert_eq!(
+            status_text(&app, &lines, 10, first, 4, Some(50), true),
+            "paused 10 [50%]"
+        );
+        assert_eq!(
+            status_text(&app, &lines, 10, lines.get(8), 8, None, true),
+            "paused 10"
+        );
+    }
+
+    #[test]
+    fn json_lines_are_colored_or_flattened_with_patterns_highlighted() {
+        let patterns = vec![build_pattern("err".to_string(), true).expect("pattern build failed")];
+        let mut app = AppState::new(patterns, false);
+        let mut lines = LogBuffer::default();
+        for text in [r#"{"msg":"err","n":1}"#, "err: not json"] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        let spans = |line: Line<'static>| -> Vec<(String, Option<Color>)> {
+            line.spans
+                .into_iter()
+                .map(|span| (span.content.into_owned(), span.style.fg))
+                .collect()
+        };
+
+        let colored = render_json_line(&lines[0], &app).expect("line is JSON");
+        assert_eq!(line_plain_text(&colored), lines[0].text);
+        assert!(spans(colored).contains(&("err".to_string(), Some(Color::Red))));
+        assert!(render_json_line(&lines[1], &app).is_none());
+
+        app.json_view = JsonView::Flat;
+        let flat = render_json_line(&lines[0], &app).expect("line is JSON");
+        assert_eq!(line_plain_text(&flat), "msg=err n=1");
+        assert!(spans(flat).contains(&("1".to_string(), Some(Color::Magenta))));
+
+        app.json_view = JsonView::Raw;
+        assert!(render_json_line(&lines[0], &app).is_none());
+    }
+
+    #[test]
+    fn incoming_preview_shows_the_newest_hidden_lines_while_paused() {
+        assert_eq!(incoming_preview(10, 0, 4, false), 7..10);
+        assert_eq!(incoming_preview(10, 5, 4, false), 9..10);
+        assert!(incoming_preview(10, 6, 4, false).is_empty());
+        assert!(incoming_preview(10, 0, 4, true).is_empty());
+    }
+
+    #[test]
+    fn logfmt_lines_are_colored_and_hidden_fields_left_out() {
+        let patterns = vec![build_pattern("auth".to_string(), true).expect("pattern build failed")];
+        let mut app = AppState::new(patterns, false);
+        let mut lines = LogBuffer::default();
+        let text = r#"ts=12:00 level=error msg="token expired" svc=auth"#;
+        push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+
+        let colored = render_logfmt_line(&lines[0], &app).expect("line is logfmt");
+        assert_eq!(line_plain_text(&colored), text);
+        let styled: Vec<(String, Option<Color>)> = colored
+            .spans
+            .into_iter()
+            .map(|span| (span.content.into_owned(), span.style.fg))
+            .collect();
+        assert!(styled.contains(&("level".to_string(), Some(Color::Cyan))));
+        assert!(styled.contains(&("auth".to_string(), Some(Color::Red))));
+
+        app.hidden_fields.insert("ts".to_string());
+        app.hidden_fields.insert("msg".to_string());
+        let trimmed = render_logfmt_line(&lines[0], &app).expect("line is logfmt");
+        assert_eq!(line_plain_text(&trimmed), "level=error svc=auth");
+    }
+
+    #[test]
+    fn level_tokens_are_colored_unless_turned_off() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::default();
+        let text = "12:00:01 WARN disk almost full";
+        push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        let level_color = |app: &AppState| {
+            render_line(&lines[0], app)
+                .spans
+                .iter()
+                .find(|span| span.content == "WARN")
+                .and_then(|span| span.style.fg)
+        };
+
+        assert_eq!(level_color(&app), Some(Color::Yellow));
+        app.level_colors = false;
+        assert_eq!(level_color(&app), None);
+    }
+
+    #[test]
+    fn line_numbers_survive_eviction_and_leave_inserted_lines_blank() {
+        let mut app = AppState::new(Vec::new(), false);
+        let mut lines = LogBuffer::new(NonZeroUsize::new(2));
+        for text in ["a", "b", "c"] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+        }
+        push_line(
+            &mut app,
+            &mut lines,
+            LogLine::marker("--- file rotated ---"),
+        );
+
+        let numbered = with_line_number(Line::from("c"), lines[0].number, 3);
+        assert_eq!(line_plain_text(&numbered), "  3 c");
+        let blank = with_line_number(Line::from("marker"), lines[1].number, 3);
+        assert_eq!(line_plain_text(&blank), "    marker");
+    }
+
+    #[test]
+    fn stripping_colors_keeps_modifiers() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 4, 1));
+        buffer.set_string(
+            0,
+            0,
+            "warn",
+            Style::default()
+                .fg(Color::Yellow)
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        );
+
+        strip_colors(&mut buffer);
+
+        let cell = &buffer.content[0];
+        assert_eq!((cell.fg, cell.bg), (Color::Reset, Color::Reset));
+        assert!(cell.modifier.contains(Modifier::BOLD));
+        assert_eq!(cell.symbol(), "w");
+    }
+
+    #[test]
+    fn exit_lines_are_the_screen_or_the_newest_lines() {
+        let mut app = AppState::new(Vec::new(), false);
+        let lines = LogBuffer::from(
+            (0..6)
+                .map(|i| LogLine::output(i.to_string()))
+                .collect::<Vec<_>>(),
+        );
+        let texts = |kept: Vec<Line>| -> Vec<String> { kept.iter().map(line_plain_text).collect() };
+
+        assert_eq!(texts(exit_lines(&lines, &app, 2, None)), vec!["4", "5"]);
+        assert_eq!(
+            texts(exit_lines(&lines, &app, 2, Some(3))),
+            vec!["3", "4", "5"]
+        );
+        app.follow = false;
+        app.scroll = 1;
+        assert_eq!(texts(exit_lines(&lines, &app, 2, None)), vec!["1", "2"]);
+        assert_eq!(exit_lines(&lines, &app, 2, Some(10)).len(), 6);
+    }
+
+    #[test]
+    fn help_lists_the_toggles_and_every_key() {
+        let mut app = AppState::new(Vec::new(), true);
+        app.filter_only = true;
+        let help: Vec<String> = help_lines(&app).iter().map(line_plain_text).collect();
+
+        assert_eq!(
+            help[0],
+            "wrap: off | follow: on | filter: on | case: insensitive"
+        );
+        assert_eq!(help.len(), crate::event::KEYMAP.len() + 2);
+        assert!(help
+            .iter()
+            .any(|line| line.starts_with("?  ") && line.ends_with("Show this help")));
+    }
+
+    #[test]
+    fn hints_name_the_keys_as_bound() {
+        let mut app = AppState::new(Vec::new(), false);
+        assert_eq!(
+            hint_text(&app, false),
+            "?: help | p: patterns | w: wrap | f: filter | j/k: scroll down/up \
+             | Ctrl-d/Ctrl-u: page down/up | q: quit"
+        );
+
+        let keys = std::collections::BTreeMap::from([("help".to_string(), vec!["F1".to_string()])]);
+        app.keys = crate::keys::KeyBindings::new(&keys).expect("bindings failed");
+        app.messages = crate::messages::Lang::Pl.messages();
+        assert_eq!(
+            hint_text(&app, true),
+            "F1:pomoc p:wzorce w:zawijanie f:filtr q:wyjście"
+        );
+        let help: Vec<String> = help_lines(&app).iter().map(line_plain_text).collect();
+        assert!(help
+            .iter()
+            .any(|line| line.starts_with("F1  ") && line.ends_with("Pokaż tę pomoc")));
+    }
+
+    #[test]
+    fn unhighlighted_rows_drop_escape_sequences_and_patterns() {
+        let line = LogLine::output("\x1b[31merror\x1b[0m\tdone".to_string());
+        let row = unhighlighted_row(&line);
+
+        assert_eq!(line_plain_text(&row), "[31merror[0m\tdone");
+        assert!(row.spans.iter().all(|span| span.style.fg.is_none()));
+    }
+
+    /// Plain text, tabs and escape sequences (well-formed or cut short) in any mix.
+    fn hostile_line() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop_oneof![
+                "\\PC{1,8}",
+                Just("\t".to_string()),
+                "\x1b\\[[0-9;:]{0,12}[@-~]?",
+                "\x1b[\\]P_^].{0,6}(\x07|\x1b\\\\)?",
+                "[\x00-\x1f\x7f\u{80}-\u{9f}]",
+            ],
+            0..12,
+        )
+        .prop_map(|parts| parts.concat())
+    }
+
+    fn fuzz_patterns() -> Vec<crate::PatternSpec> {
+        ["error", r"\d+", "é|ü", r"\s", "^.", "$"]
+            .iter()
+            .map(|pattern| build_pattern(pattern.to_string(), false).expect("pattern build failed"))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn highlighting_keeps_the_text_of_any_line(line in hostile_line()) {
+            let highlighted = highlight_line(&line, &fuzz_patterns());
+            prop_assert_eq!(
+                line_plain_text(&highlighted),
+                line_plain_text(&parse_ansi_line(&line))
+            );
+        }
+
+        #[test]
+        fn slicing_anywhere_keeps_whole_characters(
+            parts in prop::collection::vec("\\PC{0,6}", 0..6),
+            first in 0..48usize,
+            second in 0..48usize,
+        ) {
+            let line = Line::from(parts.iter().cloned().map(Span::raw).collect::<Vec<_>>());
+            let plain = line_plain_text(&line);
+            let (start, end) = (first.min(second), first.max(second));
+            let text = |start, end| {
+                slice_line_spans(&line, start, end)
+                    .iter()
+                    .map(|span| span.content.to_string())
+                    .collect::<String>()
+            };
+
+            prop_assert_eq!(
+                text(0, start) + &text(start, end) + &text(end, plain.len()),
+                plain
+            );
+        }
+
+        #[test]
+        fn cached_matches_out_of_step_with_the_text_keep_it_whole(
+            line in "\\PC{0,24}",
+            found in prop::collection::vec((0..32usize, 0..8usize), 0..4),
+        ) {
+            let matches: Vec<PatternMatch> = found
+                .into_iter()
+                .enumerate()
+                .map(|(pattern, (start, len))| PatternMatch {
+                    start,
+                    end: start + len,
+                    pattern,
+                    exclude: false,
+                })
+                .collect();
+            let level = crate::level::detect(&line);
+
+            prop_assert_eq!(
+                line_plain_text(&highlight_matches(&line, level, &matches, &[])),
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! The log view as a ratatui widget, for embedding in other TUIs.
+//!
+//! ```
+//! use crossterm::event::{KeyCode, KeyEvent};
+//! use logr::{LogView, LogViewState};
+//! use ratatui::{buffer::Buffer, layout::Rect, widgets::{Block, StatefulWidget}};
+//!
+//! let mut state = LogViewState::new(&["error".to_string()], false)?;
+//! let sender = state.sender();
+//! std::thread::spawn(move || {
+//!     sender.send("GET / 200");
+//!     sender.send("error: connection reset");
+//! })
+//! .join()
+//! .unwrap();
+//!
+//! let area = Rect::new(0, 0, 40, 5);
+//! let mut buffer = Buffer::empty(area);
+//! LogView::default()
+//!     .block(Block::bordered().title("api"))
+//!     .render(area, &mut buffer, &mut state);
+//! // Keys scrolling or filtering the view are taken, others are left to the app.
+//! assert!(state.handle_key(KeyEvent::from(KeyCode::Char('f'))));
+//! assert!(!state.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+//! # Ok::<(), logr::LogrError>(())
+//! ```
+
+use std::{num::NonZeroUsize, sync::mpsc};
+
+use crossterm::event::KeyEvent;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::Line,
+    widgets::{Block, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+use crate::{
+    AppState, LogLine, LogrError, PatternSpec, anchor_scroll, buffer::LogBuffer, build_patterns,
+    event::handle_view_key, max_start, push_line, settle_scroll, ui::render_row,
+};
+
+/// The lines shown by a [`LogView`], with the patterns highlighted in them and where the view
+/// is scrolled to. Like logr's own view, it follows new lines until scrolled up.
+pub struct LogViewState {
+    lines: LogBuffer,
+    app: AppState,
+    /// Lines sent through a [`LogSender`], added to the view when it is next rendered.
+    incoming: mpsc::Receiver<String>,
+    sender: mpsc::Sender<String>,
+    /// Height of the view when last rendered, which scrolling to the newest lines depends on.
+    height: usize,
+}
+
+/// A handle adding lines to a [`LogViewState`] from other threads or tasks, like those reading
+/// the logs. Cloned for each of them.
+#[derive(Clone)]
+pub struct LogSender {
+    sender: mpsc::Sender<String>,
+}
+
+impl LogSender {
+    /// Sends a line to the view, returning whether it is still there to show it.
+    pub fn send(&self, text: impl Into<String>) -> bool {
+        self.sender.send(text.into()).is_ok()
+    }
+}
+
+impl LogViewState {
+    /// A view highlighting the regex `patterns`, matched without case with `ignore_case`.
+    pub fn new(patterns: &[String], ignore_case: bool) -> Result<Self, LogrError> {
+        Ok(Self::with_patterns(build_patterns(patterns, ignore_case)?))
+    }
+
+    /// A view highlighting patterns built with [`PatternSpec`]'s methods or read through serde.
+    pub fn with_patterns(patterns: Vec<PatternSpec>) -> Self {
+        let (sender, incoming) = mpsc::channel();
+        LogViewState {
+            lines: LogBuffer::new(None),
+            app: AppState::new(patterns, false),
+            incoming,
+            sender,
+            height: 0,
+        }
+    }
+
+    /// A handle adding lines to the view, for the threads or tasks reading them.
+    pub fn sender(&self) -> LogSender {
+        LogSender {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Keeps only the newest `max_lines` lines, evicting the oldest.
+    #[must_use]
+    pub fn with_max_lines(mut self, max_lines: NonZeroUsize) -> Self {
+        self.lines.set_max_lines(max_lines);
+        self
+    }
+
+    /// Appends a line, matching the patterns against it.
+    pub fn push(&mut self, text: impl Into<String>) {
+        push_line(&mut self.app, &mut self.lines, LogLine::output(text.into()));
+    }
+
+    /// Removes every line, following new ones again.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.app.follow = true;
+        self.app.scroll = 0;
+        anchor_scroll(&mut self.app, &self.lines);
+    }
+
+    /// Number of lines held, shown or filtered out.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Whether no lines are held.
+    pub fn is_empty(&self) -> bool {
+        self.lines.len() == 0
+    }
+
+    /// Shows only the lines matching the patterns, or every line again.
+    pub fn set_filter_only(&mut self, filter_only: bool) {
+        if self.app.filter_only != filter_only {
+            self.app.filter_only = filter_only;
+            self.app.follow = true;
+            anchor_scroll(&mut self.app, &self.lines);
+        }
+    }
+
+    /// Whether the view shows the newest lines as they come in, rather than staying where it
+    /// was scrolled to.
+    pub fn is_following(&self) -> bool {
+        self.app.follow
+    }
+
+    /// Scrolls towards older lines, no longer following new ones.
+    pub fn scroll_up(&mut self, lines: usize) {
+        settle_scroll(&mut self.app, &self.lines);
+        if self.app.follow {
+            self.app.follow = false;
+            self.app.scroll = self.max_start();
+        }
+        self.app.scroll = self.app.scroll.saturating_sub(lines);
+        anchor_scroll(&mut self.app, &self.lines);
+    }
+
+    /// Scrolls towards newer lines, following them again once at the newest.
+    pub fn scroll_down(&mut self, lines: usize) {
+        settle_scroll(&mut self.app, &self.lines);
+        if !self.app.follow {
+            self.app.scroll += lines;
+            self.app.follow = self.app.scroll >= self.max_start();
+        }
+        anchor_scroll(&mut self.app, &self.lines);
+    }
+
+    /// Follows new lines again.
+    pub fn follow(&mut self) {
+        self.app.follow = true;
+        anchor_scroll(&mut self.app, &self.lines);
+    }
+
+    /// Handles a key the way logr's main view does, as far as scrolling the view, toggling the
+    /// filter, wrapping, clearing and undoing go. Returns whether the key was taken, so the app
+    /// can handle the others.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        self.receive();
+        settle_scroll(&mut self.app, &self.lines);
+        handle_view_key(&mut self.app, &mut self.lines, self.height, key)
+    }
+
+    /// Adds the lines sent through the [`LogSender`]s.
+    fn receive(&mut self) {
+        while let Ok(text) = self.incoming.try_recv() {
+            self.push(text);
+        }
+    }
+
+    fn max_start(&self) -> usize {
+        let total = self.app.visible.borrow_mut().len(&self.lines, &self.app);
+        max_start(total, self.height)
+    }
+}
+
+/// Renders the lines of a [`LogViewState`], highlighted as logr shows them, in an optional
+/// block.
+#[derive(Default)]
+pub struct LogView<'a> {
+    block: Option<Block<'a>>,
+}
+
+impl<'a> LogView<'a> {
+    #[must_use]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl StatefulWidget for LogView<'_> {
+    type State = LogViewState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut LogViewState) {
+        state.receive();
+        let inner = self.block.as_ref().map_or(area, |block| block.inner(area));
+        if let Some(block) = self.block {
+            block.render(area, buf);
+        }
+        state.height = inner.height as usize;
+        settle_scroll(&mut state.app, &state.lines);
+        let start = if state.app.follow {
+            state.max_start()
+        } else {
+            state.app.scroll.min(state.max_start())
+        };
+        let window = state.app.visible.borrow_mut().lines(
+            &state.lines,
+            &state.app,
+            start..start + state.height,
+        );
+        let rows: Vec<Line> = window
+            .into_iter()
+            .map(|line| render_row(line, &state.app))
+            .collect();
+        let mut paragraph = Paragraph::new(rows);
+        if state.app.wrap {
+            paragraph = paragraph.wrap(Wrap { trim: false });
+        }
+        paragraph.render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent};
+    use ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+
+    use super::{LogView, LogViewState};
+
+    fn rendered(state: &mut LogViewState) -> Vec<String> {
+        let area = Rect::new(0, 0, 8, 2);
+        let mut buffer = Buffer::empty(area);
+        LogView::default().render(area, &mut buffer, state);
+        (0..area.height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_view_follows_new_lines_until_scrolled_up() {
+        let mut state = LogViewState::new(&["b".to_string()], false).expect("patterns");
+        for text in ["a", "b", "c"] {
+            state.push(text);
+        }
+        assert_eq!(rendered(&mut state), ["b", "c"]);
+
+        state.scroll_up(1);
+        state.push("d");
+        assert_eq!(rendered(&mut state), ["a", "b"]);
+        assert!(!state.is_following());
+
+        state.scroll_down(2);
+        assert!(state.is_following());
+        assert_eq!(rendered(&mut state), ["c", "d"]);
+
+        state.set_filter_only(true);
+        assert_eq!(rendered(&mut state), ["b", ""]);
+    }
+
+    #[test]
+    fn keys_scroll_and_filter_the_view_like_the_main_one() {
+        let mut state = LogViewState::new(&["b".to_string()], false).expect("patterns");
+        let sender = state.sender();
+        for text in ["a", "b", "c"] {
+            assert!(sender.send(text));
+        }
+        assert_eq!(rendered(&mut state), ["b", "c"]);
+
+        assert!(state.handle_key(KeyEvent::from(KeyCode::Up)));
+        assert_eq!(rendered(&mut state), ["a", "b"]);
+        assert!(state.handle_key(KeyEvent::from(KeyCode::Char('f'))));
+        assert_eq!(rendered(&mut state), ["b", ""]);
+        assert!(!state.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+    }
+}
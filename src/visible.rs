@@ -0,0 +1,369 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    ops::Range,
+};
+
+use crate::{
+    AppState, LogLine, buffer::LogBuffer, filter::FilterExpr, json::JsonView, level::Level,
+    line_visible, parser::Format, ui::visual_rows,
+};
+
+/// Everything besides the lines themselves that decides which of them are shown. When any of
+/// it changes, the lines shown are looked for again.
+#[derive(PartialEq)]
+struct Settings {
+    /// See [`LogBuffer::generation`].
+    generation: u64,
+    filter_only: bool,
+    filter_expr: Option<FilterExpr>,
+    min_level: Option<Level>,
+    group_entries: bool,
+    hidden_sources: Vec<bool>,
+    /// The text, name and regex of each pattern, whether it is active and whether it excludes.
+    patterns: Vec<(String, Option<String>, String, bool, bool)>,
+}
+
+impl Settings {
+    fn of(lines: &LogBuffer, app: &AppState) -> Self {
+        Self {
+            generation: lines.generation(),
+            filter_only: app.filter_only,
+            filter_expr: app.filter_expr.clone(),
+            min_level: app.min_level,
+            group_entries: app.group_entries,
+            hidden_sources: app.sources.iter().map(|source| source.hidden).collect(),
+            patterns: app
+                .patterns
+                .iter()
+                .map(|pattern| {
+                    (
+                        pattern.pattern.clone(),
+                        pattern.name.clone(),
+                        pattern.regex.as_str().to_string(),
+                        pattern.active(),
+                        pattern.exclude,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Everything besides the lines themselves and the width that decides how many rows they take
+/// wrapped. When any of it changes, their rows are counted again.
+#[derive(PartialEq)]
+struct Layout {
+    width: u16,
+    human_epochs: bool,
+    revealed: bool,
+    json_view: JsonView,
+    hidden_fields: BTreeSet<String>,
+    formats: Vec<Format>,
+    /// See [`crate::enrich::Enricher::finished`].
+    lookups: usize,
+}
+
+impl Layout {
+    fn of(app: &AppState, width: u16) -> Self {
+        Self {
+            width,
+            human_epochs: app.human_epochs,
+            revealed: app.revealed,
+            json_view: app.json_view,
+            hidden_fields: app.hidden_fields.clone(),
+            formats: app.sources.iter().map(|source| source.format).collect(),
+            lookups: app
+                .enricher
+                .as_ref()
+                .map_or(0, |enricher| enricher.finished()),
+        }
+    }
+}
+
+/// The rows each line shown takes wrapped, in step with their ids.
+struct Rows {
+    layout: Layout,
+    counts: VecDeque<usize>,
+    total: usize,
+}
+
+/// The ids of the lines the (possibly filtered) view shows, kept as lines are pushed and
+/// evicted so drawing the view, counting its lines or finding the one it starts at doesn't go
+/// through the whole buffer for every frame.
+#[derive(Default)]
+pub(crate) struct VisibleLines {
+    settings: Option<Settings>,
+    /// Oldest first, like the lines.
+    ids: VecDeque<u64>,
+    /// Id of the newest line looked at. It is looked at again, as it may still change while it
+    /// is the last line.
+    checked: Option<u64>,
+    /// Counted once asked for with [`VisibleLines::count_rows`], and kept from then on.
+    rows: Option<Rows>,
+}
+
+impl VisibleLines {
+    /// Number of lines shown before the line at `index`, or of all of them when it is past the
+    /// end of the buffer.
+    pub(crate) fn before(&mut self, lines: &LogBuffer, app: &AppState, index: usize) -> usize {
+        self.update(lines, app);
+        match lines.get(index) {
+            Some(line) => self.ids.partition_point(|&id| id < line.id),
+            None => self.ids.len(),
+        }
+    }
+
+    /// Number of lines shown: those buffered or, while paused, those up to where it was paused.
+    pub(crate) fn len(&mut self, lines: &LogBuffer, app: &AppState) -> usize {
+        let end = app.pause.as_ref().map_or(lines.len(), |pause| pause.end);
+        self.before(lines, app, end)
+    }
+
+    /// The lines shown at `positions` among them.
+    pub(crate) fn lines<'a>(
+        &mut self,
+        lines: &'a LogBuffer,
+        app: &AppState,
+        positions: Range<usize>,
+    ) -> Vec<&'a LogLine> {
+        self.update(lines, app);
+        let end = positions.end.min(self.ids.len());
+        self.ids
+            .range(positions.start.min(end)..end)
+            .filter_map(|&id| lines.position(id))
+            .map(|index| &lines[index])
+            .collect()
+    }
+
+    /// Counts the rows the lines shown take wrapped to `width`, to be looked up with
+    /// [`VisibleLines::rows`] and [`VisibleLines::rows_before`]. Only the lines that came in
+    /// since are counted, unless how lines are shown changed.
+    pub(crate) fn count_rows(&mut self, lines: &LogBuffer, app: &AppState, width: u16) {
+        self.update(lines, app);
+        let layout = Layout::of(app, width);
+        if self.rows.as_ref().is_some_and(|rows| rows.layout == layout) {
+            return;
+        }
+        let counts: VecDeque<usize> = self
+            .ids
+            .iter()
+            .filter_map(|&id| lines.position(id))
+            .map(|index| visual_rows(&lines[index], app, width))
+            .collect();
+        self.rows = Some(Rows {
+            layout,
+            total: counts.iter().sum(),
+            counts,
+        });
+    }
+
+    /// Rows the line shown at `position` takes, as last counted.
+    pub(crate) fn rows(&self, position: usize) -> usize {
+        self.rows
+            .as_ref()
+            .and_then(|rows| rows.counts.get(position))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Rows the lines shown before `position` take, as last counted. Summed from whichever end
+    /// is nearer.
+    pub(crate) fn rows_before(&self, position: usize) -> usize {
+        let Some(rows) = &self.rows else {
+            return position;
+        };
+        let position = position.min(rows.counts.len());
+        if position <= rows.counts.len() / 2 {
+            rows.counts.range(..position).sum()
+        } else {
+            rows.total - rows.counts.range(position..).sum::<usize>()
+        }
+    }
+
+    fn push_back(&mut self, line: &LogLine, app: &AppState) {
+        self.ids.push_back(line.id);
+        if let Some(rows) = &mut self.rows {
+            let count = visual_rows(line, app, rows.layout.width);
+            rows.counts.push_back(count);
+            rows.total += count;
+        }
+    }
+
+    fn push_front(&mut self, line: &LogLine, app: &AppState) {
+        self.ids.push_front(line.id);
+        if let Some(rows) = &mut self.rows {
+            let count = visual_rows(line, app, rows.layout.width);
+            rows.counts.push_front(count);
+            rows.total += count;
+        }
+    }
+
+    fn pop_front(&mut self) {
+        self.ids.pop_front();
+        if let Some(rows) = &mut self.rows {
+            rows.total -= rows.counts.pop_front().unwrap_or(0);
+        }
+    }
+
+    fn pop_back(&mut self) {
+        self.ids.pop_back();
+        if let Some(rows) = &mut self.rows {
+            rows.total -= rows.counts.pop_back().unwrap_or(0);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.ids.clear();
+        self.checked = None;
+        if let Some(rows) = &mut self.rows {
+            rows.counts.clear();
+            rows.total = 0;
+        }
+    }
+
+    fn update(&mut self, lines: &LogBuffer, app: &AppState) {
+        let settings = Settings::of(lines, app);
+        if self.settings.as_ref() != Some(&settings) {
+            self.settings = Some(settings);
+            self.clear();
+        }
+        let Some(first) = lines.get(0) else {
+            self.clear();
+            return;
+        };
+        while self.ids.front().is_some_and(|&id| id < first.id) {
+            self.pop_front();
+        }
+        // Evicting the first line of an entry promotes the next one in its place, shown even
+        // when the entry is folded.
+        if self.checked.is_some_and(|checked| checked > first.id) {
+            let listed = self.ids.front() == Some(&first.id);
+            let visible = line_visible(first, app);
+            if visible && !listed {
+                self.push_front(first, app);
+            } else if !visible && listed {
+                self.pop_front();
+            }
+        }
+        let from = match self.checked {
+            Some(checked) => {
+                while self.ids.back().is_some_and(|&id| id >= checked) {
+                    self.pop_back();
+                }
+                lines.index_from(checked)
+            }
+            None => 0,
+        };
+        let candidates: Box<dyn Iterator<Item = usize>> =
+            if app.filter_only && app.filter_expr.is_none() {
+                Box::new(lines.filterable().skip_while(|&index| index < from))
+            } else {
+                Box::new(from..lines.len())
+            };
+        for index in candidates {
+            if line_visible(&lines[index], app) {
+                self.push_back(&lines[index], app);
+            }
+        }
+        self.checked = Some(lines[lines.len() - 1].id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VisibleLines;
+    use crate::{
+        AppState, LogLine, PatternSpec, buffer::LogBuffer, push_line, ui::visual_rows,
+        visible_indices, visible_lines,
+    };
+    use std::num::NonZeroUsize;
+
+    /// Counts the lines shown before each index both ways, kept and by going through them all.
+    fn assert_counts(visible: &mut VisibleLines, lines: &LogBuffer, app: &AppState) {
+        for index in 0..=lines.len() {
+            let scanned = visible_indices(lines, app)
+                .take_while(|&shown| shown < index)
+                .count();
+            assert_eq!(visible.before(lines, app, index), scanned, "before {index}");
+        }
+    }
+
+    #[test]
+    fn counts_follow_lines_coming_and_going_and_settings_changing() {
+        let mut app = AppState::new(
+            vec![PatternSpec::new("ERROR").expect("pattern failed")],
+            false,
+        );
+        app.group_entries = true;
+        let mut lines = LogBuffer::new(NonZeroUsize::new(6));
+        let mut visible = VisibleLines::default();
+
+        for text in [
+            "ERROR first",
+            "  at one",
+            "info",
+            "ERROR second",
+            "  at two",
+            "  at three",
+            "info again",
+            "ERROR third",
+        ] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+            assert_counts(&mut visible, &lines, &app);
+        }
+
+        app.filter_only = true;
+        assert_counts(&mut visible, &lines, &app);
+        app.group_entries = false;
+        assert_counts(&mut visible, &lines, &app);
+        push_line(
+            &mut app,
+            &mut lines,
+            LogLine::output("ERROR last".to_string()),
+        );
+        assert_counts(&mut visible, &lines, &app);
+    }
+
+    #[test]
+    fn windows_and_rows_follow_lines_coming_and_going() {
+        let mut app = AppState::new(Vec::new(), false);
+        app.group_entries = true;
+        let mut lines = LogBuffer::new(NonZeroUsize::new(5));
+        let mut visible = VisibleLines::default();
+
+        for text in [
+            "short",
+            "a line long enough to wrap twice",
+            "  continued",
+            "medium line here",
+            "x",
+            "another line long enough to wrap",
+            "last",
+        ] {
+            push_line(&mut app, &mut lines, LogLine::output(text.to_string()));
+            visible.count_rows(&lines, &app, 12);
+            let shown: Vec<&LogLine> = visible_lines(&lines, &app).collect();
+            assert_eq!(visible.len(&lines, &app), shown.len());
+            let ids = |window: &[&LogLine]| window.iter().map(|line| line.id).collect::<Vec<_>>();
+            assert_eq!(
+                ids(&visible.lines(&lines, &app, 1..3)),
+                ids(&shown[1..3.min(shown.len())])
+            );
+            for position in 0..=shown.len() {
+                let rows: usize = shown[..position]
+                    .iter()
+                    .map(|line| visual_rows(line, &app, 12))
+                    .sum();
+                assert_eq!(visible.rows_before(position), rows, "before {position}");
+            }
+        }
+
+        app.human_epochs = true;
+        visible.count_rows(&lines, &app, 40);
+        let rows: usize = visible_lines(&lines, &app)
+            .map(|line| visual_rows(line, &app, 40))
+            .sum();
+        let shown = visible.len(&lines, &app);
+        assert_eq!(visible.rows_before(shown), rows);
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use globset::{Glob, GlobSetBuilder};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::{
+    sync::mpsc,
+    time::{Instant, timeout_at},
+};
+
+use crate::LogrError;
+
+/// How long the watched files must stay quiet before a change triggers a re-run, so a single
+/// save (or a burst of them) results in one run.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub(crate) struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    changes: mpsc::UnboundedReceiver<()>,
+    /// When the latest change of a burst not reported yet was seen.
+    pending_since: Option<Instant>,
+}
+
+impl FileWatcher {
+    pub(crate) fn new(patterns: &[String]) -> Result<Self, LogrError> {
+        let cwd = std::env::current_dir()?;
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern)?);
+        }
+        let globs = builder.build()?;
+
+        let (tx, changes) = mpsc::unbounded_channel();
+        let base = cwd.clone();
+        let on_event = move |result: notify::Result<notify::Event>| {
+            let Ok(event) = result else {
+                return;
+            };
+            // Running the command reads the watched files, which must not trigger another run.
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            if event
+                .paths
+                .iter()
+                .any(|path| globs.is_match(path.strip_prefix(&base).unwrap_or(path)))
+            {
+                let _ = tx.send(());
+            }
+        };
+        let mut watcher = notify::recommended_watcher(on_event)?;
+        for pattern in patterns {
+            watcher.watch(&cwd.join(watch_root(pattern)), RecursiveMode::Recursive)?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+            pending_since: None,
+        })
+    }
+
+    /// Resolves after watched files changed and then stayed quiet for `DEBOUNCE`. Cancel safe.
+    pub(crate) async fn changed(&mut self) {
+        loop {
+            let Some(since) = self.pending_since else {
+                if self.changes.recv().await.is_none() {
+                    return std::future::pending().await;
+                }
+                self.pending_since = Some(Instant::now());
+                continue;
+            };
+            match timeout_at(since + DEBOUNCE, self.changes.recv()).await {
+                Ok(Some(())) => self.pending_since = Some(Instant::now()),
+                Ok(None) => return std::future::pending().await,
+                Err(_) => {
+                    self.pending_since = None;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// The longest leading part of the glob without wildcards, i.e. the directory to watch.
+fn watch_root(pattern: &str) -> PathBuf {
+    let mut root = PathBuf::new();
+    let mut literal = true;
+    for component in Path::new(pattern).components() {
+        if component
+            .as_os_str()
+            .to_string_lossy()
+            .contains(['*', '?', '[', '{'])
+        {
+            literal = false;
+            break;
+        }
+        root.push(component);
+    }
+
+    // A plain file path: watch its directory, as editors often replace files on save.
+    if literal {
+        root.pop();
+    }
+    if root.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::watch_root;
+    use std::path::PathBuf;
+
+    #[test]
+    fn watch_root_stops_at_first_wildcard() {
+        assert_eq!(watch_root("src/**/*.rs"), PathBuf::from("src"));
+        assert_eq!(watch_root("crates/*/src/*.rs"), PathBuf::from("crates"));
+        assert_eq!(watch_root("*.toml"), PathBuf::from("."));
+    }
+
+    #[test]
+    fn watch_root_uses_parent_of_plain_files() {
+        assert_eq!(watch_root("Cargo.toml"), PathBuf::from("."));
+        assert_eq!(watch_root("config/app.yaml"), PathBuf::from("config"));
+    }
+}
@@ -0,0 +1,215 @@
+//! End-to-end tests running the real binary in a pseudo-terminal, feeding it a log file and
+//! keystrokes and checking what ends up on the emulated screen, or with its output piped.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{Read, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+use portable_pty::{Child, CommandBuilder, MasterPty, PtySize, native_pty_system};
+
+const ROWS: u16 = 24;
+const COLS: u16 = 100;
+/// How long the screen gets to show what a test waits for, well beyond the file poll interval.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A log file in the temp directory, removed when dropped.
+struct LogFile {
+    path: PathBuf,
+}
+
+impl LogFile {
+    fn new(name: &str, lines: &[&str]) -> anyhow::Result<Self> {
+        let path = std::env::temp_dir().join(format!("logr-e2e-{}-{name}.log", std::process::id()));
+        fs::write(
+            &path,
+            lines
+                .iter()
+                .map(|line| format!("{line}\n"))
+                .collect::<String>(),
+        )?;
+        Ok(Self { path })
+    }
+
+    fn append(&self, line: &str) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        self.path.to_str().expect("temp dir path is UTF-8")
+    }
+}
+
+impl Drop for LogFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The binary running in a pseudo-terminal, its output fed to a terminal emulator.
+struct Logr {
+    child: Box<dyn Child + Send + Sync>,
+    writer: Box<dyn Write + Send>,
+    parser: Arc<Mutex<vt100::Parser>>,
+    // Closing the master side would hang up on the child.
+    _master: Box<dyn MasterPty + Send>,
+}
+
+impl Logr {
+    fn spawn(args: &[&str]) -> anyhow::Result<Self> {
+        let pair = native_pty_system().openpty(PtySize {
+            rows: ROWS,
+            cols: COLS,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        let mut command = CommandBuilder::new(env!("CARGO_BIN_EXE_logr"));
+        command.args(args);
+        command.env("TERM", "xterm-256color");
+        command.env_remove("NO_COLOR");
+        let child = pair.slave.spawn_command(command)?;
+        drop(pair.slave);
+
+        let parser = Arc::new(Mutex::new(vt100::Parser::new(ROWS, COLS, 0)));
+        let mut reader = pair.master.try_clone_reader()?;
+        let screen = Arc::clone(&parser);
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            while let Ok(read) = reader.read(&mut buf) {
+                if read == 0 {
+                    break;
+                }
+                screen
+                    .lock()
+                    .expect("parser lock poisoned")
+                    .process(&buf[..read]);
+            }
+        });
+        Ok(Self {
+            child,
+            writer: pair.master.take_writer()?,
+            parser,
+            _master: pair.master,
+        })
+    }
+
+    fn contents(&self) -> String {
+        self.parser
+            .lock()
+            .expect("parser lock poisoned")
+            .screen()
+            .contents()
+    }
+
+    /// Waits until the screen satisfies `done`, failing with what it shows after the timeout.
+    fn wait_for(&self, what: &str, done: impl Fn(&str) -> bool) -> anyhow::Result<()> {
+        let started = Instant::now();
+        while started.elapsed() < TIMEOUT {
+            if done(&self.contents()) {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        bail!(
+            "timed out waiting for {what}, the screen shows:\n{}",
+            self.contents()
+        )
+    }
+
+    fn wait_for_text(&self, text: &str) -> anyhow::Result<()> {
+        self.wait_for(&format!("{text:?}"), |screen| screen.contains(text))
+    }
+
+    fn press(&mut self, keys: &str) -> anyhow::Result<()> {
+        self.writer.write_all(keys.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Quits with `q`, returning whether the binary exited successfully.
+    fn quit(mut self) -> anyhow::Result<bool> {
+        self.press("q")?;
+        let started = Instant::now();
+        while started.elapsed() < TIMEOUT {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(status.success());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        bail!("logr did not exit after q")
+    }
+}
+
+impl Drop for Logr {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[test]
+fn lines_appended_to_a_followed_file_show_up() -> anyhow::Result<()> {
+    let log = LogFile::new("follow", &["first line"])?;
+    let logr = Logr::spawn(&[log.path()])?;
+
+    logr.wait_for_text("first line")?;
+    log.append("second line")?;
+    logr.wait_for_text("second line")?;
+
+    assert!(logr.quit()?, "logr exited with an error");
+    Ok(())
+}
+
+#[test]
+fn filter_key_hides_lines_not_matching_the_patterns() -> anyhow::Result<()> {
+    let log = LogFile::new("filter", &["all good", "an error here", "still fine"])?;
+    // The patterns take every value after them, so the file goes first.
+    let mut logr = Logr::spawn(&[log.path(), "--patterns", "error"])?;
+
+    logr.wait_for_text("still fine")?;
+    logr.press("f")?;
+    logr.wait_for("the other lines to be hidden", |screen| {
+        screen.contains("an error here") && !screen.contains("all good")
+    })?;
+    logr.press("f")?;
+    logr.wait_for_text("all good")?;
+
+    assert!(logr.quit()?, "logr exited with an error");
+    Ok(())
+}
+
+#[test]
+fn pattern_added_in_the_dialog_filters_the_view() -> anyhow::Result<()> {
+    let log = LogFile::new("dialog", &["GET /health 200", "POST /login 500"])?;
+    let mut logr = Logr::spawn(&[log.path()])?;
+
+    logr.wait_for_text("POST /login 500")?;
+    logr.press("p")?;
+    logr.wait_for_text("Patterns")?;
+    logr.press("login\r")?;
+    logr.press("f")?;
+    logr.wait_for("only the matching line", |screen| {
+        screen.contains("POST /login 500") && !screen.contains("GET /health")
+    })?;
+
+    assert!(logr.quit()?, "logr exited with an error");
+    Ok(())
+}
+
+#[test]
+fn piped_output_prints_the_matching_lines_instead_of_the_view() -> anyhow::Result<()> {
+    let log = LogFile::new("grep", &["all good", "an error here", "still fine"])?;
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_logr"))
+        .args([log.path(), "--patterns", "error"])
+        .output()?;
+
+    assert!(output.status.success(), "logr exited with an error");
+    assert_eq!(String::from_utf8(output.stdout)?, "an error here\n");
+    Ok(())
+}